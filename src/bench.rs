@@ -0,0 +1,115 @@
+//! `fancontrol bench`: times backend calls so the cost of a backend (a
+//! PowerShell spawn per Lenovo read against a sysfs read on Linux) and the
+//! effect of changes to it can be measured. Only reads; nothing is written
+//! to the hardware.
+
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use serde_json::json;
+
+use crate::platform::FanController;
+
+/// Summary of a set of timed calls, in milliseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Timings {
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub max_ms: f64,
+    pub mean_ms: f64,
+}
+
+/// Min, median, max, and mean of `samples`, which must not be empty. The
+/// median of an even count is the mean of the middle two.
+pub fn summarize(samples: &[Duration]) -> Timings {
+    let mut ms: Vec<f64> = samples
+        .iter()
+        .map(|sample| sample.as_secs_f64() * 1000.0)
+        .collect();
+    ms.sort_by(f64::total_cmp);
+    let middle = ms.len() / 2;
+    let median_ms = if ms.len().is_multiple_of(2) {
+        (ms[middle - 1] + ms[middle]) / 2.0
+    } else {
+        ms[middle]
+    };
+    Timings {
+        min_ms: ms[0],
+        median_ms,
+        max_ms: ms[ms.len() - 1],
+        mean_ms: ms.iter().sum::<f64>() / ms.len() as f64,
+    }
+}
+
+/// `ms` rounded to whole microseconds, so JSON shows no float noise.
+fn to_microseconds(ms: f64) -> f64 {
+    (ms * 1000.0).round() / 1000.0
+}
+
+/// `bench discover`: call `controller.discover()` `iterations` times and
+/// print how long the calls took. A failing call ends the run.
+pub fn discover(controller: &dyn FanController, iterations: u32, json_output: bool) -> Result<()> {
+    let mut samples = Vec::new();
+    let mut fans = 0;
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        fans = controller.discover()?.len();
+        samples.push(start.elapsed());
+    }
+    let timings = summarize(&samples);
+
+    if json_output {
+        let report = json!({
+            "operation": "discover",
+            "iterations": samples.len(),
+            "fans": fans,
+            "min_ms": to_microseconds(timings.min_ms),
+            "median_ms": to_microseconds(timings.median_ms),
+            "max_ms": to_microseconds(timings.max_ms),
+            "mean_ms": to_microseconds(timings.mean_ms),
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("discover: {} iterations, {fans} fans", samples.len());
+    for (name, ms) in [
+        ("min", timings.min_ms),
+        ("median", timings.median_ms),
+        ("max", timings.max_ms),
+        ("mean", timings.mean_ms),
+    ] {
+        println!("  {name:<6}  {ms:>9.2} ms");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(values: &[u64]) -> Vec<Duration> {
+        values.iter().map(|&ms| Duration::from_millis(ms)).collect()
+    }
+
+    #[test]
+    fn summarize_odd_count() {
+        assert_eq!(
+            summarize(&ms(&[30, 10, 20])),
+            Timings {
+                min_ms: 10.0,
+                median_ms: 20.0,
+                max_ms: 30.0,
+                mean_ms: 20.0,
+            }
+        );
+    }
+
+    #[test]
+    fn summarize_even_count_averages_middle_pair() {
+        let timings = summarize(&ms(&[40, 10, 20, 90]));
+        assert_eq!(timings.median_ms, 30.0);
+        assert_eq!(timings.mean_ms, 40.0);
+        assert_eq!((timings.min_ms, timings.max_ms), (10.0, 90.0));
+    }
+}
@@ -1,6 +1,35 @@
 // put id:"cli_def", label:"CLI Definition (clap)", output:"cli_command.internal"
 
-use clap::{ArgAction, Parser, Subcommand};
+#[cfg(feature = "metrics")]
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+
+use crate::fan::{SensorRef, ThermalMode, SMART_FAN_MODES};
+use crate::platform::Backend;
+
+/// Calls `bench` can time.
+#[derive(Subcommand)]
+pub enum BenchTarget {
+    /// Time `discover()`, the read behind `list` and every GUI and TUI
+    /// refresh, and report min/median/max/mean
+    Discover {
+        /// Number of timed calls
+        #[arg(long, default_value = "10", value_parser = clap::value_parser!(u32).range(1..))]
+        iterations: u32,
+    },
+}
+
+/// Output formats for `table --format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TableFormat {
+    Table,
+    Json,
+    Csv,
+}
 
 #[derive(Parser)]
 #[command(name = "fancontrol")]
@@ -11,10 +40,35 @@ pub struct Cli {
     #[arg(short, long, action = ArgAction::Count, global = true)]
     pub verbose: u8,
 
-    /// Output in JSON format (for list, get, table commands)
+    /// Output in JSON format (for list, get, table commands); errors are
+    /// printed to stderr as {"error": {"kind", "message"}}
     #[arg(long, global = true)]
     pub json: bool,
 
+    /// Extra config file, merged over the system, user, and local configs
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Print the PowerShell scripts the Lenovo backend runs, without
+    /// executing anything
+    #[arg(long, global = true)]
+    pub dump_script: bool,
+
+    /// Validate and print the hardware writes a command would make (PWM,
+    /// RPM, WMI calls, curve steps) without making them
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Write the log here instead of the default location. Also read from
+    /// FANCONTROL_LOG.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Force a controller backend instead of autodetecting it: hwmon,
+    /// lenovo, dell, lhm, or wmi. Also read from FANCONTROL_BACKEND.
+    #[arg(long, global = true, value_name = "NAME")]
+    pub backend: Option<Backend>,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -26,32 +80,243 @@ pub enum Commands {
 
     /// Get the current speed of a fan
     Get {
-        /// Fan ID (use 'list' to see available fans)
+        /// Fan ID, its index in 'list' (0, 1, ...), or a label such as cpu
         fan_id: String,
     },
 
-    /// Set the PWM duty cycle of a fan (0–255)
+    /// Set the PWM duty cycle of a fan (0–255), or of every controllable fan
+    /// with --all
     Set {
-        /// Fan ID (use 'list' to see available fans)
-        fan_id: String,
+        /// Fan ID, its index in 'list' (0, 1, ...), or a label such as cpu
+        #[arg(required_unless_present = "all")]
+        fan_id: Option<String>,
 
         /// PWM value (0 = off, 255 = full speed)
-        #[arg(value_parser = clap::value_parser!(u8))]
-        pwm: u8,
+        #[arg(
+            value_parser = clap::value_parser!(u8),
+            required_unless_present_any = ["percent", "all"],
+            conflicts_with = "percent"
+        )]
+        pwm: Option<u8>,
+
+        /// Set every controllable fan to this PWM value (or to --percent)
+        /// instead of one fan, reporting each fan's result
+        #[arg(
+            long,
+            value_name = "PWM",
+            num_args = 0..=1,
+            conflicts_with_all = ["fan_id", "pwm", "verify", "watch", "ramp"]
+        )]
+        all: Option<Option<u8>>,
+
+        /// Duty cycle in percent (0–100) instead of a raw PWM value
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=100))]
+        percent: Option<u8>,
+
+        /// After setting, watch the RPM and warn if the fan does not respond
+        #[arg(long, conflicts_with = "watch")]
+        verify: bool,
+
+        /// Keep re-applying the value until Ctrl+C, resisting BIOS overrides
+        #[arg(long)]
+        watch: bool,
+
+        /// Step the PWM from its current value to the target over this long,
+        /// e.g. "3s", instead of jumping at once
+        #[arg(long, value_parser = parse_duration, conflicts_with = "watch")]
+        ramp: Option<Duration>,
+
+        /// Number of increments for --ramp
+        #[arg(
+            long,
+            default_value = "5",
+            requires = "ramp",
+            value_parser = clap::value_parser!(u32).range(1..=50)
+        )]
+        ramp_steps: u32,
+
+        /// Re-apply interval in seconds for --watch
+        #[arg(
+            long,
+            default_value = "2",
+            requires = "watch",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        interval: u64,
+
+        #[command(flatten)]
+        restore: RestoreArgs,
+    },
+
+    /// Set a target speed in RPM (fans with a known RPM range, e.g. Lenovo)
+    SetRpm {
+        /// Fan ID, its index in 'list' (0, 1, ...), or a label such as cpu
+        fan_id: String,
+
+        /// Target speed in RPM; values below the fan's minimum are raised to it
+        rpm: u32,
+    },
+
+    /// Return a fan to automatic (firmware/chip) control
+    Auto {
+        /// Fan ID, its index in 'list' (0, 1, ...), or a label such as cpu
+        fan_id: String,
+    },
+
+    /// Set a fan to PWM 0 (on Lenovo this returns it to BIOS auto control)
+    Stop {
+        /// Fan ID, its index in 'list' (0, 1, ...), or a label such as cpu
+        fan_id: String,
+    },
+
+    /// Drive fans from software temperature→PWM curves until Ctrl+C
+    /// (Linux hwmon)
+    RunDaemon {
+        /// JSON array of curves: [{"fan_id", "sensor"?, "points": [{"temperature", "pwm"}]}]
+        #[arg(long, value_name = "FILE")]
+        curves: PathBuf,
+
+        /// Seconds between temperature reads [default: 2, or
+        /// `poll_interval_secs` from the config]
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+
+        #[command(flatten)]
+        restore: RestoreArgs,
+    },
+
+    /// Serve fan RPM, PWM, and temperatures in Prometheus text format over
+    /// HTTP, read fresh on every scrape
+    #[cfg(feature = "metrics")]
+    Metrics {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = crate::metrics::DEFAULT_BIND)]
+        bind: SocketAddr,
     },
 
     /// Monitor all fans in real-time
     Monitor {
-        /// Refresh interval in seconds
-        #[arg(short, long, default_value = "1")]
+        /// Refresh interval in seconds [default: 1, or `poll_interval_secs`
+        /// from the config]
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+
+        /// Append a `timestamp,fan_id,rpm,pwm,temp` row per fan to this CSV
+        /// file on every refresh
+        #[arg(long, value_name = "PATH")]
+        csv: Option<PathBuf>,
+
+        /// Take this many samples, then exit with per-fan min/avg/max RPM
+        /// and temperature
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+        count: Option<u32>,
+    },
+
+    /// Poll temperatures and force full speed while any sensor is above a
+    /// limit, restoring the previous state once it has cooled down
+    Watch {
+        /// Force full speed when any temperature exceeds this (°C)
+        #[arg(long, value_name = "CELSIUS")]
+        max_temp: u32,
+
+        /// Restore only once the hottest reading is this far below
+        /// --max-temp (°C)
+        #[arg(long, value_name = "CELSIUS", default_value = "5")]
+        hysteresis: u32,
+
+        /// Hold full speed at least this long after tripping, e.g. "30s",
+        /// "2m" (bare numbers are seconds)
+        #[arg(long, value_parser = parse_duration, default_value = "30s")]
+        min_dwell: Duration,
+
+        /// Poll interval in seconds [default: 2, or `poll_interval_secs`
+        /// from the config]
+        #[arg(short, long, value_parser = clap::value_parser!(u64).range(1..))]
+        interval: Option<u64>,
+    },
+
+    /// Time backend calls, e.g. `bench discover`, without writing anything
+    #[command(hide = true)]
+    Bench {
+        #[command(subcommand)]
+        target: BenchTarget,
+    },
+
+    /// Pin a fan at a fixed RPM for a benchmark run, logging its speed, then
+    /// return it to automatic control
+    BenchmarkHold {
+        /// Fan ID, its index in 'list' (0, 1, ...), or a label such as cpu
+        #[arg(long)]
+        fan: String,
+
+        /// Target speed in RPM
+        #[arg(long)]
+        rpm: u32,
+
+        /// How long to hold, e.g. "90s", "10m", "1h" (bare numbers are seconds)
+        #[arg(long, value_parser = parse_duration)]
+        duration: Duration,
+
+        /// Write one CSV row per sample to this file
+        #[arg(long)]
+        log_csv: Option<PathBuf>,
+
+        /// Re-apply and sample interval in seconds
+        #[arg(long, default_value = "2", value_parser = clap::value_parser!(u64).range(1..))]
         interval: u64,
     },
 
+    /// Sweep a fan through PWM values and record the RPM it reaches at
+    /// each, so PWM maps to RPM through measurements instead of linearly
+    /// (Lenovo)
+    Calibrate {
+        /// Fan ID, its index in 'list' (0, 1, ...), or a label such as cpu
+        fan_id: String,
+
+        /// Number of PWM values to measure, evenly spaced between 0 and 255
+        #[arg(long, default_value = "8", value_parser = clap::value_parser!(u32).range(1..=32))]
+        steps: u32,
+
+        /// Seconds the fan gets to settle at each value before its RPM is read
+        #[arg(long, default_value = "5", value_parser = clap::value_parser!(u64).range(1..))]
+        settle: u64,
+
+        /// Save the table to fancontrol.json as the fan's `pwm_calibration`
+        #[arg(long)]
+        save: bool,
+    },
+
     /// Display EC fan curve / table data
     Table {
         /// Show curves for a specific fan ID only (e.g. 0, 1)
         #[arg(long)]
         fan_id: Option<u32>,
+
+        /// Output format: the readable table, the curve array as JSON, or
+        /// CSV with one row per curve point. `--json` picks json
+        #[arg(long, value_enum, default_value_t = TableFormat::Table)]
+        format: TableFormat,
+    },
+
+    /// Show or set the Lenovo SmartFanMode
+    FanMode {
+        /// New mode: quiet, balanced, performance, custom, or the raw value
+        /// (1, 2, 3, 255). Omit to show the current mode.
+        #[arg(value_parser = parse_smart_fan_mode)]
+        mode: Option<u32>,
+    },
+
+    /// Turn full-speed mode on or off for all fans (Lenovo)
+    FullSpeed {
+        /// on or off
+        #[arg(action = ArgAction::Set, value_parser = parse_on_off)]
+        state: bool,
+    },
+
+    /// Switch the thermal profile (Lenovo)
+    ThermalMode {
+        /// quiet, balanced, or performance
+        mode: ThermalMode,
     },
 
     /// Set a custom fan curve (Lenovo only, requires Custom SmartFanMode)
@@ -60,9 +325,10 @@ pub enum Commands {
         #[arg(long)]
         fan_id: u32,
 
-        /// Sensor ID (3 = CPU temp, 4 = GPU temp on V1 hardware)
+        /// Sensor ID (3 = CPU temp, 4 = GPU temp on V1 hardware), or `gpu`
+        /// to use the sensor bound to the GPU fan's curve
         #[arg(long)]
-        sensor_id: u32,
+        sensor_id: SensorRef,
 
         /// 10 comma-separated speed step indices (0–10 scale).
         /// Each value indexes into the hardware's FanSpeeds array.
@@ -73,15 +339,237 @@ pub enum Commands {
         /// Save the curve to fancontrol.json for automatic re-application
         #[arg(long)]
         save: bool,
+
+        /// Reject curves that fail the advisory quiet-curve check instead
+        /// of only warning
+        #[arg(long)]
+        strict: bool,
+
+        /// Sort the steps into ascending order before validation, for steps
+        /// typed hottest-first or out of order. Without it a step lower than
+        /// the one before is an error
+        #[arg(long)]
+        sort: bool,
+
+        /// Clamp, floor, and monotonize the steps instead of rejecting them,
+        /// printing what was changed
+        #[arg(long)]
+        auto_fix: bool,
+
+        /// Resample the steps through N evenly spaced points (2–10) and
+        /// interpolate between them, for a gentler ramp between sparse steps
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(2..=10))]
+        smooth: Option<u8>,
+
+        /// Minimum speed step 9 must reach, in percent of the 0–10 scale
+        /// (step 8's floor scales with it). Below the default 50% the fan may
+        /// not keep up with a sustained load
+        #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(0..=100))]
+        min_high_rpm_percent: Option<u8>,
+    },
+
+    /// Show the RPM a custom curve would give at a temperature, without
+    /// writing anything (Lenovo)
+    PreviewCurve {
+        /// Fan ID (0 = CPU fan, 1 = GPU fan on V1 hardware)
+        #[arg(long)]
+        fan_id: u32,
+
+        /// Sensor ID (3 = CPU temp, 4 = GPU temp on V1 hardware), or `gpu`
+        /// to use the sensor bound to the GPU fan's curve
+        #[arg(long)]
+        sensor_id: SensorRef,
+
+        /// 10 comma-separated speed step indices, as for `set-curve`
+        #[arg(long, value_parser = parse_steps)]
+        steps: [u8; 10],
+
+        /// Sort the steps into ascending order first, as for `set-curve`
+        #[arg(long)]
+        sort: bool,
+
+        /// Temperature in °C to evaluate the curve at
+        #[arg(long)]
+        temp: u32,
+    },
+
+    /// Apply every custom curve JSON file found in a directory
+    ApplyCurves {
+        /// Directory containing `*.json` curve files (one curve or an array
+        /// of curves per file, same shape as `custom_curves` in the config)
+        #[arg(long)]
+        dir: PathBuf,
+    },
+
+    /// List the curve presets defined in the config
+    ListPresets,
+
+    /// Apply a named curve preset from the config's `presets`
+    ApplyPreset {
+        /// Preset name, e.g. quiet
+        name: String,
+    },
+
+    /// Restore custom curves from a JSON array file
+    RestoreCurves {
+        /// JSON array of curves, same shape as `custom_curves` in the config
+        #[arg(long)]
+        input: PathBuf,
+    },
+
+    /// Apply custom curves and keep them applied, re-writing any the EC
+    /// reverts, until Ctrl+C (Lenovo)
+    HoldCurve {
+        /// One curve or a JSON array of curves, same shape as for
+        /// `restore-curves`
+        #[arg(long)]
+        input: PathBuf,
+
+        /// Seconds between checks of the live curve tables
+        #[arg(long, default_value = "30", value_parser = clap::value_parser!(u64).range(1..))]
+        interval: u64,
+    },
+
+    /// Print the raw bytes `Fan_Get_Table` returns for one table next to
+    /// the parsed curve, for debugging the table format (Lenovo)
+    DumpTable {
+        /// Fan ID (0 = CPU fan, 1 = GPU fan on V1 hardware)
+        #[arg(long)]
+        fan_id: u32,
+
+        /// Sensor ID (3 = CPU temp, 4 = GPU temp on V1 hardware), or `gpu`
+        /// to use the sensor bound to the GPU fan's curve
+        #[arg(long)]
+        sensor_id: SensorRef,
+    },
+
+    /// Save the live EC curve tables to a JSON file, the format
+    /// `diff-curves` reads
+    BackupCurves {
+        /// File to write; relative paths resolve against the current
+        /// directory
+        #[arg(long, default_value = "fan_curves_backup.json")]
+        output: PathBuf,
+        /// Replace the file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Compare a `table --json` backup with the live EC curves, point by
+    /// point
+    DiffCurves {
+        /// Curves saved earlier with `backup-curves` or
+        /// `fancontrol table --json > FILE`
+        #[arg(long)]
+        input: PathBuf,
+    },
+
+    /// Show each config layer and the merged result
+    DumpConfig,
+
+    /// Check the environment (hwmon and PWM permissions on Linux;
+    /// PowerShell, COM, and the Lenovo WMI classes on Windows) and summarise
+    /// which capabilities work on this machine
+    Doctor,
+
+    /// Print a shell completion script to stdout
+    Completions {
+        /// Shell to write the script for
+        #[arg(value_enum)]
+        shell: Shell,
     },
 
     /// Open the graphical fan control interface
-    Gui,
+    Gui {
+        /// Discover fans once at startup, then refresh only on the Refresh
+        /// button instead of polling every 1.5 s
+        #[arg(long)]
+        once: bool,
+    },
 
     /// Open the interactive terminal UI dashboard
     Tui,
 }
 
+/// What to do with fans a loop changed when it exits (Ctrl+C / SIGTERM).
+#[derive(Args)]
+pub struct RestoreArgs {
+    /// Return changed fans to automatic control on exit (default)
+    #[arg(long, overrides_with = "no_restore")]
+    pub restore_on_exit: bool,
+
+    /// Leave changed fans in their last state on exit
+    #[arg(long, overrides_with = "restore_on_exit")]
+    pub no_restore: bool,
+}
+
+impl RestoreArgs {
+    /// Whether fans should be restored on exit. Restoring is the default.
+    pub fn restore(&self) -> bool {
+        !self.no_restore
+    }
+}
+
+impl Cli {
+    /// Checks between arguments that clap cannot express.
+    pub fn validate(&self) -> Result<(), String> {
+        match self.command {
+            Commands::Watch {
+                max_temp,
+                hysteresis,
+                ..
+            } if hysteresis >= max_temp => Err(format!(
+                "--hysteresis ({hysteresis}) must be below --max-temp ({max_temp})"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Parse a duration like "45s", "10m", or "2h". Bare numbers are seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, multiplier) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 3600),
+        _ => (s, 1),
+    };
+    let value: u64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration '{s}': expected e.g. 90s, 10m, 1h"))?;
+    let seconds = value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("duration '{s}' is too long"))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+/// Parse a SmartFanMode name (case-insensitive) or its raw value.
+fn parse_smart_fan_mode(s: &str) -> Result<u32, String> {
+    let wanted = s.trim();
+    SMART_FAN_MODES
+        .iter()
+        .find(|(value, name)| name.eq_ignore_ascii_case(wanted) || value.to_string() == wanted)
+        .map(|(value, _)| *value)
+        .ok_or_else(|| {
+            let names: Vec<String> = SMART_FAN_MODES
+                .iter()
+                .map(|(value, name)| format!("{} ({value})", name.to_lowercase()))
+                .collect();
+            format!("unknown SmartFanMode '{s}': expected {}", names.join(", "))
+        })
+}
+
+/// Parse `on`/`off` (case-insensitive).
+fn parse_on_off(s: &str) -> Result<bool, String> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => Err(format!("expected 'on' or 'off', got '{s}'")),
+    }
+}
+
 /// Parse 10 comma-separated step values into a fixed-size array.
 fn parse_steps(s: &str) -> Result<[u8; 10], String> {
     let values: Vec<u8> = s
@@ -101,3 +589,172 @@ fn parse_steps(s: &str) -> Result<[u8; 10], String> {
         .try_into()
         .map_err(|_| "expected exactly 10 values".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cli_definition_is_valid() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn table_accepts_json_after_subcommand() {
+        let cli = Cli::try_parse_from(["fancontrol", "table", "--fan-id", "1", "--json"]).unwrap();
+        assert!(cli.json);
+        assert!(matches!(
+            cli.command,
+            Commands::Table {
+                fan_id: Some(1),
+                format: TableFormat::Table
+            }
+        ));
+    }
+
+    #[test]
+    fn table_format_names() {
+        let cli = Cli::try_parse_from(["fancontrol", "table", "--format", "csv"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Table {
+                format: TableFormat::Csv,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["fancontrol", "table", "--format", "xml"]).is_err());
+    }
+
+    #[test]
+    fn calibrate_defaults_and_limits() {
+        let cli = Cli::try_parse_from(["fancontrol", "calibrate", "cpu"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Commands::Calibrate {
+                steps: 8,
+                settle: 5,
+                save: false,
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from(["fancontrol", "calibrate", "cpu", "--steps", "0"]).is_err());
+    }
+
+    #[test]
+    fn run_daemon_rejects_zero_interval() {
+        let parse = |interval| {
+            Cli::try_parse_from([
+                "fancontrol",
+                "run-daemon",
+                "--curves",
+                "c.json",
+                "-i",
+                interval,
+            ])
+        };
+        assert!(parse("1").is_ok());
+        assert!(parse("0").is_err());
+    }
+
+    #[test]
+    fn set_watch_rejects_zero_interval() {
+        let parse = |interval| {
+            Cli::try_parse_from([
+                "fancontrol",
+                "set",
+                "cpu",
+                "128",
+                "--watch",
+                "--interval",
+                interval,
+            ])
+        };
+        assert!(parse("1").is_ok());
+        assert!(parse("0").is_err());
+    }
+
+    #[test]
+    fn benchmark_hold_rejects_zero_interval() {
+        let parse = |interval| {
+            Cli::try_parse_from([
+                "fancontrol",
+                "benchmark-hold",
+                "--fan",
+                "cpu",
+                "--rpm",
+                "3000",
+                "--duration",
+                "1m",
+                "--interval",
+                interval,
+            ])
+        };
+        assert!(parse("1").is_ok());
+        assert!(parse("0").is_err());
+    }
+
+    #[test]
+    fn monitor_and_watch_reject_zero_interval() {
+        assert!(Cli::try_parse_from(["fancontrol", "monitor", "-i", "1"]).is_ok());
+        assert!(Cli::try_parse_from(["fancontrol", "monitor", "-i", "0"]).is_err());
+        let watch = |interval| {
+            Cli::try_parse_from(["fancontrol", "watch", "--max-temp", "85", "-i", interval])
+        };
+        assert!(watch("1").is_ok());
+        assert!(watch("0").is_err());
+    }
+
+    #[test]
+    fn watch_rejects_hysteresis_at_or_above_max_temp() {
+        let watch = |hysteresis| {
+            Cli::try_parse_from([
+                "fancontrol",
+                "watch",
+                "--max-temp",
+                "85",
+                "--hysteresis",
+                hysteresis,
+            ])
+            .unwrap()
+            .validate()
+        };
+        assert!(watch("84").is_ok());
+        assert!(watch("85").is_err());
+        assert!(watch("90").is_err());
+    }
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_garbage() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration("-5s").is_err());
+        assert!(parse_duration("999999999999999999h").is_err());
+    }
+
+    #[test]
+    fn parse_smart_fan_mode_names_and_values() {
+        assert_eq!(parse_smart_fan_mode("quiet"), Ok(1));
+        assert_eq!(parse_smart_fan_mode("Performance"), Ok(3));
+        assert_eq!(parse_smart_fan_mode("custom"), Ok(255));
+        assert_eq!(parse_smart_fan_mode("2"), Ok(2));
+        assert!(parse_smart_fan_mode("turbo").is_err());
+        assert!(parse_smart_fan_mode("4").is_err());
+    }
+
+    #[test]
+    fn parse_on_off_values() {
+        assert_eq!(parse_on_off("on"), Ok(true));
+        assert_eq!(parse_on_off("OFF"), Ok(false));
+        assert!(parse_on_off("1").is_err());
+    }
+}
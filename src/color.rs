@@ -0,0 +1,115 @@
+//! ANSI colouring for the `monitor` and `list` tables: fans close to their
+//! top speed turn amber, then red, and so do hot temperatures.
+//!
+//! Colour is off when `NO_COLOR` is set to anything non-empty
+//! (<https://no-color.org>) or stdout is not a terminal, so output piped to
+//! a file or another tool stays plain.
+
+use std::ffi::OsString;
+use std::io::IsTerminal;
+
+/// Share of a fan's `max_rpm` at which its RPM turns amber, in percent.
+const RPM_WARM_PERCENT: u32 = 75;
+/// Share of a fan's `max_rpm` at which its RPM turns red, in percent.
+const RPM_HOT_PERCENT: u32 = 90;
+/// Temperature at which a reading turns amber.
+const TEMP_WARM_CELSIUS: u32 = 80;
+/// Temperature at which a reading turns red.
+const TEMP_HOT_CELSIUS: u32 = 90;
+
+/// How alarming a reading is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Normal,
+    Warm,
+    Hot,
+}
+
+/// Whether to colour stdout, from `NO_COLOR` and whether it is a terminal.
+pub fn enabled() -> bool {
+    enabled_for(
+        std::env::var_os("NO_COLOR"),
+        std::io::stdout().is_terminal(),
+    )
+}
+
+fn enabled_for(no_color: Option<OsString>, is_terminal: bool) -> bool {
+    is_terminal && no_color.is_none_or(|value| value.is_empty())
+}
+
+/// Level of `rpm` against the fan's `max_rpm`. Without a known maximum
+/// there is nothing to compare against, so it stays normal.
+pub fn rpm_level(rpm: u32, max_rpm: Option<u32>) -> Level {
+    // In u64: a bogus reading near u32::MAX must not overflow.
+    let reaches =
+        |max: u32, percent: u32| u64::from(rpm) * 100 >= u64::from(max) * u64::from(percent);
+    match max_rpm {
+        Some(max) if max > 0 && reaches(max, RPM_HOT_PERCENT) => Level::Hot,
+        Some(max) if max > 0 && reaches(max, RPM_WARM_PERCENT) => Level::Warm,
+        _ => Level::Normal,
+    }
+}
+
+/// Level of a temperature in °C.
+pub fn temp_level(celsius: u32) -> Level {
+    if celsius >= TEMP_HOT_CELSIUS {
+        Level::Hot
+    } else if celsius >= TEMP_WARM_CELSIUS {
+        Level::Warm
+    } else {
+        Level::Normal
+    }
+}
+
+/// `text` wrapped in the colour for `level` when `enabled`. Pad `text`
+/// before calling, since the escape codes would count towards a width.
+pub fn paint(text: &str, level: Level, enabled: bool) -> String {
+    let code = match level {
+        Level::Warm => "33",
+        Level::Hot => "31",
+        Level::Normal => return text.to_string(),
+    };
+    if enabled {
+        format!("\x1B[{code}m{text}\x1B[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_color_or_a_pipe_turns_colour_off() {
+        assert!(enabled_for(None, true));
+        assert!(enabled_for(Some(OsString::new()), true));
+        assert!(!enabled_for(Some("1".into()), true));
+        assert!(!enabled_for(None, false));
+    }
+
+    #[test]
+    fn rpm_level_follows_max_rpm() {
+        assert_eq!(rpm_level(3000, Some(4800)), Level::Normal);
+        assert_eq!(rpm_level(3600, Some(4800)), Level::Warm);
+        assert_eq!(rpm_level(4400, Some(4800)), Level::Hot);
+        assert_eq!(rpm_level(9000, None), Level::Normal);
+        assert_eq!(rpm_level(10, Some(0)), Level::Normal);
+        assert_eq!(rpm_level(u32::MAX, Some(u32::MAX)), Level::Hot);
+        assert_eq!(rpm_level(u32::MAX, Some(4800)), Level::Hot);
+    }
+
+    #[test]
+    fn temp_level_thresholds() {
+        assert_eq!(temp_level(79), Level::Normal);
+        assert_eq!(temp_level(80), Level::Warm);
+        assert_eq!(temp_level(90), Level::Hot);
+    }
+
+    #[test]
+    fn paint_wraps_only_when_enabled() {
+        assert_eq!(paint("  4400", Level::Hot, true), "\x1B[31m  4400\x1B[0m");
+        assert_eq!(paint("  4400", Level::Hot, false), "  4400");
+        assert_eq!(paint("  3000", Level::Normal, true), "  3000");
+    }
+}
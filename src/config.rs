@@ -3,16 +3,26 @@
 
 //! Persistent configuration for custom fan curves.
 //!
-//! Stores `fancontrol.json` next to the executable (same directory as
-//! `fancontrol.log`). Gracefully falls back to defaults on missing or
+//! Stores `fancontrol.json` next to the executable. Gracefully falls back to defaults on missing or
 //! malformed files.
+//!
+//! The effective config is layered, lowest precedence first: a system-wide
+//! file, a per-user file, the file next to the executable, and an optional
+//! `--config` file. Layers are deep-merged as JSON objects; arrays and
+//! scalars from a higher layer replace the lower value. Saving always writes
+//! the file next to the executable.
 
-use std::path::PathBuf;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 
 use log::{info, warn};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use crate::fan::CustomFanCurve;
+use crate::fan::{CalibrationPoint, CustomFanCurve};
 
 /// Persistent configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,17 +34,77 @@ pub struct Config {
     /// Automatically switch to Custom SmartFanMode when applying saved curves.
     #[serde(default = "default_true")]
     pub auto_smart_fan_mode: bool,
+
+    /// Seconds a Lenovo or Dell PowerShell call may take before it is killed.
+    #[serde(default = "default_powershell_timeout_secs")]
+    pub powershell_timeout_secs: u64,
+
+    /// Tries a Lenovo or Dell PowerShell read gets when WMI fails
+    /// transiently. Writes always run once.
+    #[serde(default = "default_powershell_read_attempts")]
+    pub powershell_read_attempts: u32,
+
+    /// Refresh interval in seconds for `monitor` and `run-daemon` when
+    /// `--interval` is not given. At least 1.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_poll_interval"
+    )]
+    pub poll_interval_secs: Option<u64>,
+
+    /// Backend to use when neither `--backend` nor `FANCONTROL_BACKEND` is
+    /// set (e.g. "lhm").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+
+    /// Named curve sets for `apply-preset`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, Vec<CustomFanCurve>>,
+
+    /// Measured RPM at several PWM values per fan id, from `calibrate`.
+    /// The Lenovo backend maps PWM to RPM through these instead of linearly.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pwm_calibration: BTreeMap<String, Vec<CalibrationPoint>>,
 }
 
 fn default_true() -> bool {
     true
 }
 
+fn default_powershell_timeout_secs() -> u64 {
+    5
+}
+
+fn default_powershell_read_attempts() -> u32 {
+    3
+}
+
+/// `poll_interval_secs`, refusing 0: a zero interval would busy-loop the
+/// daemon's writes.
+fn deserialize_poll_interval<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<u64>::deserialize(deserializer)? {
+        Some(0) => Err(serde::de::Error::custom(
+            "poll_interval_secs must be at least 1",
+        )),
+        secs => Ok(secs),
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
             custom_curves: Vec::new(),
             auto_smart_fan_mode: true,
+            powershell_timeout_secs: default_powershell_timeout_secs(),
+            powershell_read_attempts: default_powershell_read_attempts(),
+            poll_interval_secs: None,
+            backend: None,
+            presets: BTreeMap::new(),
+            pwm_calibration: BTreeMap::new(),
         }
     }
 }
@@ -48,37 +118,482 @@ pub fn config_path() -> PathBuf {
         .join("fancontrol.json")
 }
 
-/// Load configuration from disk. Returns defaults on any error.
-pub fn load_config() -> Config {
-    let path = config_path();
-    match std::fs::read_to_string(&path) {
-        Ok(contents) => match serde_json::from_str(&contents) {
-            Ok(config) => {
-                info!("Loaded config from {}", path.display());
-                config
+/// Environment variable naming the log file when `--log-file` is not given.
+pub const LOG_ENV: &str = "FANCONTROL_LOG";
+
+/// The log file: `--log-file`, else `FANCONTROL_LOG`, else a default
+/// location (see `resolve_log_path`).
+pub fn log_path(cli_path: Option<PathBuf>) -> PathBuf {
+    resolve_log_path(
+        cli_path,
+        std::env::var_os(LOG_ENV),
+        std::env::var_os("XDG_STATE_HOME"),
+    )
+}
+
+/// Pick the log file from the `--log-file` value and the `FANCONTROL_LOG`
+/// and `XDG_STATE_HOME` variables. Without an explicit path the log goes
+/// next to the executable on Windows; on Linux, whose binaries usually sit
+/// in read-only directories like `/usr/bin`, it goes to
+/// `$XDG_STATE_HOME/fancontrol/`, else `/tmp`.
+fn resolve_log_path(
+    cli_path: Option<PathBuf>,
+    env_path: Option<OsString>,
+    state_home: Option<OsString>,
+) -> PathBuf {
+    if let Some(path) = cli_path {
+        return path;
+    }
+    if let Some(path) = env_path.filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+    if cfg!(target_os = "windows") {
+        return config_path().with_file_name("fancontrol.log");
+    }
+    match state_home.filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir).join("fancontrol").join("fancontrol.log"),
+        None => std::env::temp_dir().join("fancontrol.log"),
+    }
+}
+
+/// Config file passed with `--config`, set once at startup.
+static CLI_CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Register the `--config` file as the highest-precedence layer.
+pub fn set_cli_config_path(path: PathBuf) {
+    let _ = CLI_CONFIG_PATH.set(path);
+}
+
+/// System-wide base config.
+fn system_config_path() -> Option<PathBuf> {
+    if cfg!(target_os = "windows") {
+        std::env::var_os("ProgramData").map(|dir| {
+            PathBuf::from(dir)
+                .join("fancontrol")
+                .join("fancontrol.json")
+        })
+    } else {
+        Some(PathBuf::from("/etc/fancontrol/fancontrol.json"))
+    }
+}
+
+/// Per-user config.
+fn user_config_path() -> Option<PathBuf> {
+    let config_dir = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+    };
+    config_dir.map(|dir| dir.join("fancontrol").join("fancontrol.json"))
+}
+
+/// Config layers as `(name, path)`, lowest precedence first.
+pub fn config_layers() -> Vec<(&'static str, PathBuf)> {
+    let mut layers = Vec::new();
+    if let Some(path) = system_config_path() {
+        layers.push(("system", path));
+    }
+    if let Some(path) = user_config_path() {
+        layers.push(("user", path));
+    }
+    layers.push(("local", config_path()));
+    if let Some(path) = CLI_CONFIG_PATH.get() {
+        layers.push(("--config", path.clone()));
+    }
+    layers
+}
+
+/// Outcome of reading one config layer.
+#[derive(Debug)]
+pub enum LayerStatus {
+    Loaded,
+    NotFound,
+    Malformed(String),
+}
+
+/// Read a layer file as a JSON value.
+fn read_layer(path: &Path) -> Result<Value, LayerStatus> {
+    let contents = std::fs::read_to_string(path).map_err(|_| LayerStatus::NotFound)?;
+    serde_json::from_str(&contents).map_err(|error| LayerStatus::Malformed(error.to_string()))
+}
+
+/// Deep-merge `overlay` into `base`. Objects merge key by key; any other
+/// value in `overlay` replaces the one in `base`.
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
             }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Merge the given layers and report how each one was read. Malformed or
+/// missing layers are skipped, and keys that do not fit `Config` are dropped
+/// so the rest of the config still applies.
+pub fn resolve_layers(layers: &[(&'static str, PathBuf)]) -> (Config, Vec<LayerStatus>) {
+    let mut merged = Value::Object(Default::default());
+    let mut loaded = Vec::new();
+    let mut statuses = Vec::new();
+    for (name, path) in layers {
+        match read_layer(path) {
+            Ok(value) => {
+                info!("Loaded {name} config from {}", path.display());
+                loaded.push((*name, path, value.clone()));
+                merge_json(&mut merged, value);
+                statuses.push(LayerStatus::Loaded);
+            }
+            Err(status) => {
+                if let LayerStatus::Malformed(error) = &status {
+                    warn!("Malformed {name} config at {}: {error}", path.display());
+                }
+                statuses.push(status);
+            }
+        }
+    }
+    let config = loop {
+        match serde_json::from_value(merged.clone()) {
+            Ok(config) => break config,
             Err(error) => {
-                warn!("Malformed config at {}: {error}", path.display());
-                Config::default()
+                let Some((key, key_error)) = invalid_key(&merged) else {
+                    warn!("Merged config is invalid: {error}");
+                    break Config::default();
+                };
+                // The highest layer that sets the key is the one that won the merge.
+                let source = loaded
+                    .iter()
+                    .rev()
+                    .find(|(_, _, value)| value.get(&key).is_some());
+                let message = match source {
+                    Some((name, path, _)) => format!(
+                        "ignoring `{key}` from the {name} config at {}: {key_error}",
+                        path.display()
+                    ),
+                    None => format!("ignoring config key `{key}`: {key_error}"),
+                };
+                report_once(&message);
+                if let Value::Object(map) = &mut merged {
+                    map.remove(&key);
+                }
             }
-        },
-        Err(_) => Config::default(),
+        }
+    };
+    (config, statuses)
+}
+
+/// Log `message` as a warning, and print it to stderr the first time it is
+/// seen. The config is loaded many times per run.
+fn report_once(message: &str) {
+    static REPORTED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+    warn!("Config: {message}");
+    let first = REPORTED
+        .lock()
+        .map(|mut reported| reported.insert(message.to_string()))
+        .unwrap_or(false);
+    if first {
+        eprintln!("Warning: {message}");
     }
 }
 
+/// The first top-level key of `merged` whose value does not fit `Config`,
+/// with its error, found by deserializing each key on its own.
+fn invalid_key(merged: &Value) -> Option<(String, serde_json::Error)> {
+    let Value::Object(map) = merged else {
+        return None;
+    };
+    map.iter().find_map(|(key, value)| {
+        let single = serde_json::json!({ key.as_str(): value });
+        serde_json::from_value::<Config>(single)
+            .err()
+            .map(|error| (key.clone(), error))
+    })
+}
+
+/// Load the effective configuration from all layers. Returns defaults on
+/// any error.
+pub fn load_config() -> Config {
+    resolve_layers(&config_layers()).0
+}
+
+/// Load only the file next to the executable, the one `save_config` writes.
+/// Used when editing so values from other layers are not copied into it.
+pub fn load_local_config() -> Config {
+    load_local_config_at(&config_path())
+}
+
+fn load_local_config_at(path: &Path) -> Config {
+    resolve_layers(&[("local", path.to_path_buf())]).0
+}
+
 /// Save configuration to disk.
 pub fn save_config(config: &Config) -> Result<(), std::io::Error> {
-    let path = config_path();
+    save_config_to(&config_path(), config)
+}
+
+fn save_config_to(path: &Path, config: &Config) -> Result<(), std::io::Error> {
     let json = serde_json::to_string_pretty(config).map_err(std::io::Error::other)?;
-    std::fs::write(&path, json)?;
+    std::fs::write(path, json)?;
     info!("Saved config to {}", path.display());
     Ok(())
 }
 
+/// Replace the saved custom curves in the file next to the executable,
+/// keeping its other keys (presets, backend, calibration tables, ...).
+/// Returns how many curves were saved.
+pub fn save_custom_curves(curves: Vec<CustomFanCurve>) -> Result<usize, std::io::Error> {
+    save_custom_curves_to(&config_path(), curves)
+}
+
+fn save_custom_curves_to(
+    path: &Path,
+    curves: Vec<CustomFanCurve>,
+) -> Result<usize, std::io::Error> {
+    let mut cfg = load_local_config_at(path);
+    cfg.custom_curves = curves;
+    cfg.auto_smart_fan_mode = true;
+    save_config_to(path, &cfg)?;
+    Ok(cfg.custom_curves.len())
+}
+
+// ---------------------------------------------------------------------------
+// Curve profiles
+// ---------------------------------------------------------------------------
+
+/// Directory holding named curve profiles: `profiles/` beside the per-user
+/// config, or beside the executable when no user config dir is known.
+pub fn profiles_dir() -> PathBuf {
+    user_config_path()
+        .unwrap_or_else(config_path)
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join("profiles")
+}
+
+/// Path of profile `name` in `dir`. Names that could escape `dir` are
+/// rejected.
+fn profile_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("profile name is empty".to_string());
+    }
+    if name.contains(['/', '\\']) || name.starts_with('.') {
+        return Err(format!("invalid profile name '{name}'"));
+    }
+    Ok(dir.join(format!("{name}.json")))
+}
+
+/// Names of the saved profiles in `dir`, sorted. A missing directory has
+/// no profiles.
+pub fn list_profiles(dir: &Path) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            file_name.strip_suffix(".json").map(str::to_string)
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+/// Write `contents` to `path`, refusing to replace an existing file unless
+/// `overwrite` is set. Returns the absolute path written, so callers can
+/// report where a relative path landed.
+pub fn write_file(path: &Path, contents: &str, overwrite: bool) -> Result<PathBuf, String> {
+    let path = std::path::absolute(path)
+        .map_err(|error| format!("cannot resolve {}: {error}", path.display()))?;
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true);
+    if overwrite {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    options
+        .open(&path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .map_err(|error| match error.kind() {
+            std::io::ErrorKind::AlreadyExists => {
+                format!("{} already exists", path.display())
+            }
+            _ => format!("cannot write {}: {error}", path.display()),
+        })?;
+    Ok(path)
+}
+
+/// Write `curves` as profile `name`, in the same JSON array shape that
+/// `restore-curves` reads. An existing profile of that name is only
+/// replaced when `overwrite` is set.
+pub fn save_profile(
+    dir: &Path,
+    name: &str,
+    curves: &[CustomFanCurve],
+    overwrite: bool,
+) -> Result<PathBuf, String> {
+    let path = profile_path(dir, name)?;
+    let json = serde_json::to_string_pretty(curves).map_err(|error| error.to_string())?;
+    std::fs::create_dir_all(dir)
+        .map_err(|error| format!("cannot create {}: {error}", dir.display()))?;
+    let path = write_file(&path, &json, overwrite)?;
+    info!("Saved profile to {}", path.display());
+    Ok(path)
+}
+
+/// Read profile `name` from `dir`.
+pub fn load_profile(dir: &Path, name: &str) -> Result<Vec<CustomFanCurve>, String> {
+    let path = profile_path(dir, name)?;
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|error| format!("cannot read {}: {error}", path.display()))?;
+    serde_json::from_str(&contents)
+        .map_err(|error| format!("{} is not a curve profile: {error}", path.display()))
+}
+
+// ---------------------------------------------------------------------------
+// Remembered manual speeds
+// ---------------------------------------------------------------------------
+
+/// Manual fan speeds the GUI held when it last closed, and whether the user
+/// asked for them to be re-applied on the next start.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HeldSpeeds {
+    /// "Remember manual speeds" is ticked. When unset the maps are empty.
+    #[serde(default)]
+    pub remember: bool,
+    /// PWM value per fan id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub pwm: BTreeMap<String, u8>,
+    /// RPM target per fan id.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub rpm: BTreeMap<String, u32>,
+}
+
+/// The GUI's held-speeds state file, `held_speeds.json` beside the per-user
+/// config, or beside the executable when no user config dir is known.
+pub fn held_speeds_path() -> PathBuf {
+    user_config_path()
+        .unwrap_or_else(config_path)
+        .with_file_name("held_speeds.json")
+}
+
+/// Read the held speeds from `path`. A missing file means nothing is held;
+/// a malformed one is logged and ignored.
+pub fn load_held_speeds(path: &Path) -> HeldSpeeds {
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return HeldSpeeds::default();
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|error| {
+        warn!("Ignoring malformed {}: {error}", path.display());
+        HeldSpeeds::default()
+    })
+}
+
+/// Write `held` to `path`, creating its directory if needed.
+pub fn save_held_speeds(path: &Path, held: &HeldSpeeds) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(held).map_err(|error| error.to_string())?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .map_err(|error| format!("cannot create {}: {error}", dir.display()))?;
+    }
+    let path = write_file(path, &json, true)?;
+    info!("Saved held speeds to {}", path.display());
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // -- defaults and presets ------------------------------------------------
+
+    #[test]
+    fn parses_defaults_and_presets() {
+        let config: Config = serde_json::from_str(
+            r#"{
+                "poll_interval_secs": 3,
+                "backend": "lhm",
+                "presets": {"quiet": [{"fan_id": 0, "sensor_id": 3, "steps": [0,0,0,0,1,1,2,3,4,5]}]}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(config.poll_interval_secs, Some(3));
+        assert_eq!(config.backend.as_deref(), Some("lhm"));
+        assert_eq!(config.presets["quiet"][0].steps[9], 5);
+    }
+
+    #[test]
+    fn unset_defaults_are_not_saved() {
+        let json = serde_json::to_value(Config::default()).unwrap();
+        assert!(json.get("poll_interval_secs").is_none());
+        assert!(json.get("backend").is_none());
+        assert!(json.get("presets").is_none());
+    }
+
+    #[test]
+    fn invalid_key_names_the_offending_key() {
+        let merged = serde_json::json!({
+            "auto_smart_fan_mode": false,
+            "poll_interval_secs": "fast"
+        });
+        assert!(serde_json::from_value::<Config>(merged.clone()).is_err());
+        assert_eq!(
+            invalid_key(&merged).map(|(key, _)| key).as_deref(),
+            Some("poll_interval_secs")
+        );
+    }
+
+    #[test]
+    fn zero_poll_interval_is_invalid() {
+        let merged = serde_json::json!({ "poll_interval_secs": 0 });
+        assert!(serde_json::from_value::<Config>(merged.clone()).is_err());
+        assert_eq!(
+            invalid_key(&merged).map(|(key, _)| key).as_deref(),
+            Some("poll_interval_secs")
+        );
+        let config: Config = serde_json::from_value(serde_json::json!({})).unwrap();
+        assert_eq!(config.poll_interval_secs, None);
+    }
+
+    // -- log path ------------------------------------------------------------
+
+    #[test]
+    fn log_path_prefers_flag_then_env() {
+        let flag = Some(PathBuf::from("/var/log/flag.log"));
+        let env = Some(OsString::from("/var/log/env.log"));
+        assert_eq!(
+            resolve_log_path(flag, env.clone(), None),
+            PathBuf::from("/var/log/flag.log")
+        );
+        assert_eq!(
+            resolve_log_path(None, env, None),
+            PathBuf::from("/var/log/env.log")
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn log_path_defaults_to_state_home_then_tmp() {
+        assert_eq!(
+            resolve_log_path(None, Some(OsString::new()), Some("/home/u/.state".into())),
+            PathBuf::from("/home/u/.state/fancontrol/fancontrol.log")
+        );
+        assert_eq!(
+            resolve_log_path(None, None, None),
+            std::env::temp_dir().join("fancontrol.log")
+        );
+    }
+
     #[test]
     fn default_config_is_empty_curves() {
         let config = Config::default();
@@ -95,6 +610,8 @@ mod tests {
                 steps: [1, 1, 1, 1, 2, 4, 6, 7, 8, 10],
             }],
             auto_smart_fan_mode: true,
+            powershell_timeout_secs: 5,
+            ..Config::default()
         };
         let json = serde_json::to_string_pretty(&config).unwrap();
         let loaded: Config = serde_json::from_str(&json).unwrap();
@@ -111,6 +628,8 @@ mod tests {
         let config: Config = serde_json::from_str("{}").unwrap();
         assert!(config.custom_curves.is_empty());
         assert!(config.auto_smart_fan_mode);
+        assert_eq!(config.powershell_timeout_secs, 5);
+        assert_eq!(config.powershell_read_attempts, 3);
     }
 
     #[test]
@@ -138,6 +657,8 @@ mod tests {
                 },
             ],
             auto_smart_fan_mode: false,
+            powershell_timeout_secs: 12,
+            ..Config::default()
         };
         let json = serde_json::to_string_pretty(&config).unwrap();
         std::fs::write(&path, json).unwrap();
@@ -147,5 +668,230 @@ mod tests {
         assert_eq!(loaded.custom_curves[0], config.custom_curves[0]);
         assert_eq!(loaded.custom_curves[1], config.custom_curves[1]);
         assert!(!loaded.auto_smart_fan_mode);
+        assert_eq!(loaded.powershell_timeout_secs, 12);
+    }
+
+    // -- config layers -----------------------------------------------------
+
+    fn write_layer(dir: &Path, name: &str, json: &str) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, json).unwrap();
+        path
+    }
+
+    #[test]
+    fn merge_json_deep_merges_objects_and_replaces_arrays() {
+        let mut base = serde_json::json!({"a": {"x": 1, "y": 2}, "list": [1, 2]});
+        merge_json(
+            &mut base,
+            serde_json::json!({"a": {"y": 3}, "list": [9], "b": true}),
+        );
+        assert_eq!(
+            base,
+            serde_json::json!({"a": {"x": 1, "y": 3}, "list": [9], "b": true})
+        );
+    }
+
+    #[test]
+    fn resolve_layers_higher_layer_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let system = write_layer(
+            dir.path(),
+            "system.json",
+            r#"{"auto_smart_fan_mode": false,
+                "custom_curves": [{"fan_id": 0, "sensor_id": 3, "steps": [1,1,1,1,1,1,1,1,3,5]}]}"#,
+        );
+        let user = write_layer(dir.path(), "user.json", r#"{"auto_smart_fan_mode": true}"#);
+        let (config, statuses) = resolve_layers(&[("system", system), ("user", user)]);
+        assert!(config.auto_smart_fan_mode);
+        assert_eq!(config.custom_curves.len(), 1);
+        assert!(statuses.iter().all(|s| matches!(s, LayerStatus::Loaded)));
+    }
+
+    #[test]
+    fn resolve_layers_skips_missing_and_malformed() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = write_layer(dir.path(), "base.json", r#"{"auto_smart_fan_mode": false}"#);
+        let broken = write_layer(dir.path(), "broken.json", "{not json");
+        let missing = dir.path().join("missing.json");
+        let (config, statuses) =
+            resolve_layers(&[("system", base), ("user", broken), ("local", missing)]);
+        assert!(!config.auto_smart_fan_mode);
+        assert!(matches!(statuses[1], LayerStatus::Malformed(_)));
+        assert!(matches!(statuses[2], LayerStatus::NotFound));
+    }
+
+    #[test]
+    fn resolve_layers_drops_only_the_invalid_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let local = write_layer(
+            dir.path(),
+            "local.json",
+            r#"{"backend": "lhm",
+                "custom_curves": [{"fan_id": 0, "sensor_id": 3, "steps": [1,1,1,1,1,1,1,1,3,5]}]}"#,
+        );
+        let cli = write_layer(dir.path(), "cli.json", r#"{"poll_interval_secs": "fast"}"#);
+        let (config, _) = resolve_layers(&[("local", local), ("--config", cli)]);
+        assert_eq!(config.custom_curves.len(), 1);
+        assert_eq!(config.backend.as_deref(), Some("lhm"));
+        assert_eq!(config.poll_interval_secs, None);
+    }
+
+    // -- Curve profiles ----------------------------------------------------
+
+    #[test]
+    fn profile_roundtrip_and_listing() {
+        let dir = tempfile::tempdir().unwrap();
+        let profiles = dir.path().join("profiles");
+        assert!(list_profiles(&profiles).is_empty());
+
+        let curves = vec![CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [1, 1, 1, 1, 2, 4, 6, 7, 8, 10],
+        }];
+        save_profile(&profiles, "quiet", &curves, false).unwrap();
+        save_profile(&profiles, "gaming", &curves, false).unwrap();
+
+        assert_eq!(list_profiles(&profiles), vec!["gaming", "quiet"]);
+        assert_eq!(load_profile(&profiles, "quiet").unwrap(), curves);
+    }
+
+    #[test]
+    fn profile_file_is_a_restore_curves_array() {
+        let dir = tempfile::tempdir().unwrap();
+        let curves = vec![CustomFanCurve {
+            fan_id: 1,
+            sensor_id: 4,
+            steps: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        }];
+        let path = save_profile(dir.path(), "p", &curves, false).unwrap();
+        let raw: Value = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(raw[0]["sensor_id"], 4);
+    }
+
+    #[test]
+    fn load_profile_reports_missing_and_corrupt_files() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_profile(dir.path(), "absent")
+            .unwrap_err()
+            .contains("cannot read"));
+
+        write_layer(dir.path(), "broken.json", "{not json");
+        assert!(load_profile(dir.path(), "broken")
+            .unwrap_err()
+            .contains("not a curve profile"));
+    }
+
+    #[test]
+    fn existing_profiles_need_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut curves = vec![CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        }];
+        save_profile(dir.path(), "p", &curves, false).unwrap();
+        curves[0].steps[0] = 1;
+
+        let error = save_profile(dir.path(), "p", &curves, false).unwrap_err();
+        assert!(error.ends_with("p.json already exists"), "{error}");
+        assert_eq!(load_profile(dir.path(), "p").unwrap()[0].steps[0], 0);
+
+        save_profile(dir.path(), "p", &curves, true).unwrap();
+        assert_eq!(load_profile(dir.path(), "p").unwrap(), curves);
+    }
+
+    #[test]
+    fn write_file_reports_an_absolute_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_file(&dir.path().join("a.json"), "[]", false).unwrap();
+        assert!(path.is_absolute());
+        assert!(write_file(&path, "{}", false).is_err());
+        write_file(&path, "{}", true).unwrap();
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "{}");
+    }
+
+    #[test]
+    fn profile_names_cannot_escape_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(save_profile(dir.path(), "", &[], false).is_err());
+        assert!(save_profile(dir.path(), "../evil", &[], false).is_err());
+        assert!(save_profile(dir.path(), ".hidden", &[], false).is_err());
+    }
+
+    // -- Remembered manual speeds ------------------------------------------
+
+    #[test]
+    fn saving_custom_curves_keeps_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fancontrol.json");
+        std::fs::write(
+            &path,
+            r#"{"backend": "lhm", "presets": {"quiet": [{"fan_id": 0, "sensor_id": 3, "steps": [0, 0, 0, 0, 1, 1, 2, 3, 4, 5]}]}}"#,
+        )
+        .unwrap();
+        let curve = CustomFanCurve {
+            fan_id: 1,
+            sensor_id: 4,
+            steps: [1, 1, 1, 1, 2, 4, 6, 7, 8, 10],
+        };
+
+        assert_eq!(save_custom_curves_to(&path, vec![curve]).unwrap(), 1);
+
+        let saved = load_local_config_at(&path);
+        assert_eq!(saved.custom_curves.len(), 1);
+        assert!(saved.presets.contains_key("quiet"));
+        assert_eq!(saved.backend.as_deref(), Some("lhm"));
+    }
+
+    #[test]
+    fn saving_custom_curves_keeps_pwm_calibration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fancontrol.json");
+        std::fs::write(
+            &path,
+            r#"{"pwm_calibration": {"fan0": [{"pwm": 51, "rpm": 3000}, {"pwm": 153, "rpm": 4200}]}}"#,
+        )
+        .unwrap();
+
+        save_custom_curves_to(&path, Vec::new()).unwrap();
+
+        let saved = load_local_config_at(&path);
+        assert_eq!(
+            saved.pwm_calibration["fan0"],
+            [
+                CalibrationPoint { pwm: 51, rpm: 3000 },
+                CalibrationPoint {
+                    pwm: 153,
+                    rpm: 4200
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn held_speeds_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state").join("held_speeds.json");
+        assert_eq!(load_held_speeds(&path), HeldSpeeds::default());
+
+        let held = HeldSpeeds {
+            remember: true,
+            pwm: BTreeMap::from([("fan0".to_string(), 200)]),
+            rpm: BTreeMap::from([("fan1".to_string(), 3000)]),
+        };
+        save_held_speeds(&path, &held).unwrap();
+        assert_eq!(load_held_speeds(&path), held);
+    }
+
+    #[test]
+    fn malformed_held_speeds_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        write_layer(dir.path(), "held_speeds.json", r#"{"pwm": {"fan0": 999}}"#);
+        assert_eq!(
+            load_held_speeds(&dir.path().join("held_speeds.json")),
+            HeldSpeeds::default()
+        );
     }
 }
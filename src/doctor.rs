@@ -0,0 +1,199 @@
+//! `fancontrol doctor`: checks the environment the backends rely on and
+//! summarises what this machine supports, so a broken setup reports its
+//! probable cause instead of failing on the first command.
+
+use anyhow::Result;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::platform::{self, FanController};
+
+/// Outcome of one environment or capability check.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Check {
+    /// What was checked, e.g. "hwmon directory" or "set-pwm".
+    pub name: String,
+    pub ok: bool,
+    /// What was found; on failure, the probable cause and how to fix it.
+    pub detail: String,
+}
+
+impl Check {
+    pub fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: true,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn fail(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            ok: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// What `controller` can do here: read fans, set their PWM, and read EC
+/// curve tables. Only reads; nothing is written to the hardware.
+pub fn capabilities(controller: &dyn FanController) -> Vec<Check> {
+    let fans = controller.discover();
+    let read = match &fans {
+        Ok(fans) if fans.is_empty() => Check::fail("read", "no fans detected"),
+        Ok(fans) => Check::pass("read", format!("{} fans", fans.len())),
+        Err(error) => Check::fail("read", error.to_string()),
+    };
+
+    let set_pwm = match &fans {
+        Ok(fans) if !fans.is_empty() => {
+            let controllable = fans.iter().filter(|fan| fan.controllable).count();
+            match controller.check_privileges() {
+                Err(error) => Check::fail("set-pwm", error.to_string()),
+                Ok(()) if controllable == 0 => Check::fail("set-pwm", controller.read_only_hint()),
+                Ok(()) => Check::pass(
+                    "set-pwm",
+                    format!("{controllable} of {} fans controllable", fans.len()),
+                ),
+            }
+        }
+        _ => Check::fail("set-pwm", "no fans to control"),
+    };
+
+    let curves = match controller.get_fan_curves() {
+        Ok(curves) if curves.is_empty() => {
+            Check::fail("curves", "the firmware reports no curve tables")
+        }
+        Ok(curves) => Check::pass("curves", format!("{} EC curve tables", curves.len())),
+        Err(error) => Check::fail("curves", error.to_string()),
+    };
+
+    vec![read, set_pwm, curves]
+}
+
+/// Run every check and print the findings.
+pub fn run(json_output: bool) -> Result<()> {
+    let environment = platform::environment_checks();
+    let capabilities = match platform::create_controller() {
+        Ok(controller) => capabilities(&*controller),
+        Err(error) => vec![Check::fail("backend", error.to_string())],
+    };
+
+    if json_output {
+        let report = json!({
+            "environment": environment,
+            "capabilities": capabilities,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("Environment:");
+    print_checks(&environment, "ok", "FAIL");
+    println!();
+    println!("Capabilities:");
+    print_checks(&capabilities, "yes", "no");
+    println!();
+
+    let problems = environment.iter().filter(|check| !check.ok).count();
+    match problems {
+        0 => println!("No environment problems found"),
+        1 => println!("1 environment problem found"),
+        n => println!("{n} environment problems found"),
+    }
+    Ok(())
+}
+
+/// One aligned line per check: status, name, detail.
+fn print_checks(checks: &[Check], ok: &str, failed: &str) {
+    let width = checks
+        .iter()
+        .map(|check| check.name.len())
+        .max()
+        .unwrap_or(0);
+    for check in checks {
+        let status = if check.ok { ok } else { failed };
+        println!("  {:<4}  {:<width$}  {}", status, check.name, check.detail);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::FanControlError;
+    use crate::fan::{Fan, FanCurve};
+
+    fn fan(id: &str, controllable: bool) -> Fan {
+        Fan {
+            id: id.to_string(),
+            label: id.to_string(),
+            speed_rpm: 2000,
+            temperature: None,
+            pwm: None,
+            pwm_mode: None,
+            controllable,
+            min_rpm: None,
+            max_rpm: None,
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        }
+    }
+
+    /// Two fans, one controllable, optionally without write access.
+    struct Fake {
+        privileged: bool,
+    }
+
+    impl FanController for Fake {
+        fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+            Ok(vec![fan("fan0", true), fan("fan1", false)])
+        }
+
+        fn get_speed(&self, _fan_id: &str) -> Result<u32, FanControlError> {
+            Ok(2000)
+        }
+
+        fn check_privileges(&self) -> Result<(), FanControlError> {
+            if self.privileged {
+                Ok(())
+            } else {
+                Err(FanControlError::PermissionDenied("run as root".into()))
+            }
+        }
+
+        fn set_pwm(&self, _fan_id: &str, _pwm: u8) -> Result<(), FanControlError> {
+            Ok(())
+        }
+
+        fn get_fan_curves(&self) -> Result<Vec<FanCurve>, FanControlError> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn capabilities_summarise_read_write_and_curves() {
+        let checks = capabilities(&Fake { privileged: true });
+        assert_eq!(
+            checks,
+            [
+                Check::pass("read", "2 fans"),
+                Check::pass("set-pwm", "1 of 2 fans controllable"),
+                Check::fail("curves", "the firmware reports no curve tables"),
+            ]
+        );
+    }
+
+    #[test]
+    fn capabilities_blame_missing_privileges() {
+        let checks = capabilities(&Fake { privileged: false });
+        assert!(!checks[1].ok);
+        assert!(
+            checks[1].detail.contains("run as root"),
+            "{}",
+            checks[1].detail
+        );
+    }
+}
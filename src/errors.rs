@@ -5,9 +5,24 @@ pub enum FanControlError {
     #[error("fan '{0}' not found")]
     FanNotFound(String),
 
+    #[error("'{input}' matches several fans: {candidates}; pass one of their ids")]
+    AmbiguousFan { input: String, candidates: String },
+
     #[error("fan '{0}' is not controllable")]
     NotControllable(String),
 
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    #[error("PWM {value} for fan '{fan_id}' is below the driver's minimum of {min}")]
+    PwmOutOfRange { fan_id: String, value: u8, min: u8 },
+
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    #[error("SmartFanMode must be Custom (255) to write fan curves, but the EC stayed at {0}")]
+    SmartFanModeRequired(u32),
+
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    #[error("PowerShell did not answer within {0:?}")]
+    Timeout(std::time::Duration),
+
     #[error("permission denied: {0}")]
     PermissionDenied(String),
 
@@ -17,3 +32,148 @@ pub enum FanControlError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 }
+
+impl FanControlError {
+    /// Stable name of the variant, for scripts that branch on the kind of
+    /// failure in `--json` error output instead of matching the message.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FanControlError::FanNotFound(_) => "FanNotFound",
+            FanControlError::AmbiguousFan { .. } => "AmbiguousFan",
+            FanControlError::NotControllable(_) => "NotControllable",
+            FanControlError::PwmOutOfRange { .. } => "PwmOutOfRange",
+            FanControlError::SmartFanModeRequired(_) => "SmartFanModeRequired",
+            FanControlError::Timeout(_) => "Timeout",
+            FanControlError::PermissionDenied(_) => "PermissionDenied",
+            FanControlError::Platform(_) => "Platform",
+            FanControlError::Io(_) => "Io",
+        }
+    }
+}
+
+/// Kind reported for failures that are not a [`FanControlError`], such as a
+/// malformed input file.
+pub const OTHER_ERROR_KIND: &str = "Other";
+
+/// `error` as `{"error": {"kind", "message"}}`, printed to stderr under
+/// `--json`. The kind comes from the first [`FanControlError`] in the
+/// cause chain; the message is the whole chain, as printed without `--json`.
+pub fn error_json(error: &anyhow::Error) -> serde_json::Value {
+    let kind = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<FanControlError>())
+        .map_or(OTHER_ERROR_KIND, FanControlError::kind);
+    serde_json::json!({
+        "error": {
+            "kind": kind,
+            "message": format!("{error:#}"),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn kind_of(error: FanControlError) -> String {
+        error_json(&error.into())["error"]["kind"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn fan_not_found_kind() {
+        assert_eq!(
+            kind_of(FanControlError::FanNotFound("fan9".into())),
+            "FanNotFound"
+        );
+    }
+
+    #[test]
+    fn ambiguous_fan_kind() {
+        let error = FanControlError::AmbiguousFan {
+            input: "fan".into(),
+            candidates: "fan0, fan1".into(),
+        };
+        assert_eq!(kind_of(error), "AmbiguousFan");
+    }
+
+    #[test]
+    fn not_controllable_kind() {
+        assert_eq!(
+            kind_of(FanControlError::NotControllable("fan1".into())),
+            "NotControllable"
+        );
+    }
+
+    #[test]
+    fn pwm_out_of_range_kind() {
+        let error = FanControlError::PwmOutOfRange {
+            fan_id: "fan0".into(),
+            value: 10,
+            min: 30,
+        };
+        assert_eq!(kind_of(error), "PwmOutOfRange");
+    }
+
+    #[test]
+    fn smart_fan_mode_required_kind() {
+        assert_eq!(
+            kind_of(FanControlError::SmartFanModeRequired(2)),
+            "SmartFanModeRequired"
+        );
+    }
+
+    #[test]
+    fn timeout_kind() {
+        assert_eq!(
+            kind_of(FanControlError::Timeout(Duration::from_secs(30))),
+            "Timeout"
+        );
+    }
+
+    #[test]
+    fn permission_denied_kind() {
+        assert_eq!(
+            kind_of(FanControlError::PermissionDenied("run as root".into())),
+            "PermissionDenied"
+        );
+    }
+
+    #[test]
+    fn platform_kind() {
+        assert_eq!(
+            kind_of(FanControlError::Platform("no WMI".into())),
+            "Platform"
+        );
+    }
+
+    #[test]
+    fn io_kind() {
+        let error = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert_eq!(kind_of(FanControlError::Io(error)), "Io");
+    }
+
+    #[test]
+    fn error_json_finds_the_kind_behind_context() {
+        let error = anyhow::Error::from(FanControlError::NotControllable("fan1".into()))
+            .context("set --all failed");
+        assert_eq!(
+            error_json(&error),
+            serde_json::json!({
+                "error": {
+                    "kind": "NotControllable",
+                    "message": "set --all failed: fan 'fan1' is not controllable",
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn other_errors_have_kind_other() {
+        let error = anyhow::anyhow!("curves.json is not a curve file");
+        assert_eq!(error_json(&error)["error"]["kind"], OTHER_ERROR_KIND);
+    }
+}
@@ -1,10 +1,15 @@
 // put id:"fan_structs", label:"Fan/FanCurve Data Structs", node_type:"database"
 
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::errors::FanControlError;
 
 /// A single temperature→RPM point in a fan curve.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FanCurvePoint {
     /// Temperature threshold in degrees Celsius.
     pub temperature: u32,
@@ -16,7 +21,11 @@ pub struct FanCurvePoint {
 ///
 /// Each curve binds one fan to one sensor. The EC takes the maximum speed
 /// demanded across all sensor curves for a given fan.
-#[derive(Debug, Clone, Serialize)]
+///
+/// There is no `hysteresis` field: the EC evaluates these tables itself and
+/// the firmware has nowhere to store one, so fancontrol never sees the
+/// readings to apply it to. Software curves ([`PwmCurve`]) carry it instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FanCurve {
     pub fan_id: u32,
     pub sensor_id: u32,
@@ -26,10 +35,14 @@ pub struct FanCurve {
     pub max_temp: u32,
     pub points: Vec<FanCurvePoint>,
     pub active: bool,
+    /// What the sensor measures ("CPU", "GPU") when the backend can tell;
+    /// see [`label_sensors`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensor_label: Option<String>,
 }
 
 /// Represents a single fan discovered on the system.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Fan {
     /// Unique identifier (e.g. "hwmon2/fan1" on Linux, WMI instance path on Windows)
     pub id: String,
@@ -37,18 +50,32 @@ pub struct Fan {
     pub label: String,
     /// Current speed in RPM
     pub speed_rpm: u32,
+    /// Temperature of the sensor driving this fan in °C (if known)
+    #[serde(default)]
+    pub temperature: Option<u32>,
     /// PWM duty cycle 0–255 (if controllable)
     pub pwm: Option<u8>,
+    /// Raw `pwm{N}_enable` mode on Linux: 0 = no control (full speed),
+    /// 1 = manual, 2 and above = automatic. `None` when not reported.
+    #[serde(default)]
+    pub pwm_mode: Option<u8>,
     /// Whether this fan supports speed control
     pub controllable: bool,
     /// Minimum RPM from fan table data (if available).
     pub min_rpm: Option<u32>,
     /// Maximum RPM from fan table data (if available).
     pub max_rpm: Option<u32>,
+    /// Speed the driver is steering towards, from `fan{N}_target` on Linux.
+    #[serde(default)]
+    pub target_rpm: Option<u32>,
     /// Fan curves from EC table data (if available).
     pub curves: Vec<FanCurve>,
     /// Whether full speed mode is currently active (Lenovo-specific).
     pub full_speed_active: bool,
+    /// Reads 0 RPM although it is being driven: a likely stall or failed
+    /// fan (see [`is_stalled`]).
+    #[serde(default)]
+    pub stalled: bool,
 }
 
 /// A user-defined custom fan curve to write to the EC via Fan_Set_Table.
@@ -69,17 +96,2738 @@ pub struct CustomFanCurve {
     pub steps: [u8; 10],
 }
 
-impl fmt::Display for Fan {
+/// Lenovo SmartFanMode values with their names.
+pub const SMART_FAN_MODES: [(u32, &str); 4] = [
+    (1, "Quiet"),
+    (2, "Balanced"),
+    (3, "Performance"),
+    (SMART_FAN_MODE_CUSTOM, "Custom"),
+];
+
+/// SmartFanMode the EC must be in before `Fan_Set_Table` takes effect.
+pub const SMART_FAN_MODE_CUSTOM: u32 = 255;
+
+/// Name of a SmartFanMode value, or `None` for values not in
+/// `SMART_FAN_MODES`.
+pub fn smart_fan_mode_name(mode: u32) -> Option<&'static str> {
+    SMART_FAN_MODES
+        .iter()
+        .find(|(value, _)| *value == mode)
+        .map(|(_, name)| *name)
+}
+
+/// Vendor thermal profile. On Lenovo these are SmartFanMode 1–3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThermalMode {
+    Quiet,
+    Balanced,
+    Performance,
+}
+
+impl ThermalMode {
+    /// The Lenovo SmartFanMode value for this profile.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    pub fn smart_fan_mode(self) -> u32 {
+        match self {
+            ThermalMode::Quiet => 1,
+            ThermalMode::Balanced => 2,
+            ThermalMode::Performance => 3,
+        }
+    }
+}
+
+impl FromStr for ThermalMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "quiet" => Ok(ThermalMode::Quiet),
+            "balanced" => Ok(ThermalMode::Balanced),
+            "performance" => Ok(ThermalMode::Performance),
+            _ => Err(format!(
+                "invalid thermal mode '{s}': expected quiet, balanced, or performance"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ThermalMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let control_status = if self.controllable {
-            "controllable"
-        } else {
-            "read-only"
+        let name = match self {
+            ThermalMode::Quiet => "quiet",
+            ThermalMode::Balanced => "balanced",
+            ThermalMode::Performance => "performance",
         };
-        write!(
-            f,
-            "{}: {} RPM [{}]",
-            self.label, self.speed_rpm, control_status
-        )
+        f.write_str(name)
+    }
+}
+
+/// Maximum allowed value for a speed step index.
+const MAX_STEP_VALUE: u8 = 10;
+
+/// Default for `--min-high-rpm-percent`: the top step must reach half of
+/// the 0–10 scale, as in LenovoLegionToolkit V2.
+pub const DEFAULT_MIN_HIGH_TEMP_RATIO: f32 = 0.5;
+
+/// High-temperature safety floors as `(step index, minimum)` pairs for a
+/// policy `ratio`: step 9 must reach `ratio` of the scale and step 8 three
+/// fifths of that, each rounded to the nearest step. The default ratio gives
+/// the LenovoLegionToolkit V2 floors of 3 and 5.
+pub fn high_temp_floors(ratio: f32) -> [(usize, u8); 2] {
+    let floor = |fraction: f32| (fraction * ratio * f32::from(MAX_STEP_VALUE)).round() as u8;
+    [(8, floor(0.6)), (9, floor(1.0))]
+}
+
+/// Temperature above which a curve should drive the fan near full speed.
+const HIGH_TEMP_WARN_CELSIUS: u32 = 90;
+
+/// Share of the fan's maximum speed, in percent, a curve should reach above
+/// [`HIGH_TEMP_WARN_CELSIUS`].
+const HIGH_TEMP_WARN_SPEED_PERCENT: u32 = 90;
+
+/// Advisory findings for a curve that passed validation. Warnings never
+/// block a write; callers log and show them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CurveValidation {
+    pub warnings: Vec<String>,
+}
+
+/// Validate a custom curve's step values, enforcing safety constraints.
+///
+/// Rules:
+///   - All steps must be in range 0–10
+///   - Steps must be non-decreasing (no "death valley" curves)
+///   - Step 8 must be ≥ 3 (high-temp safety minimum)
+///   - Step 9 must be ≥ 5 (max-temp safety minimum)
+///
+/// Safety minimums match LenovoLegionToolkit V2: `[1,1,1,1,1,1,1,1,3,5]`.
+pub fn validate_custom_curve(curve: &CustomFanCurve) -> Result<CurveValidation, FanControlError> {
+    validate_custom_curve_with_policy(curve, DEFAULT_MIN_HIGH_TEMP_RATIO, None)
+}
+
+/// [`validate_custom_curve`] with the high-temperature floors scaled to
+/// `min_high_temp_ratio` (0.0–1.0) instead of the default 0.5; see
+/// [`high_temp_floors`]. Lower ratios let the fan stay slower at the top of
+/// the curve, so a sustained load can run hotter before the EC catches up.
+///
+/// With the EC `table` the steps index, the RPM curve they produce is also
+/// checked: a curve whose top point stays below 90% of the table's
+/// `max_speed`, although it is what the EC runs above 90°C, gets a warning.
+pub fn validate_custom_curve_with_policy(
+    curve: &CustomFanCurve,
+    min_high_temp_ratio: f32,
+    table: Option<&FanCurve>,
+) -> Result<CurveValidation, FanControlError> {
+    if !(0.0..=1.0).contains(&min_high_temp_ratio) {
+        return Err(FanControlError::Platform(format!(
+            "high-temperature minimum {min_high_temp_ratio} is outside 0.0–1.0"
+        )));
+    }
+
+    for (i, &step) in curve.steps.iter().enumerate() {
+        if step > MAX_STEP_VALUE {
+            return Err(FanControlError::Platform(format!(
+                "step {i} value {step} exceeds maximum {MAX_STEP_VALUE}"
+            )));
+        }
+    }
+
+    // Non-decreasing constraint
+    for i in 1..10 {
+        if curve.steps[i] < curve.steps[i - 1] {
+            return Err(FanControlError::Platform(format!(
+                "steps must be non-decreasing: step[{i}]={} < step[{}]={}",
+                curve.steps[i],
+                i - 1,
+                curve.steps[i - 1]
+            )));
+        }
+    }
+
+    // High-temperature safety minimums
+    for (i, floor) in high_temp_floors(min_high_temp_ratio) {
+        if curve.steps[i] < floor {
+            let what = if i == 9 { "max temp" } else { "high temp" };
+            return Err(FanControlError::Platform(format!(
+                "step {i} ({what}) must be >= {floor} for safety, got {}",
+                curve.steps[i]
+            )));
+        }
+    }
+
+    let mut validation = CurveValidation::default();
+    if let Some(warning) = table.and_then(|table| high_temp_speed_warning(table, &curve.steps)) {
+        validation.warnings.push(warning);
+    }
+    Ok(validation)
+}
+
+/// Warn when `steps` on the EC `table` never reach
+/// [`HIGH_TEMP_WARN_SPEED_PERCENT`] of the table's `max_speed` above
+/// [`HIGH_TEMP_WARN_CELSIUS`]. The EC holds the top point's speed at
+/// temperatures past it, so the top point is the fastest the fan gets there.
+fn high_temp_speed_warning(table: &FanCurve, steps: &[u8; 10]) -> Option<String> {
+    let curve = build_curve_from_points(table, steps);
+    let top = curve.points.last()?;
+    if table.max_speed == 0 || top.fan_speed * 100 >= table.max_speed * HIGH_TEMP_WARN_SPEED_PERCENT
+    {
+        return None;
+    }
+    Some(format!(
+        "curve for fan {} sensor {} tops out at {} RPM ({}% of the fan's {} RPM maximum) \
+         at {}\u{00B0}C and above; above {HIGH_TEMP_WARN_CELSIUS}\u{00B0}C it should \
+         reach at least {HIGH_TEMP_WARN_SPEED_PERCENT}%",
+        table.fan_id,
+        table.sensor_id,
+        top.fan_speed,
+        top.fan_speed * 100 / table.max_speed,
+        table.max_speed,
+        top.temperature,
+    ))
+}
+
+/// One step changed by [`auto_fix_steps`], for before/after reporting.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepFix {
+    pub index: usize,
+    pub original: u8,
+    pub adjusted: u8,
+    /// Why the step changed, in the order the fixes were applied.
+    pub notes: Vec<String>,
+}
+
+/// Adjust steps so the curve passes `validate_custom_curve_with_policy`
+/// under `min_high_temp_ratio`.
+///
+/// Fixes are applied in order: values above the maximum are lowered, the
+/// high-temperature steps are raised to their safety floors, then each step
+/// is raised to at least its predecessor. Returns the fixed steps and one
+/// entry per changed step explaining what happened.
+pub fn auto_fix_steps(steps: [u8; 10], min_high_temp_ratio: f32) -> ([u8; 10], Vec<StepFix>) {
+    let mut fixed = steps;
+    let mut notes: [Vec<String>; 10] = Default::default();
+
+    for (i, step) in fixed.iter_mut().enumerate() {
+        if *step > MAX_STEP_VALUE {
+            *step = MAX_STEP_VALUE;
+            notes[i].push(format!("lowered to maximum {MAX_STEP_VALUE}"));
+        }
+    }
+    for (i, floor) in high_temp_floors(min_high_temp_ratio) {
+        if fixed[i] < floor {
+            fixed[i] = floor;
+            notes[i].push(format!("raised to safety floor {floor}"));
+        }
+    }
+    for i in 1..10 {
+        if fixed[i] < fixed[i - 1] {
+            fixed[i] = fixed[i - 1];
+            notes[i].push(format!("raised to {} to stay non-decreasing", fixed[i]));
+        }
+    }
+
+    let fixes = (0..10)
+        .filter(|&i| fixed[i] != steps[i])
+        .map(|i| StepFix {
+            index: i,
+            original: steps[i],
+            adjusted: fixed[i],
+            notes: std::mem::take(&mut notes[i]),
+        })
+        .collect();
+    (fixed, fixes)
+}
+
+/// Resample `steps` through `points` evenly spaced anchors (2–10, clamped)
+/// and interpolate linearly between them, rounding to the nearest step, so
+/// a sparse curve ramps gently instead of jumping between a few levels.
+///
+/// The anchors sit on the original curve, itself linear between steps, and
+/// the first and last anchors fall on steps 0 and 9, which are kept exactly.
+/// With 10 anchors the steps come back unchanged. Interpolation and rounding
+/// keep a non-decreasing curve non-decreasing; the high-temperature steps
+/// are never lowered below their safety floors under `min_high_temp_ratio`,
+/// so a curve that passed validation still passes it after smoothing.
+pub fn smooth_steps(steps: [u8; 10], points: usize, min_high_temp_ratio: f32) -> [u8; 10] {
+    let points = points.clamp(2, 10);
+    let last = (steps.len() - 1) as f64;
+    let at = |curve: &[(f64, f64)], x: f64| {
+        let upper = curve
+            .iter()
+            .position(|&(px, _)| px >= x)
+            .unwrap_or(curve.len() - 1)
+            .max(1);
+        let ((x0, y0), (x1, y1)) = (curve[upper - 1], curve[upper]);
+        y0 + (x - x0) / (x1 - x0) * (y1 - y0)
+    };
+
+    let original: Vec<(f64, f64)> = steps
+        .iter()
+        .enumerate()
+        .map(|(i, &step)| (i as f64, f64::from(step)))
+        .collect();
+    let anchors: Vec<(f64, f64)> = (0..points)
+        .map(|k| {
+            let x = last * k as f64 / (points - 1) as f64;
+            (x, at(&original, x))
+        })
+        .collect();
+
+    let mut smoothed = steps;
+    for (i, step) in smoothed.iter_mut().enumerate() {
+        *step = at(&anchors, i as f64).round() as u8;
+    }
+    for (i, floor) in high_temp_floors(min_high_temp_ratio) {
+        smoothed[i] = smoothed[i].max(floor.min(steps[i]));
+    }
+    smoothed
+}
+
+/// `steps` in ascending order, for curves typed hottest-first or otherwise
+/// out of order. Each step belongs to a fixed table temperature, and a valid
+/// curve never drops as the temperature rises, so ascending is the only
+/// order in which these values pass validation. Repeated values are kept;
+/// they are flat stretches, not duplicate points.
+pub fn sort_steps(steps: [u8; 10]) -> [u8; 10] {
+    let mut sorted = steps;
+    sorted.sort_unstable();
+    sorted
+}
+
+/// Step values below this index count as "barely spinning" for the advisory
+/// invert check (30% of the 0–10 step scale).
+const QUIET_STEP_THRESHOLD: u8 = 3;
+
+impl CustomFanCurve {
+    /// Advisory check for curves that are silent across their whole range.
+    ///
+    /// Flags a curve whose steps all stay below 30% of the scale, with the two
+    /// high-temperature steps left at their safety floors (3 and 5). That shape
+    /// passes validation but almost always means the steps were entered
+    /// backwards or for the wrong sensor. A curve that ramps above the
+    /// threshold anywhere is treated as legitimately quiet.
+    pub fn quiet_curve_warning(&self) -> Option<String> {
+        let ramps_up = self.steps[..8]
+            .iter()
+            .any(|&step| step >= QUIET_STEP_THRESHOLD)
+            || self.steps[8] > 3
+            || self.steps[9] > 5;
+        if ramps_up {
+            return None;
+        }
+        Some(format!(
+            "curve for fan {} sensor {} never rises above the safety floor \
+             (steps {:?}); the fan will barely respond to temperature",
+            self.fan_id, self.sensor_id, self.steps
+        ))
+    }
+}
+
+/// Fan ID of the GPU fan on Lenovo V1 hardware.
+const GPU_FAN_ID: u32 = 1;
+/// Fan ID of the CPU fan on Lenovo V1 hardware.
+const CPU_FAN_ID: u32 = 0;
+
+/// A sensor given either by numeric ID or by symbolic name (`gpu`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensorRef {
+    Id(u32),
+    /// The discrete GPU sensor, resolved from the EC curve table.
+    Gpu,
+}
+
+impl FromStr for SensorRef {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("gpu") {
+            return Ok(SensorRef::Gpu);
+        }
+        s.parse::<u32>()
+            .map(SensorRef::Id)
+            .map_err(|_| format!("invalid sensor '{s}': expected a numeric ID or 'gpu'"))
+    }
+}
+
+/// Current temperature of one sensor, labelled by what it measures.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensorTemperature {
+    pub sensor_id: u32,
+    /// "CPU", "GPU", or "Sensor N" when the role is unknown.
+    pub label: String,
+    /// Degrees Celsius.
+    pub temperature: u32,
+}
+
+/// Header of [`curves_csv`].
+pub const CURVES_CSV_HEADER: &str = "fan_id,sensor_id,active,temp,rpm";
+
+/// `curves` as CSV rows under [`CURVES_CSV_HEADER`], one per curve point,
+/// for `table --format csv`.
+pub fn curves_csv(curves: &[FanCurve]) -> Vec<String> {
+    let rows = curves.iter().flat_map(|curve| {
+        curve.points.iter().map(|point| {
+            format!(
+                "{},{},{},{},{}",
+                curve.fan_id, curve.sensor_id, curve.active, point.temperature, point.fan_speed
+            )
+        })
+    });
+    std::iter::once(CURVES_CSV_HEADER.to_string())
+        .chain(rows)
+        .collect()
+}
+
+/// `bytes` as hex dump lines of 16 bytes with their offset, e.g.
+/// "0010: 05 00 06 00".
+pub fn hex_dump(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .chunks(16)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let hex: Vec<String> = chunk.iter().map(|byte| format!("{byte:02X}")).collect();
+            format!("{:04X}: {}", row * 16, hex.join(" "))
+        })
+        .collect()
+}
+
+/// Pick the fan `input` names, so commands need not be given backend ids:
+/// the exact id, the fan's position in `fans` ("0", "1", ...), or a
+/// case-insensitive label match where every word of `input` is a word of
+/// the label ("cpu" for "CPU Fan" or "nct6798: CPU Fan").
+pub fn match_fan_id(fans: &[Fan], input: &str) -> Result<String, FanControlError> {
+    if let Some(fan) = fans.iter().find(|fan| fan.id == input) {
+        return Ok(fan.id.clone());
+    }
+    if let Ok(index) = input.parse::<usize>() {
+        return fans
+            .get(index)
+            .map(|fan| fan.id.clone())
+            .ok_or_else(|| FanControlError::FanNotFound(input.to_string()));
+    }
+
+    let words = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    };
+    let wanted = words(input);
+    if wanted.is_empty() {
+        return Err(FanControlError::FanNotFound(input.to_string()));
+    }
+    let matches: Vec<&Fan> = fans
+        .iter()
+        .filter(|fan| {
+            let label = words(&fan.label);
+            wanted.iter().all(|word| label.contains(word))
+        })
+        .collect();
+    match matches.as_slice() {
+        [] => Err(FanControlError::FanNotFound(input.to_string())),
+        [fan] => Ok(fan.id.clone()),
+        several => Err(FanControlError::AmbiguousFan {
+            input: input.to_string(),
+            candidates: several
+                .iter()
+                .map(|fan| format!("{} ({})", fan.id, fan.label))
+                .collect::<Vec<_>>()
+                .join(", "),
+        }),
+    }
+}
+
+/// Sensor readings as one line, e.g. "CPU 72°C / GPU 65°C".
+pub fn format_sensor_temps(temps: &[SensorTemperature]) -> String {
+    temps
+        .iter()
+        .map(|t| format!("{} {}\u{00B0}C", t.label, t.temperature))
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// Name a sensor by the fan it drives in `curves`: "CPU" for the CPU fan's
+/// sensor, "GPU" for the discrete GPU sensor, "Sensor N" otherwise.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn sensor_label(curves: &[FanCurve], sensor_id: u32) -> String {
+    sensor_role(curves, sensor_id).map_or_else(|| format!("Sensor {sensor_id}"), str::to_string)
+}
+
+/// "CPU" or "GPU" when `sensor_id` drives that fan in `curves`.
+fn sensor_role(curves: &[FanCurve], sensor_id: u32) -> Option<&'static str> {
+    if bound_sensor(curves, CPU_FAN_ID) == Some(sensor_id) {
+        Some("CPU")
+    } else if find_gpu_sensor(curves) == Some(sensor_id) {
+        Some("GPU")
+    } else {
+        None
+    }
+}
+
+/// Set each curve's `sensor_label` from the fans the sensors drive.
+///
+/// The EC tables carry no sensor names, so the role is inferred as in
+/// [`sensor_label`]; sensors driving neither fan stay unlabelled.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn label_sensors(curves: &mut [FanCurve]) {
+    let roles: Vec<Option<&str>> = curves
+        .iter()
+        .map(|curve| sensor_role(curves, curve.sensor_id))
+        .collect();
+    for (curve, role) in curves.iter_mut().zip(roles) {
+        curve.sensor_label = role.map(str::to_string);
+    }
+}
+
+/// Sensor bound to a fan's active curve, or to its only curve when none is
+/// flagged active.
+fn bound_sensor(curves: &[FanCurve], fan_id: u32) -> Option<u32> {
+    let fan_curves: Vec<&FanCurve> = curves.iter().filter(|c| c.fan_id == fan_id).collect();
+    match fan_curves.iter().find(|c| c.active) {
+        Some(curve) => Some(curve.sensor_id),
+        None if fan_curves.len() == 1 => Some(fan_curves[0].sensor_id),
+        None => None,
+    }
+}
+
+/// Identify the discrete GPU temperature sensor from EC curve data.
+///
+/// The GPU sensor is the one driving the GPU fan's curve. Returns `None` on
+/// integrated-only systems, where there is no GPU fan table or the GPU fan
+/// simply follows the CPU sensor.
+pub fn find_gpu_sensor(curves: &[FanCurve]) -> Option<u32> {
+    let gpu_sensor = bound_sensor(curves, GPU_FAN_ID)?;
+    if bound_sensor(curves, CPU_FAN_ID) == Some(gpu_sensor) {
+        return None;
+    }
+    Some(gpu_sensor)
+}
+
+/// Map PWM (0-255) to RPM using the given range. A zero-width range
+/// (`min_rpm == max_rpm`, from a stubbed table) gives that one speed.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn pwm_to_rpm(min_rpm: u32, max_rpm: u32, pwm: u8) -> u32 {
+    calibrated_pwm_to_rpm(&[], min_rpm, max_rpm, pwm)
+}
+
+/// Map RPM back to approximate PWM (0-255) using the given range. A
+/// zero-width range has nothing to interpolate: 0 at or below its speed,
+/// 255 above it.
+pub fn rpm_to_pwm(min_rpm: u32, max_rpm: u32, rpm: u32) -> u8 {
+    calibrated_rpm_to_pwm(&[], min_rpm, max_rpm, rpm)
+}
+
+/// RPM a fan was measured at while driven with one PWM value, from
+/// `fancontrol calibrate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CalibrationPoint {
+    pub pwm: u8,
+    pub rpm: u32,
+}
+
+/// Check a calibration table and return it sorted by PWM: at least one
+/// point, PWM values strictly between 0 and 255 (those two are the ends of
+/// the RPM range) and distinct, RPM not falling as PWM rises.
+pub fn validate_calibration(points: &[CalibrationPoint]) -> Result<Vec<CalibrationPoint>, String> {
+    if points.is_empty() {
+        return Err("a calibration needs at least one point".to_string());
+    }
+    let mut sorted = points.to_vec();
+    sorted.sort_by_key(|point| point.pwm);
+    if let Some(point) = sorted
+        .iter()
+        .find(|point| point.pwm == 0 || point.pwm == 255)
+    {
+        return Err(format!(
+            "PWM {} is an end of the RPM range and cannot be calibrated",
+            point.pwm
+        ));
+    }
+    for pair in sorted.windows(2) {
+        if pair[0].pwm == pair[1].pwm {
+            return Err(format!("PWM {} is listed twice", pair[0].pwm));
+        }
+        if pair[1].rpm < pair[0].rpm {
+            return Err(format!(
+                "RPM falls from {} at PWM {} to {} at PWM {}",
+                pair[0].rpm, pair[0].pwm, pair[1].rpm, pair[1].pwm
+            ));
+        }
+    }
+    Ok(sorted)
+}
+
+/// The points a PWM↔RPM mapping interpolates through: the ends of the RPM
+/// range at PWM 0 and 255, with `calibration` (sorted, see
+/// [`validate_calibration`]) in between, held inside the range.
+fn mapping_points(
+    calibration: &[CalibrationPoint],
+    min_rpm: u32,
+    max_rpm: u32,
+) -> Vec<CalibrationPoint> {
+    let mut points = vec![CalibrationPoint {
+        pwm: 0,
+        rpm: min_rpm,
+    }];
+    for point in calibration {
+        let rpm = point.rpm.clamp(min_rpm, max_rpm.max(min_rpm));
+        points.push(CalibrationPoint {
+            pwm: point.pwm,
+            rpm,
+        });
+    }
+    points.push(CalibrationPoint {
+        pwm: 255,
+        rpm: max_rpm,
+    });
+    points
+}
+
+/// Map PWM to RPM through a calibration table, interpolating linearly
+/// between measured points. An empty table is the plain linear mapping
+/// across the range.
+pub fn calibrated_pwm_to_rpm(
+    calibration: &[CalibrationPoint],
+    min_rpm: u32,
+    max_rpm: u32,
+    pwm: u8,
+) -> u32 {
+    if max_rpm <= min_rpm {
+        return min_rpm;
+    }
+    let points = mapping_points(calibration, min_rpm, max_rpm);
+    let Some(pair) = points.windows(2).find(|pair| pwm <= pair[1].pwm) else {
+        return max_rpm;
+    };
+    let (low, high) = (pair[0], pair[1]);
+    let ratio = (pwm - low.pwm) as f64 / (high.pwm - low.pwm) as f64;
+    low.rpm + (ratio * (high.rpm - low.rpm) as f64) as u32
+}
+
+/// Map RPM back to PWM through a calibration table; the inverse of
+/// [`calibrated_pwm_to_rpm`]. RPM at or below the range gives 0, at or
+/// above it 255.
+pub fn calibrated_rpm_to_pwm(
+    calibration: &[CalibrationPoint],
+    min_rpm: u32,
+    max_rpm: u32,
+    rpm: u32,
+) -> u8 {
+    if rpm <= min_rpm {
+        return 0;
+    }
+    if rpm >= max_rpm || max_rpm <= min_rpm {
+        return 255;
+    }
+    let points = mapping_points(calibration, min_rpm, max_rpm);
+    let Some(pair) = points.windows(2).find(|pair| rpm < pair[1].rpm) else {
+        return 255;
+    };
+    let (low, high) = (pair[0], pair[1]);
+    let ratio = (rpm - low.rpm) as f64 / (high.rpm - low.rpm) as f64;
+    low.pwm + (ratio * (high.pwm - low.pwm) as f64) as u8
+}
+
+/// PWM values `calibrate` sweeps: `steps` evenly spaced values strictly
+/// between 0 and 255.
+pub fn calibration_pwms(steps: u32) -> Vec<u8> {
+    let steps = steps.clamp(1, 254);
+    (1..=steps)
+        .map(|step| (step * 255 / (steps + 1)) as u8)
+        .collect()
+}
+
+/// Turn measured `(pwm, rpm)` samples into a calibration table: RPM that
+/// dips below an earlier sample (measurement noise) is raised to it, so the
+/// table passes [`validate_calibration`].
+pub fn calibration_from_samples(samples: &[CalibrationPoint]) -> Vec<CalibrationPoint> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_by_key(|point| point.pwm);
+    sorted.dedup_by_key(|point| point.pwm);
+    let mut floor = 0;
+    for point in &mut sorted {
+        floor = floor.max(point.rpm);
+        point.rpm = floor;
+    }
+    sorted
+}
+
+/// The PWM values a ramp from `start` to `target` writes: `steps` evenly
+/// spaced increments ending at `target`, without repeats. `start` itself is
+/// not written; a ramp to the current value writes `target` once.
+pub fn ramp_steps(start: u8, target: u8, steps: u32) -> Vec<u8> {
+    let steps = i32::try_from(steps.max(1)).unwrap_or(i32::MAX);
+    let (start, target) = (i32::from(start), i32::from(target));
+    let mut values: Vec<u8> = (1..=steps)
+        .map(|step| start + (target - start) * step / steps)
+        .filter(|&value| value != start || value == target)
+        .map(|value| value as u8)
+        .collect();
+    values.dedup();
+    values
+}
+
+/// Convert a duty cycle percentage (0–100) to PWM (0–255), rounding to the
+/// nearest step. Values above 100 saturate at 255.
+pub fn percent_to_pwm(percent: u8) -> u8 {
+    let percent = u32::from(percent.min(100));
+    ((percent * 255 + 50) / 100) as u8
+}
+
+/// Convert PWM (0–255) to a duty cycle percentage, rounding to the nearest
+/// percent. Inverts [`percent_to_pwm`] exactly for 0–100.
+pub fn pwm_to_percent(pwm: u8) -> u8 {
+    ((u32::from(pwm) * 100 + 127) / 255) as u8
+}
+
+/// A PWM value with its percentage, e.g. "PWM 153 (60%)", as the CLI and
+/// GUI both show it.
+pub fn format_pwm(pwm: u8) -> String {
+    format!("PWM {pwm} ({}%)", pwm_to_percent(pwm))
+}
+
+/// One temperature→PWM point of a software fan curve.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PwmCurvePoint {
+    /// Temperature in degrees Celsius.
+    pub temperature: u32,
+    /// PWM duty cycle (0–255) at this temperature.
+    pub pwm: u8,
+}
+
+/// A fan curve evaluated in software by `run-daemon`, for backends whose
+/// firmware has no curve support (Linux hwmon).
+///
+/// Curve files hold a JSON array of these. This is not the `restore-curves`
+/// step format: fans and sensors are named by their `list` IDs, and each
+/// point maps a temperature to a PWM value.
+///
+/// ```json
+/// [
+///   {
+///     "fan_id": "hwmon2/fan1",
+///     "sensor": "hwmon2/temp1",
+///     "hysteresis": 3,
+///     "smoothing": 0.5,
+///     "points": [
+///       {"temperature": 40, "pwm": 80},
+///       {"temperature": 80, "pwm": 255}
+///     ]
+///   }
+/// ]
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PwmCurve {
+    /// Fan identifier as shown by `list` (e.g. "hwmon2/fan1").
+    pub fan_id: String,
+    /// Temperature input driving the fan (e.g. "hwmon2/temp1"). Defaults to
+    /// the sensor `list` shows next to the fan.
+    #[serde(default)]
+    pub sensor: Option<String>,
+    /// Points sorted by rising temperature.
+    pub points: Vec<PwmCurvePoint>,
+    /// Degrees the temperature must fall before the PWM steps back down, so
+    /// a reading wobbling around a point holds a steady speed. Defaults to 0.
+    #[serde(default)]
+    pub hysteresis: u32,
+    /// Weight (above 0, at most 1) an exponential moving average gives each
+    /// new temperature reading before the curve sees it. Lower values damp
+    /// spikes harder but follow real changes more slowly. Unset uses the
+    /// raw readings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub smoothing: Option<f64>,
+}
+
+impl PwmCurve {
+    /// Check that the curve has points, strictly rising temperatures, a PWM
+    /// that never drops as temperature rises, and a usable `smoothing`.
+    pub fn validate(&self) -> Result<(), FanControlError> {
+        if self.points.is_empty() {
+            return Err(FanControlError::Platform(format!(
+                "curve for {} has no points",
+                self.fan_id
+            )));
+        }
+        if let Some(smoothing) = self.smoothing {
+            if !(smoothing > 0.0 && smoothing <= 1.0) {
+                return Err(FanControlError::Platform(format!(
+                    "curve for {}: smoothing must be above 0 and at most 1, got {smoothing}",
+                    self.fan_id
+                )));
+            }
+        }
+        for pair in self.points.windows(2) {
+            let (low, high) = (pair[0], pair[1]);
+            if high.temperature <= low.temperature {
+                return Err(FanControlError::Platform(format!(
+                    "curve for {}: temperatures must rise, got {}\u{00B0}C after {}\u{00B0}C",
+                    self.fan_id, high.temperature, low.temperature
+                )));
+            }
+            if high.pwm < low.pwm {
+                return Err(FanControlError::Platform(format!(
+                    "curve for {}: PWM must not drop as temperature rises \
+                     ({} at {}\u{00B0}C after {} at {}\u{00B0}C)",
+                    self.fan_id, high.pwm, high.temperature, low.pwm, low.temperature
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// PWM for `temperature`, interpolated linearly between the surrounding
+    /// points and held flat below the first and above the last point.
+    ///
+    /// Expects a curve that passed `validate`; an empty curve yields 255.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn pwm_at(&self, temperature: u32) -> u8 {
+        let points: Vec<(u32, u32)> = self
+            .points
+            .iter()
+            .map(|point| (point.temperature, u32::from(point.pwm)))
+            .collect();
+        interpolate(&points, temperature).map_or(255, |pwm| pwm.min(255) as u8)
+    }
+}
+
+impl FanCurve {
+    /// Fan speed in RPM at `temperature`, interpolated linearly between the
+    /// two bracketing points and clamped to the first/last point outside
+    /// them. A curve without points yields `min_speed`.
+    pub fn speed_at(&self, temperature: u32) -> u32 {
+        let points: Vec<(u32, u32)> = self
+            .points
+            .iter()
+            .map(|point| (point.temperature, point.fan_speed))
+            .collect();
+        interpolate(&points, temperature).unwrap_or(self.min_speed)
+    }
+
+    /// "Sensor 3 (CPU)", or "Sensor 3" when the sensor is unlabelled.
+    pub fn sensor_name(&self) -> String {
+        match &self.sensor_label {
+            Some(label) => format!("Sensor {} ({label})", self.sensor_id),
+            None => format!("Sensor {}", self.sensor_id),
+        }
+    }
+}
+
+/// Exponential moving average of the temperature readings: `temperature`
+/// weighted by `smoothing` against the average up to the previous tick.
+/// Without `smoothing` (or a previous tick) this is just `temperature`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn smoothed_temperature(last: Option<f64>, temperature: u32, smoothing: Option<f64>) -> f64 {
+    let reading = f64::from(temperature);
+    match (last, smoothing) {
+        (Some(last), Some(weight)) => last + weight * (reading - last),
+        _ => reading,
+    }
+}
+
+/// Temperature to evaluate a curve at, given the one used on the previous
+/// tick. Rises are followed at once; drops are ignored until the reading is
+/// `hysteresis` degrees below `last`, then followed in full. With
+/// `hysteresis` 0 (or no previous tick) this is just `temperature`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn hysteresis_temperature(last: Option<u32>, temperature: u32, hysteresis: u32) -> u32 {
+    match last {
+        Some(last) if temperature < last && last - temperature < hysteresis => last,
+        _ => temperature,
+    }
+}
+
+/// The RPM curve `steps` would produce on the EC table `table`: point `i`
+/// keeps the table's i-th temperature and takes the RPM of the table point
+/// the step indexes (steps past the table's end use its last RPM). Only the
+/// first `min(10, table points)` steps are used, as on the hardware.
+pub fn build_curve_from_points(table: &FanCurve, steps: &[u8; 10]) -> FanCurve {
+    let points = table
+        .points
+        .iter()
+        .zip(steps)
+        .map(|(point, &step)| FanCurvePoint {
+            temperature: point.temperature,
+            fan_speed: table
+                .points
+                .get(usize::from(step))
+                .or(table.points.last())
+                .map_or(table.min_speed, |indexed| indexed.fan_speed),
+        })
+        .collect();
+    FanCurve {
+        points,
+        ..table.clone()
+    }
+}
+
+/// `points` moved into `reference`'s `min_speed..=max_speed` and
+/// `min_temp..=max_temp`, and whether any of them had to move. Without a
+/// reference they are returned unchanged. [`build_curve_from_points`] only
+/// yields speeds from the table itself; this is for RPMs typed by the user.
+pub fn clamp_curve_points(
+    points: &[FanCurvePoint],
+    reference: Option<&FanCurve>,
+) -> (Vec<FanCurvePoint>, bool) {
+    let Some(reference) = reference else {
+        return (points.to_vec(), false);
+    };
+    let clamp = |value: u32, min: u32, max: u32| value.max(min).min(max);
+    let clamped: Vec<FanCurvePoint> = points
+        .iter()
+        .map(|point| FanCurvePoint {
+            temperature: clamp(point.temperature, reference.min_temp, reference.max_temp),
+            fan_speed: clamp(point.fan_speed, reference.min_speed, reference.max_speed),
+        })
+        .collect();
+    let changed = clamped.as_slice() != points;
+    (clamped, changed)
+}
+
+/// The speed the EC demands from fan `fan_id` across its active sensor
+/// curves: at every temperature any of them names, the highest speed any of
+/// them gives there (interpolated with [`FanCurve::speed_at`]). Treats all
+/// sensors as reading the same temperature. `None` if the fan has no active
+/// curve. The result keeps the first curve's `sensor_id`, which is
+/// meaningless for a combined curve.
+pub fn combine_curves_for_fan(fan_id: u32, curves: &[FanCurve]) -> Option<FanCurve> {
+    let curves: Vec<&FanCurve> = curves
+        .iter()
+        .filter(|curve| curve.fan_id == fan_id && curve.active)
+        .collect();
+    let first = *curves.first()?;
+
+    let mut temperatures: Vec<u32> = curves
+        .iter()
+        .flat_map(|curve| curve.points.iter().map(|point| point.temperature))
+        .collect();
+    temperatures.sort_unstable();
+    temperatures.dedup();
+    let points = temperatures
+        .into_iter()
+        .map(|temperature| FanCurvePoint {
+            temperature,
+            fan_speed: curves
+                .iter()
+                .map(|curve| curve.speed_at(temperature))
+                .max()
+                .unwrap_or(first.min_speed),
+        })
+        .collect();
+
+    Some(FanCurve {
+        fan_id,
+        sensor_id: first.sensor_id,
+        min_speed: curves.iter().map(|c| c.min_speed).max().unwrap_or_default(),
+        max_speed: curves.iter().map(|c| c.max_speed).max().unwrap_or_default(),
+        min_temp: curves.iter().map(|c| c.min_temp).min().unwrap_or_default(),
+        max_temp: curves.iter().map(|c| c.max_temp).max().unwrap_or_default(),
+        points,
+        active: true,
+        sensor_label: None,
+    })
+}
+
+/// How one point differs between a backed-up curve and the live one.
+/// Points are matched by temperature.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum PointChange {
+    /// Only the live curve has a point at this temperature.
+    Added { temperature: u32, fan_speed: u32 },
+    /// Only the backup has a point at this temperature.
+    Removed { temperature: u32, fan_speed: u32 },
+    /// Both have the point, at different speeds.
+    Changed {
+        temperature: u32,
+        backup_speed: u32,
+        live_speed: u32,
+    },
+}
+
+/// How a fan/sensor curve differs between a backup and the live tables.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CurveChange {
+    OnlyInBackup,
+    OnlyLive,
+    Changed { points: Vec<PointChange> },
+}
+
+/// One differing fan/sensor curve, from [`diff_curves`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CurveDiff {
+    pub fan_id: u32,
+    pub sensor_id: u32,
+    #[serde(flatten)]
+    pub change: CurveChange,
+}
+
+/// Compare `backup` curves with `live` ones point by point, matching curves
+/// by fan and sensor and points by temperature. Only curves that differ are
+/// returned, sorted by fan, sensor, then temperature, so the output is
+/// stable for scripts.
+pub fn diff_curves(backup: &[FanCurve], live: &[FanCurve]) -> Vec<CurveDiff> {
+    let find = |curves: &[FanCurve], pair: (u32, u32)| {
+        curves
+            .iter()
+            .find(|curve| (curve.fan_id, curve.sensor_id) == pair)
+            .cloned()
+    };
+    let mut pairs: Vec<(u32, u32)> = backup
+        .iter()
+        .chain(live)
+        .map(|curve| (curve.fan_id, curve.sensor_id))
+        .collect();
+    pairs.sort_unstable();
+    pairs.dedup();
+
+    pairs
+        .into_iter()
+        .filter_map(|(fan_id, sensor_id)| {
+            let change = match (
+                find(backup, (fan_id, sensor_id)),
+                find(live, (fan_id, sensor_id)),
+            ) {
+                (Some(_), None) => CurveChange::OnlyInBackup,
+                (None, Some(_)) => CurveChange::OnlyLive,
+                (Some(backup), Some(live)) => {
+                    let points = diff_points(&backup.points, &live.points);
+                    if points.is_empty() {
+                        return None;
+                    }
+                    CurveChange::Changed { points }
+                }
+                (None, None) => return None,
+            };
+            Some(CurveDiff {
+                fan_id,
+                sensor_id,
+                change,
+            })
+        })
+        .collect()
+}
+
+/// Fan/sensor pairs of `held`, the tables read back right after writing the
+/// held curves, whose `live` table has since changed or gone, e.g. because
+/// the firmware restored its default curves. Live tables for other pairs
+/// are ignored.
+pub fn reverted_curves(held: &[FanCurve], live: &[FanCurve]) -> Vec<(u32, u32)> {
+    let live: Vec<FanCurve> = live
+        .iter()
+        .filter(|curve| {
+            held.iter()
+                .any(|held| (held.fan_id, held.sensor_id) == (curve.fan_id, curve.sensor_id))
+        })
+        .cloned()
+        .collect();
+    diff_curves(held, &live)
+        .into_iter()
+        .map(|diff| (diff.fan_id, diff.sensor_id))
+        .collect()
+}
+
+/// Point changes from `backup` to `live`, by rising temperature.
+fn diff_points(backup: &[FanCurvePoint], live: &[FanCurvePoint]) -> Vec<PointChange> {
+    let speed_at = |points: &[FanCurvePoint], temperature: u32| {
+        points
+            .iter()
+            .find(|point| point.temperature == temperature)
+            .map(|point| point.fan_speed)
+    };
+    let mut temperatures: Vec<u32> = backup
+        .iter()
+        .chain(live)
+        .map(|point| point.temperature)
+        .collect();
+    temperatures.sort_unstable();
+    temperatures.dedup();
+
+    temperatures
+        .into_iter()
+        .filter_map(|temperature| {
+            match (speed_at(backup, temperature), speed_at(live, temperature)) {
+                (Some(fan_speed), None) => Some(PointChange::Removed {
+                    temperature,
+                    fan_speed,
+                }),
+                (None, Some(fan_speed)) => Some(PointChange::Added {
+                    temperature,
+                    fan_speed,
+                }),
+                (Some(backup_speed), Some(live_speed)) if backup_speed != live_speed => {
+                    Some(PointChange::Changed {
+                        temperature,
+                        backup_speed,
+                        live_speed,
+                    })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Linear interpolation over `(temperature, value)` points sorted by rising
+/// temperature, holding the end values outside the covered range. `None`
+/// for an empty slice.
+fn interpolate(points: &[(u32, u32)], temperature: u32) -> Option<u32> {
+    let (&(first_temp, first_value), &(last_temp, last_value)) = (points.first()?, points.last()?);
+    if temperature <= first_temp {
+        return Some(first_value);
+    }
+    if temperature >= last_temp {
+        return Some(last_value);
+    }
+    let upper = points
+        .iter()
+        .position(|&(temp, _)| temp >= temperature)
+        .unwrap_or(points.len() - 1);
+    let ((low_temp, low_value), (high_temp, high_value)) = (points[upper - 1], points[upper]);
+    let ratio = f64::from(temperature - low_temp) / f64::from(high_temp - low_temp);
+    let value = f64::from(low_value) + ratio * (f64::from(high_value) - f64::from(low_value));
+    Some(value.round() as u32)
+}
+
+/// One evaluation of a software curve: the temperature read and the PWM
+/// written for a fan.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct CurveSample {
+    pub fan_id: String,
+    pub temperature: u32,
+    pub pwm: u8,
+}
+
+/// Smallest RPM change counted as the fan responding to a new PWM value.
+/// Readings jitter by a few dozen RPM at a steady duty cycle.
+const MIN_RPM_RESPONSE: u32 = 100;
+
+/// Result of watching a fan's RPM after a PWM change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedResponse {
+    /// RPM moved in the expected direction.
+    Responded { rpm: u32 },
+    /// RPM stayed put (or moved the wrong way) for every sample.
+    NoChange { rpm: u32 },
+}
+
+/// Which way RPM should move when PWM changes from `old_pwm` to `new_pwm`.
+/// `None` when the current PWM is unknown or unchanged, so there is nothing
+/// to verify.
+pub fn expected_rpm_direction(old_pwm: Option<u8>, new_pwm: u8) -> Option<Ordering> {
+    match new_pwm.cmp(&old_pwm?) {
+        Ordering::Equal => None,
+        direction => Some(direction),
+    }
+}
+
+/// Poll `sample` up to `max_polls` times until the RPM moves at least
+/// `MIN_RPM_RESPONSE` away from `before_rpm` in `direction`.
+///
+/// `sample` performs one (possibly sleeping) read and returns `None` on a
+/// failed read, which counts as a poll without a response.
+pub fn watch_rpm_response(
+    before_rpm: u32,
+    direction: Ordering,
+    max_polls: usize,
+    mut sample: impl FnMut() -> Option<u32>,
+) -> SpeedResponse {
+    let mut last_rpm = before_rpm;
+    for _ in 0..max_polls {
+        let Some(rpm) = sample() else {
+            continue;
+        };
+        last_rpm = rpm;
+        let responded = match direction {
+            Ordering::Greater => rpm >= before_rpm.saturating_add(MIN_RPM_RESPONSE),
+            Ordering::Less => rpm.saturating_add(MIN_RPM_RESPONSE) <= before_rpm,
+            Ordering::Equal => true,
+        };
+        if responded {
+            return SpeedResponse::Responded { rpm };
+        }
+    }
+    SpeedResponse::NoChange { rpm: last_rpm }
+}
+
+/// What a fan has been told to do, as far as its backend knows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FanCommand {
+    /// Under firmware or driver control.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Auto,
+    /// A manual duty cycle.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Pwm(u8),
+    /// A manual RPM target.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Rpm(u32),
+    /// Full-speed mode.
+    FullSpeed,
+}
+
+/// Whether a fan reading `speed_rpm` under `command` has likely stalled:
+/// it reads 0 RPM while being driven. Fans at 0 RPM under automatic control
+/// are not flagged, since many firmwares stop them when cool.
+pub fn is_stalled(speed_rpm: u32, command: FanCommand) -> bool {
+    speed_rpm == 0
+        && match command {
+            FanCommand::Auto => false,
+            FanCommand::Pwm(pwm) => pwm > 0,
+            FanCommand::Rpm(rpm) => rpm > 0,
+            FanCommand::FullSpeed => true,
+        }
+}
+
+/// What `watch` should do after an [`OverheatGuard`] sees a reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuardAction {
+    /// The limit was crossed: force the fans to full speed.
+    Trip,
+    /// The temperature has settled: restore the state from before the trip.
+    Release,
+}
+
+/// Overheat trip with hysteresis and a minimum dwell. Trips as soon as a
+/// reading exceeds `max_temp`; releases only once the reading is below
+/// `max_temp - hysteresis` and at least `min_dwell` has passed since the
+/// trip, so a reading hovering at the limit cannot flap the fans.
+#[derive(Debug, Clone)]
+pub struct OverheatGuard {
+    max_temp: u32,
+    hysteresis: u32,
+    min_dwell: Duration,
+    tripped_at: Option<Instant>,
+}
+
+impl OverheatGuard {
+    pub fn new(max_temp: u32, hysteresis: u32, min_dwell: Duration) -> Self {
+        Self {
+            max_temp,
+            hysteresis,
+            min_dwell,
+            tripped_at: None,
+        }
+    }
+
+    /// Temperature a tripped guard must fall below to release.
+    pub fn release_below(&self) -> u32 {
+        self.max_temp.saturating_sub(self.hysteresis)
+    }
+
+    pub fn tripped(&self) -> bool {
+        self.tripped_at.is_some()
+    }
+
+    /// Feed the hottest reading taken at `now`.
+    pub fn update(&mut self, temperature: u32, now: Instant) -> Option<GuardAction> {
+        match self.tripped_at {
+            None if temperature > self.max_temp => {
+                self.tripped_at = Some(now);
+                Some(GuardAction::Trip)
+            }
+            Some(since)
+                if temperature < self.release_below()
+                    && now.saturating_duration_since(since) >= self.min_dwell =>
+            {
+                self.tripped_at = None;
+                Some(GuardAction::Release)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Running minimum, maximum, and mean of one reading.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReadingStats {
+    pub min: u32,
+    pub max: u32,
+    sum: u64,
+    count: u32,
+}
+
+impl ReadingStats {
+    fn new(value: u32) -> Self {
+        Self {
+            min: value,
+            max: value,
+            sum: u64::from(value),
+            count: 1,
+        }
+    }
+
+    fn add(&mut self, value: u32) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += u64::from(value);
+        self.count += 1;
+    }
+
+    /// Mean of every reading added, rounded.
+    pub fn average(&self) -> u32 {
+        ((self.sum + u64::from(self.count) / 2) / u64::from(self.count)) as u32
+    }
+}
+
+/// RPM and temperature statistics of one fan over a `monitor` run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FanStats {
+    pub id: String,
+    pub label: String,
+    pub rpm: ReadingStats,
+    /// `None` while the fan has reported no temperature.
+    pub temperature: Option<ReadingStats>,
+}
+
+/// Per-fan statistics over every refresh of a `monitor` run, in discovery
+/// order.
+#[derive(Debug, Clone, Default)]
+pub struct MonitorStats {
+    pub fans: Vec<FanStats>,
+    /// Refreshes recorded.
+    pub samples: u32,
+}
+
+impl MonitorStats {
+    /// Add one refresh's readings.
+    pub fn record(&mut self, fans: &[Fan]) {
+        self.samples += 1;
+        for fan in fans {
+            let Some(stats) = self.fans.iter_mut().find(|stats| stats.id == fan.id) else {
+                self.fans.push(FanStats {
+                    id: fan.id.clone(),
+                    label: fan.label.clone(),
+                    rpm: ReadingStats::new(fan.speed_rpm),
+                    temperature: fan.temperature.map(ReadingStats::new),
+                });
+                continue;
+            };
+            stats.rpm.add(fan.speed_rpm);
+            if let Some(temperature) = fan.temperature {
+                match stats.temperature.as_mut() {
+                    Some(temps) => temps.add(temperature),
+                    None => stats.temperature = Some(ReadingStats::new(temperature)),
+                }
+            }
+        }
+    }
+}
+
+impl Fan {
+    /// `pwm_mode` as a word for the `list` STATUS column.
+    pub fn pwm_mode_name(&self) -> Option<&'static str> {
+        self.pwm_mode.map(|mode| match mode {
+            0 => "full",
+            1 => "manual",
+            _ => "auto",
+        })
+    }
+
+    /// Label with the sensor temperature appended when known, e.g.
+    /// "CPU Fan (45°C)".
+    pub fn display_label(&self) -> String {
+        match self.temperature {
+            Some(temp) => format!("{} ({temp}\u{00B0}C)", self.label),
+            None => self.label.clone(),
+        }
+    }
+
+    /// `min_rpm`–`max_rpm` for the `list` RANGE column, e.g. "1600–4800".
+    /// A one-sided range shows "?" for the missing bound.
+    pub fn rpm_range_display(&self) -> Option<String> {
+        let bound = |rpm: Option<u32>| rpm.map_or_else(|| "?".to_string(), |rpm| rpm.to_string());
+        if self.min_rpm.is_none() && self.max_rpm.is_none() {
+            return None;
+        }
+        Some(format!(
+            "{}\u{2013}{}",
+            bound(self.min_rpm),
+            bound(self.max_rpm)
+        ))
+    }
+}
+
+impl fmt::Display for Fan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let control_status = if self.controllable {
+            "controllable"
+        } else {
+            "read-only"
+        };
+        write!(
+            f,
+            "{}: {} RPM [{}]",
+            self.display_label(),
+            self.speed_rpm,
+            control_status
+        )
+    }
+}
+
+/// One-line count of `fans`, e.g. "2 fans (1 controllable, 1 read-only)".
+pub fn fan_count_summary(fans: &[Fan]) -> String {
+    let controllable = fans.iter().filter(|fan| fan.controllable).count();
+    format!(
+        "{} fan{} ({} controllable, {} read-only)",
+        fans.len(),
+        if fans.len() == 1 { "" } else { "s" },
+        controllable,
+        fans.len() - controllable
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn custom_curve(steps: [u8; 10]) -> CustomFanCurve {
+        CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps,
+        }
+    }
+
+    // -- validate_custom_curve -----------------------------------------------
+
+    #[test]
+    fn validate_custom_curve_llt_v2_minimum() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [1, 1, 1, 1, 1, 1, 1, 1, 3, 5],
+        };
+        assert!(validate_custom_curve(&curve).is_ok());
+    }
+
+    #[test]
+    fn validate_custom_curve_all_max() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [10; 10],
+        };
+        assert!(validate_custom_curve(&curve).is_ok());
+    }
+
+    #[test]
+    fn validate_custom_curve_ascending() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [0, 1, 2, 3, 4, 5, 6, 7, 8, 10],
+        };
+        assert!(validate_custom_curve(&curve).is_ok());
+    }
+
+    #[test]
+    fn validate_custom_curve_flat_then_ramp() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [0, 0, 0, 0, 0, 0, 0, 0, 5, 10],
+        };
+        assert!(validate_custom_curve(&curve).is_ok());
+    }
+
+    #[test]
+    fn validate_custom_curve_step_exceeds_max() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [1, 1, 1, 1, 1, 1, 1, 1, 3, 11],
+        };
+        let err = validate_custom_curve(&curve).unwrap_err();
+        assert!(err.to_string().contains("exceeds maximum"));
+    }
+
+    #[test]
+    fn validate_custom_curve_decreasing_steps() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [5, 4, 3, 2, 1, 1, 1, 1, 3, 5],
+        };
+        let err = validate_custom_curve(&curve).unwrap_err();
+        assert!(err.to_string().contains("non-decreasing"));
+    }
+
+    #[test]
+    fn validate_custom_curve_step8_too_low() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [0, 0, 0, 0, 0, 0, 0, 0, 2, 5],
+        };
+        let err = validate_custom_curve(&curve).unwrap_err();
+        assert!(err.to_string().contains("step 8"));
+    }
+
+    #[test]
+    fn validate_custom_curve_step9_too_low() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [0, 0, 0, 0, 0, 0, 0, 0, 3, 4],
+        };
+        let err = validate_custom_curve(&curve).unwrap_err();
+        assert!(err.to_string().contains("step 9"));
+    }
+
+    #[test]
+    fn validate_custom_curve_single_decrease_at_end() {
+        let curve = CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: [1, 2, 3, 4, 5, 6, 7, 8, 10, 9],
+        };
+        let err = validate_custom_curve(&curve).unwrap_err();
+        assert!(err.to_string().contains("non-decreasing"));
+    }
+
+    // -- validate_custom_curve_with_policy ----------------------------------
+
+    #[test]
+    fn high_temp_floors_default_to_llt_v2() {
+        assert_eq!(
+            high_temp_floors(DEFAULT_MIN_HIGH_TEMP_RATIO),
+            [(8, 3), (9, 5)]
+        );
+        assert_eq!(high_temp_floors(0.0), [(8, 0), (9, 0)]);
+        assert_eq!(high_temp_floors(1.0), [(8, 6), (9, 10)]);
+    }
+
+    #[test]
+    fn validate_with_policy_lowers_floors() {
+        let curve = custom_curve([0, 0, 0, 0, 0, 0, 0, 0, 1, 2]);
+        assert!(validate_custom_curve(&curve).is_err());
+        assert!(validate_custom_curve_with_policy(&curve, 0.2, None).is_ok());
+        let err = validate_custom_curve_with_policy(&curve, 0.3, None).unwrap_err();
+        assert!(err.to_string().contains("step 8 (high temp) must be >= 2"));
+    }
+
+    #[test]
+    fn validate_with_policy_raises_floors() {
+        let curve = custom_curve([1, 1, 1, 1, 1, 1, 1, 1, 6, 8]);
+        assert!(validate_custom_curve(&curve).is_ok());
+        let err = validate_custom_curve_with_policy(&curve, 1.0, None).unwrap_err();
+        assert!(err.to_string().contains("step 9 (max temp) must be >= 10"));
+    }
+
+    #[test]
+    fn validate_with_policy_rejects_ratio_out_of_range() {
+        let curve = custom_curve([10; 10]);
+        for ratio in [-0.1, 1.5, f32::NAN] {
+            let err = validate_custom_curve_with_policy(&curve, ratio, None).unwrap_err();
+            assert!(err.to_string().contains("outside 0.0–1.0"), "{err}");
+        }
+    }
+
+    // -- high-temperature speed warning ---------------------------------------
+
+    #[test]
+    fn validate_warns_when_top_point_is_slow() {
+        let table = rpm_curve(&[(60, 1600), (75, 3200), (85, 4000), (95, 4800)]);
+        let curve = custom_curve([0, 1, 2, 2, 2, 2, 2, 2, 3, 5]);
+        let validation = validate_custom_curve_with_policy(&curve, 0.5, Some(&table)).unwrap();
+        assert_eq!(validation.warnings.len(), 1);
+        assert!(
+            validation.warnings[0].contains("tops out at 4000 RPM (83% of the fan's 4800 RPM"),
+            "{:?}",
+            validation.warnings
+        );
+    }
+
+    #[test]
+    fn validate_accepts_top_point_near_max_speed() {
+        // Only the first three steps index this three-point table.
+        let curve = custom_curve([0, 1, 2, 2, 2, 2, 2, 2, 3, 5]);
+        for top in [4800, 4400] {
+            // 4400 of 4800 RPM is 91%.
+            let table = rpm_curve(&[(60, 1600), (80, 3200), (95, top)]);
+            let validation = validate_custom_curve_with_policy(&curve, 0.5, Some(&table)).unwrap();
+            assert!(validation.warnings.is_empty(), "{:?}", validation.warnings);
+        }
+    }
+
+    #[test]
+    fn validate_without_table_has_no_warnings() {
+        let curve = custom_curve([0, 0, 0, 0, 0, 0, 0, 0, 3, 5]);
+        assert_eq!(
+            validate_custom_curve(&curve).unwrap(),
+            CurveValidation::default()
+        );
+    }
+
+    // -- serde ---------------------------------------------------------------
+
+    #[test]
+    fn fan_json_roundtrip() {
+        let fan = Fan {
+            id: "fan0".into(),
+            label: "CPU Fan (45\u{00B0}C)".into(),
+            speed_rpm: 2100,
+            temperature: Some(45),
+            pwm: Some(40),
+            pwm_mode: None,
+            controllable: true,
+            min_rpm: Some(1600),
+            max_rpm: Some(4800),
+            target_rpm: None,
+            curves: vec![FanCurve {
+                fan_id: 0,
+                sensor_id: 3,
+                min_speed: 1600,
+                max_speed: 4800,
+                min_temp: 58,
+                max_temp: 100,
+                points: vec![
+                    FanCurvePoint {
+                        temperature: 58,
+                        fan_speed: 1600,
+                    },
+                    FanCurvePoint {
+                        temperature: 100,
+                        fan_speed: 4800,
+                    },
+                ],
+                active: true,
+                sensor_label: None,
+            }],
+            full_speed_active: true,
+            stalled: false,
+        };
+        let json = serde_json::to_value(&fan).unwrap();
+        assert_eq!(json["curves"][0]["points"][1]["fan_speed"], 4800);
+        assert_eq!(json["full_speed_active"], true);
+
+        let loaded: Fan = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&loaded).unwrap(), json);
+        assert_eq!(loaded.min_rpm, Some(1600));
+        assert_eq!(loaded.temperature, Some(45));
+        assert_eq!(loaded.curves[0].points.len(), 2);
+    }
+
+    #[test]
+    fn fan_json_has_list_fields() {
+        // `list --json` output is a scripting contract; keep these keys stable.
+        let fan = Fan {
+            id: "hwmon2/fan1".into(),
+            label: "Chassis Fan".into(),
+            speed_rpm: 900,
+            temperature: None,
+            pwm: None,
+            pwm_mode: None,
+            controllable: false,
+            min_rpm: None,
+            max_rpm: None,
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        };
+        let json = serde_json::to_value(&fan).unwrap();
+        for key in [
+            "id",
+            "label",
+            "speed_rpm",
+            "temperature",
+            "pwm",
+            "pwm_mode",
+            "controllable",
+            "min_rpm",
+            "max_rpm",
+            "full_speed_active",
+        ] {
+            assert!(json.get(key).is_some(), "missing key {key}");
+        }
+        assert!(json["pwm"].is_null());
+    }
+
+    // -- SensorRef / find_gpu_sensor -----------------------------------------
+
+    fn table_curve(fan_id: u32, sensor_id: u32, active: bool) -> FanCurve {
+        FanCurve {
+            fan_id,
+            sensor_id,
+            min_speed: 1600,
+            max_speed: 4800,
+            min_temp: 58,
+            max_temp: 100,
+            points: Vec::new(),
+            active,
+            sensor_label: None,
+        }
+    }
+
+    #[test]
+    fn sensor_ref_parses_id_and_gpu() {
+        assert_eq!("4".parse::<SensorRef>(), Ok(SensorRef::Id(4)));
+        assert_eq!("gpu".parse::<SensorRef>(), Ok(SensorRef::Gpu));
+        assert_eq!("GPU".parse::<SensorRef>(), Ok(SensorRef::Gpu));
+        assert!("cpu".parse::<SensorRef>().is_err());
+    }
+
+    #[test]
+    fn find_gpu_sensor_uses_active_gpu_fan_curve() {
+        // Highest sensor ID is not the one bound to the GPU fan.
+        let curves = vec![
+            table_curve(0, 3, true),
+            table_curve(1, 4, true),
+            table_curve(1, 5, false),
+        ];
+        assert_eq!(find_gpu_sensor(&curves), Some(4));
+    }
+
+    #[test]
+    fn find_gpu_sensor_single_inactive_curve() {
+        let curves = vec![table_curve(0, 3, true), table_curve(1, 4, false)];
+        assert_eq!(find_gpu_sensor(&curves), Some(4));
+    }
+
+    #[test]
+    fn find_gpu_sensor_none_without_gpu_fan() {
+        let curves = vec![table_curve(0, 3, true), table_curve(0, 0, false)];
+        assert_eq!(find_gpu_sensor(&curves), None);
+    }
+
+    #[test]
+    fn find_gpu_sensor_none_when_gpu_fan_follows_cpu_sensor() {
+        let curves = vec![table_curve(0, 3, true), table_curve(1, 3, true)];
+        assert_eq!(find_gpu_sensor(&curves), None);
+    }
+
+    #[test]
+    fn sensor_label_names_cpu_and_gpu_sensors() {
+        let curves = vec![
+            table_curve(0, 3, true),
+            table_curve(1, 4, true),
+            table_curve(1, 5, false),
+        ];
+        assert_eq!(sensor_label(&curves, 3), "CPU");
+        assert_eq!(sensor_label(&curves, 4), "GPU");
+        assert_eq!(sensor_label(&curves, 5), "Sensor 5");
+    }
+
+    #[test]
+    fn label_sensors_names_curves_by_role() {
+        let mut curves = vec![
+            table_curve(0, 3, true),
+            table_curve(1, 4, true),
+            table_curve(1, 5, false),
+        ];
+        label_sensors(&mut curves);
+        let names: Vec<String> = curves.iter().map(FanCurve::sensor_name).collect();
+        assert_eq!(names, ["Sensor 3 (CPU)", "Sensor 4 (GPU)", "Sensor 5"]);
+    }
+
+    // -- auto_fix_steps --------------------------------------------------------
+
+    #[test]
+    fn sort_steps_reverses_hottest_first_input() {
+        let hottest_first = [10, 8, 7, 6, 4, 2, 1, 0, 0, 0];
+        let sorted = sort_steps(hottest_first);
+        assert_eq!(sorted, [0, 0, 0, 1, 2, 4, 6, 7, 8, 10]);
+        assert!(validate_custom_curve(&CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps: sorted,
+        })
+        .is_ok());
+        assert_eq!(sort_steps(sorted), sorted);
+    }
+
+    #[test]
+    fn auto_fix_steps_leaves_valid_curve_alone() {
+        let steps = [0, 1, 2, 3, 4, 5, 6, 7, 8, 10];
+        let (fixed, fixes) = auto_fix_steps(steps, DEFAULT_MIN_HIGH_TEMP_RATIO);
+        assert_eq!(fixed, steps);
+        assert!(fixes.is_empty());
+    }
+
+    #[test]
+    fn auto_fix_steps_raises_safety_floors() {
+        let (fixed, fixes) =
+            auto_fix_steps([0, 0, 0, 0, 0, 0, 0, 0, 1, 2], DEFAULT_MIN_HIGH_TEMP_RATIO);
+        assert_eq!(fixed, [0, 0, 0, 0, 0, 0, 0, 0, 3, 5]);
+        assert_eq!(fixes.len(), 2);
+        assert_eq!(fixes[0].index, 8);
+        assert_eq!(fixes[0].original, 1);
+        assert_eq!(fixes[0].adjusted, 3);
+        assert_eq!(fixes[0].notes, vec!["raised to safety floor 3"]);
+    }
+
+    #[test]
+    fn auto_fix_steps_uses_the_given_policy() {
+        let steps = [0; 10];
+        let (fixed, _) = auto_fix_steps(steps, 0.2);
+        assert_eq!(fixed, [0, 0, 0, 0, 0, 0, 0, 0, 1, 2]);
+        assert!(validate_custom_curve_with_policy(&custom_curve(fixed), 0.2, None).is_ok());
+        assert!(validate_custom_curve(&custom_curve(fixed)).is_err());
+    }
+
+    #[test]
+    fn auto_fix_steps_monotonizes_and_clamps() {
+        let (fixed, fixes) =
+            auto_fix_steps([2, 1, 4, 3, 12, 5, 6, 7, 8, 9], DEFAULT_MIN_HIGH_TEMP_RATIO);
+        assert_eq!(fixed, [2, 2, 4, 4, 10, 10, 10, 10, 10, 10]);
+        assert!(validate_custom_curve(&custom_curve(fixed)).is_ok());
+        let step4 = fixes.iter().find(|f| f.index == 4).unwrap();
+        assert_eq!(step4.notes, vec!["lowered to maximum 10"]);
+        let step1 = fixes.iter().find(|f| f.index == 1).unwrap();
+        assert_eq!(step1.notes, vec!["raised to 2 to stay non-decreasing"]);
+    }
+
+    // -- ramp_steps ---------------------------------------------------------
+
+    #[test]
+    fn ramp_steps_spread_evenly_to_target() {
+        assert_eq!(ramp_steps(0, 200, 4), vec![50, 100, 150, 200]);
+        assert_eq!(ramp_steps(200, 100, 4), vec![175, 150, 125, 100]);
+    }
+
+    #[test]
+    fn ramp_steps_skip_repeats_and_keep_target() {
+        assert_eq!(ramp_steps(100, 102, 5), vec![101, 102]);
+        assert_eq!(ramp_steps(128, 128, 5), vec![128]);
+        assert_eq!(ramp_steps(0, 255, 0), vec![255]);
+    }
+
+    // -- pwm_to_rpm / rpm_to_pwm -------------------------------------------
+
+    #[test]
+    fn pwm_to_rpm_boundaries() {
+        // PWM 0 → min RPM
+        assert_eq!(pwm_to_rpm(1600, 4800, 0), 1600);
+        // PWM 255 → max RPM
+        assert_eq!(pwm_to_rpm(1600, 4800, 255), 4800);
+    }
+
+    #[test]
+    fn pwm_to_rpm_midrange() {
+        // PWM 128 ≈ mid-range
+        let mid = pwm_to_rpm(1600, 4800, 128);
+        assert!(mid > 1600 && mid < 4800, "mid was {mid}");
+    }
+
+    #[test]
+    fn pwm_to_rpm_custom_range() {
+        assert_eq!(pwm_to_rpm(2000, 5400, 0), 2000);
+        assert_eq!(pwm_to_rpm(2000, 5400, 255), 5400);
+    }
+
+    #[test]
+    fn rpm_to_pwm_boundaries() {
+        // At or below min → 0
+        assert_eq!(rpm_to_pwm(1600, 4800, 1600), 0);
+        assert_eq!(rpm_to_pwm(1600, 4800, 0), 0);
+        // At or above max → 255
+        assert_eq!(rpm_to_pwm(1600, 4800, 4800), 255);
+        assert_eq!(rpm_to_pwm(1600, 4800, 9999), 255);
+    }
+
+    #[test]
+    fn rpm_to_pwm_midrange() {
+        let mid_rpm = 3200; // exactly halfway in 1600..4800
+        let pwm = rpm_to_pwm(1600, 4800, mid_rpm);
+        assert!(pwm > 100 && pwm < 160, "pwm was {pwm}");
+    }
+
+    #[test]
+    fn zero_width_range_maps_to_its_single_speed() {
+        assert_eq!(pwm_to_rpm(2400, 2400, 0), 2400);
+        assert_eq!(pwm_to_rpm(2400, 2400, 128), 2400);
+        assert_eq!(pwm_to_rpm(2400, 2400, 255), 2400);
+        assert_eq!(rpm_to_pwm(2400, 2400, 0), 0);
+        assert_eq!(rpm_to_pwm(2400, 2400, 2400), 0);
+        assert_eq!(rpm_to_pwm(2400, 2400, 2500), 255);
+        // Stubbed all-zero tables.
+        assert_eq!(pwm_to_rpm(0, 0, 128), 0);
+        assert_eq!(rpm_to_pwm(0, 0, 1800), 255);
+    }
+
+    #[test]
+    fn inverted_range_does_not_underflow() {
+        assert_eq!(pwm_to_rpm(3000, 2000, 128), 3000);
+        assert_eq!(rpm_to_pwm(3000, 2000, 2500), 0);
+        assert_eq!(rpm_to_pwm(3000, 2000, 3500), 255);
+        let calibration = [CalibrationPoint {
+            pwm: 100,
+            rpm: 2500,
+        }];
+        assert_eq!(calibrated_pwm_to_rpm(&calibration, 2400, 2400, 200), 2400);
+    }
+
+    #[test]
+    fn pwm_rpm_roundtrip() {
+        // pwm → rpm → pwm should be close to the original
+        let original_pwm: u8 = 100;
+        let rpm = pwm_to_rpm(1600, 4800, original_pwm);
+        let recovered_pwm = rpm_to_pwm(1600, 4800, rpm);
+        let diff = (original_pwm as i16 - recovered_pwm as i16).unsigned_abs();
+        assert!(
+            diff <= 1,
+            "original={original_pwm} recovered={recovered_pwm}"
+        );
+    }
+
+    // -- PWM calibration -------------------------------------------------------
+
+    fn point(pwm: u8, rpm: u32) -> CalibrationPoint {
+        CalibrationPoint { pwm, rpm }
+    }
+
+    #[test]
+    fn calibrated_mapping_interpolates_through_points() {
+        // A fan that reaches most of its speed in the lower half of the PWM range.
+        let calibration = [point(51, 3000), point(153, 4200)];
+        assert_eq!(calibrated_pwm_to_rpm(&calibration, 1600, 4800, 0), 1600);
+        assert_eq!(calibrated_pwm_to_rpm(&calibration, 1600, 4800, 51), 3000);
+        assert_eq!(calibrated_pwm_to_rpm(&calibration, 1600, 4800, 102), 3600);
+        assert_eq!(calibrated_pwm_to_rpm(&calibration, 1600, 4800, 255), 4800);
+
+        assert_eq!(calibrated_rpm_to_pwm(&calibration, 1600, 4800, 3000), 51);
+        assert_eq!(calibrated_rpm_to_pwm(&calibration, 1600, 4800, 3600), 102);
+        assert_eq!(calibrated_rpm_to_pwm(&calibration, 1600, 4800, 1000), 0);
+        assert_eq!(calibrated_rpm_to_pwm(&calibration, 1600, 4800, 5000), 255);
+        // Linear would put 3600 RPM at PWM 159.
+        assert_eq!(rpm_to_pwm(1600, 4800, 3600), 159);
+    }
+
+    #[test]
+    fn calibrated_rpm_to_pwm_skips_flat_segments() {
+        let calibration = [point(51, 2400), point(102, 2400)];
+        assert_eq!(calibrated_rpm_to_pwm(&calibration, 1600, 4800, 2400), 102);
+    }
+
+    #[test]
+    fn validate_calibration_sorts_and_rejects_bad_tables() {
+        assert_eq!(
+            validate_calibration(&[point(200, 4000), point(50, 2000)]).unwrap(),
+            vec![point(50, 2000), point(200, 4000)]
+        );
+        assert!(validate_calibration(&[]).is_err());
+        assert!(validate_calibration(&[point(0, 1600)]).is_err());
+        assert!(validate_calibration(&[point(50, 2000), point(50, 2100)]).is_err());
+        assert!(validate_calibration(&[point(50, 3000), point(100, 2000)]).is_err());
+    }
+
+    #[test]
+    fn calibration_sweep_and_samples() {
+        assert_eq!(calibration_pwms(4), vec![51, 102, 153, 204]);
+        assert_eq!(calibration_pwms(1), vec![127]);
+        assert_eq!(
+            calibration_from_samples(&[point(102, 2900), point(51, 3000), point(153, 3500)]),
+            vec![point(51, 3000), point(102, 3000), point(153, 3500)]
+        );
+    }
+
+    // -- percent_to_pwm --------------------------------------------------------
+
+    #[test]
+    fn percent_to_pwm_maps_scale() {
+        assert_eq!(percent_to_pwm(0), 0);
+        assert_eq!(percent_to_pwm(60), 153);
+        assert_eq!(percent_to_pwm(50), 128);
+        assert_eq!(percent_to_pwm(100), 255);
+        assert_eq!(percent_to_pwm(150), 255);
+    }
+
+    #[test]
+    fn pwm_to_percent_round_trips_every_percent() {
+        for percent in 0..=100 {
+            assert_eq!(pwm_to_percent(percent_to_pwm(percent)), percent);
+        }
+        assert_eq!(format_pwm(153), "PWM 153 (60%)");
+        assert_eq!(format_pwm(255), "PWM 255 (100%)");
+    }
+
+    // -- FanCurve::speed_at ------------------------------------------------
+
+    fn rpm_curve(points: &[(u32, u32)]) -> FanCurve {
+        FanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            min_speed: 1600,
+            max_speed: 4800,
+            min_temp: 0,
+            max_temp: 100,
+            points: points
+                .iter()
+                .map(|&(temperature, fan_speed)| FanCurvePoint {
+                    temperature,
+                    fan_speed,
+                })
+                .collect(),
+            active: true,
+            sensor_label: None,
+        }
+    }
+
+    #[test]
+    fn speed_at_hits_points_exactly() {
+        let curve = rpm_curve(&[(40, 1600), (60, 2800), (90, 4800)]);
+        assert_eq!(curve.speed_at(40), 1600);
+        assert_eq!(curve.speed_at(60), 2800);
+        assert_eq!(curve.speed_at(90), 4800);
+    }
+
+    #[test]
+    fn speed_at_interpolates_midpoints() {
+        let curve = rpm_curve(&[(40, 1600), (60, 2800), (90, 4800)]);
+        assert_eq!(curve.speed_at(50), 2200);
+        assert_eq!(curve.speed_at(75), 3800);
+    }
+
+    #[test]
+    fn speed_at_clamps_outside_range() {
+        let curve = rpm_curve(&[(40, 1600), (60, 2800), (90, 4800)]);
+        assert_eq!(curve.speed_at(10), 1600);
+        assert_eq!(curve.speed_at(110), 4800);
+    }
+
+    #[test]
+    fn speed_at_single_segment_and_degenerate_curves() {
+        let segment = rpm_curve(&[(30, 2000), (70, 4000)]);
+        assert_eq!(segment.speed_at(50), 3000);
+        assert_eq!(segment.speed_at(20), 2000);
+        assert_eq!(segment.speed_at(80), 4000);
+
+        let single = rpm_curve(&[(50, 2500)]);
+        assert_eq!(single.speed_at(10), 2500);
+        assert_eq!(single.speed_at(90), 2500);
+
+        assert_eq!(rpm_curve(&[]).speed_at(50), 1600);
+    }
+
+    // -- hysteresis ----------------------------------------------------------
+
+    /// Feed `temperatures` through a curve tick by tick, as the daemon does.
+    fn pwms_with_hysteresis(curve: &PwmCurve, temperatures: &[u32]) -> Vec<u8> {
+        let mut last = None;
+        temperatures
+            .iter()
+            .map(|&temperature| {
+                let effective = hysteresis_temperature(last, temperature, curve.hysteresis);
+                last = Some(effective);
+                curve.pwm_at(effective)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hysteresis_holds_pwm_while_temperature_wobbles() {
+        let mut curve = pwm_curve(&[(40, 60), (60, 160), (90, 250)]);
+        curve.hysteresis = 3;
+        let pwms = pwms_with_hysteresis(&curve, &[61, 59, 60, 59, 61, 59]);
+        assert_eq!(pwms, vec![163; 6]);
+    }
+
+    #[test]
+    fn hysteresis_follows_drops_past_the_band() {
+        let mut curve = pwm_curve(&[(40, 60), (60, 160), (90, 250)]);
+        curve.hysteresis = 3;
+        assert_eq!(
+            pwms_with_hysteresis(&curve, &[60, 58, 57, 50]),
+            vec![160, 160, 145, 110]
+        );
+    }
+
+    #[test]
+    fn zero_hysteresis_follows_every_reading() {
+        let curve = pwm_curve(&[(40, 60), (60, 160), (90, 250)]);
+        assert_eq!(
+            pwms_with_hysteresis(&curve, &[60, 58, 61, 59]),
+            vec![160, 150, 163, 155]
+        );
+        assert_eq!(hysteresis_temperature(None, 58, 5), 58);
+    }
+
+    #[test]
+    fn smoothed_temperature_moves_part_way_to_each_reading() {
+        assert_eq!(smoothed_temperature(None, 60, Some(0.25)), 60.0);
+        assert_eq!(smoothed_temperature(Some(60.0), 80, Some(0.25)), 65.0);
+        assert_eq!(smoothed_temperature(Some(60.0), 80, Some(1.0)), 80.0);
+        assert_eq!(smoothed_temperature(Some(60.0), 80, None), 80.0);
+    }
+
+    // -- build_curve_from_points -------------------------------------------
+
+    #[test]
+    fn build_curve_from_points_indexes_table_speeds() {
+        let table = rpm_curve(&[(40, 1600), (50, 2000), (60, 2800), (70, 3600), (80, 4800)]);
+        let curve = build_curve_from_points(&table, &[0, 0, 1, 3, 4, 4, 4, 4, 4, 4]);
+        let points: Vec<(u32, u32)> = curve
+            .points
+            .iter()
+            .map(|p| (p.temperature, p.fan_speed))
+            .collect();
+        assert_eq!(
+            points,
+            vec![(40, 1600), (50, 1600), (60, 2000), (70, 3600), (80, 4800)]
+        );
+        assert_eq!(curve.speed_at(75), 4200);
+    }
+
+    #[test]
+    fn build_curve_from_points_clamps_steps_past_table() {
+        let table = rpm_curve(&[(40, 1600), (60, 2800)]);
+        let curve = build_curve_from_points(&table, &[5, 10, 10, 10, 10, 10, 10, 10, 10, 10]);
+        assert_eq!(curve.points.len(), 2);
+        assert!(curve.points.iter().all(|p| p.fan_speed == 2800));
+    }
+
+    #[test]
+    fn clamp_curve_points_bounds_speeds_and_temperatures() {
+        let reference = FanCurve {
+            min_temp: 40,
+            max_temp: 90,
+            ..rpm_curve(&[(40, 1600), (90, 4800)])
+        };
+        let typed = rpm_curve(&[(30, 900), (60, 3000), (95, 6000)]).points;
+
+        let (points, clamped) = clamp_curve_points(&typed, Some(&reference));
+        let points: Vec<(u32, u32)> = points
+            .iter()
+            .map(|p| (p.temperature, p.fan_speed))
+            .collect();
+        assert_eq!(points, vec![(40, 1600), (60, 3000), (90, 4800)]);
+        assert!(clamped);
+
+        let (points, clamped) = clamp_curve_points(&typed[1..2], Some(&reference));
+        assert_eq!(points, typed[1..2]);
+        assert!(!clamped);
+    }
+
+    #[test]
+    fn clamp_curve_points_without_reference_keeps_points() {
+        let typed = rpm_curve(&[(30, 900), (95, 6000)]).points;
+        assert_eq!(clamp_curve_points(&typed, None), (typed, false));
+    }
+
+    #[test]
+    fn combine_curves_for_fan_takes_max_at_every_point() {
+        // The CPU curve leads at low temperatures, the GPU curve at high ones.
+        let cpu = rpm_curve(&[(40, 2000), (60, 2400), (80, 3000)]);
+        let gpu = FanCurve {
+            sensor_id: 4,
+            ..rpm_curve(&[(50, 1600), (70, 3600), (90, 4800)])
+        };
+        let combined = combine_curves_for_fan(0, &[cpu, gpu]).unwrap();
+        let points: Vec<(u32, u32)> = combined
+            .points
+            .iter()
+            .map(|p| (p.temperature, p.fan_speed))
+            .collect();
+        assert_eq!(
+            points,
+            vec![
+                (40, 2000),
+                (50, 2200),
+                (60, 2600),
+                (70, 3600),
+                (80, 4200),
+                (90, 4800)
+            ]
+        );
+        assert_eq!(combined.fan_id, 0);
+    }
+
+    #[test]
+    fn combine_curves_for_fan_ignores_inactive_and_other_fans() {
+        let inactive = FanCurve {
+            active: false,
+            ..rpm_curve(&[(40, 4800)])
+        };
+        let other_fan = FanCurve {
+            fan_id: 1,
+            ..rpm_curve(&[(40, 4000)])
+        };
+        let cpu = rpm_curve(&[(40, 2000)]);
+        let combined = combine_curves_for_fan(0, &[inactive.clone(), other_fan, cpu]).unwrap();
+        assert_eq!(combined.points[0].fan_speed, 2000);
+        assert!(combine_curves_for_fan(0, &[inactive]).is_none());
+    }
+
+    // -- stall detection -----------------------------------------------------
+
+    #[test]
+    fn stalled_only_when_driven_and_still() {
+        assert!(is_stalled(0, FanCommand::Pwm(128)));
+        assert!(is_stalled(0, FanCommand::Rpm(2000)));
+        assert!(is_stalled(0, FanCommand::FullSpeed));
+        assert!(!is_stalled(0, FanCommand::Auto));
+        assert!(!is_stalled(0, FanCommand::Pwm(0)));
+        assert!(!is_stalled(0, FanCommand::Rpm(0)));
+        assert!(!is_stalled(850, FanCommand::Pwm(128)));
+        assert!(!is_stalled(1, FanCommand::FullSpeed));
+    }
+
+    // -- MonitorStats --------------------------------------------------------
+
+    fn monitored(id: &str, speed_rpm: u32, temperature: Option<u32>) -> Fan {
+        Fan {
+            id: id.to_string(),
+            label: id.to_uppercase(),
+            speed_rpm,
+            temperature,
+            pwm: None,
+            pwm_mode: None,
+            controllable: false,
+            min_rpm: None,
+            max_rpm: None,
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        }
+    }
+
+    #[test]
+    fn monitor_stats_track_min_max_average_per_fan() {
+        let mut stats = MonitorStats::default();
+        stats.record(&[
+            monitored("fan0", 2000, Some(50)),
+            monitored("fan1", 0, None),
+        ]);
+        stats.record(&[
+            monitored("fan0", 2600, Some(55)),
+            monitored("fan1", 0, None),
+        ]);
+        stats.record(&[monitored("fan0", 2300, Some(61))]);
+
+        assert_eq!(stats.samples, 3);
+        assert_eq!(stats.fans.len(), 2);
+        let cpu = &stats.fans[0];
+        assert_eq!(
+            (cpu.rpm.min, cpu.rpm.max, cpu.rpm.average()),
+            (2000, 2600, 2300)
+        );
+        let temps = cpu.temperature.unwrap();
+        assert_eq!((temps.min, temps.max, temps.average()), (50, 61, 55));
+        assert_eq!(stats.fans[1].label, "FAN1");
+        assert_eq!(stats.fans[1].temperature, None);
+    }
+
+    #[test]
+    fn monitor_stats_pick_up_late_temperatures() {
+        let mut stats = MonitorStats::default();
+        stats.record(&[monitored("fan0", 1000, None)]);
+        stats.record(&[monitored("fan0", 1000, Some(40))]);
+        assert_eq!(stats.fans[0].temperature.map(|t| t.average()), Some(40));
+    }
+
+    // -- SmartFanMode --------------------------------------------------------
+
+    #[test]
+    fn smart_fan_mode_names() {
+        assert_eq!(smart_fan_mode_name(2), Some("Balanced"));
+        assert_eq!(smart_fan_mode_name(SMART_FAN_MODE_CUSTOM), Some("Custom"));
+        assert_eq!(smart_fan_mode_name(7), None);
+    }
+
+    #[test]
+    fn thermal_mode_parses_and_maps_to_smart_fan_mode() {
+        let mode: ThermalMode = "Performance".parse().unwrap();
+        assert_eq!(mode, ThermalMode::Performance);
+        assert_eq!(mode.smart_fan_mode(), 3);
+        assert_eq!(
+            smart_fan_mode_name(ThermalMode::Quiet.smart_fan_mode()),
+            Some("Quiet")
+        );
+        assert_eq!(ThermalMode::Balanced.to_string(), "balanced");
+        assert!("custom".parse::<ThermalMode>().is_err());
+    }
+
+    // -- PwmCurve ----------------------------------------------------------
+
+    fn pwm_curve(points: &[(u32, u8)]) -> PwmCurve {
+        PwmCurve {
+            fan_id: "hwmon0/fan1".to_string(),
+            sensor: None,
+            points: points
+                .iter()
+                .map(|&(temperature, pwm)| PwmCurvePoint { temperature, pwm })
+                .collect(),
+            hysteresis: 0,
+            smoothing: None,
+        }
+    }
+
+    #[test]
+    fn pwm_at_interpolates_and_holds_ends() {
+        let curve = pwm_curve(&[(40, 60), (60, 160), (80, 255)]);
+        assert_eq!(curve.pwm_at(20), 60);
+        assert_eq!(curve.pwm_at(40), 60);
+        assert_eq!(curve.pwm_at(50), 110);
+        assert_eq!(curve.pwm_at(60), 160);
+        assert_eq!(curve.pwm_at(70), 208);
+        assert_eq!(curve.pwm_at(80), 255);
+        assert_eq!(curve.pwm_at(95), 255);
+    }
+
+    #[test]
+    fn pwm_curve_validate_rejects_bad_shapes() {
+        assert!(pwm_curve(&[(40, 60), (80, 255)]).validate().is_ok());
+        assert!(pwm_curve(&[]).validate().is_err());
+        assert!(pwm_curve(&[(60, 100), (40, 150)]).validate().is_err());
+        assert!(pwm_curve(&[(40, 150), (60, 100)]).validate().is_err());
+        for smoothing in [0.0, -0.5, 1.5, f64::NAN] {
+            let curve = PwmCurve {
+                smoothing: Some(smoothing),
+                ..pwm_curve(&[(40, 60)])
+            };
+            assert!(curve.validate().is_err(), "smoothing {smoothing}");
+        }
+        let curve = PwmCurve {
+            smoothing: Some(1.0),
+            ..pwm_curve(&[(40, 60)])
+        };
+        assert!(curve.validate().is_ok());
+    }
+
+    #[test]
+    fn pwm_curve_file_defaults_sensor() {
+        let curves: Vec<PwmCurve> = serde_json::from_str(
+            r#"[{"fan_id": "hwmon0/fan1", "points": [{"temperature": 40, "pwm": 60}]}]"#,
+        )
+        .unwrap();
+        assert_eq!(curves[0].sensor, None);
+        assert_eq!(curves[0].pwm_at(30), 60);
+    }
+
+    // -- expected_rpm_direction / watch_rpm_response ---------------------------
+
+    #[test]
+    fn expected_rpm_direction_from_pwm_change() {
+        assert_eq!(
+            expected_rpm_direction(Some(100), 200),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(expected_rpm_direction(Some(200), 100), Some(Ordering::Less));
+        assert_eq!(expected_rpm_direction(Some(100), 100), None);
+        assert_eq!(expected_rpm_direction(None, 100), None);
+    }
+
+    #[test]
+    fn watch_rpm_response_detects_spin_up() {
+        let mut readings = [2050, 2300, 2900].into_iter();
+        let response = watch_rpm_response(2000, Ordering::Greater, 5, || readings.next());
+        assert_eq!(response, SpeedResponse::Responded { rpm: 2300 });
+    }
+
+    #[test]
+    fn watch_rpm_response_detects_slow_down() {
+        let mut readings = [3000, 2800].into_iter();
+        let response = watch_rpm_response(3000, Ordering::Less, 5, || readings.next());
+        assert_eq!(response, SpeedResponse::Responded { rpm: 2800 });
+    }
+
+    #[test]
+    fn watch_rpm_response_ignores_jitter() {
+        let mut readings = [2040, 1980, 2060, 2010].into_iter();
+        let response = watch_rpm_response(2000, Ordering::Greater, 4, || readings.next());
+        assert_eq!(response, SpeedResponse::NoChange { rpm: 2010 });
+    }
+
+    #[test]
+    fn watch_rpm_response_wrong_direction_is_no_change() {
+        let mut readings = [1500, 1200].into_iter();
+        let response = watch_rpm_response(2000, Ordering::Greater, 2, || readings.next());
+        assert_eq!(response, SpeedResponse::NoChange { rpm: 1200 });
+    }
+
+    #[test]
+    fn watch_rpm_response_survives_failed_reads() {
+        let mut readings = [None, None, Some(2600)].into_iter();
+        let response = watch_rpm_response(2000, Ordering::Greater, 3, || readings.next().flatten());
+        assert_eq!(response, SpeedResponse::Responded { rpm: 2600 });
+
+        let response = watch_rpm_response(2000, Ordering::Greater, 3, || None);
+        assert_eq!(response, SpeedResponse::NoChange { rpm: 2000 });
+    }
+
+    // -- quiet_curve_warning -------------------------------------------------
+
+    #[test]
+    fn quiet_curve_warning_flags_floor_only_curve() {
+        let curve = custom_curve([0, 0, 0, 0, 0, 0, 0, 0, 3, 5]);
+        let warning = curve.quiet_curve_warning().expect("should warn");
+        assert!(warning.contains("fan 0 sensor 3"));
+    }
+
+    #[test]
+    fn quiet_curve_warning_flags_low_flat_curve() {
+        let curve = custom_curve([2, 2, 2, 2, 2, 2, 2, 2, 3, 5]);
+        assert!(curve.quiet_curve_warning().is_some());
+    }
+
+    #[test]
+    fn quiet_curve_warning_accepts_legitimately_quiet_curve() {
+        // Silent at idle but ramps up before the safety floor kicks in.
+        let curve = custom_curve([0, 0, 0, 0, 1, 1, 2, 3, 4, 6]);
+        assert!(curve.quiet_curve_warning().is_none());
+    }
+
+    #[test]
+    fn quiet_curve_warning_accepts_raised_top_step() {
+        let curve = custom_curve([0, 0, 0, 0, 0, 0, 0, 0, 3, 8]);
+        assert!(curve.quiet_curve_warning().is_none());
+    }
+
+    #[test]
+    fn quiet_curve_warning_accepts_identity_curve() {
+        let curve = custom_curve([0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        assert!(curve.quiet_curve_warning().is_none());
+    }
+
+    // -- OverheatGuard -------------------------------------------------------
+
+    #[test]
+    fn overheat_guard_trips_above_limit() {
+        let start = Instant::now();
+        let mut guard = OverheatGuard::new(85, 5, Duration::ZERO);
+        assert_eq!(guard.update(85, start), None);
+        assert_eq!(guard.update(86, start), Some(GuardAction::Trip));
+        assert!(guard.tripped());
+        assert_eq!(guard.update(95, start), None);
+    }
+
+    #[test]
+    fn overheat_guard_releases_below_hysteresis_band() {
+        let start = Instant::now();
+        let mut guard = OverheatGuard::new(85, 5, Duration::ZERO);
+        guard.update(90, start);
+        assert_eq!(guard.update(82, start), None);
+        assert_eq!(guard.update(80, start), None);
+        assert_eq!(guard.update(79, start), Some(GuardAction::Release));
+        assert!(!guard.tripped());
+    }
+
+    #[test]
+    fn overheat_guard_holds_for_min_dwell() {
+        let start = Instant::now();
+        let mut guard = OverheatGuard::new(85, 5, Duration::from_secs(30));
+        guard.update(90, start);
+        assert_eq!(guard.update(60, start + Duration::from_secs(10)), None);
+        assert_eq!(
+            guard.update(60, start + Duration::from_secs(30)),
+            Some(GuardAction::Release)
+        );
+    }
+
+    // -- match_fan_id --------------------------------------------------------
+
+    fn labelled(id: &str, label: &str) -> Fan {
+        Fan {
+            label: label.to_string(),
+            ..monitored(id, 2000, None)
+        }
+    }
+
+    #[test]
+    fn match_fan_id_accepts_id_index_and_label() {
+        let fans = [
+            labelled("hwmon2/fan1", "nct6798: CPU Fan"),
+            labelled("hwmon2/fan2", "nct6798: Chassis Fan"),
+        ];
+        assert_eq!(match_fan_id(&fans, "hwmon2/fan2").unwrap(), "hwmon2/fan2");
+        assert_eq!(match_fan_id(&fans, "1").unwrap(), "hwmon2/fan2");
+        assert_eq!(match_fan_id(&fans, "CPU").unwrap(), "hwmon2/fan1");
+        assert_eq!(match_fan_id(&fans, "chassis fan").unwrap(), "hwmon2/fan2");
+        assert!(matches!(
+            match_fan_id(&fans, "2"),
+            Err(FanControlError::FanNotFound(_))
+        ));
+        assert!(matches!(
+            match_fan_id(&fans, "gpu"),
+            Err(FanControlError::FanNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn match_fan_id_lists_candidates_when_ambiguous() {
+        let fans = [labelled("fan0", "CPU Fan"), labelled("fan1", "GPU Fan")];
+        let error = match_fan_id(&fans, "fan").unwrap_err().to_string();
+        assert_eq!(
+            error,
+            "'fan' matches several fans: fan0 (CPU Fan), fan1 (GPU Fan); pass one of their ids"
+        );
+    }
+
+    // -- hex_dump ------------------------------------------------------------
+
+    #[test]
+    fn curves_csv_has_one_row_per_point() {
+        let cpu = rpm_curve(&[(40, 1600), (60, 2800)]);
+        let gpu = FanCurve {
+            fan_id: 1,
+            sensor_id: 4,
+            active: false,
+            ..rpm_curve(&[(50, 2000)])
+        };
+        assert_eq!(
+            curves_csv(&[cpu, gpu]),
+            [
+                "fan_id,sensor_id,active,temp,rpm",
+                "0,3,true,40,1600",
+                "0,3,true,60,2800",
+                "1,4,false,50,2000",
+            ]
+        );
+        assert_eq!(curves_csv(&[]), [CURVES_CSV_HEADER]);
+    }
+
+    #[test]
+    fn hex_dump_rows_of_sixteen_with_offsets() {
+        let bytes: Vec<u8> = (0..18).collect();
+        assert_eq!(
+            hex_dump(&bytes),
+            vec![
+                "0000: 00 01 02 03 04 05 06 07 08 09 0A 0B 0C 0D 0E 0F",
+                "0010: 10 11",
+            ]
+        );
+        assert!(hex_dump(&[]).is_empty());
+    }
+
+    // -- rpm_range_display ---------------------------------------------------
+
+    #[test]
+    fn rpm_range_display_marks_missing_bounds() {
+        let fan = |min_rpm, max_rpm| Fan {
+            id: "hwmon0/fan1".to_string(),
+            label: "fan1".to_string(),
+            speed_rpm: 1200,
+            temperature: None,
+            pwm: None,
+            pwm_mode: None,
+            controllable: false,
+            min_rpm,
+            max_rpm,
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        };
+        assert_eq!(
+            fan(Some(600), Some(2400)).rpm_range_display().as_deref(),
+            Some("600\u{2013}2400")
+        );
+        assert_eq!(
+            fan(None, Some(2400)).rpm_range_display().as_deref(),
+            Some("?\u{2013}2400")
+        );
+        assert_eq!(fan(None, None).rpm_range_display(), None);
+    }
+
+    // -- fan_count_summary ---------------------------------------------------
+
+    #[test]
+    fn fan_count_summary_counts_controllable_fans() {
+        let fan = |controllable| Fan {
+            id: "fan0".to_string(),
+            label: "CPU Fan".to_string(),
+            speed_rpm: 2100,
+            temperature: None,
+            pwm: None,
+            pwm_mode: None,
+            controllable,
+            min_rpm: None,
+            max_rpm: None,
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        };
+        assert_eq!(
+            fan_count_summary(&[fan(true), fan(false), fan(true)]),
+            "3 fans (2 controllable, 1 read-only)"
+        );
+        assert_eq!(
+            fan_count_summary(&[fan(false)]),
+            "1 fan (0 controllable, 1 read-only)"
+        );
+    }
+
+    // -- smooth_steps --------------------------------------------------------
+
+    #[test]
+    fn smooth_steps_keeps_steps_with_ten_points() {
+        let steps = [0, 0, 0, 1, 2, 4, 6, 7, 8, 10];
+        assert_eq!(smooth_steps(steps, 10, DEFAULT_MIN_HIGH_TEMP_RATIO), steps);
+    }
+
+    #[test]
+    fn smooth_steps_two_points_draw_a_straight_line() {
+        // i * 10 / 9, rounded: 1.11 -> 1, 4.44 -> 4, 5.56 -> 6.
+        assert_eq!(
+            smooth_steps(
+                [0, 0, 0, 0, 0, 0, 0, 0, 5, 10],
+                2,
+                DEFAULT_MIN_HIGH_TEMP_RATIO
+            ),
+            [0, 1, 2, 3, 4, 6, 7, 8, 9, 10]
+        );
+    }
+
+    #[test]
+    fn smooth_steps_keeps_endpoints() {
+        let steps = [2, 2, 2, 2, 3, 3, 3, 3, 6, 9];
+        for points in 2..=10 {
+            let smoothed = smooth_steps(steps, points, DEFAULT_MIN_HIGH_TEMP_RATIO);
+            assert_eq!(smoothed[0], 2, "{points} points");
+            assert_eq!(smoothed[9], 9, "{points} points");
+        }
+    }
+
+    #[test]
+    fn smooth_steps_spreads_a_sparse_jump() {
+        // Anchors at 0, 4.5, and 9 read 0, 0, and 10; steps 5-8 are
+        // 1.11, 3.33, 5.56, and 7.78 before rounding.
+        assert_eq!(
+            smooth_steps(
+                [0, 0, 0, 0, 0, 0, 0, 0, 0, 10],
+                3,
+                DEFAULT_MIN_HIGH_TEMP_RATIO
+            ),
+            [0, 0, 0, 0, 0, 1, 3, 6, 8, 10]
+        );
+    }
+
+    #[test]
+    fn smooth_steps_keeps_valid_curves_valid() {
+        let curves = [
+            [0, 0, 0, 0, 0, 0, 0, 0, 3, 5],
+            [1, 1, 1, 1, 1, 1, 1, 1, 3, 5],
+            [0, 0, 0, 1, 2, 4, 6, 7, 8, 10],
+            [0, 0, 0, 0, 0, 0, 0, 0, 10, 10],
+            [10, 10, 10, 10, 10, 10, 10, 10, 10, 10],
+        ];
+        for steps in curves {
+            for points in 2..=10 {
+                let smoothed =
+                    custom_curve(smooth_steps(steps, points, DEFAULT_MIN_HIGH_TEMP_RATIO));
+                assert!(
+                    validate_custom_curve(&smoothed).is_ok(),
+                    "{steps:?} at {points} points gave {:?}",
+                    smoothed.steps
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_steps_clamps_point_count() {
+        let steps = [0, 0, 0, 0, 0, 0, 0, 0, 5, 10];
+        assert_eq!(
+            smooth_steps(steps, 0, DEFAULT_MIN_HIGH_TEMP_RATIO),
+            smooth_steps(steps, 2, DEFAULT_MIN_HIGH_TEMP_RATIO)
+        );
+        assert_eq!(smooth_steps(steps, 50, DEFAULT_MIN_HIGH_TEMP_RATIO), steps);
+    }
+
+    // -- diff_curves ---------------------------------------------------------
+
+    fn paired_curve(fan_id: u32, sensor_id: u32, points: &[(u32, u32)]) -> FanCurve {
+        FanCurve {
+            fan_id,
+            sensor_id,
+            ..rpm_curve(points)
+        }
+    }
+
+    #[test]
+    fn diff_curves_ignores_matching_curves() {
+        let curves = [paired_curve(0, 3, &[(60, 1600), (80, 3200)])];
+        assert!(diff_curves(&curves, &curves).is_empty());
+    }
+
+    #[test]
+    fn diff_curves_reports_point_changes_by_temperature() {
+        let backup = [paired_curve(0, 3, &[(60, 1600), (70, 2400), (80, 3200)])];
+        let live = [paired_curve(0, 3, &[(60, 1600), (80, 2800), (90, 4800)])];
+        assert_eq!(
+            diff_curves(&backup, &live),
+            vec![CurveDiff {
+                fan_id: 0,
+                sensor_id: 3,
+                change: CurveChange::Changed {
+                    points: vec![
+                        PointChange::Removed {
+                            temperature: 70,
+                            fan_speed: 2400
+                        },
+                        PointChange::Changed {
+                            temperature: 80,
+                            backup_speed: 3200,
+                            live_speed: 2800
+                        },
+                        PointChange::Added {
+                            temperature: 90,
+                            fan_speed: 4800
+                        },
+                    ]
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_curves_handles_one_sided_curves_in_order() {
+        let backup = [
+            paired_curve(1, 4, &[(60, 1600)]),
+            paired_curve(0, 3, &[(60, 1600)]),
+        ];
+        let live = [
+            paired_curve(0, 3, &[(60, 1600)]),
+            paired_curve(0, 4, &[(60, 1600)]),
+        ];
+        let diffs = diff_curves(&backup, &live);
+        let summary: Vec<(u32, u32, CurveChange)> = diffs
+            .into_iter()
+            .map(|diff| (diff.fan_id, diff.sensor_id, diff.change))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (0, 4, CurveChange::OnlyLive),
+                (1, 4, CurveChange::OnlyInBackup),
+            ]
+        );
+    }
+
+    #[test]
+    fn reverted_curves_only_checks_held_pairs() {
+        let held = [
+            paired_curve(0, 3, &[(60, 2000), (80, 3600)]),
+            paired_curve(1, 4, &[(60, 2000)]),
+        ];
+        let mut live = vec![
+            paired_curve(0, 3, &[(60, 2000), (80, 3600)]),
+            paired_curve(1, 4, &[(60, 2000)]),
+            paired_curve(0, 4, &[(60, 1600)]),
+        ];
+        assert!(reverted_curves(&held, &live).is_empty());
+
+        live[0].points[1].fan_speed = 3200;
+        live.remove(1);
+        assert_eq!(reverted_curves(&held, &live), [(0, 3), (1, 4)]);
+    }
+
+    #[test]
+    fn curve_diff_json_is_flat() {
+        let diff = CurveDiff {
+            fan_id: 1,
+            sensor_id: 4,
+            change: CurveChange::OnlyInBackup,
+        };
+        assert_eq!(
+            serde_json::to_string(&diff).unwrap(),
+            r#"{"fan_id":1,"sensor_id":4,"status":"only_in_backup"}"#
+        );
     }
 }
@@ -8,32 +8,98 @@
 //!
 //! The controller lives on a dedicated worker thread (required because WMI COM
 //! objects are `!Send`). Communication happens over `mpsc` channels. The worker
-//! auto-polls fan data every 1.5 s via `recv_timeout`.
+//! auto-polls fan data every 1.5 s via `recv_timeout`; with `gui --once` it
+//! discovers once and then blocks until a command such as Refresh arrives.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use eframe::egui;
+use egui_plot::{Legend, Line, LineStyle, MarkerShape, Plot, PlotPoint, PlotPoints, Points};
 use log::{debug, info, warn};
 
-use crate::fan::{Fan, FanCurve};
-use crate::platform::create_controller;
+use crate::config::{self, HeldSpeeds};
+use crate::errors::FanControlError;
+use crate::fan::{
+    clamp_curve_points, format_pwm, format_sensor_temps, high_temp_floors, percent_to_pwm,
+    pwm_to_percent, validate_custom_curve, validate_custom_curve_with_policy, CustomFanCurve, Fan,
+    FanCurve, SensorTemperature, DEFAULT_MIN_HIGH_TEMP_RATIO,
+};
+use crate::platform::{create_controller, no_control_reason, FanController};
 
 // ---------------------------------------------------------------------------
 // Worker <-> UI protocol
 // ---------------------------------------------------------------------------
 
+/// One fan/sensor curve in the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct CurveEditKey {
+    fan_id: u32,
+    sensor_id: u32,
+}
+
 enum WorkerCommand {
     Refresh,
-    SetPwm { fan_id: String, pwm: u8 },
+    SetPwm {
+        fan_id: String,
+        pwm: u8,
+    },
+    SetRpm {
+        fan_id: String,
+        rpm: u32,
+    },
+    /// Send PWM 0 (automatic) to every listed fan and stop re-applying
+    /// held values.
+    ResetAllAuto {
+        fan_ids: Vec<String>,
+    },
+    /// Write an edited curve to the EC.
+    SetCurve {
+        key: CurveEditKey,
+        curve: CustomFanCurve,
+    },
+    /// Turn "Remember manual speeds" on or off.
+    RememberSpeeds(bool),
 }
 
 enum WorkerResponse {
     FanData(Vec<Fan>),
     CurveData(HashMap<String, Vec<FanCurve>>),
-    PwmSet { fan_id: String, pwm: u8 },
+    PwmSet {
+        fan_id: String,
+        pwm: u8,
+    },
+    RpmSet {
+        fan_id: String,
+        rpm: u32,
+    },
+    /// Per-sensor temperatures, sent each poll when the backend has them.
+    SensorTemps(Vec<SensorTemperature>),
+    /// `count` fans were returned to automatic control.
+    AllAuto {
+        count: usize,
+    },
+    /// Manual speeds remembered from the last session were re-applied.
+    SpeedsRestored {
+        pwm: HashMap<String, u8>,
+        rpm: HashMap<String, u32>,
+    },
+    /// The curve for `key` was written.
+    CurveSet {
+        key: CurveEditKey,
+    },
+    /// Writing the curve for `key` failed.
+    CurveFailed {
+        key: CurveEditKey,
+        message: String,
+    },
+    /// The process lacks the rights to change fan settings.
+    ReadOnly(String),
+    /// Fans were found but none can be controlled, with the reason.
+    NoControllableFans(String),
     Error(String),
 }
 
@@ -41,11 +107,116 @@ enum WorkerResponse {
 // Worker thread
 // ---------------------------------------------------------------------------
 
+/// Send the current sensor temperatures, if the backend reports any. A
+/// failed read is only logged; fan data still arrives.
+fn send_sensor_temps(controller: &dyn FanController, response_tx: &mpsc::Sender<WorkerResponse>) {
+    match controller.sensor_temperatures() {
+        Ok(temps) if !temps.is_empty() => {
+            let _ = response_tx.send(WorkerResponse::SensorTemps(temps));
+        }
+        Ok(_) => {}
+        Err(error) => debug!("sensor temperatures unavailable: {error}"),
+    }
+}
+
+/// What a failed privilege check tells the UI. Only missing privileges make
+/// it read-only; any other failure is shown as an error and leaves the
+/// controls enabled.
+fn privilege_response(error: FanControlError) -> WorkerResponse {
+    match error {
+        FanControlError::PermissionDenied(_) => WorkerResponse::ReadOnly(error.to_string()),
+        error => WorkerResponse::Error(format!("privilege check failed: {error}")),
+    }
+}
+
+/// How often the worker refreshes on its own unless started with `--once`.
+const AUTO_POLL: Duration = Duration::from_millis(1500);
+
+/// The worker's next command. With `auto_poll`, a quiet period of that length
+/// yields a `Refresh`; without it, block until the UI sends something. `None`
+/// once the UI has gone away.
+fn next_command(
+    command_rx: &mpsc::Receiver<WorkerCommand>,
+    auto_poll: Option<Duration>,
+) -> Option<WorkerCommand> {
+    match auto_poll {
+        Some(interval) => match command_rx.recv_timeout(interval) {
+            Ok(command) => Some(command),
+            Err(mpsc::RecvTimeoutError::Timeout) => Some(WorkerCommand::Refresh),
+            Err(mpsc::RecvTimeoutError::Disconnected) => None,
+        },
+        None => command_rx.recv().ok(),
+    }
+}
+
+/// The remembered speeds from `saved` that can still be applied: those of
+/// fans in `fans` that are controllable. Anything else is stale, e.g. a fan
+/// that went away with a dock or a backend change, and is dropped.
+fn restore_held_speeds(
+    saved: HeldSpeeds,
+    fans: &[Fan],
+) -> (HashMap<String, u8>, HashMap<String, u32>) {
+    if !saved.remember {
+        return (HashMap::new(), HashMap::new());
+    }
+    let usable = |fan_id: &String| {
+        let found = fans.iter().any(|fan| &fan.id == fan_id && fan.controllable);
+        if !found {
+            warn!("not restoring held speed for {fan_id}: no such controllable fan");
+        }
+        found
+    };
+    let pwm = saved
+        .pwm
+        .into_iter()
+        .filter(|(fan_id, _)| usable(fan_id))
+        .collect();
+    let rpm = saved
+        .rpm
+        .into_iter()
+        .filter(|(fan_id, _)| usable(fan_id))
+        .collect();
+    (pwm, rpm)
+}
+
+/// Write the held speeds to `path` for the next start. With `remember` off
+/// only that choice is written, so nothing is restored.
+fn save_held_speeds(
+    path: &Path,
+    remember: bool,
+    held_pwm: &HashMap<String, u8>,
+    held_rpm: &HashMap<String, u32>,
+) {
+    let mut held = HeldSpeeds {
+        remember,
+        ..HeldSpeeds::default()
+    };
+    if remember {
+        held.pwm = held_pwm
+            .iter()
+            .map(|(id, pwm)| (id.clone(), *pwm))
+            .collect();
+        held.rpm = held_rpm
+            .iter()
+            .map(|(id, rpm)| (id.clone(), *rpm))
+            .collect();
+    }
+    if let Err(error) = config::save_held_speeds(path, &held) {
+        warn!("saving held speeds failed: {error}");
+    }
+}
+
+/// Start the worker. `saved` holds the speeds remembered from the last
+/// session, re-applied after the first discovery; on a clean exit the
+/// current ones are written back to `state_path` if remembering is on.
 fn spawn_worker(
     command_rx: mpsc::Receiver<WorkerCommand>,
     response_tx: mpsc::Sender<WorkerResponse>,
     repaint_ctx: egui::Context,
-) {
+    auto_poll: Option<Duration>,
+    saved: HeldSpeeds,
+    state_path: PathBuf,
+) -> JoinHandle<()> {
     thread::spawn(move || {
         let controller = match create_controller() {
             Ok(c) => c,
@@ -57,9 +228,17 @@ fn spawn_worker(
                 return;
             }
         };
+        if let Err(error) = controller.check_privileges() {
+            warn!("privilege check: {error}");
+            let _ = response_tx.send(privilege_response(error));
+        }
+
         // Last PWM value set by the user per fan. Re-applied each poll
         // cycle so Fn+Q or other BIOS overrides don't stick.
         let mut held_pwm: HashMap<String, u8> = HashMap::new();
+        // Same, for fans driven by an RPM target instead of a PWM value.
+        let mut held_rpm: HashMap<String, u32> = HashMap::new();
+        let mut remember = saved.remember;
 
         // Initial discovery — includes curve data on first call.
         match controller.discover() {
@@ -75,31 +254,35 @@ fn spawn_worker(
                 if !curves_map.is_empty() {
                     let _ = response_tx.send(WorkerResponse::CurveData(curves_map));
                 }
+                if !fans.is_empty() && !fans.iter().any(|f| f.controllable) {
+                    let reason = no_control_reason(&*controller);
+                    warn!("no controllable fans: {reason}");
+                    let _ = response_tx.send(WorkerResponse::NoControllableFans(reason));
+                }
+                (held_pwm, held_rpm) = restore_held_speeds(saved, fans);
+                if !held_pwm.is_empty() || !held_rpm.is_empty() {
+                    info!("restoring held speeds: {held_pwm:?} {held_rpm:?}");
+                    reapply_held(&*controller, &held_pwm, &held_rpm);
+                    let _ = response_tx.send(WorkerResponse::SpeedsRestored {
+                        pwm: held_pwm.clone(),
+                        rpm: held_rpm.clone(),
+                    });
+                }
                 let _ = response_tx.send(WorkerResponse::FanData(fans.clone()));
             }
             Err(error) => {
                 let _ = response_tx.send(WorkerResponse::Error(error.to_string()));
             }
         }
+        send_sensor_temps(&*controller, &response_tx);
         repaint_ctx.request_repaint();
 
-        loop {
-            // Wait for a command, or timeout to auto-poll.
-            let command = match command_rx.recv_timeout(Duration::from_millis(1500)) {
-                Ok(command) => command,
-                Err(mpsc::RecvTimeoutError::Timeout) => WorkerCommand::Refresh,
-                Err(mpsc::RecvTimeoutError::Disconnected) => break,
-            };
-
+        // Wait for a command, or timeout to auto-poll.
+        while let Some(command) = next_command(&command_rx, auto_poll) {
             match command {
                 WorkerCommand::Refresh => {
-                    // Re-apply held PWM values before polling.
-                    for (fan_id, pwm) in &held_pwm {
-                        debug!("re-applying held PWM: {fan_id}={pwm}");
-                        if let Err(error) = controller.set_pwm(fan_id, *pwm) {
-                            warn!("re-apply {fan_id}={pwm} failed: {error}");
-                        }
-                    }
+                    // Re-apply held values before polling.
+                    reapply_held(&*controller, &held_pwm, &held_rpm);
                     match controller.discover() {
                         Ok(ref fans) => {
                             for fan in fans {
@@ -112,11 +295,13 @@ fn spawn_worker(
                             let _ = response_tx.send(WorkerResponse::Error(error.to_string()));
                         }
                     }
+                    send_sensor_temps(&*controller, &response_tx);
                 }
                 WorkerCommand::SetPwm { fan_id, pwm } => {
                     info!("user SetPwm: {fan_id}={pwm}");
                     match controller.set_pwm(&fan_id, pwm) {
                         Ok(()) => {
+                            held_rpm.remove(&fan_id);
                             if pwm == 0 {
                                 // PWM 0 = return to BIOS auto; stop re-applying.
                                 held_pwm.remove(&fan_id);
@@ -132,11 +317,95 @@ fn spawn_worker(
                         }
                     }
                 }
+                WorkerCommand::SetRpm { fan_id, rpm } => {
+                    info!("user SetRpm: {fan_id}={rpm}");
+                    match controller.set_rpm(&fan_id, rpm) {
+                        Ok(()) => {
+                            held_pwm.remove(&fan_id);
+                            held_rpm.insert(fan_id.clone(), rpm);
+                            info!("held_rpm updated: {:?}", held_rpm);
+                            let _ = response_tx.send(WorkerResponse::RpmSet { fan_id, rpm });
+                        }
+                        Err(error) => {
+                            warn!("SetRpm {fan_id}={rpm} failed: {error}");
+                            let _ = response_tx.send(WorkerResponse::Error(error.to_string()));
+                        }
+                    }
+                }
+                WorkerCommand::ResetAllAuto { fan_ids } => {
+                    info!("user ResetAllAuto: {:?}", fan_ids);
+                    held_pwm.clear();
+                    held_rpm.clear();
+                    let mut count = 0;
+                    for fan_id in &fan_ids {
+                        match controller.set_pwm(fan_id, 0) {
+                            Ok(()) => count += 1,
+                            Err(error) => {
+                                warn!("reset {fan_id} to auto failed: {error}");
+                                let _ = response_tx
+                                    .send(WorkerResponse::Error(format!("{fan_id}: {error}")));
+                            }
+                        }
+                    }
+                    let _ = response_tx.send(WorkerResponse::AllAuto { count });
+                }
+                WorkerCommand::SetCurve { key, curve } => {
+                    info!(
+                        "user SetCurve: fan {} sensor {} steps {:?}",
+                        key.fan_id, key.sensor_id, curve.steps
+                    );
+                    match controller.set_custom_curve(&curve) {
+                        Ok(()) => {
+                            let _ = response_tx.send(WorkerResponse::CurveSet { key });
+                        }
+                        Err(error) => {
+                            warn!(
+                                "SetCurve fan {} sensor {} failed: {error}",
+                                key.fan_id, key.sensor_id
+                            );
+                            let _ = response_tx.send(WorkerResponse::CurveFailed {
+                                key,
+                                message: error.to_string(),
+                            });
+                        }
+                    }
+                }
+                WorkerCommand::RememberSpeeds(enabled) => {
+                    info!("user RememberSpeeds: {enabled}");
+                    remember = enabled;
+                    save_held_speeds(&state_path, remember, &held_pwm, &held_rpm);
+                }
             }
 
             repaint_ctx.request_repaint();
         }
-    });
+
+        // The UI hung up: a clean exit.
+        if remember {
+            save_held_speeds(&state_path, remember, &held_pwm, &held_rpm);
+        }
+    })
+}
+
+/// Write the held PWM values and RPM targets again. Failures are only
+/// logged; the next poll tries again.
+fn reapply_held(
+    controller: &dyn FanController,
+    held_pwm: &HashMap<String, u8>,
+    held_rpm: &HashMap<String, u32>,
+) {
+    for (fan_id, pwm) in held_pwm {
+        debug!("re-applying held PWM: {fan_id}={pwm}");
+        if let Err(error) = controller.set_pwm(fan_id, *pwm) {
+            warn!("re-apply {fan_id}={pwm} failed: {error}");
+        }
+    }
+    for (fan_id, rpm) in held_rpm {
+        debug!("re-applying held RPM: {fan_id}={rpm}");
+        if let Err(error) = controller.set_rpm(fan_id, *rpm) {
+            warn!("re-apply {fan_id}={rpm} RPM failed: {error}");
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -146,64 +415,464 @@ fn spawn_worker(
 struct FanControlApp {
     fans: Vec<Fan>,
     slider_values: HashMap<String, f32>,
+    /// Drive sliders in RPM (for fans with a known RPM range) instead of PWM.
+    rpm_mode: bool,
+    /// Drive PWM sliders in percent (0–100) instead of raw 0–255 values.
+    percent_mode: bool,
+    /// Moving or setting one PWM slider does the same to every controllable
+    /// fan ("Link fans").
+    link_fans: bool,
+    rpm_slider_values: HashMap<String, f32>,
+    /// Last RPM target the user set per fan, shown next to the actual RPM.
+    rpm_targets: HashMap<String, u32>,
+    /// Latest per-sensor temperatures, shown in the header.
+    sensor_temps: Vec<SensorTemperature>,
+    /// Recent RPM readings per fan, oldest first, capped at `RPM_HISTORY_LEN`.
+    rpm_history: HashMap<String, VecDeque<u32>>,
+    /// Set when the worker reports missing privileges; disables controls.
+    read_only_reason: Option<String>,
+    /// Set when no discovered fan is controllable, with the reason.
+    no_control_reason: Option<String>,
     /// Curve data per fan, sent once at startup.
     fan_curves: HashMap<String, Vec<FanCurve>>,
+    /// Working copy of `fan_curves` that the curve editor modifies.
+    editing_curves: HashMap<String, Vec<FanCurve>>,
+    /// Curves sent to the worker whose write has not answered yet.
+    applying_curves: HashSet<CurveEditKey>,
+    /// Where curve profiles are saved and listed from.
+    profiles_dir: PathBuf,
+    /// Saved profile names, refreshed after each save.
+    profiles: Vec<String>,
+    /// Name typed for the next "Save Profile".
+    profile_name: String,
+    /// Existing profile the next "Save Profile" may replace, set after the
+    /// first click on a taken name.
+    confirm_overwrite: Option<String>,
+    /// Profile picked in the dropdown for "Load Profile".
+    selected_profile: Option<String>,
     status_message: String,
+    /// When fan data last arrived from the worker.
+    last_updated: Option<SystemTime>,
+    /// Dark (default) or light visuals, kept for the session.
+    dark_mode: bool,
+    /// Re-apply manual speeds on the next start ("Remember manual speeds").
+    remember_speeds: bool,
+    /// Recent errors shown as dismissible banners, oldest first.
+    error_toasts: VecDeque<ErrorToast>,
     command_tx: mpsc::Sender<WorkerCommand>,
     response_rx: mpsc::Receiver<WorkerResponse>,
+    /// The worker thread, joined on exit so it can save held speeds.
+    worker: Option<JoinHandle<()>>,
 }
 
+/// An error banner and when it was raised.
+struct ErrorToast {
+    raised: Instant,
+    message: String,
+}
+
+/// How long an error banner stays up unless dismissed.
+const ERROR_TOAST_TTL: Duration = Duration::from_secs(6);
+
+/// Most error banners shown at once; older ones are dropped first.
+const MAX_ERROR_TOASTS: usize = 5;
+
+/// RPM samples kept per fan for the history graph (~90 s at the 1.5 s poll).
+const RPM_HISTORY_LEN: usize = 60;
+
 impl FanControlApp {
     fn new(
         command_tx: mpsc::Sender<WorkerCommand>,
         response_rx: mpsc::Receiver<WorkerResponse>,
     ) -> Self {
+        let profiles_dir = config::profiles_dir();
         Self {
             fans: Vec::new(),
             slider_values: HashMap::new(),
+            rpm_mode: false,
+            percent_mode: false,
+            link_fans: false,
+            rpm_slider_values: HashMap::new(),
+            rpm_targets: HashMap::new(),
+            sensor_temps: Vec::new(),
+            rpm_history: HashMap::new(),
+            read_only_reason: None,
+            no_control_reason: None,
             fan_curves: HashMap::new(),
+            editing_curves: HashMap::new(),
+            applying_curves: HashSet::new(),
+            profiles: config::list_profiles(&profiles_dir),
+            profiles_dir,
+            profile_name: String::new(),
+            confirm_overwrite: None,
+            selected_profile: None,
             status_message: "Discovering fans...".into(),
+            last_updated: None,
+            dark_mode: true,
+            remember_speeds: false,
+            error_toasts: VecDeque::new(),
             command_tx,
             response_rx,
+            worker: None,
         }
     }
 
     fn drain_responses(&mut self) {
         while let Ok(response) = self.response_rx.try_recv() {
-            match response {
-                WorkerResponse::FanData(fans) => {
-                    for fan in &fans {
-                        if let Some(pwm) = fan.pwm {
-                            self.slider_values
-                                .entry(fan.id.clone())
-                                .or_insert(pwm as f32);
-                        }
+            self.handle_response(response);
+        }
+    }
+
+    /// Apply a single worker response to the UI state. Kept free of egui
+    /// calls so it can be unit tested.
+    fn handle_response(&mut self, response: WorkerResponse) {
+        match response {
+            WorkerResponse::FanData(fans) => {
+                for fan in &fans {
+                    if let Some(pwm) = fan.pwm {
+                        self.slider_values
+                            .entry(fan.id.clone())
+                            .or_insert(pwm as f32);
+                    }
+                    if let (Some(min_rpm), Some(max_rpm)) = (fan.min_rpm, fan.max_rpm) {
+                        self.rpm_slider_values
+                            .entry(fan.id.clone())
+                            .or_insert(fan.speed_rpm.clamp(min_rpm, max_rpm) as f32);
+                    }
+                }
+                // Forget fans that disappeared so their history does not
+                // resurface stale if the id comes back.
+                self.rpm_history
+                    .retain(|id, _| fans.iter().any(|fan| &fan.id == id));
+                for fan in &fans {
+                    let history = self.rpm_history.entry(fan.id.clone()).or_default();
+                    if history.len() == RPM_HISTORY_LEN {
+                        history.pop_front();
+                    }
+                    history.push_back(fan.speed_rpm);
+                }
+                self.fans = fans;
+                self.status_message = "OK".into();
+                self.last_updated = Some(SystemTime::now());
+            }
+            WorkerResponse::CurveData(curves) => {
+                self.editing_curves = curves.clone();
+                self.fan_curves = curves;
+            }
+            WorkerResponse::PwmSet { fan_id, pwm } => {
+                self.rpm_targets.remove(&fan_id);
+                self.status_message = format!("Set {} to {}", fan_id, format_pwm(pwm));
+            }
+            WorkerResponse::RpmSet { fan_id, rpm } => {
+                self.status_message = format!("Set {} target to {} RPM", fan_id, rpm);
+                self.rpm_targets.insert(fan_id, rpm);
+            }
+            WorkerResponse::SensorTemps(temps) => self.sensor_temps = temps,
+            WorkerResponse::AllAuto { count } => {
+                // Drop user-set slider positions so the next poll re-seeds
+                // them from the firmware-reported values.
+                self.slider_values.clear();
+                self.rpm_slider_values.clear();
+                self.rpm_targets.clear();
+                self.status_message = format!("Returned {} fans to automatic", count);
+            }
+            WorkerResponse::SpeedsRestored { pwm, rpm } => {
+                self.status_message =
+                    format!("Restored manual speeds for {} fans", pwm.len() + rpm.len());
+                for (fan_id, pwm) in pwm {
+                    self.slider_values.insert(fan_id, pwm as f32);
+                }
+                for (fan_id, rpm) in rpm {
+                    self.rpm_slider_values.insert(fan_id.clone(), rpm as f32);
+                    self.rpm_targets.insert(fan_id, rpm);
+                }
+            }
+            WorkerResponse::CurveSet { key } => {
+                self.applying_curves.remove(&key);
+                // The EC now holds the edited curve; make it the baseline
+                // that "Reset to Current" returns to.
+                let applied =
+                    self.editing_curves.values().flatten().find(|curve| {
+                        curve.fan_id == key.fan_id && curve.sensor_id == key.sensor_id
+                    });
+                if let Some(applied) = applied.cloned() {
+                    if let Some(current) = self.fan_curves.values_mut().flatten().find(|curve| {
+                        curve.fan_id == key.fan_id && curve.sensor_id == key.sensor_id
+                    }) {
+                        current.points = applied.points;
                     }
-                    self.fans = fans;
-                    self.status_message = "OK".into();
                 }
-                WorkerResponse::CurveData(curves) => {
-                    self.fan_curves = curves;
+                self.status_message = format!(
+                    "Applied curve for fan {} sensor {}",
+                    key.fan_id, key.sensor_id
+                );
+            }
+            WorkerResponse::CurveFailed { key, message } => {
+                self.applying_curves.remove(&key);
+                self.push_error(format!(
+                    "fan {} sensor {}: {message}",
+                    key.fan_id, key.sensor_id
+                ));
+            }
+            WorkerResponse::ReadOnly(reason) => {
+                self.read_only_reason = Some(reason);
+            }
+            WorkerResponse::NoControllableFans(reason) => {
+                self.no_control_reason = Some(reason);
+            }
+            WorkerResponse::Error(message) => self.push_error(message),
+        }
+    }
+
+    /// With "Link fans" on, move every other controllable fan's PWM slider to
+    /// `pwm` after `fan_id`'s was moved.
+    fn link_sliders(&mut self, fan_id: &str, pwm: f32) {
+        if !self.link_fans {
+            return;
+        }
+        for fan in self.fans.iter().filter(|fan| fan.controllable) {
+            if fan.id != fan_id {
+                self.slider_values.insert(fan.id.clone(), pwm);
+            }
+        }
+    }
+
+    /// Send `pwm` for `fan_id`, or with "Link fans" on for every
+    /// controllable fan. Each write is answered on its own, so a fan that
+    /// fails gets its own error banner while the others report success.
+    fn send_pwm(&mut self, fan_id: &str, pwm: u8) {
+        let fan_ids: Vec<String> = if self.link_fans {
+            self.fans
+                .iter()
+                .filter(|fan| fan.controllable)
+                .map(|fan| fan.id.clone())
+                .collect()
+        } else {
+            vec![fan_id.to_string()]
+        };
+        for fan_id in fan_ids {
+            let _ = self.command_tx.send(WorkerCommand::SetPwm { fan_id, pwm });
+        }
+    }
+
+    /// Show `message` as an error banner, leaving the status line with the
+    /// last successful action.
+    fn push_error(&mut self, message: impl Into<String>) {
+        if self.error_toasts.len() == MAX_ERROR_TOASTS {
+            self.error_toasts.pop_front();
+        }
+        self.error_toasts.push_back(ErrorToast {
+            raised: Instant::now(),
+            message: message.into(),
+        });
+    }
+
+    /// Drop error banners older than `ERROR_TOAST_TTL` at `now`.
+    fn prune_errors(&mut self, now: Instant) {
+        self.error_toasts
+            .retain(|toast| now.saturating_duration_since(toast.raised) < ERROR_TOAST_TTL);
+    }
+
+    /// Send the edited curve for `key` to the worker, unless a write for it
+    /// is still in flight. Returns whether a command was sent.
+    fn apply_curve(&mut self, key: CurveEditKey) -> bool {
+        if self.applying_curves.contains(&key) {
+            return false;
+        }
+        let pair = self.fan_curves.iter().find_map(|(fan_id, curves)| {
+            let index = curves
+                .iter()
+                .position(|c| c.fan_id == key.fan_id && c.sensor_id == key.sensor_id)?;
+            Some((&curves[index], self.editing_curves.get(fan_id)?.get(index)?))
+        });
+        let Some((current, edited)) = pair else {
+            return false;
+        };
+        if current.points.len() != 10 {
+            self.push_error(format!(
+                "fan {} sensor {}: only 10-point curves can be applied",
+                key.fan_id, key.sensor_id
+            ));
+            return false;
+        }
+        if clamp_curve_points(&edited.points, Some(current)).1 {
+            warn!("{}", clamp_warning(current));
+        }
+        let curve = custom_curve_from_edit(current, edited);
+        match validate_custom_curve(&curve) {
+            Ok(validation) => {
+                for warning in &validation.warnings {
+                    warn!("{warning}");
                 }
-                WorkerResponse::PwmSet { fan_id, pwm } => {
-                    self.status_message = format!("Set {} PWM to {}", fan_id, pwm);
+            }
+            Err(error) => {
+                self.push_error(error.to_string());
+                return false;
+            }
+        }
+        if self
+            .command_tx
+            .send(WorkerCommand::SetCurve { key, curve })
+            .is_err()
+        {
+            return false;
+        }
+        self.applying_curves.insert(key);
+        self.status_message = format!(
+            "Applying curve for fan {} sensor {}...",
+            key.fan_id, key.sensor_id
+        );
+        true
+    }
+
+    /// Save the edited curves as profile `profile_name`. An existing profile
+    /// is only replaced on a second click with the same name.
+    fn save_profile(&mut self) {
+        let name = self.profile_name.trim().to_string();
+        let overwrite = self
+            .confirm_overwrite
+            .take()
+            .is_some_and(|armed| armed == name);
+        if !overwrite && config::list_profiles(&self.profiles_dir).contains(&name) {
+            self.push_error(format!(
+                "profile '{name}' already exists; click Save Profile again to overwrite it"
+            ));
+            self.confirm_overwrite = Some(name);
+            return;
+        }
+        let profile = profile_from_curves(&self.fan_curves, &self.editing_curves);
+        match config::save_profile(&self.profiles_dir, &name, &profile, overwrite) {
+            Ok(path) => {
+                self.profiles = config::list_profiles(&self.profiles_dir);
+                self.selected_profile = Some(self.profile_name.trim().to_string());
+                self.status_message = format!("Saved profile to {}", path.display());
+                let warnings = profile_warnings(&self.fan_curves, &profile);
+                for warning in &warnings {
+                    warn!("{warning}");
                 }
-                WorkerResponse::Error(message) => {
-                    self.status_message = format!("Error: {}", message);
+                if let Some(first) = warnings.first() {
+                    self.status_message
+                        .push_str(&format!(" \u{2014} Warning: {first}"));
+                    if warnings.len() > 1 {
+                        self.status_message
+                            .push_str(&format!(" (+{} more in the log)", warnings.len() - 1));
+                    }
                 }
             }
+            Err(error) => self.push_error(error),
+        }
+    }
+
+    /// Load the selected profile into the curve editor.
+    fn load_profile(&mut self) {
+        let Some(name) = self.selected_profile.clone() else {
+            return;
+        };
+        let profile = match config::load_profile(&self.profiles_dir, &name) {
+            Ok(profile) => profile,
+            Err(error) => {
+                self.push_error(error);
+                return;
+            }
+        };
+        let matched = apply_profile(&self.fan_curves, &mut self.editing_curves, &profile);
+        if matched == 0 {
+            self.push_error(format!(
+                "profile '{name}' matches none of this machine's curves"
+            ));
+        } else {
+            self.status_message = format!("Loaded profile '{name}' ({matched} curves)");
         }
     }
 }
 
 impl eframe::App for FanControlApp {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Hang up so the worker leaves its loop, then wait for it to save
+        // the held speeds.
+        self.command_tx = mpsc::channel().0;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.drain_responses();
+        self.prune_errors(Instant::now());
+        if !self.error_toasts.is_empty() {
+            // Keep ticking so banners age out without user input.
+            ctx.request_repaint_after(Duration::from_secs(1));
+        }
 
         // Top panel — header.
         egui::TopBottomPanel::top("header").show(ctx, |ui| {
             ui.add_space(4.0);
-            ui.heading("Fan Control");
+            ui.horizontal(|ui| {
+                ui.heading("Fan Control");
+                if !self.sensor_temps.is_empty() {
+                    ui.label(format_sensor_temps(&self.sensor_temps));
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    let fan_ids: Vec<String> = self
+                        .fans
+                        .iter()
+                        .filter(|f| f.controllable)
+                        .map(|f| f.id.clone())
+                        .collect();
+                    let can_link = fan_ids.len() > 1;
+                    if ui
+                        .button("Refresh")
+                        .on_hover_text("Read fan speeds and temperatures now")
+                        .clicked()
+                    {
+                        let _ = self.command_tx.send(WorkerCommand::Refresh);
+                    }
+                    let theme_label = if self.dark_mode { "Light" } else { "Dark" };
+                    if ui
+                        .button(theme_label)
+                        .on_hover_text("Switch between dark and light theme")
+                        .clicked()
+                    {
+                        self.dark_mode = !self.dark_mode;
+                        ctx.set_visuals(visuals_for(self.dark_mode));
+                    }
+                    let can_reset = self.read_only_reason.is_none() && !fan_ids.is_empty();
+                    if ui
+                        .add_enabled(can_reset, egui::Button::new("Reset all to auto"))
+                        .clicked()
+                    {
+                        let _ = self
+                            .command_tx
+                            .send(WorkerCommand::ResetAllAuto { fan_ids });
+                    }
+                    if self
+                        .fans
+                        .iter()
+                        .any(|f| f.min_rpm.is_some() && f.max_rpm.is_some())
+                    {
+                        ui.checkbox(&mut self.rpm_mode, "Control in RPM");
+                    }
+                    if can_link {
+                        ui.checkbox(&mut self.link_fans, "Link fans").on_hover_text(
+                            "Move every fan's PWM slider together and set them all at once",
+                        );
+                    }
+                    ui.checkbox(&mut self.percent_mode, "Percent")
+                        .on_hover_text("Set PWM in percent instead of 0\u{2013}255");
+                    if ui
+                        .checkbox(&mut self.remember_speeds, "Remember manual speeds")
+                        .on_hover_text(
+                            "Re-apply the speeds set here on the next start instead of \
+                             returning to automatic",
+                        )
+                        .changed()
+                    {
+                        let _ = self
+                            .command_tx
+                            .send(WorkerCommand::RememberSpeeds(self.remember_speeds));
+                    }
+                });
+            });
             ui.add_space(4.0);
         });
 
@@ -213,18 +882,87 @@ impl eframe::App for FanControlApp {
             ui.horizontal(|ui| {
                 ui.label("Status:");
                 ui.label(&self.status_message);
+                if let Some(updated) = self.last_updated {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(format!("Last updated {} UTC", clock_time(updated)));
+                    });
+                }
             });
             ui.add_space(2.0);
         });
 
         // Central panel — fan cards.
+        let mut apply_key = None;
         egui::CentralPanel::default().show(ctx, |ui| {
             egui::ScrollArea::vertical().show(ui, |ui| {
+                // Transient error banners, newest last.
+                let mut dismissed = None;
+                for (index, toast) in self.error_toasts.iter().enumerate() {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(170, 30, 30))
+                        .inner_margin(6.0)
+                        .rounding(4.0)
+                        .show(ui, |ui| {
+                            ui.set_min_width(ui.available_width());
+                            ui.horizontal(|ui| {
+                                ui.colored_label(
+                                    egui::Color32::WHITE,
+                                    format!(
+                                        "[{}s ago] {}",
+                                        toast.raised.elapsed().as_secs(),
+                                        toast.message
+                                    ),
+                                );
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        if ui.small_button("\u{2715}").clicked() {
+                                            dismissed = Some(index);
+                                        }
+                                    },
+                                );
+                            });
+                        });
+                    ui.add_space(2.0);
+                }
+                if let Some(index) = dismissed {
+                    self.error_toasts.remove(index);
+                }
+
+                // Missing privileges banner — shown before any fan data so
+                // users know upfront that the sliders will not work.
+                if let Some(reason) = &self.read_only_reason {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(200, 120, 20))
+                        .inner_margin(8.0)
+                        .rounding(4.0)
+                        .show(ui, |ui| {
+                            ui.colored_label(egui::Color32::WHITE, format!("Read-only: {reason}"));
+                        });
+                    ui.add_space(4.0);
+                }
+
                 if self.fans.is_empty() {
                     ui.label("No fans detected.");
                     return;
                 }
 
+                // No controllable fans — explain why once instead of leaving
+                // a column of slider-less cards.
+                if let Some(reason) = &self.no_control_reason {
+                    egui::Frame::none()
+                        .fill(egui::Color32::from_rgb(60, 90, 140))
+                        .inner_margin(8.0)
+                        .rounding(4.0)
+                        .show(ui, |ui| {
+                            ui.colored_label(
+                                egui::Color32::WHITE,
+                                format!("No controllable fans found: {reason}"),
+                            );
+                        });
+                    ui.add_space(4.0);
+                }
+
                 // Full speed mode banner.
                 if self.fans.iter().any(|f| f.full_speed_active) {
                     egui::Frame::none()
@@ -237,13 +975,19 @@ impl eframe::App for FanControlApp {
                     ui.add_space(4.0);
                 }
 
+                if !self.fan_curves.is_empty() {
+                    self.profile_bar(ui);
+                    ui.add_space(4.0);
+                }
+
                 let fans: Vec<Fan> = self.fans.clone();
+                let controls_enabled = self.read_only_reason.is_none();
 
                 for fan in &fans {
                     egui::Frame::group(ui.style()).show(ui, |ui| {
                         ui.set_min_width(ui.available_width());
 
-                        ui.strong(&fan.label);
+                        ui.strong(fan.display_label());
 
                         // RPM range from table data.
                         if let (Some(min_rpm), Some(max_rpm)) = (fan.min_rpm, fan.max_rpm) {
@@ -254,51 +998,145 @@ impl eframe::App for FanControlApp {
                         ui.horizontal(|ui| {
                             ui.label("Now:");
                             ui.label(format!("{} RPM", fan.speed_rpm));
+                            if let Some(target) = self.rpm_targets.get(&fan.id) {
+                                ui.separator();
+                                ui.label(format!("Target {} RPM", target));
+                            }
                             if let Some(pwm) = fan.pwm {
                                 ui.separator();
-                                ui.label(format!("PWM {}", pwm));
+                                ui.label(format_pwm(pwm));
                             }
                         });
 
-                        if fan.controllable {
-                            if let Some(slider_value) = self.slider_values.get_mut(&fan.id) {
+                        if let Some(history) = self.rpm_history.get(&fan.id) {
+                            rpm_history_plot(ui, &fan.id, history);
+                        }
+
+                        let rpm_range = fan.min_rpm.zip(fan.max_rpm).filter(|_| self.rpm_mode);
+                        if let (true, Some((min_rpm, max_rpm))) = (fan.controllable, rpm_range) {
+                            if let Some(slider_value) = self.rpm_slider_values.get_mut(&fan.id) {
                                 ui.horizontal(|ui| {
+                                    if !controls_enabled {
+                                        ui.disable();
+                                    }
                                     ui.add(
-                                        egui::Slider::new(slider_value, 0.0..=255.0)
-                                            .step_by(1.0)
-                                            .fixed_decimals(0)
-                                            .text("PWM"),
+                                        egui::Slider::new(
+                                            slider_value,
+                                            min_rpm as f32..=max_rpm as f32,
+                                        )
+                                        .step_by(50.0)
+                                        .fixed_decimals(0)
+                                        .text("RPM"),
                                     );
                                     if ui.button("Set").clicked() {
-                                        let _ = self.command_tx.send(WorkerCommand::SetPwm {
+                                        let _ = self.command_tx.send(WorkerCommand::SetRpm {
                                             fan_id: fan.id.clone(),
-                                            pwm: *slider_value as u8,
+                                            rpm: *slider_value as u32,
                                         });
                                     }
                                 });
                             }
+                        } else if fan.controllable {
+                            let mut moved = None;
+                            let mut set = None;
+                            if let Some(slider_value) = self.slider_values.get_mut(&fan.id) {
+                                ui.horizontal(|ui| {
+                                    if !controls_enabled {
+                                        ui.disable();
+                                    }
+                                    // The slider value stays in PWM; percent
+                                    // mode converts both ways, so a value set
+                                    // here matches `set --percent`.
+                                    if self.percent_mode {
+                                        let mut percent = pwm_to_percent(*slider_value as u8);
+                                        let response = ui.add(
+                                            egui::Slider::new(&mut percent, 0..=100).text("%"),
+                                        );
+                                        if response.changed() {
+                                            *slider_value = f32::from(percent_to_pwm(percent));
+                                            moved = Some(*slider_value);
+                                        }
+                                        ui.label(format!("PWM {}", *slider_value as u8));
+                                    } else {
+                                        let response = ui.add(
+                                            egui::Slider::new(&mut *slider_value, 0.0..=255.0)
+                                                .step_by(1.0)
+                                                .fixed_decimals(0)
+                                                .text("PWM"),
+                                        );
+                                        if response.changed() {
+                                            moved = Some(*slider_value);
+                                        }
+                                        ui.label(format!(
+                                            "{}%",
+                                            pwm_to_percent(*slider_value as u8)
+                                        ));
+                                    }
+                                    if ui.button("Set").clicked() {
+                                        set = Some(*slider_value as u8);
+                                    }
+                                });
+                            }
+                            if let Some(pwm) = moved {
+                                self.link_sliders(&fan.id, pwm);
+                            }
+                            if let Some(pwm) = set {
+                                self.send_pwm(&fan.id, pwm);
+                            }
                         } else {
                             ui.label("read-only");
                         }
 
                         // Collapsible fan curve section.
+                        let can_write = self.read_only_reason.is_none();
                         if let Some(curves) = self.fan_curves.get(&fan.id) {
                             if !curves.is_empty() {
                                 ui.add_space(4.0);
                                 egui::CollapsingHeader::new("Fan Curve")
                                     .default_open(false)
                                     .show(ui, |ui| {
-                                        for curve in curves {
+                                        let Some(edited) = self.editing_curves.get_mut(&fan.id)
+                                        else {
+                                            return;
+                                        };
+                                        for (curve, edited_curve) in
+                                            curves.iter().zip(edited.iter_mut())
+                                        {
                                             let active_tag =
                                                 if curve.active { "Active" } else { "Inactive" };
                                             ui.label(format!(
-                                                "Sensor {} [{}] \u{2014} {}\u{2013}{}\u{00B0}C",
-                                                curve.sensor_id,
+                                                "{} [{}] \u{2014} {}\u{2013}{}\u{00B0}C",
+                                                curve.sensor_name(),
                                                 active_tag,
                                                 curve.min_temp,
                                                 curve.max_temp
                                             ));
 
+                                            let rpm_range = fan
+                                                .min_rpm
+                                                .zip(fan.max_rpm)
+                                                .unwrap_or((curve.min_speed, curve.max_speed));
+                                            curve_plot(ui, curve, edited_curve, rpm_range);
+
+                                            // Checked against the default floors,
+                                            // as "Apply Curve" is.
+                                            let custom = (curve.points.len() == 10).then(|| {
+                                                custom_curve_from_edit(curve, edited_curve)
+                                            });
+                                            let bad_points = custom
+                                                .as_ref()
+                                                .map(|custom| invalid_points(&custom.steps))
+                                                .unwrap_or_default();
+                                            let edit_error = custom.as_ref().and_then(|custom| {
+                                                validate_custom_curve(custom).err()
+                                            });
+                                            // The RPM fields follow the fan's range,
+                                            // which can be wider than the table's.
+                                            let (_, clamped) = clamp_curve_points(
+                                                &edited_curve.points,
+                                                Some(curve),
+                                            );
+
                                             egui::Grid::new(format!(
                                                 "curve_{}_{}",
                                                 curve.fan_id, curve.sensor_id
@@ -310,19 +1148,86 @@ impl eframe::App for FanControlApp {
                                                     ui.strong("Temp");
                                                     ui.strong("RPM");
                                                     ui.end_row();
-                                                    for point in &curve.points {
+                                                    for (index, point) in
+                                                        edited_curve.points.iter_mut().enumerate()
+                                                    {
                                                         ui.label(format!(
                                                             "{}\u{00B0}C",
                                                             point.temperature
                                                         ));
-                                                        ui.label(format!("{}", point.fan_speed));
+                                                        ui.scope(|ui| {
+                                                            if bad_points.contains(&index) {
+                                                                outline_invalid(ui);
+                                                            }
+                                                            ui.add(
+                                                                egui::DragValue::new(
+                                                                    &mut point.fan_speed,
+                                                                )
+                                                                .range(rpm_range.0..=rpm_range.1)
+                                                                .speed(10.0),
+                                                            );
+                                                        });
                                                         ui.end_row();
                                                     }
                                                 },
                                             );
 
+                                            let key = CurveEditKey {
+                                                fan_id: curve.fan_id,
+                                                sensor_id: curve.sensor_id,
+                                            };
+                                            if let Some(error) = &edit_error {
+                                                ui.colored_label(
+                                                    ui.visuals().error_fg_color,
+                                                    error.to_string(),
+                                                );
+                                            }
+                                            if clamped {
+                                                ui.colored_label(
+                                                    ui.visuals().warn_fg_color,
+                                                    clamp_warning(curve),
+                                                );
+                                            }
+                                            ui.horizontal(|ui| {
+                                                let sorted =
+                                                    edited_curve.points.windows(2).all(|pair| {
+                                                        pair[0].fan_speed <= pair[1].fan_speed
+                                                    });
+                                                if ui
+                                                    .add_enabled(
+                                                        !sorted,
+                                                        egui::Button::new("Sort points"),
+                                                    )
+                                                    .on_hover_text(
+                                                        "Put the RPM values in ascending order",
+                                                    )
+                                                    .clicked()
+                                                {
+                                                    sort_speeds(edited_curve);
+                                                }
+                                                let applying = self.applying_curves.contains(&key);
+                                                if ui
+                                                    .add_enabled(
+                                                        can_write
+                                                            && !applying
+                                                            && edit_error.is_none(),
+                                                        egui::Button::new("Apply Curve"),
+                                                    )
+                                                    .clicked()
+                                                {
+                                                    apply_key = Some(key);
+                                                }
+                                                if applying {
+                                                    ui.spinner();
+                                                    ui.label("Applying...");
+                                                }
+                                            });
+
                                             ui.add_space(4.0);
                                         }
+                                        if ui.button("Reset to Current").clicked() {
+                                            *edited = curves.clone();
+                                        }
                                     });
                             }
                         }
@@ -332,14 +1237,372 @@ impl eframe::App for FanControlApp {
                 }
             });
         });
+        if let Some(key) = apply_key {
+            self.apply_curve(key);
+        }
+    }
+}
+
+impl FanControlApp {
+    /// Save/load row for curve profiles.
+    fn profile_bar(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.profile_name)
+                    .hint_text("profile name")
+                    .desired_width(110.0),
+            );
+            let can_save = !self.profile_name.trim().is_empty();
+            let save_label = if self.confirm_overwrite.as_deref() == Some(self.profile_name.trim())
+            {
+                "Overwrite Profile"
+            } else {
+                "Save Profile"
+            };
+            if ui
+                .add_enabled(can_save, egui::Button::new(save_label))
+                .clicked()
+            {
+                self.save_profile();
+            }
+            ui.separator();
+            let selected_text = self.selected_profile.as_deref().unwrap_or("\u{2014}");
+            egui::ComboBox::from_id_salt("profiles")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    for name in &self.profiles {
+                        ui.selectable_value(&mut self.selected_profile, Some(name.clone()), name);
+                    }
+                });
+            if ui
+                .add_enabled(
+                    self.selected_profile.is_some(),
+                    egui::Button::new("Load Profile"),
+                )
+                .clicked()
+            {
+                self.load_profile();
+            }
+        });
+    }
+}
+
+/// egui visuals for the selected theme. Banners use fixed fills with white
+/// text, so they read the same on either background.
+fn visuals_for(dark_mode: bool) -> egui::Visuals {
+    if dark_mode {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Curve profiles
+// ---------------------------------------------------------------------------
+
+/// Step index whose EC table speed is closest to `rpm`. The EC curve's
+/// points list the FanSpeeds table in step order, so step `k` runs at
+/// `current.points[k].fan_speed`.
+fn rpm_to_step(current: &FanCurve, rpm: u32) -> u8 {
+    current
+        .points
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, point)| point.fan_speed.abs_diff(rpm))
+        .map_or(0, |(index, _)| index as u8)
+}
+
+/// Sort `curve`'s RPM values ascending, keeping each point's temperature,
+/// like `set-curve --sort` does with steps.
+fn sort_speeds(curve: &mut FanCurve) {
+    let mut speeds: Vec<u32> = curve.points.iter().map(|point| point.fan_speed).collect();
+    speeds.sort_unstable();
+    for (point, speed) in curve.points.iter_mut().zip(speeds) {
+        point.fan_speed = speed;
+    }
+}
+
+/// Shown when edited RPMs fall outside the `current` table's range: the
+/// nearest table speed is written instead.
+fn clamp_warning(current: &FanCurve) -> String {
+    format!(
+        "fan {} sensor {}: RPMs outside the table's {}\u{2013}{} RPM are clamped to it",
+        current.fan_id, current.sensor_id, current.min_speed, current.max_speed
+    )
+}
+
+/// Convert one edited curve into step indices of the `current` table.
+fn custom_curve_from_edit(current: &FanCurve, edited: &FanCurve) -> CustomFanCurve {
+    CustomFanCurve {
+        fan_id: current.fan_id,
+        sensor_id: current.sensor_id,
+        steps: std::array::from_fn(|i| {
+            let rpm = edited.points.get(i).map_or(0, |point| point.fan_speed);
+            rpm_to_step(current, rpm)
+        }),
+    }
+}
+
+/// Indices of the steps that break [`validate_custom_curve`]: each one
+/// lower than the step before it, and each high-temperature step below its
+/// default safety floor.
+fn invalid_points(steps: &[u8; 10]) -> Vec<usize> {
+    let mut invalid: Vec<usize> = (1..steps.len())
+        .filter(|&i| steps[i] < steps[i - 1])
+        .collect();
+    for (index, floor) in high_temp_floors(DEFAULT_MIN_HIGH_TEMP_RATIO) {
+        if steps[index] < floor && !invalid.contains(&index) {
+            invalid.push(index);
+        }
+    }
+    invalid
+}
+
+/// Draw the next widget in `ui` with an error-coloured outline.
+fn outline_invalid(ui: &mut egui::Ui) {
+    let stroke = egui::Stroke::new(1.5, ui.visuals().error_fg_color);
+    let widgets = &mut ui.visuals_mut().widgets;
+    for visuals in [
+        &mut widgets.inactive,
+        &mut widgets.hovered,
+        &mut widgets.active,
+    ] {
+        visuals.bg_stroke = stroke;
+    }
+}
+
+/// Convert the edited curves into `CustomFanCurve` steps, the JSON shape
+/// `restore-curves` reads. Curves without exactly 10 points are skipped.
+fn profile_from_curves(
+    current: &HashMap<String, Vec<FanCurve>>,
+    edited: &HashMap<String, Vec<FanCurve>>,
+) -> Vec<CustomFanCurve> {
+    let mut profile: Vec<CustomFanCurve> = current
+        .iter()
+        .filter_map(|(fan_id, curves)| Some((curves, edited.get(fan_id)?)))
+        .flat_map(|(curves, edited)| curves.iter().zip(edited))
+        .filter(|(curve, _)| curve.points.len() == 10)
+        .map(|(curve, edited_curve)| custom_curve_from_edit(curve, edited_curve))
+        .collect();
+    profile.sort_by_key(|curve| (curve.fan_id, curve.sensor_id));
+    profile
+}
+
+/// `time` as "HH:MM:SS" in UTC, the zone the log file uses.
+fn clock_time(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+        % 86_400;
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// Advisory warnings for `profile`, each curve checked against the EC table
+/// its steps index.
+fn profile_warnings(
+    current: &HashMap<String, Vec<FanCurve>>,
+    profile: &[CustomFanCurve],
+) -> Vec<String> {
+    profile
+        .iter()
+        .filter_map(|custom| {
+            let table = current
+                .values()
+                .flatten()
+                .find(|curve| curve.fan_id == custom.fan_id && curve.sensor_id == custom.sensor_id);
+            validate_custom_curve_with_policy(custom, DEFAULT_MIN_HIGH_TEMP_RATIO, table).ok()
+        })
+        .flat_map(|validation| validation.warnings)
+        .collect()
+}
+
+/// Write a profile's steps into the matching edited curves as RPM values.
+/// Returns how many profile curves matched a known fan/sensor pair.
+fn apply_profile(
+    current: &HashMap<String, Vec<FanCurve>>,
+    edited: &mut HashMap<String, Vec<FanCurve>>,
+    profile: &[CustomFanCurve],
+) -> usize {
+    let mut matched = 0;
+    for custom in profile {
+        for (fan_id, curves) in current {
+            let Some(index) = curves
+                .iter()
+                .position(|c| c.fan_id == custom.fan_id && c.sensor_id == custom.sensor_id)
+            else {
+                continue;
+            };
+            let (Some(curve), Some(edited_curve)) = (
+                curves.get(index),
+                edited.get_mut(fan_id).and_then(|e| e.get_mut(index)),
+            ) else {
+                continue;
+            };
+            let last = curve.points.len().saturating_sub(1);
+            for (point, &step) in edited_curve.points.iter_mut().zip(&custom.steps) {
+                if let Some(table) = curve.points.get(usize::from(step).min(last)) {
+                    point.fan_speed = table.fan_speed;
+                }
+            }
+            matched += 1;
+        }
     }
+    matched
+}
+
+// ---------------------------------------------------------------------------
+// RPM history plot
+// ---------------------------------------------------------------------------
+
+/// Plot points for an RPM history: x is the sample index, y the RPM.
+fn rpm_history_points(history: &VecDeque<u32>) -> PlotPoints {
+    history
+        .iter()
+        .enumerate()
+        .map(|(index, &rpm)| [index as f64, rpm as f64])
+        .collect()
+}
+
+/// Small rolling line chart of a fan's recent RPM readings.
+fn rpm_history_plot(ui: &mut egui::Ui, fan_id: &str, history: &VecDeque<u32>) {
+    Plot::new(format!("rpm_history_{}", fan_id))
+        .height(70.0)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .show_x(false)
+        .include_x(0.0)
+        .include_x((RPM_HISTORY_LEN - 1) as f64)
+        .include_y(0.0)
+        .x_axis_formatter(|_, _| String::new())
+        .show(ui, |plot_ui| {
+            plot_ui.line(Line::new(rpm_history_points(history)).name("RPM"));
+        });
+}
+
+// ---------------------------------------------------------------------------
+// Curve plot
+// ---------------------------------------------------------------------------
+
+fn curve_plot_points(curve: &FanCurve) -> PlotPoints {
+    curve
+        .points
+        .iter()
+        .map(|point| [point.temperature as f64, point.fan_speed as f64])
+        .collect()
+}
+
+/// Grab radius around a curve node, in screen points.
+const NODE_GRAB_RADIUS: f32 = 12.0;
+
+/// RPM granularity for dragged nodes, matching the RPM slider step.
+const NODE_RPM_STEP: u32 = 50;
+
+/// Index of the node closest to `pointer`, if any lies within
+/// `NODE_GRAB_RADIUS`.
+fn nearest_node(nodes: &[egui::Pos2], pointer: egui::Pos2) -> Option<usize> {
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(index, node)| (index, node.distance(pointer)))
+        .filter(|&(_, distance)| distance <= NODE_GRAB_RADIUS)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
+}
+
+/// Snap a dragged RPM to `NODE_RPM_STEP` and clamp it into the fan's range.
+fn snap_rpm(rpm: f64, (min_rpm, max_rpm): (u32, u32)) -> u32 {
+    let step = f64::from(NODE_RPM_STEP);
+    let snapped = (rpm / step).round() * step;
+    (snapped.max(0.0) as u32).clamp(min_rpm, max_rpm)
+}
+
+/// Plot the hardware curve as a dashed reference line behind the edited one,
+/// so deviations from the current EC table are visible at a glance.
+///
+/// The edited curve's nodes can be dragged vertically to change their RPM
+/// (snapped into `rpm_range`); temperatures are fixed by the EC table. This
+/// edits the same points as the grid below, so both stay in sync.
+fn curve_plot(ui: &mut egui::Ui, current: &FanCurve, edited: &mut FanCurve, rpm_range: (u32, u32)) {
+    let plot_id = format!("curve_plot_{}_{}", current.fan_id, current.sensor_id);
+    let drag_id = egui::Id::new(&plot_id).with("dragged_node");
+    let mut dragged: Option<usize> = ui.ctx().data(|data| data.get_temp(drag_id));
+
+    Plot::new(plot_id)
+        .height(160.0)
+        .legend(Legend::default())
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false)
+        .include_y(rpm_range.0 as f64)
+        .include_y(rpm_range.1 as f64)
+        .x_axis_label("\u{00B0}C")
+        .y_axis_label("RPM")
+        .show(ui, |plot_ui| {
+            let response = plot_ui.response().clone();
+            if response.drag_started() {
+                dragged = response.interact_pointer_pos().and_then(|pointer| {
+                    let nodes: Vec<egui::Pos2> = edited
+                        .points
+                        .iter()
+                        .map(|point| {
+                            plot_ui.screen_from_plot(PlotPoint::new(
+                                point.temperature,
+                                point.fan_speed,
+                            ))
+                        })
+                        .collect();
+                    nearest_node(&nodes, pointer)
+                });
+            }
+            if response.dragged() {
+                let target = dragged.zip(plot_ui.pointer_coordinate());
+                if let Some((index, pointer)) = target {
+                    if let Some(point) = edited.points.get_mut(index) {
+                        point.fan_speed = snap_rpm(pointer.y, rpm_range);
+                    }
+                }
+            }
+            if response.drag_stopped() {
+                dragged = None;
+            }
+
+            plot_ui.line(
+                Line::new(curve_plot_points(current))
+                    .name("Current")
+                    .color(egui::Color32::GRAY)
+                    .style(LineStyle::dashed_loose()),
+            );
+            plot_ui.line(
+                Line::new(curve_plot_points(edited))
+                    .name("Edited")
+                    .width(2.0),
+            );
+            plot_ui.points(
+                Points::new(curve_plot_points(edited))
+                    .name("Edited")
+                    .shape(MarkerShape::Circle)
+                    .filled(true)
+                    .radius(4.0),
+            );
+        });
+
+    ui.ctx().data_mut(|data| match dragged {
+        Some(index) => data.insert_temp(drag_id, index),
+        None => data.remove::<usize>(drag_id),
+    });
 }
 
 // ---------------------------------------------------------------------------
 // Entry point
 // ---------------------------------------------------------------------------
 
-pub fn run() -> anyhow::Result<()> {
+/// Open the GUI. With `once`, the worker discovers fans at startup and then
+/// only on the Refresh button, instead of polling every 1.5 s.
+pub fn run(once: bool) -> anyhow::Result<()> {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([400.0, 600.0])
@@ -354,10 +1617,688 @@ pub fn run() -> anyhow::Result<()> {
             let (command_tx, command_rx) = mpsc::channel();
             let (response_tx, response_rx) = mpsc::channel();
 
-            spawn_worker(command_rx, response_tx, cc.egui_ctx.clone());
+            let auto_poll = (!once).then_some(AUTO_POLL);
+            let state_path = config::held_speeds_path();
+            let saved = config::load_held_speeds(&state_path);
+            let remember_speeds = saved.remember;
+            let worker = spawn_worker(
+                command_rx,
+                response_tx,
+                cc.egui_ctx.clone(),
+                auto_poll,
+                saved,
+                state_path,
+            );
 
-            Ok(Box::new(FanControlApp::new(command_tx, response_rx)))
+            let mut app = FanControlApp::new(command_tx, response_rx);
+            app.remember_speeds = remember_speeds;
+            app.worker = Some(worker);
+            cc.egui_ctx.set_visuals(visuals_for(app.dark_mode));
+            Ok(Box::new(app))
         }),
     )
     .map_err(|error| anyhow::anyhow!("eframe error: {}", error))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fan::FanCurvePoint;
+
+    fn test_app() -> (FanControlApp, mpsc::Sender<WorkerResponse>) {
+        let (command_tx, _command_rx) = mpsc::channel();
+        let (response_tx, response_rx) = mpsc::channel();
+        (FanControlApp::new(command_tx, response_rx), response_tx)
+    }
+
+    fn test_fan(id: &str, speed_rpm: u32, pwm: Option<u8>) -> Fan {
+        Fan {
+            id: id.to_string(),
+            label: id.to_string(),
+            speed_rpm,
+            temperature: None,
+            pwm,
+            pwm_mode: None,
+            controllable: true,
+            min_rpm: Some(1600),
+            max_rpm: Some(4800),
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        }
+    }
+
+    fn test_curve(speeds: &[u32]) -> FanCurve {
+        FanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            min_speed: 1600,
+            max_speed: 4800,
+            min_temp: 58,
+            max_temp: 100,
+            points: speeds
+                .iter()
+                .enumerate()
+                .map(|(i, &fan_speed)| FanCurvePoint {
+                    temperature: 58 + i as u32 * 10,
+                    fan_speed,
+                })
+                .collect(),
+            active: true,
+            sensor_label: None,
+        }
+    }
+
+    // -- last updated ---------------------------------------------------------
+
+    #[test]
+    fn clock_time_formats_utc_time_of_day() {
+        assert_eq!(clock_time(UNIX_EPOCH), "00:00:00");
+        // 2024-01-02 13:05:09 UTC.
+        let time = UNIX_EPOCH + Duration::from_secs(1_704_200_709);
+        assert_eq!(clock_time(time), "13:05:09");
+    }
+
+    #[test]
+    fn fan_data_records_last_updated() {
+        let (mut app, _) = test_app();
+        assert!(app.last_updated.is_none());
+        app.handle_response(WorkerResponse::FanData(Vec::new()));
+        assert!(app.last_updated.is_some());
+    }
+
+    // -- next_command ---------------------------------------------------------
+
+    #[test]
+    fn next_command_auto_polls_on_timeout() {
+        let (_command_tx, command_rx) = mpsc::channel();
+        let command = next_command(&command_rx, Some(Duration::from_millis(1)));
+        assert!(matches!(command, Some(WorkerCommand::Refresh)));
+    }
+
+    #[test]
+    fn next_command_without_auto_poll_waits_for_commands() {
+        let (command_tx, command_rx) = mpsc::channel();
+        command_tx
+            .send(WorkerCommand::SetPwm {
+                fan_id: "fan0".into(),
+                pwm: 128,
+            })
+            .unwrap();
+        assert!(matches!(
+            next_command(&command_rx, None),
+            Some(WorkerCommand::SetPwm { pwm: 128, .. })
+        ));
+        command_tx.send(WorkerCommand::Refresh).unwrap();
+        assert!(matches!(
+            next_command(&command_rx, None),
+            Some(WorkerCommand::Refresh)
+        ));
+        drop(command_tx);
+        assert!(next_command(&command_rx, None).is_none());
+    }
+
+    // -- handle_response ------------------------------------------------------
+
+    #[test]
+    fn fan_data_seeds_sliders_only_when_unset() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::FanData(vec![test_fan(
+            "fan0",
+            2100,
+            Some(40),
+        )]));
+        assert_eq!(app.slider_values["fan0"], 40.0);
+        assert_eq!(app.rpm_slider_values["fan0"], 2100.0);
+        assert_eq!(app.status_message, "OK");
+
+        // User moved the sliders; later polls must not overwrite them.
+        app.slider_values.insert("fan0".into(), 200.0);
+        app.rpm_slider_values.insert("fan0".into(), 4000.0);
+        app.handle_response(WorkerResponse::FanData(vec![test_fan(
+            "fan0",
+            3000,
+            Some(90),
+        )]));
+        assert_eq!(app.slider_values["fan0"], 200.0);
+        assert_eq!(app.rpm_slider_values["fan0"], 4000.0);
+        assert_eq!(app.fans[0].speed_rpm, 3000);
+    }
+
+    #[test]
+    fn fan_data_appends_capped_rpm_history() {
+        let (mut app, _) = test_app();
+        for rpm in 0..(RPM_HISTORY_LEN as u32 + 5) {
+            app.handle_response(WorkerResponse::FanData(vec![test_fan("fan0", rpm, None)]));
+        }
+        let history = &app.rpm_history["fan0"];
+        assert_eq!(history.len(), RPM_HISTORY_LEN);
+        assert_eq!(history.front(), Some(&5));
+        assert_eq!(history.back(), Some(&(RPM_HISTORY_LEN as u32 + 4)));
+    }
+
+    #[test]
+    fn fan_data_drops_history_of_vanished_fans() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::FanData(vec![
+            test_fan("fan0", 2000, None),
+            test_fan("fan1", 2500, None),
+        ]));
+        app.handle_response(WorkerResponse::FanData(vec![test_fan("fan1", 2600, None)]));
+        assert!(!app.rpm_history.contains_key("fan0"));
+        assert_eq!(app.rpm_history["fan1"], VecDeque::from([2500, 2600]));
+
+        app.handle_response(WorkerResponse::FanData(vec![test_fan("fan0", 1900, None)]));
+        assert_eq!(app.rpm_history["fan0"], VecDeque::from([1900]));
+    }
+
+    #[test]
+    fn fan_data_clamps_rpm_slider_to_range() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::FanData(vec![test_fan("fan1", 0, None)]));
+        assert_eq!(app.rpm_slider_values["fan1"], 1600.0);
+        assert!(!app.slider_values.contains_key("fan1"));
+    }
+
+    #[test]
+    fn curve_data_caches_and_seeds_editor() {
+        let (mut app, _) = test_app();
+        let curves = HashMap::from([("fan0".to_string(), vec![test_curve(&[1600, 2400])])]);
+        app.handle_response(WorkerResponse::CurveData(curves));
+        assert_eq!(app.fan_curves["fan0"].len(), 1);
+        assert_eq!(app.editing_curves["fan0"][0].points[1].fan_speed, 2400);
+    }
+
+    #[test]
+    fn pwm_set_clears_rpm_target() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::RpmSet {
+            fan_id: "fan0".into(),
+            rpm: 3000,
+        });
+        assert_eq!(app.rpm_targets["fan0"], 3000);
+        assert_eq!(app.status_message, "Set fan0 target to 3000 RPM");
+
+        app.handle_response(WorkerResponse::PwmSet {
+            fan_id: "fan0".into(),
+            pwm: 128,
+        });
+        assert!(!app.rpm_targets.contains_key("fan0"));
+        assert_eq!(app.status_message, "Set fan0 to PWM 128 (50%)");
+    }
+
+    // -- linked fans ----------------------------------------------------------
+
+    fn linked_app() -> (FanControlApp, mpsc::Receiver<WorkerCommand>) {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (_response_tx, response_rx) = mpsc::channel();
+        let mut app = FanControlApp::new(command_tx, response_rx);
+        let mut locked = test_fan("fan2", 2000, Some(80));
+        locked.controllable = false;
+        app.handle_response(WorkerResponse::FanData(vec![
+            test_fan("fan0", 2000, Some(40)),
+            test_fan("fan1", 2000, Some(60)),
+            locked,
+        ]));
+        (app, command_rx)
+    }
+
+    fn sent_pwm(command_rx: &mpsc::Receiver<WorkerCommand>) -> Vec<(String, u8)> {
+        command_rx
+            .try_iter()
+            .filter_map(|command| match command {
+                WorkerCommand::SetPwm { fan_id, pwm } => Some((fan_id, pwm)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn linked_fans_move_and_set_together() {
+        let (mut app, command_rx) = linked_app();
+        app.link_fans = true;
+        app.link_sliders("fan0", 200.0);
+        assert_eq!(app.slider_values["fan1"], 200.0);
+        assert_eq!(app.slider_values["fan2"], 80.0);
+
+        app.send_pwm("fan0", 200);
+        assert_eq!(
+            sent_pwm(&command_rx),
+            [("fan0".to_string(), 200), ("fan1".to_string(), 200)]
+        );
+    }
+
+    #[test]
+    fn unlinked_fans_move_alone() {
+        let (mut app, command_rx) = linked_app();
+        app.link_sliders("fan0", 200.0);
+        assert_eq!(app.slider_values["fan1"], 60.0);
+        app.send_pwm("fan0", 200);
+        assert_eq!(sent_pwm(&command_rx), [("fan0".to_string(), 200)]);
+    }
+
+    #[test]
+    fn all_auto_clears_sliders_for_reseeding() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::FanData(vec![test_fan(
+            "fan0",
+            2100,
+            Some(40),
+        )]));
+        app.slider_values.insert("fan0".into(), 200.0);
+        app.rpm_targets.insert("fan0".into(), 4000);
+
+        app.handle_response(WorkerResponse::AllAuto { count: 2 });
+        assert!(app.slider_values.is_empty());
+        assert!(app.rpm_slider_values.is_empty());
+        assert!(app.rpm_targets.is_empty());
+        assert_eq!(app.status_message, "Returned 2 fans to automatic");
+
+        app.handle_response(WorkerResponse::FanData(vec![test_fan(
+            "fan0",
+            1800,
+            Some(0),
+        )]));
+        assert_eq!(app.slider_values["fan0"], 0.0);
+        assert_eq!(app.rpm_slider_values["fan0"], 1800.0);
+    }
+
+    // -- remembered manual speeds ---------------------------------------------
+
+    #[test]
+    fn restored_speeds_survive_the_first_fan_data() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::SpeedsRestored {
+            pwm: HashMap::from([("fan0".to_string(), 200)]),
+            rpm: HashMap::from([("fan1".to_string(), 3000)]),
+        });
+        assert_eq!(app.status_message, "Restored manual speeds for 2 fans");
+
+        app.handle_response(WorkerResponse::FanData(vec![
+            test_fan("fan0", 2100, Some(40)),
+            test_fan("fan1", 2100, Some(40)),
+        ]));
+        assert_eq!(app.slider_values["fan0"], 200.0);
+        assert_eq!(app.rpm_slider_values["fan1"], 3000.0);
+        assert_eq!(app.rpm_targets["fan1"], 3000);
+    }
+
+    #[test]
+    fn restore_drops_stale_and_uncontrollable_fans() {
+        let mut locked = test_fan("fan1", 2000, None);
+        locked.controllable = false;
+        let fans = [test_fan("fan0", 2000, Some(40)), locked];
+        let saved = HeldSpeeds {
+            remember: true,
+            pwm: [("fan0".to_string(), 200), ("fan7".to_string(), 100)].into(),
+            rpm: [("fan1".to_string(), 3000)].into(),
+        };
+
+        let (pwm, rpm) = restore_held_speeds(saved.clone(), &fans);
+        assert_eq!(pwm, HashMap::from([("fan0".to_string(), 200)]));
+        assert!(rpm.is_empty());
+
+        let forgotten = HeldSpeeds {
+            remember: false,
+            ..saved
+        };
+        let (pwm, rpm) = restore_held_speeds(forgotten, &fans);
+        assert!(pwm.is_empty() && rpm.is_empty());
+    }
+
+    #[test]
+    fn held_speeds_are_only_kept_when_remembering() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("held_speeds.json");
+        let held_pwm = HashMap::from([("fan0".to_string(), 200)]);
+        let held_rpm = HashMap::from([("fan1".to_string(), 3000)]);
+
+        save_held_speeds(&path, true, &held_pwm, &held_rpm);
+        let saved = config::load_held_speeds(&path);
+        assert!(saved.remember);
+        assert_eq!(saved.pwm["fan0"], 200);
+        assert_eq!(saved.rpm["fan1"], 3000);
+
+        save_held_speeds(&path, false, &held_pwm, &held_rpm);
+        assert_eq!(config::load_held_speeds(&path), HeldSpeeds::default());
+    }
+
+    // -- Curve profiles -------------------------------------------------------
+
+    const TABLE: [u32; 10] = [1600, 1800, 2000, 2200, 2400, 2800, 3200, 3600, 4200, 4800];
+
+    fn app_with_curves(dir: &std::path::Path) -> FanControlApp {
+        let (mut app, _) = test_app();
+        app.profiles_dir = dir.to_path_buf();
+        app.handle_response(WorkerResponse::CurveData(HashMap::from([(
+            "fan0".to_string(),
+            vec![test_curve(&TABLE)],
+        )])));
+        app
+    }
+
+    #[test]
+    fn rpm_to_step_picks_nearest_table_speed() {
+        let curve = test_curve(&TABLE);
+        assert_eq!(rpm_to_step(&curve, 1600), 0);
+        assert_eq!(rpm_to_step(&curve, 2950), 5);
+        assert_eq!(rpm_to_step(&curve, 9000), 9);
+    }
+
+    #[test]
+    fn sort_speeds_keeps_temperatures() {
+        let mut curve = test_curve(&[4800, 3600, 1600]);
+        sort_speeds(&mut curve);
+        let points: Vec<(u32, u32)> = curve
+            .points
+            .iter()
+            .map(|p| (p.temperature, p.fan_speed))
+            .collect();
+        assert_eq!(points, vec![(58, 1600), (68, 3600), (78, 4800)]);
+    }
+
+    #[test]
+    fn profile_from_curves_uses_step_indices() {
+        let current = HashMap::from([("fan0".to_string(), vec![test_curve(&TABLE)])]);
+        let mut edited = current.clone();
+        edited.get_mut("fan0").unwrap()[0].points[2].fan_speed = 3200;
+
+        let profile = profile_from_curves(&current, &edited);
+        assert_eq!(profile.len(), 1);
+        assert_eq!(profile[0].sensor_id, 3);
+        assert_eq!(profile[0].steps, [0, 1, 6, 3, 4, 5, 6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn profile_save_then_load_restores_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_with_curves(dir.path());
+        app.editing_curves.get_mut("fan0").unwrap()[0].points[0].fan_speed = 2400;
+        app.profile_name = "warm".into();
+        app.save_profile();
+        assert_eq!(app.profiles, vec!["warm"]);
+
+        app.editing_curves = app.fan_curves.clone();
+        app.load_profile();
+        assert_eq!(app.editing_curves["fan0"][0].points[0].fan_speed, 2400);
+        assert_eq!(app.status_message, "Loaded profile 'warm' (1 curves)");
+    }
+
+    #[test]
+    fn profile_save_warns_about_slow_top_point() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_with_curves(dir.path());
+        // Top point at step 8 (4200 of 4800 RPM, 87%).
+        app.editing_curves.get_mut("fan0").unwrap()[0].points[9].fan_speed = 4200;
+        app.profile_name = "quiet".into();
+        app.save_profile();
+        assert!(app.status_message.starts_with("Saved profile to"));
+        assert!(
+            app.status_message
+                .contains("Warning: curve for fan 0 sensor 3 tops out at 4200 RPM"),
+            "{}",
+            app.status_message
+        );
+
+        app.editing_curves = app.fan_curves.clone();
+        app.save_profile();
+        app.save_profile();
+        assert!(!app.status_message.contains("Warning"));
+    }
+
+    #[test]
+    fn profile_save_asks_before_overwriting() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_with_curves(dir.path());
+        app.profile_name = "warm".into();
+        app.save_profile();
+
+        app.editing_curves.get_mut("fan0").unwrap()[0].points[0].fan_speed = 2400;
+        app.save_profile();
+        assert_eq!(app.confirm_overwrite.as_deref(), Some("warm"));
+        assert!(app.error_toasts[0].message.contains("already exists"));
+        let saved = config::load_profile(dir.path(), "warm").unwrap();
+        assert_eq!(saved[0].steps[0], 0);
+
+        app.save_profile();
+        assert!(app.confirm_overwrite.is_none());
+        let saved = config::load_profile(dir.path(), "warm").unwrap();
+        assert_eq!(saved[0].steps[0], 4);
+    }
+
+    #[test]
+    fn overwrite_confirmation_is_per_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut app = app_with_curves(dir.path());
+        for name in ["a", "b", "a", "b"] {
+            app.profile_name = name.into();
+            app.save_profile();
+        }
+        // Switching names disarms the confirmation each time.
+        assert_eq!(app.confirm_overwrite.as_deref(), Some("b"));
+        assert_eq!(app.error_toasts.len(), 2);
+    }
+
+    #[test]
+    fn profile_load_errors_go_to_status() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("broken.json"), "[{").unwrap();
+        let mut app = app_with_curves(dir.path());
+
+        app.selected_profile = Some("broken".into());
+        app.load_profile();
+        assert!(app.error_toasts[0].message.contains("not a curve profile"));
+
+        app.selected_profile = Some("absent".into());
+        app.load_profile();
+        assert!(app.error_toasts[1].message.starts_with("cannot read"));
+        assert_eq!(app.editing_curves["fan0"][0].points[0].fan_speed, 1600);
+    }
+
+    #[test]
+    fn error_becomes_toast_and_keeps_status() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::PwmSet {
+            fan_id: "fan0".into(),
+            pwm: 128,
+        });
+        app.handle_response(WorkerResponse::Error("powershell error: boom".into()));
+        assert_eq!(app.status_message, "Set fan0 to PWM 128 (50%)");
+        assert_eq!(app.error_toasts.len(), 1);
+        assert_eq!(app.error_toasts[0].message, "powershell error: boom");
+    }
+
+    #[test]
+    fn error_toasts_are_capped_and_expire() {
+        let (mut app, _) = test_app();
+        for i in 0..MAX_ERROR_TOASTS + 2 {
+            app.push_error(format!("error {i}"));
+        }
+        assert_eq!(app.error_toasts.len(), MAX_ERROR_TOASTS);
+        assert_eq!(app.error_toasts[0].message, "error 2");
+
+        let raised = app.error_toasts[0].raised;
+        app.prune_errors(raised + ERROR_TOAST_TTL / 2);
+        assert_eq!(app.error_toasts.len(), MAX_ERROR_TOASTS);
+        app.prune_errors(Instant::now() + ERROR_TOAST_TTL);
+        assert!(app.error_toasts.is_empty());
+    }
+
+    #[test]
+    fn visuals_follow_theme_choice() {
+        assert!(visuals_for(true).dark_mode);
+        assert!(!visuals_for(false).dark_mode);
+        let (app, _) = test_app();
+        assert!(app.dark_mode);
+    }
+
+    #[test]
+    fn sensor_temps_replace_previous_reading() {
+        let (mut app, _) = test_app();
+        let reading = |label: &str, sensor_id, temperature| SensorTemperature {
+            sensor_id,
+            label: label.to_string(),
+            temperature,
+        };
+        app.handle_response(WorkerResponse::SensorTemps(vec![reading("CPU", 3, 80)]));
+        app.handle_response(WorkerResponse::SensorTemps(vec![
+            reading("CPU", 3, 72),
+            reading("GPU", 4, 65),
+        ]));
+        assert_eq!(app.sensor_temps.len(), 2);
+        assert_eq!(
+            format_sensor_temps(&app.sensor_temps),
+            "CPU 72\u{00B0}C / GPU 65\u{00B0}C"
+        );
+    }
+
+    #[test]
+    fn read_only_records_reason() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::ReadOnly("not root".into()));
+        assert_eq!(app.read_only_reason.as_deref(), Some("not root"));
+    }
+
+    #[test]
+    fn only_permission_denied_makes_the_gui_read_only() {
+        let (mut app, _) = test_app();
+        app.handle_response(privilege_response(FanControlError::Platform(
+            "powershell error: boom".into(),
+        )));
+        assert!(app.read_only_reason.is_none());
+        assert_eq!(app.error_toasts.len(), 1);
+        app.handle_response(privilege_response(FanControlError::PermissionDenied(
+            "not root".into(),
+        )));
+        assert!(app.read_only_reason.is_some());
+    }
+
+    #[test]
+    fn no_controllable_fans_records_reason() {
+        let (mut app, _) = test_app();
+        app.handle_response(WorkerResponse::NoControllableFans("read-only".into()));
+        assert_eq!(app.no_control_reason.as_deref(), Some("read-only"));
+    }
+
+    #[test]
+    fn drain_responses_applies_queued_responses_in_order() {
+        let (mut app, response_tx) = test_app();
+        response_tx
+            .send(WorkerResponse::Error("first".into()))
+            .unwrap();
+        response_tx
+            .send(WorkerResponse::FanData(vec![test_fan(
+                "fan0",
+                2100,
+                Some(40),
+            )]))
+            .unwrap();
+        app.drain_responses();
+        assert_eq!(app.fans.len(), 1);
+        assert_eq!(app.status_message, "OK");
+    }
+
+    // -- curve_plot_points ------------------------------------------------------
+
+    #[test]
+    fn nearest_node_picks_closest_within_radius() {
+        let nodes = [egui::pos2(10.0, 10.0), egui::pos2(30.0, 10.0)];
+        assert_eq!(nearest_node(&nodes, egui::pos2(26.0, 12.0)), Some(1));
+        assert_eq!(nearest_node(&nodes, egui::pos2(12.0, 8.0)), Some(0));
+        assert_eq!(nearest_node(&nodes, egui::pos2(80.0, 80.0)), None);
+        assert_eq!(nearest_node(&[], egui::pos2(0.0, 0.0)), None);
+    }
+
+    #[test]
+    fn snap_rpm_rounds_to_step_and_clamps() {
+        assert_eq!(snap_rpm(2_424.0, (1600, 4800)), 2400);
+        assert_eq!(snap_rpm(2_426.0, (1600, 4800)), 2450);
+        assert_eq!(snap_rpm(900.0, (1600, 4800)), 1600);
+        assert_eq!(snap_rpm(-300.0, (1600, 4800)), 1600);
+        assert_eq!(snap_rpm(6_000.0, (1600, 4800)), 4800);
+    }
+
+    #[test]
+    fn curve_plot_points_maps_temperature_to_rpm() {
+        let points = curve_plot_points(&test_curve(&[1600, 2400, 3200]));
+        let values: Vec<[f64; 2]> = points.points().iter().map(|p| [p.x, p.y]).collect();
+        assert_eq!(values, vec![[58.0, 1600.0], [68.0, 2400.0], [78.0, 3200.0]]);
+    }
+
+    // -- apply curve ----------------------------------------------------------
+
+    const KEY: CurveEditKey = CurveEditKey {
+        fan_id: 0,
+        sensor_id: 3,
+    };
+
+    fn app_with_worker() -> (FanControlApp, mpsc::Receiver<WorkerCommand>) {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (_response_tx, response_rx) = mpsc::channel();
+        let mut app = FanControlApp::new(command_tx, response_rx);
+        app.handle_response(WorkerResponse::CurveData(HashMap::from([(
+            "fan0".to_string(),
+            vec![test_curve(&TABLE)],
+        )])));
+        (app, command_rx)
+    }
+
+    #[test]
+    fn apply_curve_waits_for_the_pending_write() {
+        let (mut app, command_rx) = app_with_worker();
+        app.editing_curves.get_mut("fan0").unwrap()[0].points[2].fan_speed = 2200;
+
+        assert!(app.apply_curve(KEY));
+        assert!(!app.apply_curve(KEY));
+        let commands: Vec<WorkerCommand> = command_rx.try_iter().collect();
+        assert_eq!(commands.len(), 1);
+        let WorkerCommand::SetCurve { key, curve } = &commands[0] else {
+            panic!("expected SetCurve");
+        };
+        assert_eq!(*key, KEY);
+        assert_eq!(curve.steps, [0, 1, 3, 3, 4, 5, 6, 7, 8, 9]);
+
+        app.handle_response(WorkerResponse::CurveSet { key: KEY });
+        assert!(app.applying_curves.is_empty());
+        // The applied curve becomes the new baseline.
+        assert_eq!(app.fan_curves["fan0"][0].points[2].fan_speed, 2200);
+        assert!(app.apply_curve(KEY));
+    }
+
+    #[test]
+    fn failed_curve_write_reenables_apply() {
+        let (mut app, _command_rx) = app_with_worker();
+        assert!(app.apply_curve(KEY));
+        app.handle_response(WorkerResponse::CurveFailed {
+            key: KEY,
+            message: "Fan_Set_Table timed out".into(),
+        });
+        assert!(app.applying_curves.is_empty());
+        assert_eq!(app.error_toasts.len(), 1);
+        assert!(app.error_toasts[0]
+            .message
+            .contains("Fan_Set_Table timed out"));
+        assert_eq!(app.fan_curves["fan0"][0].points[2].fan_speed, 2000);
+    }
+
+    #[test]
+    fn apply_curve_rejects_unsafe_curves() {
+        let (mut app, command_rx) = app_with_worker();
+        app.editing_curves.get_mut("fan0").unwrap()[0].points[9].fan_speed = 1600;
+        assert!(!app.apply_curve(KEY));
+        assert!(command_rx.try_recv().is_err());
+        assert!(app.applying_curves.is_empty());
+        assert_eq!(app.error_toasts.len(), 1);
+    }
+
+    #[test]
+    fn invalid_points_flags_dips_and_low_high_temp_steps() {
+        assert!(invalid_points(&[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]).is_empty());
+        assert_eq!(invalid_points(&[0, 1, 4, 3, 4, 5, 6, 7, 8, 9]), vec![3]);
+        // Flat at 2 leaves steps 8 and 9 under their floors of 3 and 5.
+        assert_eq!(invalid_points(&[2; 10]), vec![8, 9]);
+        // A dip that also lands under a floor is listed once.
+        assert_eq!(invalid_points(&[0, 0, 0, 0, 0, 0, 0, 6, 2, 6]), vec![8]);
+    }
+}
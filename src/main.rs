@@ -1,25 +1,47 @@
+mod bench;
 mod cli;
+mod color;
 mod config;
+mod doctor;
 mod errors;
 mod fan;
 mod gui;
+#[cfg(feature = "metrics")]
+mod metrics;
 mod platform;
+mod shutdown;
 mod tui;
 
+use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use std::fs::File;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitCode;
 
 use anyhow::Result;
-use clap::Parser;
-use log::info;
+use clap::{CommandFactory, Parser};
+use log::{debug, info, warn};
 use serde_json::json;
-use simplelog::{ConfigBuilder, LevelFilter, WriteLogger};
+use simplelog::{
+    ColorChoice, CombinedLogger, ConfigBuilder, LevelFilter, SharedLogger, TermLogger,
+    TerminalMode, WriteLogger,
+};
 
-use cli::{Cli, Commands};
-use fan::CustomFanCurve;
-use platform::{create_controller, FanController};
+use cli::{BenchTarget, Cli, Commands, TableFormat};
+use errors::FanControlError;
+use fan::{
+    auto_fix_steps, build_curve_from_points, calibration_from_samples, calibration_pwms,
+    combine_curves_for_fan, curves_csv, diff_curves, expected_rpm_direction, fan_count_summary,
+    format_pwm, format_sensor_temps, hex_dump, percent_to_pwm, reverted_curves,
+    smart_fan_mode_name, smooth_steps, sort_steps, validate_calibration, validate_custom_curve,
+    validate_custom_curve_with_policy, watch_rpm_response, CalibrationPoint, CurveChange,
+    CustomFanCurve, Fan, FanCurve, GuardAction, MonitorStats, OverheatGuard, PointChange, PwmCurve,
+    SensorRef, SpeedResponse, StepFix, DEFAULT_MIN_HIGH_TEMP_RATIO, SMART_FAN_MODE_CUSTOM,
+};
+use platform::{create_controller, resolve_fan_id, DryRunController, FanController};
 
 // put id:"cli_parse", label:"Parse CLI Arguments", output:"cli_command.internal"
 // put id:"setup_logging", label:"Setup File Logger", output:"fancontrol.log"
@@ -35,55 +57,380 @@ fn level_from_verbosity(verbosity: u8) -> LevelFilter {
     }
 }
 
-fn main() -> Result<()> {
+/// Level for the stderr logger: only with `-v` or more, or when there is no
+/// log file to write to, so default runs stay quiet. Never for the TUI,
+/// which owns the terminal.
+fn console_log_level(verbosity: u8, command: &Commands, file_logging: bool) -> Option<LevelFilter> {
+    match command {
+        Commands::Tui => None,
+        _ if verbosity == 0 && file_logging => None,
+        _ => Some(level_from_verbosity(verbosity)),
+    }
+}
+
+/// Create the log file, and its directory if needed.
+fn create_log_file(path: &Path) -> std::io::Result<File> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    File::create(path)
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
+    if let Err(message) = cli.validate() {
+        Cli::command()
+            .error(clap::error::ErrorKind::ArgumentConflict, message)
+            .exit();
+    }
+    let json_output = cli.json;
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            if json_output {
+                eprintln!("{}", errors::error_json(&error));
+            } else {
+                eprintln!("Error: {error:?}");
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    // Log to fancontrol.log next to the executable.
-    let log_path = std::env::current_exe()
-        .unwrap_or_default()
-        .parent()
-        .unwrap_or(std::path::Path::new("."))
-        .join("fancontrol.log");
+fn run(cli: Cli) -> Result<()> {
+    // Log to the log file, and to stderr with -v or when the file cannot be
+    // written.
+    let log_path = config::log_path(cli.log_file.clone());
     let log_config = ConfigBuilder::new().set_time_format_rfc3339().build();
     let log_level = level_from_verbosity(cli.verbose);
-    if let Ok(file) = File::create(&log_path) {
-        let _ = WriteLogger::init(log_level, log_config, file);
+    let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::new();
+    match create_log_file(&log_path) {
+        Ok(file) => loggers.push(WriteLogger::new(log_level, log_config.clone(), file)),
+        Err(error) => eprintln!(
+            "Warning: cannot write log file {}: {error}; logging to the console only",
+            log_path.display()
+        ),
+    }
+    let file_logging = !loggers.is_empty();
+    if let Some(level) = console_log_level(cli.verbose, &cli.command, file_logging) {
+        loggers.push(TermLogger::new(
+            level,
+            log_config,
+            TerminalMode::Stderr,
+            ColorChoice::Auto,
+        ));
     }
+    let _ = CombinedLogger::init(loggers);
     info!("fancontrol started (log level: {})", log_level);
 
     let json_output = cli.json;
+    let dry_run = cli.dry_run;
+    if let Some(path) = cli.config {
+        config::set_cli_config_path(path);
+    }
+    if let Some(backend) = cli.backend {
+        platform::set_backend_override(backend);
+    }
+
+    if cli.dump_script {
+        return cmd_dump_script();
+    }
 
     match cli.command {
-        Commands::Gui => {
+        Commands::Gui { once } => {
             if json_output {
                 eprintln!("Warning: --json flag has no effect with the gui subcommand");
             }
-            gui::run()
+            if dry_run {
+                eprintln!("Warning: --dry-run flag has no effect with the gui subcommand");
+            }
+            gui::run(once)
+        }
+        Commands::DumpConfig => cmd_dump_config(json_output),
+        Commands::Doctor => doctor::run(json_output),
+        Commands::ListPresets => cmd_list_presets(json_output),
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "fancontrol", &mut io::stdout());
+            Ok(())
         }
         Commands::Tui => {
             if json_output {
                 eprintln!("Warning: --json flag has no effect with the tui subcommand");
             }
+            if dry_run {
+                eprintln!("Warning: --dry-run flag has no effect with the tui subcommand");
+            }
             tui::run()
         }
         other => {
-            let controller = create_controller()?;
-            match other {
+            if matches!(other, Commands::Calibrate { .. }) {
+                platform::ignore_pwm_calibration();
+            }
+            let mut controller = create_controller()?;
+            if dry_run {
+                controller = Box::new(DryRunController::new(controller));
+            }
+            let result = match other {
                 Commands::List => cmd_list(&*controller, json_output),
-                Commands::Get { fan_id } => cmd_get(&*controller, &fan_id, json_output),
-                Commands::Set { fan_id, pwm } => cmd_set(&*controller, &fan_id, pwm),
-                Commands::Monitor { interval } => cmd_monitor(&*controller, interval),
-                Commands::Table { fan_id } => cmd_table(&*controller, fan_id, json_output),
+                Commands::Get { fan_id } => {
+                    let fan_id = resolve_fan_id(&*controller, &fan_id)?;
+                    cmd_get(&*controller, &fan_id, json_output)
+                }
+                Commands::Set {
+                    fan_id,
+                    pwm,
+                    all,
+                    percent,
+                    verify,
+                    watch,
+                    ramp,
+                    ramp_steps,
+                    interval,
+                    restore,
+                } => {
+                    if let Some(all_pwm) = all {
+                        let target = match (all_pwm, percent) {
+                            (Some(pwm), None) => PwmTarget::Raw(pwm),
+                            (None, Some(percent)) => PwmTarget::Percent(percent),
+                            (Some(_), Some(_)) => {
+                                anyhow::bail!(
+                                    "pass a PWM value to --all or use --percent, not both"
+                                )
+                            }
+                            (None, None) => {
+                                anyhow::bail!(
+                                    "--all needs a PWM value, e.g. --all 128, or --percent"
+                                )
+                            }
+                        };
+                        return cmd_set_all(&*controller, target, dry_run);
+                    }
+                    // clap requires a fan id without --all.
+                    let fan_id = resolve_fan_id(&*controller, &fan_id.unwrap_or_default())?;
+                    // clap guarantees exactly one of the two is present.
+                    let target = match percent {
+                        Some(percent) => PwmTarget::Percent(percent),
+                        None => PwmTarget::Raw(pwm.unwrap_or_default()),
+                    };
+                    if watch && !dry_run {
+                        cmd_set_watch(&*controller, &fan_id, target, interval, restore.restore())
+                    } else {
+                        let ramp = ramp.map(|duration| (duration, ramp_steps));
+                        cmd_set(&*controller, &fan_id, target, verify, ramp, dry_run)?;
+                        if watch {
+                            println!("Watch: skipped, a dry run writes nothing to hold");
+                        }
+                        Ok(())
+                    }
+                }
+                Commands::SetRpm { fan_id, rpm } => {
+                    let fan_id = resolve_fan_id(&*controller, &fan_id)?;
+                    cmd_set_rpm(&*controller, &fan_id, rpm)
+                }
+                Commands::Auto { fan_id } => {
+                    let fan_id = resolve_fan_id(&*controller, &fan_id)?;
+                    cmd_auto(&*controller, &fan_id)
+                }
+                Commands::Stop { fan_id } => {
+                    let fan_id = resolve_fan_id(&*controller, &fan_id)?;
+                    cmd_stop(&*controller, &fan_id)
+                }
+                Commands::RunDaemon {
+                    curves,
+                    interval,
+                    restore,
+                } => cmd_run_daemon(
+                    &*controller,
+                    &curves,
+                    poll_interval(interval, 2),
+                    restore.restore(),
+                    dry_run,
+                ),
+                Commands::Monitor {
+                    interval,
+                    csv,
+                    count,
+                } => cmd_monitor(
+                    &*controller,
+                    poll_interval(interval, 1),
+                    csv.as_deref(),
+                    count,
+                ),
+                Commands::Watch {
+                    max_temp,
+                    hysteresis,
+                    min_dwell,
+                    interval,
+                } => cmd_watch(
+                    &*controller,
+                    max_temp,
+                    hysteresis,
+                    min_dwell,
+                    poll_interval(interval, 2),
+                ),
+                Commands::Bench {
+                    target: BenchTarget::Discover { iterations },
+                } => bench::discover(&*controller, iterations, json_output),
+                Commands::BenchmarkHold { .. } if dry_run => {
+                    anyhow::bail!(
+                        "benchmark-hold measures how the fan responds, which needs real \
+                         writes: run it without --dry-run"
+                    )
+                }
+                Commands::BenchmarkHold {
+                    fan,
+                    rpm,
+                    duration,
+                    log_csv,
+                    interval,
+                } => {
+                    let fan = resolve_fan_id(&*controller, &fan)?;
+                    cmd_benchmark_hold(
+                        &*controller,
+                        &fan,
+                        rpm,
+                        duration,
+                        log_csv.as_deref(),
+                        interval,
+                    )
+                }
+                Commands::Calibrate { .. } if dry_run => {
+                    anyhow::bail!(
+                        "calibrate measures how the fan responds, which needs real writes: \
+                         run it without --dry-run"
+                    )
+                }
+                Commands::Calibrate {
+                    fan_id,
+                    steps,
+                    settle,
+                    save,
+                } => {
+                    let fan_id = resolve_fan_id(&*controller, &fan_id)?;
+                    cmd_calibrate(&*controller, &fan_id, steps, settle, save, json_output)
+                }
+                Commands::Table { fan_id, format } => {
+                    let format = if json_output {
+                        TableFormat::Json
+                    } else {
+                        format
+                    };
+                    cmd_table(&*controller, fan_id, format)
+                }
+                Commands::FanMode { mode } => cmd_fan_mode(&*controller, mode, json_output),
+                Commands::FullSpeed { state } => cmd_full_speed(&*controller, state),
+                Commands::ThermalMode { mode } => {
+                    controller.set_thermal_mode(mode)?;
+                    println!("Thermal mode set to {}", mode);
+                    Ok(())
+                }
                 Commands::SetCurve {
                     fan_id,
                     sensor_id,
                     steps,
                     save,
-                } => cmd_set_curve(&*controller, fan_id, sensor_id, steps, save),
-                Commands::Gui | Commands::Tui => unreachable!(),
+                    strict,
+                    sort,
+                    auto_fix,
+                    smooth,
+                    min_high_rpm_percent,
+                } => cmd_set_curve(
+                    &*controller,
+                    fan_id,
+                    sensor_id,
+                    sorted_if(sort, steps),
+                    save,
+                    strict,
+                    auto_fix,
+                    smooth,
+                    min_high_rpm_percent,
+                    dry_run,
+                ),
+                Commands::PreviewCurve {
+                    fan_id,
+                    sensor_id,
+                    steps,
+                    sort,
+                    temp,
+                } => cmd_preview_curve(
+                    &*controller,
+                    fan_id,
+                    sensor_id,
+                    sorted_if(sort, steps),
+                    temp,
+                ),
+                Commands::ApplyCurves { dir } => cmd_apply_curves(&*controller, &dir),
+                #[cfg(feature = "metrics")]
+                Commands::Metrics { bind } => metrics::serve(&*controller, bind),
+                Commands::ApplyPreset { name } => cmd_apply_preset(&*controller, &name, dry_run),
+                Commands::DumpTable { fan_id, sensor_id } => {
+                    cmd_dump_table(&*controller, fan_id, sensor_id, json_output)
+                }
+                Commands::BackupCurves { output, force } => {
+                    cmd_backup_curves(&*controller, &output, force)
+                }
+                Commands::DiffCurves { input } => {
+                    cmd_diff_curves(&*controller, &input, json_output)
+                }
+                Commands::RestoreCurves { input } => {
+                    cmd_restore_curves(&*controller, &input, dry_run)
+                }
+                Commands::HoldCurve { input, interval } => {
+                    cmd_hold_curve(&*controller, &input, interval, dry_run)
+                }
+                Commands::Gui { .. }
+                | Commands::Tui
+                | Commands::DumpConfig
+                | Commands::Doctor
+                | Commands::ListPresets
+                | Commands::Completions { .. } => unreachable!(),
+            };
+            if dry_run && result.is_ok() {
+                println!("Dry run: nothing was written to the hardware");
             }
+            result
+        }
+    }
+}
+
+fn cmd_dump_script() -> Result<()> {
+    let Some(scripts) = platform::lenovo_scripts() else {
+        anyhow::bail!("--dump-script is only available for the Lenovo backend (Windows)");
+    };
+    for (label, script) in scripts {
+        println!("# {label}");
+        println!("{script}");
+        println!();
+    }
+    Ok(())
+}
+
+/// `--interval` if given, else the config's `poll_interval_secs`, else
+/// `default`.
+fn poll_interval(flag: Option<u64>, default: u64) -> u64 {
+    flag.or_else(|| config::load_config().poll_interval_secs)
+        .unwrap_or(default)
+}
+
+fn cmd_dump_config(json_output: bool) -> Result<()> {
+    let layers = config::config_layers();
+    let (merged, statuses) = config::resolve_layers(&layers);
+
+    if !json_output {
+        println!("Config layers (lowest to highest precedence):");
+        for ((name, path), status) in layers.iter().zip(&statuses) {
+            let status = match status {
+                config::LayerStatus::Loaded => "loaded".to_string(),
+                config::LayerStatus::NotFound => "not found".to_string(),
+                config::LayerStatus::Malformed(error) => format!("malformed, skipped: {error}"),
+            };
+            println!("  {:<9} {} ({})", name, path.display(), status);
         }
+        println!();
+        println!("Merged config:");
     }
+    println!("{}", serde_json::to_string_pretty(&merged)?);
+    Ok(())
 }
 
 fn cmd_list(controller: &dyn FanController, json_output: bool) -> Result<()> {
@@ -104,23 +451,61 @@ fn cmd_list(controller: &dyn FanController, json_output: bool) -> Result<()> {
     }
 
     println!(
-        "{:<25} {:<20} {:>8} {:>6} STATUS",
-        "ID", "LABEL", "RPM", "PWM"
+        "{:<25} {:<20} {:>8} {:>11} {:>6} STATUS",
+        "ID", "LABEL", "RPM", "RANGE", "PWM"
     );
-    println!("{}", "-".repeat(70));
+    println!("{}", "-".repeat(82));
+    let colored = color::enabled();
     for fan in &fans {
         let pwm_display = fan
             .pwm
             .map(|p| format!("{}", p))
             .unwrap_or_else(|| "\u{2014}".into());
-        let status = if fan.controllable {
+        let control = if fan.controllable {
             "controllable"
         } else {
             "read-only"
         };
+        let mut status = match fan.pwm_mode_name() {
+            Some(mode) => format!("{control} ({mode})"),
+            None => control.to_string(),
+        };
+        if let Some(target) = fan.target_rpm {
+            status.push_str(&format!(" target {target} RPM"));
+        }
+        if fan.stalled {
+            status.push_str(" STALLED");
+        }
+        let range_display = fan.rpm_range_display().unwrap_or_else(|| "\u{2014}".into());
+        println!(
+            "{:<25} {:<20} {} {:>11} {:>6} {}",
+            fan.id,
+            fan.display_label(),
+            rpm_cell(fan, 8, colored),
+            range_display,
+            pwm_display,
+            status
+        );
+    }
+    println!("{}", "-".repeat(82));
+    println!("{}", fan_count_summary(&fans));
+    print_stall_warnings(&fans);
+
+    // Only backends with vendor profiles report a mode; others stay silent.
+    if let Ok(Some(mode)) = controller.get_smart_fan_mode() {
+        println!();
+        println!(
+            "Thermal mode: {} ({})",
+            smart_fan_mode_name(mode).unwrap_or("Unknown"),
+            mode
+        );
+    }
+
+    if !fans.iter().any(|f| f.controllable) {
+        println!();
         println!(
-            "{:<25} {:<20} {:>8} {:>6} {}",
-            fan.id, fan.label, fan.speed_rpm, pwm_display, status
+            "No controllable fans found, so `set` will fail: {}",
+            platform::no_control_reason(controller)
         );
     }
     Ok(())
@@ -138,148 +523,1714 @@ fn cmd_get(controller: &dyn FanController, fan_id: &str, json_output: bool) -> R
     Ok(())
 }
 
-fn cmd_set(controller: &dyn FanController, fan_id: &str, pwm: u8) -> Result<()> {
-    controller.set_pwm(fan_id, pwm)?;
-    println!("Set {} PWM to {}", fan_id, pwm);
-    Ok(())
+/// A `set` value as the user gave it: raw PWM or a percentage.
+#[derive(Clone, Copy)]
+enum PwmTarget {
+    Raw(u8),
+    Percent(u8),
 }
 
-fn cmd_table(
-    controller: &dyn FanController,
-    filter_fan_id: Option<u32>,
-    json_output: bool,
-) -> Result<()> {
-    // Prefer curves already attached to fans from discover(), falling back
-    // to the dedicated get_fan_curves() method.
-    let fans = controller.discover()?;
+impl PwmTarget {
+    fn pwm(self) -> u8 {
+        match self {
+            PwmTarget::Raw(pwm) => pwm,
+            PwmTarget::Percent(percent) => percent_to_pwm(percent),
+        }
+    }
+}
 
-    let full_speed_active = fans.iter().any(|f| f.full_speed_active);
-    let has_embedded_curves = fans.iter().any(|f| !f.curves.is_empty());
+/// Shown the same way however it was given, and as the GUI shows it:
+/// `--percent 60` and `153` both print "PWM 153 (60%)".
+impl std::fmt::Display for PwmTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&format_pwm(self.pwm()))
+    }
+}
 
-    let curves = if has_embedded_curves {
-        fans.into_iter().flat_map(|f| f.curves).collect::<Vec<_>>()
+/// RPM polls after `set --verify`, one per `VERIFY_POLL_INTERVAL`. Fans take
+/// a few seconds to spin up or down.
+const VERIFY_POLLS: usize = 8;
+const VERIFY_POLL_INTERVAL: Duration = Duration::from_millis(750);
+
+/// `ramp` is the `--ramp` duration and step count.
+fn cmd_set(
+    controller: &dyn FanController,
+    fan_id: &str,
+    target: PwmTarget,
+    verify: bool,
+    ramp: Option<(Duration, u32)>,
+    dry_run: bool,
+) -> Result<()> {
+    let pwm = target.pwm();
+    if dry_run {
+        controller.set_pwm(fan_id, pwm)?;
+        println!("Would set {} to {}", fan_id, target);
+        if let Some((duration, steps)) = ramp {
+            println!("Ramp: {steps} steps over {}s", duration.as_secs_f64());
+        }
+        if verify {
+            println!("Verify: skipped, the fan cannot respond to a dry run");
+        }
+        return Ok(());
+    }
+    // Snapshot before the write so there is a baseline to compare against.
+    let before = if verify {
+        controller
+            .discover()?
+            .into_iter()
+            .find(|fan| fan.id == fan_id)
     } else {
-        controller.get_fan_curves()?
+        None
     };
 
-    let has_any_curves = !curves.is_empty();
+    match ramp {
+        Some((duration, steps)) => {
+            let delay = duration / steps.saturating_sub(1).max(1);
+            let stop = shutdown::stop_flag();
+            let left = platform::ramp_to(controller, fan_id, pwm, steps, delay, &stop)?;
+            if left != pwm {
+                println!("Ramp stopped: {fan_id} left at {}", format_pwm(left));
+                return Ok(());
+            }
+            println!(
+                "Set {} to {} (ramped over {}s)",
+                fan_id,
+                target,
+                duration.as_secs_f64()
+            );
+        }
+        None => {
+            controller.set_pwm(fan_id, pwm)?;
+            println!("Set {} to {}", fan_id, target);
+        }
+    }
 
-    let filtered: Vec<_> = match filter_fan_id {
-        Some(fid) => curves.into_iter().filter(|c| c.fan_id == fid).collect(),
-        None => curves,
+    if let Some(before) = before {
+        verify_pwm_effect(controller, fan_id, pwm, before.speed_rpm, before.pwm);
+    }
+    Ok(())
+}
+
+/// `set --all`: write `target` to every controllable fan, one line per fan.
+/// Fans that are not controllable are listed as skipped; fails if any write
+/// failed, after reporting the fans that were set.
+fn cmd_set_all(controller: &dyn FanController, target: PwmTarget, dry_run: bool) -> Result<()> {
+    let results = platform::set_pwm_all(controller, target.pwm())?;
+    if results.is_empty() {
+        anyhow::bail!("no fans detected");
+    }
+    let verb = if dry_run { "Would set" } else { "Set" };
+    let (mut set, mut failed) = (0, 0);
+    for (fan_id, result) in &results {
+        match result {
+            Ok(()) => {
+                set += 1;
+                println!("{verb} {fan_id} to {target}");
+            }
+            Err(FanControlError::NotControllable(_)) => {
+                println!("Skipped {fan_id}: not controllable");
+            }
+            Err(error) => {
+                failed += 1;
+                warn!("set {fan_id} failed: {error}");
+                eprintln!("Failed {fan_id}: {error}");
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{failed} of {} fans could not be set", set + failed);
+    }
+    if set == 0 {
+        anyhow::bail!(
+            "no controllable fans: {}",
+            platform::no_control_reason(controller)
+        );
+    }
+    Ok(())
+}
+
+/// Watch the fan after a PWM write and warn when the RPM does not follow.
+fn verify_pwm_effect(
+    controller: &dyn FanController,
+    fan_id: &str,
+    pwm: u8,
+    before_rpm: u32,
+    before_pwm: Option<u8>,
+) {
+    let Some(direction) = expected_rpm_direction(before_pwm, pwm) else {
+        println!("Verify: PWM unchanged or unknown, nothing to check");
+        return;
     };
+    let response = watch_rpm_response(before_rpm, direction, VERIFY_POLLS, || {
+        thread::sleep(VERIFY_POLL_INTERVAL);
+        controller
+            .get_speed(fan_id)
+            .inspect_err(|error| warn!("verify read {fan_id} failed: {error}"))
+            .ok()
+    });
 
-    if json_output {
-        println!("{}", serde_json::to_string_pretty(&filtered)?);
-        return Ok(());
+    match response {
+        SpeedResponse::Responded { rpm } => {
+            println!(
+                "Verify: {} responded ({} \u{2192} {} RPM)",
+                fan_id, before_rpm, rpm
+            );
+        }
+        SpeedResponse::NoChange { rpm } => {
+            let expected = if direction.is_gt() { "rise" } else { "fall" };
+            let waited = VERIFY_POLL_INTERVAL * VERIFY_POLLS as u32;
+            warn!("{fan_id} did not respond to PWM {pwm}: {before_rpm} -> {rpm} RPM");
+            eprintln!(
+                "Warning: {} RPM did not {} within {}s ({} \u{2192} {} RPM); \
+                 the firmware may be ignoring manual control",
+                fan_id,
+                expected,
+                waited.as_secs(),
+                before_rpm,
+                rpm
+            );
+            match controller.get_smart_fan_mode() {
+                Ok(Some(mode)) if mode != 255 => eprintln!(
+                    "Hint: SmartFanMode is {mode}, not Custom (255); switch thermal \
+                     mode to Custom (Fn+Q) so manual speeds stick"
+                ),
+                _ => {}
+            }
+        }
     }
+}
 
-    if full_speed_active {
-        println!("** FULL SPEED MODE ACTIVE **\n");
+fn cmd_set_rpm(controller: &dyn FanController, fan_id: &str, rpm: u32) -> Result<()> {
+    controller.set_rpm(fan_id, rpm)?;
+    println!("Set {} target to {} RPM", fan_id, rpm);
+    Ok(())
+}
+
+fn cmd_auto(controller: &dyn FanController, fan_id: &str) -> Result<()> {
+    controller.set_auto(fan_id)?;
+    println!("Returned {} to automatic control", fan_id);
+    Ok(())
+}
+
+fn cmd_stop(controller: &dyn FanController, fan_id: &str) -> Result<()> {
+    controller.set_pwm(fan_id, 0)?;
+    println!("Stopped {}: {}", fan_id, controller.stop_note());
+    Ok(())
+}
+
+fn cmd_full_speed(controller: &dyn FanController, enabled: bool) -> Result<()> {
+    controller.set_full_speed(enabled)?;
+    if enabled {
+        println!("Full-speed mode on");
+    } else {
+        println!("Full-speed mode off: fans are back under automatic control");
     }
+    Ok(())
+}
 
-    if filtered.is_empty() {
-        if has_any_curves {
-            println!("No fan curves found for the specified fan ID.");
-        } else {
-            println!("No fan curve data available on this platform.");
+/// Hold a fan at a PWM value until Ctrl+C, then optionally restore auto.
+fn cmd_set_watch(
+    controller: &dyn FanController,
+    fan_id: &str,
+    target: PwmTarget,
+    interval_secs: u64,
+    restore_on_exit: bool,
+) -> Result<()> {
+    let stop = shutdown::stop_flag();
+    let pwm = target.pwm();
+
+    controller.set_pwm(fan_id, pwm)?;
+    println!(
+        "Holding {} at {} (re-applied every {}s) — Ctrl+C to stop",
+        fan_id, target, interval_secs
+    );
+
+    while !shutdown::sleep_or_stop(&stop, Duration::from_secs(interval_secs)) {
+        if let Err(error) = controller.set_pwm(fan_id, pwm) {
+            warn!("re-apply {fan_id}={pwm} failed: {error}");
         }
-        return Ok(());
     }
 
-    for curve in &filtered {
-        let fan_label = match curve.fan_id {
-            0 => "CPU Fan",
-            1 => "GPU Fan",
-            _ => "Fan",
-        };
-        let active_tag = if curve.active { "Active" } else { "Inactive" };
+    if restore_on_exit {
+        controller.set_auto(fan_id)?;
+        println!("\nReturned {} to automatic control", fan_id);
+    } else {
+        println!("\nLeaving {} at {}", fan_id, target);
+    }
+    Ok(())
+}
+
+/// `calibrate`: hold `fan_id` at each of `steps` PWM values for
+/// `settle_secs`, read the RPM it reached, then return it to automatic
+/// control. The table is printed and, with `save`, stored as the fan's
+/// `pwm_calibration` in the config next to the executable.
+fn cmd_calibrate(
+    controller: &dyn FanController,
+    fan_id: &str,
+    steps: u32,
+    settle_secs: u64,
+    save: bool,
+    json_output: bool,
+) -> Result<()> {
+    let pwms = calibration_pwms(steps);
+    let stop = shutdown::stop_flag();
+    if !json_output {
         println!(
-            "Fan {} ({}) \u{2014} Sensor {} [{}]",
-            curve.fan_id, fan_label, curve.sensor_id, active_tag
+            "Calibrating {fan_id} at {} PWM values, {settle_secs}s each — Ctrl+C to stop",
+            pwms.len()
         );
-        println!(
-            "  Speed: {}\u{2013}{} RPM | Temp: {}\u{2013}{}\u{00B0}C",
-            curve.min_speed, curve.max_speed, curve.min_temp, curve.max_temp
+    }
+
+    let mut samples = Vec::new();
+    let mut sweep = || -> Result<bool> {
+        for &pwm in &pwms {
+            controller.set_pwm(fan_id, pwm)?;
+            if shutdown::sleep_or_stop(&stop, Duration::from_secs(settle_secs)) {
+                return Ok(false);
+            }
+            let rpm = controller.get_speed(fan_id)?;
+            info!("calibrate: {fan_id} PWM {pwm} -> {rpm} RPM");
+            if !json_output {
+                println!("  PWM {pwm:>3}  {rpm:>5} RPM");
+            }
+            samples.push(CalibrationPoint { pwm, rpm });
+        }
+        Ok(true)
+    };
+    let finished = sweep();
+    let restored = controller.set_auto(fan_id);
+    // The sweep error is returned below; do not lose this one with it.
+    if let (Err(_), Err(error)) = (&finished, &restored) {
+        let warning = format!("could not return {fan_id} to automatic control: {error}");
+        warn!("{warning}");
+        eprintln!("Warning: {warning}");
+    }
+    if !finished? {
+        restored?;
+        anyhow::bail!(
+            "stopped after {} of {} values; {fan_id} is back under automatic control \
+             and nothing was saved",
+            samples.len(),
+            pwms.len()
         );
-        for point in &curve.points {
-            println!(
-                "  {}{}\u{00B0}C \u{2192} {} RPM",
-                if point.temperature < 100 { " " } else { "" },
-                point.temperature,
-                point.fan_speed
-            );
+    }
+    restored?;
+
+    let table = calibration_from_samples(&samples);
+    validate_calibration(&table).map_err(|error| anyhow::anyhow!("{fan_id}: {error}"))?;
+    if json_output {
+        let report = json!({ "fan_id": fan_id, "points": table });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("Returned {fan_id} to automatic control");
+        if table != samples {
+            println!("Note: the RPM dipped as PWM rose; dips were raised to the value before");
         }
-        println!();
     }
 
+    if save {
+        let mut cfg = config::load_local_config();
+        cfg.pwm_calibration.insert(fan_id.to_string(), table);
+        config::save_config(&cfg)?;
+        if !json_output {
+            println!("Saved to {}", config::config_path().display());
+        }
+    } else if !json_output {
+        println!("Use --save to map PWM to RPM through this table");
+    }
     Ok(())
 }
 
-fn cmd_set_curve(
+fn cmd_benchmark_hold(
     controller: &dyn FanController,
-    fan_id: u32,
-    sensor_id: u32,
-    steps: [u8; 10],
-    save: bool,
+    fan_id: &str,
+    rpm: u32,
+    duration: Duration,
+    log_csv: Option<&Path>,
+    interval_secs: u64,
 ) -> Result<()> {
-    let curve = CustomFanCurve {
-        fan_id,
-        sensor_id,
-        steps,
+    let mut csv = match log_csv {
+        Some(path) => {
+            let mut file =
+                BufWriter::new(File::create(path).map_err(|error| {
+                    anyhow::anyhow!("cannot create {}: {error}", path.display())
+                })?);
+            writeln!(file, "elapsed_secs,fan_id,target_rpm,rpm,temp_c")?;
+            Some(file)
+        }
+        None => None,
     };
 
-    controller.set_custom_curve(&curve)?;
-
+    let stop = shutdown::stop_flag();
+    controller.set_rpm(fan_id, rpm)?;
     println!(
-        "Custom fan curve set for fan {} sensor {}",
-        fan_id, sensor_id
+        "Holding {} at {} RPM for {}s (re-applied every {}s) — Ctrl+C to stop early",
+        fan_id,
+        rpm,
+        duration.as_secs(),
+        interval_secs
     );
-    println!("Steps: {:?}", steps);
 
-    if save {
-        let mut cfg = config::load_config();
-        // Upsert: replace existing curve for this fan+sensor, or add new
-        cfg.custom_curves
-            .retain(|c| !(c.fan_id == fan_id && c.sensor_id == sensor_id));
-        cfg.custom_curves.push(curve);
-        config::save_config(&cfg)?;
-        println!("Saved to {}", config::config_path().display());
+    let start = Instant::now();
+    let mut hold = || -> Result<bool> {
+        loop {
+            let elapsed = start.elapsed();
+            let sample = controller.discover().and_then(|fans| {
+                fans.into_iter()
+                    .find(|fan| fan.id == fan_id)
+                    .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_string()))
+            });
+            match sample {
+                Ok(fan) => {
+                    let temp = fan
+                        .temperature
+                        .map_or("—".to_string(), |celsius| format!("{celsius}°C"));
+                    println!(
+                        "[{:>5}s] {} {} RPM, {} (target {})",
+                        elapsed.as_secs(),
+                        fan_id,
+                        fan.speed_rpm,
+                        temp,
+                        rpm
+                    );
+                    if let Some(file) = csv.as_mut() {
+                        let temp_c = fan.temperature.map_or(String::new(), |c| c.to_string());
+                        writeln!(
+                            file,
+                            "{},{},{},{},{}",
+                            elapsed.as_secs(),
+                            fan_id,
+                            rpm,
+                            fan.speed_rpm,
+                            temp_c
+                        )?;
+                        file.flush()?;
+                    }
+                }
+                Err(error) => warn!("sample {fan_id} failed: {error}"),
+            }
+
+            let Some(remaining) = duration.checked_sub(elapsed).filter(|d| !d.is_zero()) else {
+                return Ok(false);
+            };
+            if shutdown::sleep_or_stop(&stop, remaining.min(Duration::from_secs(interval_secs))) {
+                return Ok(true);
+            }
+            if let Err(error) = controller.set_rpm(fan_id, rpm) {
+                warn!("re-apply {fan_id}={rpm} RPM failed: {error}");
+            }
+        }
+    };
+    // A failed CSV write must not leave the fan pinned at the target.
+    let held = hold();
+    let restored = controller.set_auto(fan_id);
+    let interrupted = held?;
+    restored?;
+    if interrupted {
+        println!("\nStopped early; returned {} to automatic control", fan_id);
     } else {
-        println!();
-        println!("Note: Custom curves require SmartFanMode=Custom and are volatile");
-        println!("      (lost on reboot, sleep, or power mode change).");
-        println!("      Use --save to persist to config file.");
+        println!("\nBenchmark done; returned {} to automatic control", fan_id);
+    }
+    if let Some(path) = log_csv {
+        println!("Samples written to {}", path.display());
     }
-
     Ok(())
 }
 
-fn cmd_monitor(controller: &dyn FanController, interval_secs: u64) -> Result<()> {
-    println!("Monitoring fans (Ctrl+C to stop)...\n");
-    loop {
-        // Clear screen with ANSI escape
-        print!("\x1B[2J\x1B[H");
-        println!("Fan Monitor (every {}s) — Ctrl+C to stop\n", interval_secs);
+/// How `watch` forced the fans to full speed, and so how to undo it.
+enum ForcedFans {
+    /// The vendor full-speed mode; `was_on` if it was already on before.
+    FullSpeedMode { was_on: bool },
+    /// PWM 255 on each controllable fan, with its manual PWM from before
+    /// (`None` if it was under automatic control).
+    MaxPwm(Vec<(String, Option<u8>)>),
+}
 
-        let fans = controller.discover()?;
-        if fans.is_empty() {
-            println!("No fans detected.");
-        } else {
-            if fans.iter().any(|f| f.full_speed_active) {
-                println!("** FULL SPEED MODE ACTIVE **\n");
+/// The hottest of the sensor readings and the fans' own temperatures.
+fn hottest_reading(controller: &dyn FanController, fans: &[Fan]) -> Option<(String, u32)> {
+    let sensors = controller.sensor_temperatures().unwrap_or_else(|error| {
+        debug!("sensor temperatures unavailable: {error}");
+        Vec::new()
+    });
+    sensors
+        .into_iter()
+        .map(|sensor| (sensor.label, sensor.temperature))
+        .chain(
+            fans.iter()
+                .filter_map(|fan| Some((fan.label.clone(), fan.temperature?))),
+        )
+        .max_by_key(|(_, temperature)| *temperature)
+}
+
+/// Force full speed: the vendor full-speed mode where there is one,
+/// otherwise PWM 255 on every controllable fan.
+fn force_full_speed(controller: &dyn FanController, fans: &[Fan]) -> Result<ForcedFans> {
+    match controller.set_full_speed(true) {
+        Ok(()) => {
+            return Ok(ForcedFans::FullSpeedMode {
+                was_on: fans.iter().any(|fan| fan.full_speed_active),
+            })
+        }
+        Err(FanControlError::Platform(reason)) => {
+            debug!("no full-speed mode ({reason}); using PWM 255")
+        }
+        Err(error) => return Err(error.into()),
+    }
+
+    let mut previous = Vec::new();
+    for fan in fans.iter().filter(|fan| fan.controllable) {
+        match controller.set_pwm(&fan.id, 255) {
+            Ok(()) => {
+                let manual = fan.pwm_mode == Some(1);
+                previous.push((fan.id.clone(), fan.pwm.filter(|_| manual)));
             }
-            println!("{:<25} {:>8} {:>6}", "FAN", "RPM", "PWM");
-            println!("{}", "-".repeat(45));
-            for fan in &fans {
-                let pwm_display = fan
-                    .pwm
-                    .map(|p| format!("{}", p))
-                    .unwrap_or_else(|| "—".into());
-                println!("{:<25} {:>8} {:>6}", fan.label, fan.speed_rpm, pwm_display);
+            Err(error) => warn!("forcing {} to PWM 255 failed: {error}", fan.id),
+        }
+    }
+    if previous.is_empty() {
+        anyhow::bail!("no fan could be forced to full speed");
+    }
+    Ok(ForcedFans::MaxPwm(previous))
+}
+
+/// Undo [`force_full_speed`].
+fn restore_forced_fans(controller: &dyn FanController, forced: &ForcedFans) -> Result<()> {
+    match forced {
+        ForcedFans::FullSpeedMode { was_on: true } => {
+            println!("Full-speed mode was already on before the trip; leaving it on");
+        }
+        ForcedFans::FullSpeedMode { was_on: false } => {
+            controller.set_full_speed(false)?;
+            println!("Full-speed mode off");
+        }
+        ForcedFans::MaxPwm(previous) => {
+            for (fan_id, pwm) in previous {
+                let result = match *pwm {
+                    Some(pwm) => controller.set_pwm(fan_id, pwm),
+                    None => controller.set_auto(fan_id),
+                };
+                match (result, pwm) {
+                    (Ok(()), Some(pwm)) => println!("Returned {fan_id} to PWM {pwm}"),
+                    (Ok(()), None) => println!("Returned {fan_id} to automatic control"),
+                    (Err(error), _) => eprintln!("Could not restore {fan_id}: {error}"),
+                }
             }
         }
+    }
+    Ok(())
+}
 
-        thread::sleep(Duration::from_secs(interval_secs));
+/// Force full speed while any temperature is over `max_temp`, until Ctrl+C.
+fn cmd_watch(
+    controller: &dyn FanController,
+    max_temp: u32,
+    hysteresis: u32,
+    min_dwell: Duration,
+    interval_secs: u64,
+) -> Result<()> {
+    let fans = controller.discover()?;
+    if hottest_reading(controller, &fans).is_none() {
+        anyhow::bail!("no temperature readings to watch on this system");
     }
+
+    let stop = shutdown::stop_flag();
+    let mut guard = OverheatGuard::new(max_temp, hysteresis, min_dwell);
+    let mut forced: Option<ForcedFans> = None;
+    println!(
+        "Watching temperatures every {}s: full speed above {}°C, restored below {}°C \
+         after at least {}s — Ctrl+C to stop",
+        interval_secs,
+        max_temp,
+        guard.release_below(),
+        min_dwell.as_secs()
+    );
+
+    loop {
+        let fans = controller.discover().unwrap_or_else(|error| {
+            warn!("discover failed: {error}");
+            Vec::new()
+        });
+        match hottest_reading(controller, &fans) {
+            Some((label, temperature)) => match guard.update(temperature, Instant::now()) {
+                Some(GuardAction::Trip) => {
+                    eprintln!(
+                        "Warning: {label} at {temperature}°C is above {max_temp}°C; \
+                         forcing fans to full speed"
+                    );
+                    warn!("{label} at {temperature}°C is above {max_temp}°C; forcing full speed");
+                }
+                Some(GuardAction::Release) => {
+                    println!(
+                        "{label} is down to {temperature}°C; restoring the previous fan state"
+                    );
+                    info!("{label} at {temperature}°C; releasing full speed");
+                }
+                None => debug!("hottest reading: {label} at {temperature}°C"),
+            },
+            None => warn!("no temperature readings this tick"),
+        }
+
+        // Both retried every tick until they stick, should an attempt fail.
+        if !guard.tripped() {
+            if let Some(previous) = &forced {
+                match restore_forced_fans(controller, previous) {
+                    Ok(()) => forced = None,
+                    Err(error) => {
+                        eprintln!("Warning: could not restore the previous fan state: {error}");
+                        warn!("restoring the previous fan state failed: {error}");
+                    }
+                }
+            }
+        }
+        if guard.tripped() && forced.is_none() && !fans.is_empty() {
+            match force_full_speed(controller, &fans) {
+                Ok(done) => forced = Some(done),
+                Err(error) => {
+                    eprintln!("Warning: could not force full speed: {error}");
+                    warn!("forcing full speed failed: {error}");
+                }
+            }
+        }
+
+        if shutdown::sleep_or_stop(&stop, Duration::from_secs(interval_secs)) {
+            break;
+        }
+    }
+
+    println!("\nStopped watching");
+    if let Some(previous) = &forced {
+        restore_forced_fans(controller, previous)?;
+    }
+    Ok(())
+}
+
+fn cmd_fan_mode(
+    controller: &dyn FanController,
+    mode: Option<u32>,
+    json_output: bool,
+) -> Result<()> {
+    if let Some(mode) = mode {
+        controller.set_smart_fan_mode(mode)?;
+        println!(
+            "SmartFanMode set to {} ({})",
+            smart_fan_mode_name(mode).unwrap_or("Unknown"),
+            mode
+        );
+        return Ok(());
+    }
+
+    let current = controller.get_smart_fan_mode()?;
+    if json_output {
+        let output = json!({
+            "smart_fan_mode": current,
+            "name": current.and_then(smart_fan_mode_name),
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+    match current {
+        Some(mode) => println!(
+            "SmartFanMode: {} ({})",
+            smart_fan_mode_name(mode).unwrap_or("Unknown"),
+            mode
+        ),
+        None => println!("SmartFanMode is not available on this platform"),
+    }
+    Ok(())
+}
+
+fn cmd_table(
+    controller: &dyn FanController,
+    filter_fan_id: Option<u32>,
+    format: TableFormat,
+) -> Result<()> {
+    // Prefer curves already attached to fans from discover(), falling back
+    // to the dedicated get_fan_curves() method.
+    let fans = controller.discover()?;
+
+    let full_speed_active = fans.iter().any(|f| f.full_speed_active);
+    let has_embedded_curves = fans.iter().any(|f| !f.curves.is_empty());
+
+    let curves = if has_embedded_curves {
+        fans.into_iter().flat_map(|f| f.curves).collect::<Vec<_>>()
+    } else {
+        controller.get_fan_curves()?
+    };
+
+    let has_any_curves = !curves.is_empty();
+
+    let filtered: Vec<_> = match filter_fan_id {
+        Some(fid) => curves.into_iter().filter(|c| c.fan_id == fid).collect(),
+        None => curves,
+    };
+
+    match format {
+        TableFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(&filtered)?);
+            return Ok(());
+        }
+        TableFormat::Csv => {
+            for row in curves_csv(&filtered) {
+                println!("{row}");
+            }
+            return Ok(());
+        }
+        TableFormat::Table => {}
+    }
+
+    if full_speed_active {
+        println!("** FULL SPEED MODE ACTIVE **\n");
+    }
+
+    if filtered.is_empty() {
+        if has_any_curves {
+            println!("No fan curves found for the specified fan ID.");
+        } else {
+            println!("No fan curve data available on this platform.");
+        }
+        return Ok(());
+    }
+
+    for curve in &filtered {
+        // Fans beyond CPU and GPU (e.g. a Legion system fan) have no role
+        // label; "Fan 2" alone names them.
+        let fan_label = match curve.fan_id {
+            0 => " (CPU Fan)",
+            1 => " (GPU Fan)",
+            _ => "",
+        };
+        let active_tag = if curve.active { "Active" } else { "Inactive" };
+        println!(
+            "Fan {}{} \u{2014} {} [{}]",
+            curve.fan_id,
+            fan_label,
+            curve.sensor_name(),
+            active_tag
+        );
+        println!(
+            "  Speed: {}\u{2013}{} RPM | Temp: {}\u{2013}{}\u{00B0}C",
+            curve.min_speed, curve.max_speed, curve.min_temp, curve.max_temp
+        );
+        print_curve_points(curve);
+        println!();
+    }
+
+    // With several active sensor curves the EC follows whichever demands
+    // the most, so show that combined curve too.
+    let mut fan_ids: Vec<u32> = filtered.iter().map(|c| c.fan_id).collect();
+    fan_ids.sort_unstable();
+    fan_ids.dedup();
+    for fan_id in fan_ids {
+        let sensors = filtered
+            .iter()
+            .filter(|c| c.fan_id == fan_id && c.active)
+            .count();
+        if sensors < 2 {
+            continue;
+        }
+        if let Some(effective) = combine_curves_for_fan(fan_id, &filtered) {
+            println!(
+                "Fan {} \u{2014} Effective (highest of {} active sensor curves)",
+                fan_id, sensors
+            );
+            print_curve_points(&effective);
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+fn print_curve_points(curve: &FanCurve) {
+    for point in &curve.points {
+        println!(
+            "  {}{}\u{00B0}C \u{2192} {} RPM",
+            if point.temperature < 100 { " " } else { "" },
+            point.temperature,
+            point.fan_speed
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cmd_set_curve(
+    controller: &dyn FanController,
+    fan_id: u32,
+    sensor: SensorRef,
+    steps: [u8; 10],
+    save: bool,
+    strict: bool,
+    auto_fix: bool,
+    smooth: Option<u8>,
+    min_high_rpm_percent: Option<u8>,
+    dry_run: bool,
+) -> Result<()> {
+    let min_high_temp_ratio = min_high_rpm_percent.map_or(DEFAULT_MIN_HIGH_TEMP_RATIO, |percent| {
+        f32::from(percent) / 100.0
+    });
+    if let Some(percent) = min_high_rpm_percent {
+        if min_high_temp_ratio < DEFAULT_MIN_HIGH_TEMP_RATIO {
+            let warning = format!(
+                "--min-high-rpm-percent {percent} lowers the high-temperature safety floor \
+                 below 50%: the fan may stay too slow under sustained load, letting the \
+                 CPU or GPU throttle or overheat"
+            );
+            warn!("{warning}");
+            eprintln!("Warning: {warning}");
+        }
+    }
+
+    let steps = match smooth {
+        Some(points) => {
+            let smoothed = smooth_steps(steps, usize::from(points), min_high_temp_ratio);
+            println!("Smoothed through {points} points: {steps:?} -> {smoothed:?}");
+            smoothed
+        }
+        None => steps,
+    };
+    let steps = if auto_fix {
+        let (fixed, fixes) = auto_fix_steps(steps, min_high_temp_ratio);
+        print_step_fixes(&steps, &fixed, &fixes);
+        fixed
+    } else {
+        steps
+    };
+
+    let sensor_id = resolve_sensor(controller, sensor)?;
+    let curve = CustomFanCurve {
+        fan_id,
+        sensor_id,
+        steps,
+    };
+
+    // Advisory only: the backend validates again before writing.
+    let table = controller
+        .get_fan_curves()
+        .unwrap_or_else(|error| {
+            debug!("no EC table to check the curve against: {error}");
+            Vec::new()
+        })
+        .into_iter()
+        .find(|table| table.fan_id == fan_id && table.sensor_id == sensor_id);
+    let validation =
+        validate_custom_curve_with_policy(&curve, min_high_temp_ratio, table.as_ref())?;
+    let sensor_name = table
+        .as_ref()
+        .map_or_else(|| format!("Sensor {sensor_id}"), FanCurve::sensor_name);
+    for warning in &validation.warnings {
+        warn!("{warning}");
+        eprintln!("Warning: {warning}");
+    }
+
+    // Saved curves are re-applied later under the default floors.
+    if save {
+        if let Err(error) = validate_custom_curve(&curve) {
+            anyhow::bail!(
+                "{error}: saved curves are re-applied under the default safety floors, \
+                 so this curve cannot be saved; drop --save to apply it for this session"
+            );
+        }
+    }
+
+    if let Some(warning) = curve.quiet_curve_warning() {
+        if strict {
+            anyhow::bail!("{warning} (rejected by --strict)");
+        }
+        warn!("{warning}");
+        eprintln!("Warning: {warning}");
+    }
+
+    controller.set_custom_curve_with_policy(&curve, min_high_temp_ratio)?;
+
+    if dry_run {
+        println!("Would set custom fan curve for fan {fan_id}, {sensor_name}");
+        println!("Steps: {:?}", steps);
+        if save {
+            println!("Would save to {}", config::config_path().display());
+        }
+        return Ok(());
+    }
+
+    println!("Custom fan curve set for fan {fan_id}, {sensor_name}");
+    println!("Steps: {:?}", steps);
+
+    if save {
+        let mut cfg = config::load_local_config();
+        // Upsert: replace existing curve for this fan+sensor, or add new
+        cfg.custom_curves
+            .retain(|c| !(c.fan_id == fan_id && c.sensor_id == sensor_id));
+        cfg.custom_curves.push(curve);
+        config::save_config(&cfg)?;
+        println!("Saved to {}", config::config_path().display());
+    } else {
+        println!();
+        println!("Note: Custom curves require SmartFanMode=Custom and are volatile");
+        println!("      (lost on reboot, sleep, or power mode change).");
+        println!("      Use --save to persist to config file.");
+    }
+
+    Ok(())
+}
+
+/// The numeric sensor ID behind a `--sensor-id` value.
+fn resolve_sensor(controller: &dyn FanController, sensor: SensorRef) -> Result<u32> {
+    match sensor {
+        SensorRef::Id(sensor_id) => Ok(sensor_id),
+        SensorRef::Gpu => controller.gpu_sensor_id()?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "no discrete GPU temperature sensor found (integrated graphics only?); \
+                 pass a numeric --sensor-id instead"
+            )
+        }),
+    }
+}
+
+/// Evaluate a custom curve against the EC table for its fan/sensor pair and
+/// print the RPM at `temp`. Reads the table only; nothing is written.
+fn cmd_preview_curve(
+    controller: &dyn FanController,
+    fan_id: u32,
+    sensor: SensorRef,
+    steps: [u8; 10],
+    temp: u32,
+) -> Result<()> {
+    let sensor_id = resolve_sensor(controller, sensor)?;
+    let custom = CustomFanCurve {
+        fan_id,
+        sensor_id,
+        steps,
+    };
+    validate_custom_curve(&custom)?;
+    if let Some(warning) = custom.quiet_curve_warning() {
+        eprintln!("Warning: {warning}");
+    }
+
+    let tables = controller.get_fan_curves()?;
+    let table = tables
+        .iter()
+        .find(|curve| curve.fan_id == fan_id && curve.sensor_id == sensor_id)
+        .ok_or_else(|| {
+            anyhow::anyhow!("the EC reports no fan table for fan {fan_id} sensor {sensor_id}")
+        })?;
+    let curve = build_curve_from_points(table, &steps);
+
+    println!("Curve for fan {fan_id} sensor {sensor_id}:");
+    for point in &curve.points {
+        println!("  {:>3}°C  {:>5} RPM", point.temperature, point.fan_speed);
+    }
+    println!();
+    println!("At {temp}°C: {} RPM", curve.speed_at(temp));
+    Ok(())
+}
+
+/// `steps` sorted ascending when `--sort` was given, reporting the new order
+/// if it differs.
+fn sorted_if(sort: bool, steps: [u8; 10]) -> [u8; 10] {
+    if !sort {
+        return steps;
+    }
+    let sorted = sort_steps(steps);
+    if sorted != steps {
+        println!("Sorted steps: {steps:?} -> {sorted:?}");
+    }
+    sorted
+}
+
+/// Show what `--auto-fix` changed: both step arrays and a note per step.
+fn print_step_fixes(original: &[u8; 10], fixed: &[u8; 10], fixes: &[StepFix]) {
+    if fixes.is_empty() {
+        println!("Auto-fix: no changes needed");
+        return;
+    }
+    println!("Auto-fix adjusted the curve:");
+    println!("  Original: {:?}", original);
+    println!("  Adjusted: {:?}", fixed);
+    for fix in fixes {
+        println!(
+            "  step {}: {} \u{2192} {} ({})",
+            fix.index,
+            fix.original,
+            fix.adjusted,
+            fix.notes.join("; ")
+        );
+    }
+    println!();
+}
+
+/// Parse a curve file holding either a single curve or an array of curves.
+fn parse_curve_file(contents: &str) -> Result<Vec<CustomFanCurve>> {
+    if let Ok(curves) = serde_json::from_str::<Vec<CustomFanCurve>>(contents) {
+        return Ok(curves);
+    }
+    let curve: CustomFanCurve = serde_json::from_str(contents)?;
+    Ok(vec![curve])
+}
+
+/// Fan/sensor pairs the hardware actually has tables for. Empty when the
+/// platform exposes no curve data, in which case matching is skipped and the
+/// backend reports its own error on write.
+fn known_curve_pairs(controller: &dyn FanController) -> Result<Vec<(u32, u32)>> {
+    Ok(controller
+        .discover()?
+        .iter()
+        .flat_map(|fan| fan.curves.iter().map(|c| (c.fan_id, c.sensor_id)))
+        .collect())
+}
+
+/// Validate a curve, check its fan/sensor pair exists, and write it.
+fn apply_checked_curve(
+    controller: &dyn FanController,
+    curve: &CustomFanCurve,
+    known_pairs: &[(u32, u32)],
+) -> Result<()> {
+    validate_custom_curve(curve)?;
+    if !known_pairs.is_empty() && !known_pairs.contains(&(curve.fan_id, curve.sensor_id)) {
+        anyhow::bail!(
+            "no fan table for fan {} sensor {} on this hardware",
+            curve.fan_id,
+            curve.sensor_id
+        );
+    }
+    controller.set_custom_curve(curve)?;
+    Ok(())
+}
+
+fn cmd_apply_curves(controller: &dyn FanController, dir: &Path) -> Result<()> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)
+        .map_err(|error| anyhow::anyhow!("cannot read {}: {error}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    if paths.is_empty() {
+        println!("No *.json curve files found in {}", dir.display());
+        return Ok(());
+    }
+
+    let known_pairs = known_curve_pairs(controller)?;
+
+    let mut applied = 0;
+    let mut failed = 0;
+
+    for path in &paths {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let curves = match std::fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| parse_curve_file(&contents))
+        {
+            Ok(curves) => curves,
+            Err(error) => {
+                println!("FAIL {file_name}: not a curve file: {error}");
+                failed += 1;
+                continue;
+            }
+        };
+
+        for curve in curves {
+            let target = format!("fan {} sensor {}", curve.fan_id, curve.sensor_id);
+            match apply_checked_curve(controller, &curve, &known_pairs) {
+                Ok(()) => {
+                    println!("OK   {file_name} -> {target}");
+                    applied += 1;
+                }
+                Err(error) => {
+                    warn!("apply-curves {file_name} ({target}) failed: {error}");
+                    println!("FAIL {file_name} -> {target}: {error}");
+                    failed += 1;
+                }
+            }
+        }
+    }
+
+    println!();
+    println!("{}/{} curves applied", applied, applied + failed);
+
+    if failed > 0 {
+        anyhow::bail!("{failed} curve(s) failed to apply");
+    }
+    Ok(())
+}
+
+/// Apply software curves from `curves_path` every `interval_secs` until
+/// Ctrl+C, then optionally return the fans to automatic control.
+fn cmd_run_daemon(
+    controller: &dyn FanController,
+    curves_path: &Path,
+    interval_secs: u64,
+    restore_on_exit: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(curves_path)
+        .map_err(|error| anyhow::anyhow!("cannot read {}: {error}", curves_path.display()))?;
+    let curves: Vec<PwmCurve> = serde_json::from_str(&contents).map_err(|error| {
+        anyhow::anyhow!(
+            "{} is not a curve array (expected [{{\"fan_id\", \"points\": [{{\"temperature\", \"pwm\"}}]}}, ...]): {error}",
+            curves_path.display()
+        )
+    })?;
+    if curves.is_empty() {
+        anyhow::bail!("no curves in {}", curves_path.display());
+    }
+    for curve in &curves {
+        curve.validate()?;
+    }
+
+    controller.set_pwm_curves(&curves)?;
+    if dry_run {
+        // One tick shows what the loop would write; repeating it adds nothing.
+        controller.apply_pwm_curves()?;
+        println!(
+            "Would drive {} fan(s) from {} every {}s",
+            curves.len(),
+            curves_path.display(),
+            interval_secs
+        );
+        return Ok(());
+    }
+    let stop = shutdown::stop_flag();
+    println!(
+        "Driving {} fan(s) from {} (every {}s) — Ctrl+C to stop",
+        curves.len(),
+        curves_path.display(),
+        interval_secs
+    );
+
+    loop {
+        match controller.apply_pwm_curves() {
+            Ok(samples) => {
+                for sample in samples {
+                    info!(
+                        "{}: {}\u{00B0}C -> PWM {}",
+                        sample.fan_id, sample.temperature, sample.pwm
+                    );
+                }
+            }
+            Err(error) => warn!("applying curves failed: {error}"),
+        }
+        if shutdown::sleep_or_stop(&stop, Duration::from_secs(interval_secs)) {
+            break;
+        }
+    }
+
+    if restore_on_exit {
+        for curve in &curves {
+            match controller.set_auto(&curve.fan_id) {
+                Ok(()) => println!("\nReturned {} to automatic control", curve.fan_id),
+                Err(error) => eprintln!("\nCould not restore {}: {error}", curve.fan_id),
+            }
+        }
+    } else {
+        println!("\nLeaving fans at their last curve PWM");
+    }
+    Ok(())
+}
+
+/// Background CSV sink for `monitor --csv`.
+///
+/// Rows are handed to a writer thread so a slow disk never delays the screen
+/// redraw; the thread flushes after every batch (one refresh interval).
+struct MonitorCsv {
+    sender: Option<mpsc::Sender<Vec<String>>>,
+    writer: Option<thread::JoinHandle<()>>,
+}
+
+impl MonitorCsv {
+    const HEADER: &'static str = "timestamp,fan_id,rpm,pwm,temp";
+
+    /// Open `path` for appending, writing the header only if the file is new
+    /// or empty.
+    fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|error| anyhow::anyhow!("cannot open {}: {error}", path.display()))?;
+        let needs_header = file.metadata()?.len() == 0;
+        let mut file = BufWriter::new(file);
+        if needs_header {
+            writeln!(file, "{}", Self::HEADER)?;
+            file.flush()?;
+        }
+
+        let (sender, receiver) = mpsc::channel::<Vec<String>>();
+        let display = path.display().to_string();
+        let writer = thread::spawn(move || {
+            for rows in receiver {
+                let result = rows
+                    .iter()
+                    .try_for_each(|row| writeln!(file, "{row}"))
+                    .and_then(|()| file.flush());
+                if let Err(error) = result {
+                    warn!("writing {display} failed: {error}");
+                }
+            }
+        });
+
+        Ok(Self {
+            sender: Some(sender),
+            writer: Some(writer),
+        })
+    }
+
+    /// Queue one row per fan, all stamped with the same Unix timestamp.
+    fn record(&self, fans: &[Fan]) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let rows = fans
+            .iter()
+            .map(|fan| {
+                format!(
+                    "{},{},{},{},{}",
+                    timestamp,
+                    fan.id,
+                    fan.speed_rpm,
+                    fan.pwm.map(|p| p.to_string()).unwrap_or_default(),
+                    fan.temperature.map(|t| t.to_string()).unwrap_or_default()
+                )
+            })
+            .collect();
+        if let Some(sender) = &self.sender {
+            // A send only fails once the writer thread is gone; it has
+            // already logged why.
+            let _ = sender.send(rows);
+        }
+    }
+}
+
+impl Drop for MonitorCsv {
+    /// Close the channel and wait for queued rows to reach the file.
+    fn drop(&mut self) {
+        self.sender.take();
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+fn cmd_monitor(
+    controller: &dyn FanController,
+    interval_secs: u64,
+    csv_path: Option<&Path>,
+    count: Option<u32>,
+) -> Result<()> {
+    let csv = csv_path.map(MonitorCsv::open).transpose()?;
+    let stop = shutdown::stop_flag();
+    let started = Instant::now();
+    let mut stats = MonitorStats::default();
+    let mut last_fans;
+    let colored = color::enabled();
+
+    println!("Monitoring fans (Ctrl+C to stop)...\n");
+    let terminal = TerminalReset;
+    loop {
+        match count {
+            // A bounded run keeps every sample on screen for the log.
+            Some(count) => println!("Sample {}/{}\n", stats.samples + 1, count),
+            None => {
+                // Clear screen with ANSI escape
+                print!("\x1B[2J\x1B[H");
+                println!("Fan Monitor (every {}s) — Ctrl+C to stop\n", interval_secs);
+            }
+        }
+        if let Some(path) = csv_path {
+            println!("Logging to {}\n", path.display());
+        }
+
+        let fans = controller.discover()?;
+        if fans.is_empty() {
+            println!("No fans detected.");
+        } else {
+            if fans.iter().any(|f| f.full_speed_active) {
+                println!("** FULL SPEED MODE ACTIVE **\n");
+            }
+            println!("{:<25} {:>8} {:>6} {:>6}", "FAN", "RPM", "PWM", "TEMP");
+            println!("{}", "-".repeat(52));
+            for fan in &fans {
+                let pwm_display = fan
+                    .pwm
+                    .map(|p| format!("{}", p))
+                    .unwrap_or_else(|| "—".into());
+                let temp_display = fan
+                    .temperature
+                    .map(|t| format!("{}\u{00B0}C", t))
+                    .unwrap_or_else(|| "—".into());
+                let temp_level = fan
+                    .temperature
+                    .map_or(color::Level::Normal, color::temp_level);
+                println!(
+                    "{:<25} {} {:>6} {}{}",
+                    fan.label,
+                    rpm_cell(fan, 8, colored),
+                    pwm_display,
+                    color::paint(&format!("{temp_display:>6}"), temp_level, colored),
+                    if fan.stalled { "  STALLED" } else { "" }
+                );
+            }
+            print_stall_warnings(&fans);
+            if let Some(csv) = &csv {
+                csv.record(&fans);
+            }
+        }
+        stats.record(&fans);
+        last_fans = fans;
+        // Shown even without fans: some machines only report thermals.
+        match controller.sensor_temperatures() {
+            Ok(temps) if !temps.is_empty() => {
+                println!("\nSensors: {}", format_sensor_temps(&temps));
+            }
+            Ok(_) => {}
+            Err(error) => debug!("sensor temperatures unavailable: {error}"),
+        }
+
+        if count.is_some_and(|count| stats.samples >= count) {
+            break;
+        }
+        if shutdown::sleep_or_stop(&stop, Duration::from_secs(interval_secs)) {
+            break;
+        }
+    }
+    drop(terminal);
+
+    // Dropping the sink flushes any rows still queued.
+    drop(csv);
+    println!(
+        "\nMonitored for {}s ({} samples)",
+        started.elapsed().as_secs(),
+        stats.samples
+    );
+    print_monitor_stats(&stats);
+    if let Some(path) = csv_path {
+        println!("Samples written to {}", path.display());
+    }
+    for note in held_overrides(controller, &last_fans) {
+        println!("Note: {note}");
+    }
+    Ok(())
+}
+
+/// `fan`'s RPM right-aligned to `width`, amber or red as it nears the fan's
+/// `max_rpm` when `colored`.
+fn rpm_cell(fan: &Fan, width: usize, colored: bool) -> String {
+    let level = color::rpm_level(fan.speed_rpm, fan.max_rpm);
+    color::paint(&format!("{:>width$}", fan.speed_rpm), level, colored)
+}
+
+/// Warn about every fan reading 0 RPM while it is being driven.
+fn print_stall_warnings(fans: &[Fan]) {
+    for fan in fans.iter().filter(|fan| fan.stalled) {
+        eprintln!(
+            "Warning: {} reads 0 RPM while being driven; it may be stalled or failed",
+            fan.label
+        );
+    }
+}
+
+/// Per-fan min/avg/max RPM and temperature over a `monitor` run.
+fn print_monitor_stats(stats: &MonitorStats) {
+    if stats.fans.is_empty() {
+        return;
+    }
+    println!(
+        "{:<25} {:>20} {:>16}",
+        "FAN", "RPM min/avg/max", "TEMP min/avg/max"
+    );
+    println!("{}", "-".repeat(63));
+    for fan in &stats.fans {
+        let rpm = format!("{}/{}/{}", fan.rpm.min, fan.rpm.average(), fan.rpm.max);
+        let temp = fan
+            .temperature
+            .map(|t| format!("{}/{}/{}\u{00B0}C", t.min, t.average(), t.max))
+            .unwrap_or_else(|| "—".into());
+        println!("{:<25} {:>20} {:>16}", fan.label, rpm, temp);
+    }
+}
+
+/// Restores terminal attributes and the cursor when `monitor` ends, however
+/// it ends.
+struct TerminalReset;
+
+impl Drop for TerminalReset {
+    fn drop(&mut self) {
+        print!("\x1B[0m\x1B[?25h");
+        let _ = std::io::stdout().flush();
+    }
+}
+
+/// Manual settings still in force after `monitor` exits: full-speed mode
+/// and, on Lenovo, a Custom SmartFanMode holding manual speeds or curves.
+fn held_overrides(controller: &dyn FanController, fans: &[Fan]) -> Vec<String> {
+    let mut notes = Vec::new();
+    if fans.iter().any(|fan| fan.full_speed_active) {
+        notes.push("full-speed mode is still on (`fancontrol full-speed off`)".to_string());
+    }
+    if let Ok(Some(SMART_FAN_MODE_CUSTOM)) = controller.get_smart_fan_mode() {
+        notes.push(format!(
+            "SmartFanMode is Custom ({SMART_FAN_MODE_CUSTOM}): manual speeds and custom \
+             curves stay in effect"
+        ));
+    }
+    notes
+}
+
+fn cmd_restore_curves(controller: &dyn FanController, input: &Path, dry_run: bool) -> Result<()> {
+    let contents = std::fs::read_to_string(input)
+        .map_err(|error| anyhow::anyhow!("cannot read {}: {error}", input.display()))?;
+    let curves: Vec<CustomFanCurve> = serde_json::from_str(&contents).map_err(|error| {
+        anyhow::anyhow!(
+            "{} is not a curve array (expected [{{\"fan_id\", \"sensor_id\", \"steps\"}}, ...]): {error}",
+            input.display()
+        )
+    })?;
+
+    if curves.is_empty() {
+        println!("No curves in {}", input.display());
+        return Ok(());
+    }
+    let done = if dry_run {
+        "would be restored"
+    } else {
+        "restored"
+    };
+    apply_curve_set(controller, &curves, "restore-curves", done, false)
+}
+
+/// Apply the curves in `input`, then every `interval_secs` compare the live
+/// tables with the ones read back after writing and re-write each curve the
+/// EC reverted. Ctrl+C stops holding and leaves the curves as they are.
+fn cmd_hold_curve(
+    controller: &dyn FanController,
+    input: &Path,
+    interval_secs: u64,
+    dry_run: bool,
+) -> Result<()> {
+    let contents = std::fs::read_to_string(input)
+        .map_err(|error| anyhow::anyhow!("cannot read {}: {error}", input.display()))?;
+    let curves = parse_curve_file(&contents).map_err(|error| {
+        anyhow::anyhow!(
+            "{} is not a curve file (expected {{\"fan_id\", \"sensor_id\", \"steps\"}} or an array of them): {error}",
+            input.display()
+        )
+    })?;
+    if curves.is_empty() {
+        anyhow::bail!("no curves in {}", input.display());
+    }
+    let done = if dry_run {
+        "would be applied"
+    } else {
+        "applied"
+    };
+    apply_curve_set(controller, &curves, "hold-curve", done, false)?;
+    if dry_run {
+        println!("Hold: skipped, a dry run writes nothing to hold");
+        return Ok(());
+    }
+
+    let stop = shutdown::stop_flag();
+    let mut held = written_tables(controller, &curves)?;
+    println!(
+        "\nHolding {} curves from {} (checked every {}s) — Ctrl+C to stop",
+        curves.len(),
+        input.display(),
+        interval_secs
+    );
+
+    let start = Instant::now();
+    while !shutdown::sleep_or_stop(&stop, Duration::from_secs(interval_secs)) {
+        let live = match controller.get_fan_curves() {
+            Ok(live) => live,
+            Err(error) => {
+                warn!("hold-curve: reading the live curves failed: {error}");
+                continue;
+            }
+        };
+        let reverted = reverted_curves(&held, &live);
+        if reverted.is_empty() {
+            debug!("hold-curve: live curves unchanged");
+            continue;
+        }
+        for curve in curves
+            .iter()
+            .filter(|curve| reverted.contains(&(curve.fan_id, curve.sensor_id)))
+        {
+            let target = format!("fan {} sensor {}", curve.fan_id, curve.sensor_id);
+            let elapsed = start.elapsed().as_secs();
+            match controller.set_custom_curve(curve) {
+                Ok(()) => {
+                    info!("hold-curve: the EC reverted {target}; re-applied it");
+                    println!("[{elapsed:>5}s] Re-applied {target}: the EC had reverted it");
+                }
+                Err(error) => {
+                    warn!("hold-curve: re-applying {target} failed: {error}");
+                    eprintln!("[{elapsed:>5}s] Re-applying {target} failed: {error}");
+                }
+            }
+        }
+        match written_tables(controller, &curves) {
+            Ok(tables) => held = tables,
+            Err(error) => warn!("hold-curve: reading back the curves failed: {error}"),
+        }
+    }
+
+    println!("\nStopped holding; the curves stay applied");
+    Ok(())
+}
+
+/// The live tables for the fan/sensor pairs of `curves`, as the EC holds
+/// them right after they were written.
+fn written_tables(
+    controller: &dyn FanController,
+    curves: &[CustomFanCurve],
+) -> Result<Vec<FanCurve>> {
+    Ok(controller
+        .get_fan_curves()?
+        .into_iter()
+        .filter(|table| {
+            curves
+                .iter()
+                .any(|curve| (curve.fan_id, curve.sensor_id) == (table.fan_id, table.sensor_id))
+        })
+        .collect())
+}
+
+/// Print the raw `Fan_Get_Table` bytes for one table and the curve parsed
+/// from `LENOVO_FAN_TABLE_DATA` for the same pair, so the two can be compared.
+fn cmd_dump_table(
+    controller: &dyn FanController,
+    fan_id: u32,
+    sensor: SensorRef,
+    json_output: bool,
+) -> Result<()> {
+    let sensor_id = resolve_sensor(controller, sensor)?;
+    let raw = controller.raw_fan_table(fan_id, sensor_id)?;
+    let parsed = controller
+        .get_fan_curves()
+        .unwrap_or_else(|error| {
+            debug!("no parsed table to show next to the raw bytes: {error}");
+            Vec::new()
+        })
+        .into_iter()
+        .find(|curve| curve.fan_id == fan_id && curve.sensor_id == sensor_id);
+
+    if json_output {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&json!({
+                "fan_id": fan_id,
+                "sensor_id": sensor_id,
+                "raw": raw,
+                "parsed": parsed,
+            }))?
+        );
+        return Ok(());
+    }
+
+    println!("Fan_Get_Table(fan {fan_id}, sensor {sensor_id}):");
+    if raw.is_empty() {
+        println!("  firmware returned no data");
+    } else {
+        println!("  {} bytes", raw.len());
+        for line in hex_dump(&raw) {
+            println!("  {line}");
+        }
+    }
+    println!();
+    println!("LENOVO_FAN_TABLE_DATA (parsed):");
+    match parsed {
+        Some(curve) => {
+            println!(
+                "  Speed: {}\u{2013}{} RPM | Temp: {}\u{2013}{}\u{00B0}C",
+                curve.min_speed, curve.max_speed, curve.min_temp, curve.max_temp
+            );
+            print_curve_points(&curve);
+        }
+        None => println!("  no table for this fan/sensor pair"),
+    }
+    Ok(())
+}
+
+/// Save the live curve tables to `output`, refusing to replace an existing
+/// file unless `force` is set.
+fn cmd_backup_curves(controller: &dyn FanController, output: &Path, force: bool) -> Result<()> {
+    let curves = controller.get_fan_curves()?;
+    if curves.is_empty() {
+        anyhow::bail!("no fan curves to back up");
+    }
+    let json = serde_json::to_string_pretty(&curves)?;
+    let path = config::write_file(output, &json, force).map_err(|error| {
+        if force {
+            anyhow::anyhow!(error)
+        } else {
+            anyhow::anyhow!("{error}; pass --force to overwrite it")
+        }
+    })?;
+    info!("Backed up {} curves to {}", curves.len(), path.display());
+    println!("Backed up {} curves to {}", curves.len(), path.display());
+    Ok(())
+}
+
+/// Compare the curves saved in `input` by `table --json` with the live ones.
+fn cmd_diff_curves(controller: &dyn FanController, input: &Path, json_output: bool) -> Result<()> {
+    let contents = std::fs::read_to_string(input)
+        .map_err(|error| anyhow::anyhow!("cannot read {}: {error}", input.display()))?;
+    let backup: Vec<FanCurve> = serde_json::from_str(&contents).map_err(|error| {
+        anyhow::anyhow!(
+            "{} is not a curve table backup (save one with `fancontrol table --json`): {error}",
+            input.display()
+        )
+    })?;
+    let live = controller.get_fan_curves()?;
+    let diffs = diff_curves(&backup, &live);
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&diffs)?);
+        return Ok(());
+    }
+    if diffs.is_empty() {
+        println!("No differences: the live curves match {}", input.display());
+        return Ok(());
+    }
+    for diff in &diffs {
+        let curve = format!("fan {} sensor {}", diff.fan_id, diff.sensor_id);
+        match &diff.change {
+            CurveChange::OnlyInBackup => println!("{curve}: only in backup"),
+            CurveChange::OnlyLive => println!("{curve}: only live"),
+            CurveChange::Changed { points } => {
+                for point in points {
+                    match point {
+                        PointChange::Added {
+                            temperature,
+                            fan_speed,
+                        } => println!("{curve}: added {temperature}°C {fan_speed} RPM"),
+                        PointChange::Removed {
+                            temperature,
+                            fan_speed,
+                        } => println!("{curve}: removed {temperature}°C {fan_speed} RPM"),
+                        PointChange::Changed {
+                            temperature,
+                            backup_speed,
+                            live_speed,
+                        } => println!(
+                            "{curve}: changed {temperature}°C {backup_speed} -> {live_speed} RPM"
+                        ),
+                    }
+                }
+            }
+        }
+    }
+    println!(
+        "{} of {} curves differ",
+        diffs.len(),
+        backup
+            .iter()
+            .chain(&live)
+            .map(|curve| (curve.fan_id, curve.sensor_id))
+            .collect::<std::collections::BTreeSet<_>>()
+            .len()
+    );
+    Ok(())
+}
+
+/// Print each config preset with one line per curve.
+fn cmd_list_presets(json_output: bool) -> Result<()> {
+    let presets = config::load_config().presets;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&presets)?);
+        return Ok(());
+    }
+    if presets.is_empty() {
+        println!("No presets defined (add a \"presets\" object to fancontrol.json)");
+        return Ok(());
+    }
+    for (name, curves) in &presets {
+        println!("{name}");
+        for curve in curves {
+            let steps: Vec<String> = curve.steps.iter().map(u8::to_string).collect();
+            println!(
+                "  fan {} sensor {}: {}",
+                curve.fan_id,
+                curve.sensor_id,
+                steps.join(",")
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Apply the curves of config preset `name`.
+fn cmd_apply_preset(controller: &dyn FanController, name: &str, dry_run: bool) -> Result<()> {
+    let presets = config::load_config().presets;
+    let Some(curves) = presets.get(name) else {
+        if presets.is_empty() {
+            anyhow::bail!("unknown preset '{name}': the config defines no presets");
+        }
+        let names: Vec<&str> = presets.keys().map(String::as_str).collect();
+        anyhow::bail!(
+            "unknown preset '{name}': configured presets are {}",
+            names.join(", ")
+        );
+    };
+    if curves.is_empty() {
+        println!("Preset '{name}' has no curves");
+        return Ok(());
+    }
+    let done = if dry_run {
+        "would be applied"
+    } else {
+        "applied"
+    };
+    apply_curve_set(controller, curves, "apply-preset", done, true)
+}
+
+/// Validate and write each curve, printing OK/FAIL per curve and a
+/// summary. One bad curve does not stop the others; the result is an error
+/// if any failed. With `skip_unknown`, curves for fan/sensor pairs this
+/// hardware lacks are reported as SKIP instead of failing.
+fn apply_curve_set(
+    controller: &dyn FanController,
+    curves: &[CustomFanCurve],
+    command: &str,
+    done: &str,
+    skip_unknown: bool,
+) -> Result<()> {
+    let known_pairs = known_curve_pairs(controller)?;
+    let mut succeeded = 0;
+    let mut skipped = 0;
+    for curve in curves {
+        let target = format!("fan {} sensor {}", curve.fan_id, curve.sensor_id);
+        if skip_unknown
+            && !known_pairs.is_empty()
+            && !known_pairs.contains(&(curve.fan_id, curve.sensor_id))
+        {
+            println!("SKIP {target}: not on this hardware");
+            skipped += 1;
+            continue;
+        }
+        match apply_checked_curve(controller, curve, &known_pairs) {
+            Ok(()) => {
+                println!("OK   {target}");
+                succeeded += 1;
+            }
+            Err(error) => {
+                warn!("{command} ({target}) failed: {error}");
+                println!("FAIL {target}: {error}");
+            }
+        }
+    }
+
+    let attempted = curves.len() - skipped;
+    println!();
+    if skipped > 0 {
+        println!("{succeeded}/{attempted} curves {done}, {skipped} skipped");
+    } else {
+        println!("{succeeded}/{attempted} curves {done}");
+    }
+
+    if succeeded < attempted {
+        anyhow::bail!("{} curve(s) failed", attempted - succeeded);
+    }
+    Ok(())
 }
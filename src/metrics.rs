@@ -0,0 +1,282 @@
+//! `fancontrol metrics`: fan readings in Prometheus text format over HTTP.
+//!
+//! A single-threaded server built on `std::net`, so the feature adds no
+//! dependencies. Every scrape of `/metrics` runs `discover` and
+//! `sensor_temperatures` afresh; nothing is cached between scrapes.
+
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{debug, info, warn};
+
+use crate::fan::{Fan, SensorTemperature};
+use crate::platform::FanController;
+use crate::shutdown;
+
+/// Address `metrics` listens on without `--bind`.
+pub const DEFAULT_BIND: &str = "127.0.0.1:9102";
+
+/// How long a client may take to send its request line.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Pause between accept attempts while no client is waiting.
+const ACCEPT_POLL: Duration = Duration::from_millis(100);
+
+/// Escape a label value: backslash, double quote, and newline.
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Append one metric family: its HELP and TYPE lines, then a sample per
+/// `(labels, value)`. Families without samples are left out.
+fn family(out: &mut String, name: &str, help: &str, samples: &[(String, u32)]) {
+    if samples.is_empty() {
+        return;
+    }
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} gauge");
+    for (labels, value) in samples {
+        let _ = writeln!(out, "{name}{{{labels}}} {value}");
+    }
+}
+
+/// Render fans and sensors in Prometheus text exposition format.
+pub fn render(fans: &[Fan], temps: &[SensorTemperature]) -> String {
+    let fan_labels = |fan: &Fan| {
+        format!(
+            "id=\"{}\",label=\"{}\"",
+            escape_label(&fan.id),
+            escape_label(&fan.label)
+        )
+    };
+    let rpm: Vec<(String, u32)> = fans
+        .iter()
+        .map(|fan| (fan_labels(fan), fan.speed_rpm))
+        .collect();
+    let pwm: Vec<(String, u32)> = fans
+        .iter()
+        .filter_map(|fan| Some((fan_labels(fan), u32::from(fan.pwm?))))
+        .collect();
+    let fan_temps: Vec<(String, u32)> = fans
+        .iter()
+        .filter_map(|fan| Some((fan_labels(fan), fan.temperature?)))
+        .collect();
+    let sensors: Vec<(String, u32)> = temps
+        .iter()
+        .map(|sensor| {
+            (
+                format!(
+                    "id=\"{}\",label=\"{}\"",
+                    sensor.sensor_id,
+                    escape_label(&sensor.label)
+                ),
+                sensor.temperature,
+            )
+        })
+        .collect();
+
+    let mut out = String::new();
+    family(&mut out, "fancontrol_fan_rpm", "Fan speed in RPM.", &rpm);
+    family(
+        &mut out,
+        "fancontrol_fan_pwm",
+        "Fan PWM duty cycle (0-255).",
+        &pwm,
+    );
+    family(
+        &mut out,
+        "fancontrol_fan_temperature_celsius",
+        "Temperature of the sensor attached to the fan.",
+        &fan_temps,
+    );
+    family(
+        &mut out,
+        "fancontrol_sensor_temperature_celsius",
+        "Temperature sensor reading.",
+        &sensors,
+    );
+    out
+}
+
+/// Status line and body answering a request line such as
+/// `GET /metrics HTTP/1.1`.
+fn respond(controller: &dyn FanController, request_line: &str) -> (&'static str, String) {
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    if method != "GET" {
+        return ("405 Method Not Allowed", "only GET is supported\n".into());
+    }
+    match path.split('?').next().unwrap_or("") {
+        "/metrics" => {
+            let fans = match controller.discover() {
+                Ok(fans) => fans,
+                Err(error) => {
+                    warn!("metrics scrape failed: {error}");
+                    return ("500 Internal Server Error", format!("{error}\n"));
+                }
+            };
+            let temps = controller.sensor_temperatures().unwrap_or_else(|error| {
+                debug!("sensor temperatures unavailable: {error}");
+                Vec::new()
+            });
+            ("200 OK", render(&fans, &temps))
+        }
+        "/" => ("200 OK", "fancontrol metrics: see /metrics\n".into()),
+        _ => ("404 Not Found", "not found: try /metrics\n".into()),
+    }
+}
+
+/// Answer one connection.
+fn handle(controller: &dyn FanController, stream: TcpStream) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Drain the headers; the request line is all that matters.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, body) = respond(controller, request_line.trim());
+    debug!("{} -> {status}", request_line.trim());
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\n\
+         Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Connection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// Listen on `bind` and answer scrapes until Ctrl+C.
+pub fn serve(controller: &dyn FanController, bind: SocketAddr) -> Result<()> {
+    let listener = TcpListener::bind(bind).map_err(|error| match error.kind() {
+        ErrorKind::AddrInUse => anyhow::anyhow!(
+            "cannot listen on {bind}: the port is already in use; pick another with --bind"
+        ),
+        _ => anyhow::anyhow!("cannot listen on {bind}: {error}"),
+    })?;
+    // Non-blocking accept lets the loop notice Ctrl+C between clients.
+    listener.set_nonblocking(true)?;
+    let stop = shutdown::stop_flag();
+    println!("Serving metrics on http://{bind}/metrics — Ctrl+C to stop");
+    info!("metrics listening on {bind}");
+
+    loop {
+        match listener.accept() {
+            Ok((stream, peer)) => {
+                if let Err(error) = handle(controller, stream) {
+                    warn!("metrics request from {peer} failed: {error}");
+                }
+            }
+            Err(error) if error.kind() == ErrorKind::WouldBlock => {
+                if shutdown::sleep_or_stop(&stop, ACCEPT_POLL) {
+                    break;
+                }
+            }
+            Err(error) => warn!("accepting a metrics connection failed: {error}"),
+        }
+    }
+    println!("\nMetrics server stopped");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fan(id: &str, label: &str, pwm: Option<u8>, temperature: Option<u32>) -> Fan {
+        Fan {
+            id: id.to_string(),
+            label: label.to_string(),
+            speed_rpm: 2100,
+            temperature,
+            pwm,
+            pwm_mode: None,
+            controllable: true,
+            min_rpm: None,
+            max_rpm: None,
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        }
+    }
+
+    #[test]
+    fn render_writes_gauges_per_fan() {
+        let fans = [fan("fan0", "CPU Fan", Some(128), Some(55))];
+        let temps = [SensorTemperature {
+            sensor_id: 3,
+            label: "CPU".to_string(),
+            temperature: 61,
+        }];
+        let text = render(&fans, &temps);
+        assert!(text.contains("# TYPE fancontrol_fan_rpm gauge\n"));
+        assert!(text.contains("fancontrol_fan_rpm{id=\"fan0\",label=\"CPU Fan\"} 2100\n"));
+        assert!(text.contains("fancontrol_fan_pwm{id=\"fan0\",label=\"CPU Fan\"} 128\n"));
+        assert!(
+            text.contains("fancontrol_fan_temperature_celsius{id=\"fan0\",label=\"CPU Fan\"} 55\n")
+        );
+        assert!(text.contains("fancontrol_sensor_temperature_celsius{id=\"3\",label=\"CPU\"} 61\n"));
+    }
+
+    #[test]
+    fn render_skips_unknown_readings() {
+        let text = render(&[fan("fan1", "GPU", None, None)], &[]);
+        assert!(text.contains("fancontrol_fan_rpm"));
+        assert!(!text.contains("fancontrol_fan_pwm"));
+        assert!(!text.contains("temperature"));
+    }
+
+    struct Fixed;
+
+    impl FanController for Fixed {
+        fn discover(&self) -> Result<Vec<Fan>, crate::errors::FanControlError> {
+            Ok(vec![fan("fan0", "CPU Fan", Some(128), None)])
+        }
+
+        fn get_speed(&self, _fan_id: &str) -> Result<u32, crate::errors::FanControlError> {
+            Ok(2100)
+        }
+
+        fn set_pwm(&self, _fan_id: &str, _pwm: u8) -> Result<(), crate::errors::FanControlError> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn respond_routes_requests() {
+        let (status, body) = respond(&Fixed, "GET /metrics HTTP/1.1");
+        assert_eq!(status, "200 OK");
+        assert!(body.contains("fancontrol_fan_rpm{id=\"fan0\""));
+        assert_eq!(respond(&Fixed, "GET /nope HTTP/1.1").0, "404 Not Found");
+        assert_eq!(
+            respond(&Fixed, "POST /metrics HTTP/1.1").0,
+            "405 Method Not Allowed"
+        );
+    }
+
+    #[test]
+    fn serve_reports_port_in_use() {
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let error = serve(&Fixed, taken.local_addr().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("already in use"), "{error}");
+    }
+
+    #[test]
+    fn escape_label_quotes_specials() {
+        assert_eq!(escape_label("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
@@ -0,0 +1,324 @@
+//! Dell fan backend using Dell Command | Monitor WMI.
+//!
+//! Fan speeds and temperatures come from `DCIM_NumericSensor` in the
+//! `root\dcim\sysman` namespace. Dell's BIOS interface has no per-fan
+//! speed control; the closest it offers is the "Thermal Management" BIOS
+//! setting, written through `DCIM_BIOSService.SetBIOSAttributes`. Fans are
+//! therefore reported as read-only, `thermal-mode` picks a BIOS profile,
+//! and `auto` restores the default "Optimized" profile. Like the Lenovo
+//! backend, every call runs as a PowerShell script.
+
+use std::time::Duration;
+
+use log::{debug, info, warn};
+
+use super::powershell::{PowerShell, PRIVILEGES_SCRIPT};
+use super::{unsupported_write, FanController, HardwareWrite};
+use crate::errors::FanControlError;
+use crate::fan::{Fan, SensorTemperature, ThermalMode};
+
+/// `DCIM_NumericSensor.SensorType` of fan tachometers.
+const SENSOR_TYPE_TACHOMETER: u32 = 5;
+/// `DCIM_NumericSensor.SensorType` of temperature sensors.
+const SENSOR_TYPE_TEMPERATURE: u32 = 2;
+
+/// BIOS thermal profile the firmware ships with.
+const THERMAL_PROFILE_DEFAULT: &str = "Optimized";
+
+// ---------------------------------------------------------------------------
+// Pure parsing functions (no I/O — testable on any platform)
+// ---------------------------------------------------------------------------
+
+/// One `SENSOR|type|device_id|name|reading|unit_modifier` line.
+#[derive(Debug, Clone, PartialEq)]
+struct DellSensor {
+    sensor_type: u32,
+    device_id: String,
+    name: String,
+    /// Reading scaled by `10^unit_modifier`, rounded.
+    value: u32,
+}
+
+/// Parse a `SENSOR|...` line. Returns `None` if malformed.
+fn parse_sensor_line(line: &str) -> Option<DellSensor> {
+    let parts: Vec<&str> = line.trim().split('|').collect();
+    if parts.len() < 6 || parts[0] != "SENSOR" {
+        return None;
+    }
+    let reading: f64 = parts[4].trim().parse().ok()?;
+    let modifier: i32 = parts[5].trim().parse().unwrap_or(0);
+    Some(DellSensor {
+        sensor_type: parts[1].trim().parse().ok()?,
+        device_id: parts[2].trim().to_string(),
+        name: parts[3].trim().to_string(),
+        value: (reading * 10f64.powi(modifier)).max(0.0).round() as u32,
+    })
+}
+
+/// Every well-formed sensor line in `output`.
+fn parse_sensors(output: &str) -> Vec<DellSensor> {
+    output.lines().filter_map(parse_sensor_line).collect()
+}
+
+/// Tachometer sensors as read-only fans.
+fn fans_from_sensors(sensors: &[DellSensor]) -> Vec<Fan> {
+    sensors
+        .iter()
+        .filter(|sensor| sensor.sensor_type == SENSOR_TYPE_TACHOMETER)
+        .map(|sensor| Fan {
+            id: sensor.device_id.clone(),
+            label: sensor.name.clone(),
+            speed_rpm: sensor.value,
+            temperature: None,
+            pwm: None,
+            pwm_mode: None,
+            controllable: false,
+            min_rpm: None,
+            max_rpm: None,
+            target_rpm: None,
+            curves: Vec::new(),
+            full_speed_active: false,
+            stalled: false,
+        })
+        .collect()
+}
+
+/// Temperature sensors as readings, numbered in query order.
+fn temperatures_from_sensors(sensors: &[DellSensor]) -> Vec<SensorTemperature> {
+    sensors
+        .iter()
+        .filter(|sensor| sensor.sensor_type == SENSOR_TYPE_TEMPERATURE)
+        .enumerate()
+        .map(|(index, sensor)| SensorTemperature {
+            sensor_id: index as u32,
+            label: sensor.name.clone(),
+            temperature: sensor.value,
+        })
+        .collect()
+}
+
+/// The "Thermal Management" BIOS value for a thermal mode.
+fn thermal_profile(mode: ThermalMode) -> &'static str {
+    match mode {
+        ThermalMode::Quiet => "Quiet",
+        ThermalMode::Balanced => THERMAL_PROFILE_DEFAULT,
+        ThermalMode::Performance => "Ultra Performance",
+    }
+}
+
+// ---------------------------------------------------------------------------
+// PowerShell script builders (pure — no I/O)
+// ---------------------------------------------------------------------------
+
+/// Fan tachometers and temperature sensors, one
+/// `SENSOR|type|device_id|name|reading|unit_modifier` line each.
+const SENSORS_SCRIPT: &str =
+    "$sensors = Get-WmiObject -Namespace root/dcim/sysman -Class DCIM_NumericSensor; \
+     foreach ($s in $sensors) { \
+       if ($s.SensorType -eq 5 -or $s.SensorType -eq 2) { \
+         Write-Output \"SENSOR|$($s.SensorType)|$($s.DeviceID)|$($s.ElementName)|$($s.CurrentReading)|$($s.UnitModifier)\" \
+       } \
+     }";
+
+/// Set the "Thermal Management" BIOS attribute to `profile`.
+fn set_thermal_profile_script(profile: &str) -> String {
+    format!(
+        "$bios = Get-WmiObject -Namespace root/dcim/sysman -Class DCIM_BIOSService; \
+         $result = $bios.SetBIOSAttributes($null, $null, 'Thermal Management', '{profile}'); \
+         if ($result.SetResult[0] -ne 0) {{ throw \"SetBIOSAttributes returned $($result.SetResult[0])\" }}"
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Controller
+// ---------------------------------------------------------------------------
+
+/// Dell fan controller backed by Dell Command | Monitor WMI.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub struct DellFanController {
+    powershell: PowerShell,
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+impl DellFanController {
+    pub fn new() -> Self {
+        Self {
+            powershell: PowerShell::new(),
+        }
+    }
+
+    /// Kill PowerShell calls that take longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.powershell = self.powershell.with_timeout(timeout);
+        self
+    }
+
+    /// Try read scripts up to `attempts` times before giving up.
+    pub fn with_read_attempts(mut self, attempts: u32) -> Self {
+        self.powershell = self.powershell.with_read_attempts(attempts);
+        self
+    }
+
+    fn read_sensors(&self) -> Result<Vec<DellSensor>, FanControlError> {
+        let sensors = parse_sensors(&self.powershell.run_read(SENSORS_SCRIPT)?);
+        debug!("dell sensors: {sensors:?}");
+        Ok(sensors)
+    }
+
+    fn set_thermal_profile(&self, profile: &str) -> Result<(), FanControlError> {
+        info!("Thermal Management -> {profile}");
+        self.powershell
+            .run(&set_thermal_profile_script(profile))
+            .map(|_| ())
+    }
+}
+
+impl FanController for DellFanController {
+    fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+        let fans = fans_from_sensors(&self.read_sensors()?);
+        if fans.is_empty() {
+            warn!("DCIM_NumericSensor reported no fan tachometers");
+        }
+        Ok(fans)
+    }
+
+    fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError> {
+        fans_from_sensors(&self.read_sensors()?)
+            .into_iter()
+            .find(|fan| fan.id == fan_id)
+            .map(|fan| fan.speed_rpm)
+            .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_owned()))
+    }
+
+    fn check_privileges(&self) -> Result<(), FanControlError> {
+        // BIOS attribute writes are rejected without an elevated token.
+        let output = self.powershell.run_read(PRIVILEGES_SCRIPT)?;
+        if output.eq_ignore_ascii_case("true") {
+            Ok(())
+        } else {
+            Err(FanControlError::PermissionDenied(
+                "changing BIOS thermal settings requires administrator rights: \
+                 run as administrator"
+                    .to_string(),
+            ))
+        }
+    }
+
+    fn read_only_hint(&self) -> String {
+        "Dell BIOS WMI has no per-fan speed control; use `thermal-mode` to \
+         pick a BIOS thermal profile instead"
+            .to_string()
+    }
+
+    fn set_pwm(&self, fan_id: &str, _pwm: u8) -> Result<(), FanControlError> {
+        if !self.discover()?.iter().any(|fan| fan.id == fan_id) {
+            return Err(FanControlError::FanNotFound(fan_id.to_owned()));
+        }
+        Err(FanControlError::NotControllable(format!(
+            "{fan_id}: {}",
+            self.read_only_hint()
+        )))
+    }
+
+    fn set_auto(&self, fan_id: &str) -> Result<(), FanControlError> {
+        if !self.discover()?.iter().any(|fan| fan.id == fan_id) {
+            return Err(FanControlError::FanNotFound(fan_id.to_owned()));
+        }
+        // The BIOS profile applies to every fan at once.
+        self.set_thermal_profile(THERMAL_PROFILE_DEFAULT)
+    }
+
+    fn sensor_temperatures(&self) -> Result<Vec<SensorTemperature>, FanControlError> {
+        Ok(temperatures_from_sensors(&self.read_sensors()?))
+    }
+
+    fn set_thermal_mode(&self, mode: ThermalMode) -> Result<(), FanControlError> {
+        self.set_thermal_profile(thermal_profile(mode))
+    }
+
+    fn describe_write(&self, write: &HardwareWrite) -> Result<Vec<String>, FanControlError> {
+        let profile = match write {
+            HardwareWrite::Auto { .. } => THERMAL_PROFILE_DEFAULT,
+            HardwareWrite::ThermalMode(mode) => thermal_profile(*mode),
+            other => return Err(unsupported_write(other)),
+        };
+        Ok(vec![
+            format!("WMI: DCIM_BIOSService.SetBIOSAttributes('Thermal Management', '{profile}')"),
+            format!("PowerShell: {}", set_thermal_profile_script(profile)),
+        ])
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests — pure parsing functions, runnable on any platform (no WMI needed)
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_OUTPUT: &str = "SENSOR|5|Root/MainSystemChassis/Fan:1|CPU Fan|2450|0\r\n\
+         SENSOR|2|Root/MainSystemChassis/Temp:1|CPU Temp|715|-1\r\n\
+         SENSOR|5|Root/MainSystemChassis/Fan:2|Video Fan|0|0\r\n\
+         garbage\r\n";
+
+    // -- parse_sensor_line ---------------------------------------------------
+
+    #[test]
+    fn parse_sensor_line_applies_unit_modifier() {
+        let sensor = parse_sensor_line("SENSOR|2|Temp:1|CPU Temp|715|-1").unwrap();
+        assert_eq!(sensor.sensor_type, SENSOR_TYPE_TEMPERATURE);
+        assert_eq!(sensor.device_id, "Temp:1");
+        assert_eq!(sensor.name, "CPU Temp");
+        assert_eq!(sensor.value, 72);
+    }
+
+    #[test]
+    fn parse_sensor_line_defaults_missing_modifier() {
+        let sensor = parse_sensor_line("SENSOR|5|Fan:1|CPU Fan|2450|").unwrap();
+        assert_eq!(sensor.value, 2450);
+    }
+
+    #[test]
+    fn parse_sensor_line_rejects_malformed() {
+        assert_eq!(parse_sensor_line("SENSOR|5|Fan:1|CPU Fan"), None);
+        assert_eq!(parse_sensor_line("SENSOR|5|Fan:1|CPU Fan||0"), None);
+        assert_eq!(parse_sensor_line("FAN|5|Fan:1|CPU Fan|2450|0"), None);
+    }
+
+    // -- mapping -------------------------------------------------------------
+
+    #[test]
+    fn fans_and_temperatures_from_sample_output() {
+        let sensors = parse_sensors(SAMPLE_OUTPUT);
+        assert_eq!(sensors.len(), 3);
+
+        let fans = fans_from_sensors(&sensors);
+        assert_eq!(fans.len(), 2);
+        assert_eq!(fans[0].id, "Root/MainSystemChassis/Fan:1");
+        assert_eq!(fans[0].label, "CPU Fan");
+        assert_eq!(fans[0].speed_rpm, 2450);
+        assert!(!fans[0].controllable);
+
+        let temps = temperatures_from_sensors(&sensors);
+        assert_eq!(temps.len(), 1);
+        assert_eq!(temps[0].label, "CPU Temp");
+        assert_eq!(temps[0].temperature, 72);
+    }
+
+    // -- script builders -----------------------------------------------------
+
+    #[test]
+    fn set_thermal_profile_script_names_attribute_and_value() {
+        let script = set_thermal_profile_script(thermal_profile(ThermalMode::Performance));
+        assert!(script.contains("'Thermal Management', 'Ultra Performance'"));
+        assert!(!script.contains('\n'));
+        assert!(!SENSORS_SCRIPT.contains('\n'));
+    }
+
+    #[test]
+    fn balanced_maps_to_bios_default() {
+        assert_eq!(thermal_profile(ThermalMode::Balanced), "Optimized");
+        assert_eq!(thermal_profile(ThermalMode::Quiet), "Quiet");
+    }
+}
@@ -0,0 +1,420 @@
+//! `--dry-run`: a controller wrapper that performs reads but only prints the
+//! writes it is asked for.
+//!
+//! Every write method is overridden, so nothing reaches the wrapped
+//! backend's write paths. The backend is only asked to describe each write
+//! (the file it would change, the WMI method and script it would run) via
+//! [`FanController::describe_write`], which may read but never writes.
+
+use std::cell::Cell;
+use std::fmt;
+
+use super::FanController;
+use crate::errors::FanControlError;
+use crate::fan::{
+    smart_fan_mode_name, validate_custom_curve_with_policy, CurveSample, CustomFanCurve, Fan,
+    FanCurve, PwmCurve, SensorTemperature, ThermalMode,
+};
+
+/// A hardware write that `--dry-run` reports instead of performing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HardwareWrite {
+    Pwm {
+        fan_id: String,
+        pwm: u8,
+    },
+    Rpm {
+        fan_id: String,
+        rpm: u32,
+    },
+    Auto {
+        fan_id: String,
+    },
+    /// A curve checked against the safety floors for `min_high_temp_ratio`.
+    CustomCurve {
+        curve: CustomFanCurve,
+        min_high_temp_ratio: f32,
+    },
+    /// One `run-daemon` tick over this many software curves.
+    PwmCurves(usize),
+    SmartFanMode(u32),
+    FullSpeed(bool),
+    ThermalMode(ThermalMode),
+}
+
+impl fmt::Display for HardwareWrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HardwareWrite::Pwm { fan_id, pwm } => write!(f, "set {fan_id} to PWM {pwm}"),
+            HardwareWrite::Rpm { fan_id, rpm } => write!(f, "set {fan_id} to {rpm} RPM"),
+            HardwareWrite::Auto { fan_id } => write!(f, "return {fan_id} to automatic control"),
+            HardwareWrite::CustomCurve { curve, .. } => write!(
+                f,
+                "write the curve for fan {} sensor {}: steps {:?}",
+                curve.fan_id, curve.sensor_id, curve.steps
+            ),
+            HardwareWrite::PwmCurves(count) => write!(
+                f,
+                "evaluate {count} software curve(s) and write each fan's PWM"
+            ),
+            HardwareWrite::SmartFanMode(mode) => write!(
+                f,
+                "set SmartFanMode to {} ({mode})",
+                smart_fan_mode_name(*mode).unwrap_or("Unknown")
+            ),
+            HardwareWrite::FullSpeed(enabled) => write!(
+                f,
+                "turn full-speed mode {}",
+                if *enabled { "on" } else { "off" }
+            ),
+            HardwareWrite::ThermalMode(mode) => write!(f, "switch the thermal mode to {mode}"),
+        }
+    }
+}
+
+/// Wraps a controller for `--dry-run`: reads pass through, writes are
+/// validated and printed.
+pub struct DryRunController {
+    inner: Box<dyn FanController>,
+    /// Curves handed to `set_pwm_curves`, for reporting a daemon tick.
+    curve_count: Cell<usize>,
+}
+
+impl DryRunController {
+    pub fn new(inner: Box<dyn FanController>) -> Self {
+        Self {
+            inner,
+            curve_count: Cell::new(0),
+        }
+    }
+
+    /// Print `write` and the backend's detail lines for it.
+    fn report(&self, write: HardwareWrite) -> Result<(), FanControlError> {
+        let details = self.inner.describe_write(&write)?;
+        println!("[dry-run] would {write}");
+        for line in details {
+            println!("[dry-run]   {line}");
+        }
+        Ok(())
+    }
+
+    /// The fan `fan_id`, which must exist.
+    fn fan(&self, fan_id: &str) -> Result<Fan, FanControlError> {
+        self.inner
+            .discover()?
+            .into_iter()
+            .find(|fan| fan.id == fan_id)
+            .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_owned()))
+    }
+
+    /// Fail like a real write would when `fan_id` is missing or read-only.
+    fn check_controllable(&self, fan_id: &str) -> Result<(), FanControlError> {
+        if self.fan(fan_id)?.controllable {
+            Ok(())
+        } else {
+            Err(FanControlError::NotControllable(format!(
+                "{fan_id}: {}",
+                self.inner.read_only_hint()
+            )))
+        }
+    }
+}
+
+impl FanController for DryRunController {
+    fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+        self.inner.discover()
+    }
+
+    fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError> {
+        self.inner.get_speed(fan_id)
+    }
+
+    fn check_privileges(&self) -> Result<(), FanControlError> {
+        self.inner.check_privileges()
+    }
+
+    fn read_only_hint(&self) -> String {
+        self.inner.read_only_hint()
+    }
+
+    fn set_pwm(&self, fan_id: &str, pwm: u8) -> Result<(), FanControlError> {
+        self.check_controllable(fan_id)?;
+        self.report(HardwareWrite::Pwm {
+            fan_id: fan_id.to_owned(),
+            pwm,
+        })
+    }
+
+    fn stop_note(&self) -> String {
+        self.inner.stop_note()
+    }
+
+    /// Only existence is checked: some backends (Dell) restore automatic
+    /// control for fans they cannot drive manually.
+    fn set_auto(&self, fan_id: &str) -> Result<(), FanControlError> {
+        self.fan(fan_id)?;
+        self.report(HardwareWrite::Auto {
+            fan_id: fan_id.to_owned(),
+        })
+    }
+
+    fn set_rpm(&self, fan_id: &str, rpm: u32) -> Result<(), FanControlError> {
+        self.check_controllable(fan_id)?;
+        self.report(HardwareWrite::Rpm {
+            fan_id: fan_id.to_owned(),
+            rpm,
+        })
+    }
+
+    fn get_fan_curves(&self) -> Result<Vec<FanCurve>, FanControlError> {
+        self.inner.get_fan_curves()
+    }
+
+    fn raw_fan_table(&self, fan_id: u32, sensor_id: u32) -> Result<Vec<u8>, FanControlError> {
+        self.inner.raw_fan_table(fan_id, sensor_id)
+    }
+
+    fn sensor_temperatures(&self) -> Result<Vec<SensorTemperature>, FanControlError> {
+        self.inner.sensor_temperatures()
+    }
+
+    fn gpu_sensor_id(&self) -> Result<Option<u32>, FanControlError> {
+        self.inner.gpu_sensor_id()
+    }
+
+    fn set_custom_curve_with_policy(
+        &self,
+        curve: &CustomFanCurve,
+        min_high_temp_ratio: f32,
+    ) -> Result<(), FanControlError> {
+        validate_custom_curve_with_policy(curve, min_high_temp_ratio, None)?;
+        self.report(HardwareWrite::CustomCurve {
+            curve: curve.clone(),
+            min_high_temp_ratio,
+        })
+    }
+
+    /// Installing curves only stores them in memory, so the backend still
+    /// checks them; `apply_pwm_curves` is what writes.
+    fn set_pwm_curves(&self, curves: &[PwmCurve]) -> Result<(), FanControlError> {
+        self.inner.set_pwm_curves(curves)?;
+        self.curve_count.set(curves.len());
+        Ok(())
+    }
+
+    fn apply_pwm_curves(&self) -> Result<Vec<CurveSample>, FanControlError> {
+        self.report(HardwareWrite::PwmCurves(self.curve_count.get()))?;
+        Ok(Vec::new())
+    }
+
+    fn get_smart_fan_mode(&self) -> Result<Option<u32>, FanControlError> {
+        self.inner.get_smart_fan_mode()
+    }
+
+    fn set_smart_fan_mode(&self, mode: u32) -> Result<(), FanControlError> {
+        self.report(HardwareWrite::SmartFanMode(mode))
+    }
+
+    fn set_full_speed(&self, enabled: bool) -> Result<(), FanControlError> {
+        self.report(HardwareWrite::FullSpeed(enabled))
+    }
+
+    fn set_thermal_mode(&self, mode: ThermalMode) -> Result<(), FanControlError> {
+        self.report(HardwareWrite::ThermalMode(mode))
+    }
+
+    fn describe_write(&self, write: &HardwareWrite) -> Result<Vec<String>, FanControlError> {
+        self.inner.describe_write(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    /// Records every write call it receives.
+    struct Recorder {
+        writes: Rc<RefCell<Vec<String>>>,
+        controllable: bool,
+    }
+
+    impl Recorder {
+        fn wrapped(controllable: bool) -> (DryRunController, Rc<RefCell<Vec<String>>>) {
+            let writes = Rc::new(RefCell::new(Vec::new()));
+            let recorder = Recorder {
+                writes: Rc::clone(&writes),
+                controllable,
+            };
+            (DryRunController::new(Box::new(recorder)), writes)
+        }
+
+        fn record(&self, call: &str) -> Result<(), FanControlError> {
+            self.writes.borrow_mut().push(call.to_string());
+            Ok(())
+        }
+    }
+
+    impl FanController for Recorder {
+        fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+            Ok(vec![Fan {
+                id: "fan1".to_string(),
+                label: "CPU Fan".to_string(),
+                speed_rpm: 1200,
+                temperature: None,
+                pwm: Some(100),
+                pwm_mode: None,
+                controllable: self.controllable,
+                min_rpm: None,
+                max_rpm: None,
+                target_rpm: None,
+                curves: Vec::new(),
+                full_speed_active: false,
+                stalled: false,
+            }])
+        }
+
+        fn get_speed(&self, _fan_id: &str) -> Result<u32, FanControlError> {
+            Ok(1200)
+        }
+
+        fn set_pwm(&self, _fan_id: &str, _pwm: u8) -> Result<(), FanControlError> {
+            self.record("set_pwm")
+        }
+
+        fn set_auto(&self, _fan_id: &str) -> Result<(), FanControlError> {
+            self.record("set_auto")
+        }
+
+        fn set_rpm(&self, _fan_id: &str, _rpm: u32) -> Result<(), FanControlError> {
+            self.record("set_rpm")
+        }
+
+        fn set_custom_curve(&self, _curve: &CustomFanCurve) -> Result<(), FanControlError> {
+            self.record("set_custom_curve")
+        }
+
+        fn set_pwm_curves(&self, _curves: &[PwmCurve]) -> Result<(), FanControlError> {
+            Ok(())
+        }
+
+        fn apply_pwm_curves(&self) -> Result<Vec<CurveSample>, FanControlError> {
+            self.record("apply_pwm_curves")?;
+            Ok(Vec::new())
+        }
+
+        fn set_smart_fan_mode(&self, _mode: u32) -> Result<(), FanControlError> {
+            self.record("set_smart_fan_mode")
+        }
+
+        fn set_full_speed(&self, _enabled: bool) -> Result<(), FanControlError> {
+            self.record("set_full_speed")
+        }
+
+        fn set_thermal_mode(&self, _mode: ThermalMode) -> Result<(), FanControlError> {
+            self.record("set_thermal_mode")
+        }
+
+        fn describe_write(&self, write: &HardwareWrite) -> Result<Vec<String>, FanControlError> {
+            Ok(vec![format!("recorder: {write}")])
+        }
+    }
+
+    /// Backend relying on every default write method.
+    struct ReadOnly;
+
+    impl FanController for ReadOnly {
+        fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+            Ok(Vec::new())
+        }
+
+        fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError> {
+            Err(FanControlError::FanNotFound(fan_id.to_string()))
+        }
+
+        fn set_pwm(&self, fan_id: &str, _pwm: u8) -> Result<(), FanControlError> {
+            Err(FanControlError::NotControllable(fan_id.to_string()))
+        }
+    }
+
+    fn curve(steps: [u8; 10]) -> CustomFanCurve {
+        CustomFanCurve {
+            fan_id: 0,
+            sensor_id: 3,
+            steps,
+        }
+    }
+
+    #[test]
+    fn writes_never_reach_the_backend() {
+        let (controller, writes) = Recorder::wrapped(true);
+        controller.set_pwm("fan1", 128).unwrap();
+        controller.set_auto("fan1").unwrap();
+        controller.set_rpm("fan1", 2000).unwrap();
+        controller
+            .set_custom_curve(&curve([1, 2, 3, 4, 5, 6, 7, 8, 9, 10]))
+            .unwrap();
+        controller.set_pwm_curves(&[]).unwrap();
+        controller.apply_pwm_curves().unwrap();
+        controller.set_smart_fan_mode(255).unwrap();
+        controller.set_full_speed(true).unwrap();
+        controller.set_thermal_mode(ThermalMode::Quiet).unwrap();
+        assert!(writes.borrow().is_empty(), "{:?}", writes.borrow());
+    }
+
+    #[test]
+    fn reads_pass_through() {
+        let (controller, _) = Recorder::wrapped(true);
+        assert_eq!(controller.discover().unwrap().len(), 1);
+        assert_eq!(controller.get_speed("fan1").unwrap(), 1200);
+    }
+
+    #[test]
+    fn validation_still_fails() {
+        let (controller, _) = Recorder::wrapped(false);
+        assert!(matches!(
+            controller.set_pwm("fan1", 128),
+            Err(FanControlError::NotControllable(_))
+        ));
+        assert!(matches!(
+            controller.set_pwm("fan9", 128),
+            Err(FanControlError::FanNotFound(_))
+        ));
+        // Restoring automatic control only needs the fan to exist.
+        assert!(controller.set_auto("fan1").is_ok());
+        assert!(controller
+            .set_custom_curve(&curve([10, 9, 8, 7, 6, 5, 4, 3, 2, 1]))
+            .is_err());
+    }
+
+    #[test]
+    fn unsupported_writes_fail_like_the_backend() {
+        let controller = DryRunController::new(Box::new(ReadOnly));
+        let error = controller.set_smart_fan_mode(255).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            ReadOnly.set_smart_fan_mode(255).unwrap_err().to_string()
+        );
+        assert!(controller.set_full_speed(true).is_err());
+        assert!(controller.set_thermal_mode(ThermalMode::Quiet).is_err());
+    }
+
+    #[test]
+    fn describes_writes_in_plain_words() {
+        let pwm = HardwareWrite::Pwm {
+            fan_id: "hwmon0/fan1".to_string(),
+            pwm: 128,
+        };
+        assert_eq!(pwm.to_string(), "set hwmon0/fan1 to PWM 128");
+        assert_eq!(
+            HardwareWrite::SmartFanMode(255).to_string(),
+            "set SmartFanMode to Custom (255)"
+        );
+        assert_eq!(
+            HardwareWrite::FullSpeed(false).to_string(),
+            "turn full-speed mode off"
+        );
+    }
+}
@@ -6,22 +6,44 @@
 //! Lenovo Legion fan controller backend using vendor-specific WMI.
 //!
 //! Uses `LENOVO_FAN_METHOD` and `LENOVO_FAN_TABLE_DATA` in the `root\WMI`
-//! namespace. WMI method calls are performed via PowerShell subprocess since
-//! the `wmi` crate only supports queries, not method invocation.
-
-use std::collections::HashMap;
-use std::process::Command;
+//! namespace. WMI method calls are performed via PowerShell subprocess.
+//!
+//! With the `wmi-direct` feature (on by default), the per-tick speed and
+//! temperature reads, `Fan_SetCurrentFanSpeed`, `Fan_Set_FullSpeed` and the
+//! SmartFanMode calls go straight through COM `ExecMethod` instead (see
+//! [`WmiMethods`]). The first direct call that fails logs a warning and
+//! switches the controller back to PowerShell for good. Discovery, table
+//! reads and `Fan_Set_Table` always use PowerShell: the `wmi` crate cannot
+//! pass a byte array in-parameter.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
 
 use log::{debug, info, warn};
 
-use super::FanController;
+use super::powershell::{CommandRunner, PowerShell, PRIVILEGES_SCRIPT};
+use super::{unsupported_write, FanController, HardwareWrite};
+use crate::doctor::Check;
 use crate::errors::FanControlError;
-use crate::fan::{CustomFanCurve, Fan, FanCurve, FanCurvePoint};
+use crate::fan::{
+    calibrated_pwm_to_rpm, calibrated_rpm_to_pwm, find_gpu_sensor, is_stalled, label_sensors,
+    pwm_to_rpm, rpm_to_pwm, sensor_label, validate_calibration, validate_custom_curve_with_policy,
+    CalibrationPoint, CustomFanCurve, Fan, FanCommand, FanCurve, FanCurvePoint, SensorTemperature,
+    ThermalMode, SMART_FAN_MODE_CUSTOM,
+};
 
 /// Fallback RPM range used when table data is unavailable.
 const DEFAULT_MIN_RPM: u32 = 1600;
 const DEFAULT_MAX_RPM: u32 = 4800;
 
+/// Fan IDs queried when discover() finds no tables (CPU and GPU fan).
+const DEFAULT_FAN_IDS: [u32; 2] = [0, 1];
+
+/// How long a batched speed read answers `get_speed` before PowerShell is
+/// spawned again. Long enough to cover reading both fans in one tick.
+const SPEED_CACHE_TTL: Duration = Duration::from_millis(500);
+
 /// Per-fan RPM range learned from table data.
 #[derive(Debug, Clone)]
 struct FanRpmRange {
@@ -41,24 +63,6 @@ fn parse_fan_id(fan_id: &str) -> Result<u32, FanControlError> {
         .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_string()))
 }
 
-/// Map PWM (0-255) to RPM using the given range.
-fn pwm_to_rpm(min_rpm: u32, max_rpm: u32, pwm: u8) -> u32 {
-    let ratio = pwm as f64 / 255.0;
-    min_rpm + (ratio * (max_rpm - min_rpm) as f64) as u32
-}
-
-/// Map RPM back to approximate PWM (0-255) using the given range.
-fn rpm_to_pwm(min_rpm: u32, max_rpm: u32, rpm: u32) -> u8 {
-    if rpm <= min_rpm {
-        return 0;
-    }
-    if rpm >= max_rpm {
-        return 255;
-    }
-    let ratio = (rpm - min_rpm) as f64 / (max_rpm - min_rpm) as f64;
-    (ratio * 255.0) as u8
-}
-
 /// Scan discover output for the FULLSPEED| line and return its value.
 fn parse_fullspeed(output: &str) -> bool {
     for line in output.lines() {
@@ -112,6 +116,7 @@ fn parse_table_line(line: &str) -> Option<(FanCurve, FanRpmRange)> {
         max_temp,
         points,
         active,
+        sensor_label: None,
     };
 
     let range = FanRpmRange {
@@ -128,6 +133,7 @@ fn parse_table_line(line: &str) -> Option<(FanCurve, FanRpmRange)> {
 fn parse_fan_line(
     line: &str,
     rpm_ranges: &HashMap<u32, FanRpmRange>,
+    calibration: &HashMap<u32, Vec<CalibrationPoint>>,
     curves_by_fan: &mut HashMap<u32, Vec<FanCurve>>,
     full_speed_active: bool,
 ) -> Option<Fan> {
@@ -155,24 +161,40 @@ fn parse_fan_line(
 
     Some(Fan {
         id: format!("fan{fan_id}"),
-        label: format!("{label} ({temp}\u{00B0}C)"),
+        label,
         speed_rpm,
-        pwm: Some(rpm_to_pwm(min_rpm, max_rpm, speed_rpm)),
+        temperature: Some(temp),
+        pwm: Some(match calibration.get(&fan_id) {
+            Some(points) => calibrated_rpm_to_pwm(points, min_rpm, max_rpm, speed_rpm),
+            None => rpm_to_pwm(min_rpm, max_rpm, speed_rpm),
+        }),
+        pwm_mode: None,
         controllable: true,
         min_rpm: range.map(|r| r.min_rpm),
         max_rpm: range.map(|r| r.max_rpm),
+        target_rpm: None,
         curves,
         full_speed_active,
+        stalled: full_speed_active && is_stalled(speed_rpm, FanCommand::FullSpeed),
     })
 }
 
+/// Parse `id|value` lines from the batched speed and temperature scripts.
+/// Malformed lines are skipped.
+fn parse_id_values(output: &str) -> HashMap<u32, u32> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (fan_id, speed) = line.trim().split_once('|')?;
+            Some((fan_id.trim().parse().ok()?, speed.trim().parse().ok()?))
+        })
+        .collect()
+}
+
 // ---------------------------------------------------------------------------
 // Custom fan curve encoding and validation (pure — no I/O)
 // ---------------------------------------------------------------------------
 
-/// Maximum allowed value for a speed step index.
-const MAX_STEP_VALUE: u8 = 10;
-
 /// Size of the Fan_Set_Table byte buffer.
 const FAN_TABLE_BUFFER_SIZE: usize = 64;
 
@@ -198,51 +220,53 @@ fn encode_fan_table_bytes(curve: &CustomFanCurve) -> [u8; FAN_TABLE_BUFFER_SIZE]
     bytes
 }
 
-/// Validate a custom curve's step values, enforcing safety constraints.
-///
-/// Rules:
-///   - All steps must be in range 0–10
-///   - Steps must be non-decreasing (no "death valley" curves)
-///   - Step 8 must be ≥ 3 (high-temp safety minimum)
-///   - Step 9 must be ≥ 5 (max-temp safety minimum)
-///
-/// Safety minimums match LenovoLegionToolkit V2: `[1,1,1,1,1,1,1,1,3,5]`.
-fn validate_custom_curve(curve: &CustomFanCurve) -> Result<(), FanControlError> {
-    for (i, &step) in curve.steps.iter().enumerate() {
-        if step > MAX_STEP_VALUE {
-            return Err(FanControlError::Platform(format!(
-                "step {i} value {step} exceeds maximum {MAX_STEP_VALUE}"
-            )));
-        }
+/// Fit `steps` to a firmware table with `table_len` points. Entries past the
+/// table's end are set to its last step so nothing beyond it asks for a
+/// slower fan; tables of 10 or more points (or of unknown size) keep all
+/// steps, since the buffer only holds 10.
+fn fit_steps_to_table(steps: &[u8; 10], table_len: usize) -> [u8; 10] {
+    if table_len == 0 || table_len >= steps.len() {
+        return *steps;
     }
-
-    // Non-decreasing constraint
-    for i in 1..10 {
-        if curve.steps[i] < curve.steps[i - 1] {
-            return Err(FanControlError::Platform(format!(
-                "steps must be non-decreasing: step[{i}]={} < step[{}]={}",
-                curve.steps[i],
-                i - 1,
-                curve.steps[i - 1]
-            )));
-        }
+    let mut fitted = *steps;
+    let last = steps[table_len - 1];
+    for step in &mut fitted[table_len..] {
+        *step = last;
     }
+    fitted
+}
 
-    // High-temperature safety minimums
-    if curve.steps[8] < 3 {
-        return Err(FanControlError::Platform(format!(
-            "step 8 (high temp) must be >= 3 for safety, got {}",
-            curve.steps[8]
-        )));
-    }
-    if curve.steps[9] < 5 {
-        return Err(FanControlError::Platform(format!(
-            "step 9 (max temp) must be >= 5 for safety, got {}",
-            curve.steps[9]
-        )));
+/// Look up the point count of the table for `fan_id`/`sensor_id`, or
+/// explain which pairs the firmware did report.
+fn table_len_for(
+    table_sizes: &HashMap<(u32, u32), usize>,
+    fan_id: u32,
+    sensor_id: u32,
+) -> Result<usize, FanControlError> {
+    if let Some(&len) = table_sizes.get(&(fan_id, sensor_id)) {
+        return Ok(len);
     }
-
-    Ok(())
+    let mut sensors: Vec<u32> = table_sizes
+        .keys()
+        .filter(|(fan, _)| *fan == fan_id)
+        .map(|&(_, sensor)| sensor)
+        .collect();
+    sensors.sort_unstable();
+    let known = if sensors.is_empty() {
+        "no tables for this fan".to_string()
+    } else {
+        format!(
+            "known sensors: {}",
+            sensors
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    };
+    Err(FanControlError::Platform(format!(
+        "LENOVO_FAN_TABLE_DATA has no table for fan {fan_id} sensor {sensor_id} ({known})"
+    )))
 }
 
 /// Format a byte array as a PowerShell byte array literal: `@(1,0,0,...)`.
@@ -251,120 +275,614 @@ fn format_ps_byte_array(bytes: &[u8]) -> String {
     format!("@({})", values.join(","))
 }
 
+// ---------------------------------------------------------------------------
+// PowerShell script builders (pure — no I/O)
+// ---------------------------------------------------------------------------
+
+/// Single discovery pass: fan speeds, sensor temps, full table data (curves +
+/// RPM ranges), and full speed status.
+///
+/// Each fan reports the sensor bound to its active curve, falling back to the
+/// highest Sensor_ID. Output format:
+///   FULLSPEED|0/1
+///   FAN|fan_id|sensor_id|speed|temp          — one per fan (bound sensor)
+///   TABLE|fan_id|sensor_id|active|min_speed|max_speed|min_temp|max_temp|speeds_csv|temps_csv
+const DISCOVER_SCRIPT: &str = "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
+     $tables = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_TABLE_DATA; \
+     $fs = ($fm.Fan_Get_FullSpeed()).Status; \
+     $fsVal = if ($fs) { '1' } else { '0' }; \
+     Write-Output \"FULLSPEED|$fsVal\"; \
+     $best = @{}; \
+     $bound = @{}; \
+     foreach ($t in $tables) { \
+       $fid = $t.Fan_Id; \
+       if ($t.Active) { \
+         $best[$fid] = $t.Sensor_ID; \
+         $bound[$fid] = $true \
+       } elseif (-not $bound.ContainsKey($fid) -and \
+                 (-not $best.ContainsKey($fid) -or $t.Sensor_ID -gt $best[$fid])) { \
+         $best[$fid] = $t.Sensor_ID \
+       } \
+     }; \
+     foreach ($t in $tables) { \
+       $fid = $t.Fan_Id; \
+       $sid = $t.Sensor_ID; \
+       $active = if ($t.Active) { '1' } else { '0' }; \
+       $speeds = ($t.FanTable_Data -join ','); \
+       $temps = ($t.SensorTable_Data -join ','); \
+       $minSpd = ($t.FanTable_Data | Measure-Object -Minimum).Minimum; \
+       $maxSpd = ($t.FanTable_Data | Measure-Object -Maximum).Maximum; \
+       $minTmp = ($t.SensorTable_Data | Measure-Object -Minimum).Minimum; \
+       $maxTmp = ($t.SensorTable_Data | Measure-Object -Maximum).Maximum; \
+       Write-Output \"TABLE|$fid|$sid|$active|$minSpd|$maxSpd|$minTmp|$maxTmp|$speeds|$temps\" \
+     }; \
+     foreach ($fid in ($best.Keys | Sort-Object)) { \
+       $sid = $best[$fid]; \
+       $speed = ($fm.Fan_GetCurrentFanSpeed($fid)).CurrentFanSpeed; \
+       $temp = ($fm.Fan_GetCurrentSensorTemperature($sid)).CurrentSensorTemperature; \
+       Write-Output \"FAN|$fid|$sid|$speed|$temp\" \
+     }";
+
+/// Table data only (no speed/temp reads). Same fields as the discover
+/// `TABLE|` lines, without the prefix.
+const FAN_CURVES_SCRIPT: &str =
+    "$tables = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_TABLE_DATA; \
+     foreach ($t in $tables) { \
+       $fid = $t.Fan_Id; \
+       $sid = $t.Sensor_ID; \
+       $active = if ($t.Active) { '1' } else { '0' }; \
+       $speeds = ($t.FanTable_Data -join ','); \
+       $temps = ($t.SensorTable_Data -join ','); \
+       $minSpd = ($t.FanTable_Data | Measure-Object -Minimum).Minimum; \
+       $maxSpd = ($t.FanTable_Data | Measure-Object -Maximum).Maximum; \
+       $minTmp = ($t.SensorTable_Data | Measure-Object -Minimum).Minimum; \
+       $maxTmp = ($t.SensorTable_Data | Measure-Object -Maximum).Maximum; \
+       Write-Output \"$fid|$sid|$active|$minSpd|$maxSpd|$minTmp|$maxTmp|$speeds|$temps\" \
+     }";
+
+/// WMI classes in `root\WMI` the backend calls into.
+const WMI_CLASSES: [&str; 3] = [
+    "LENOVO_FAN_METHOD",
+    "LENOVO_FAN_TABLE_DATA",
+    "LENOVO_GAMEZONE_DATA",
+];
+
+/// Prints `Class|1` for each of [`WMI_CLASSES`] that exists, `Class|0`
+/// otherwise. Used by `doctor`.
+const WMI_CLASSES_SCRIPT: &str =
+    "foreach ($c in @('LENOVO_FAN_METHOD','LENOVO_FAN_TABLE_DATA','LENOVO_GAMEZONE_DATA')) { \
+       $found = if (Get-CimClass -Namespace root/WMI -ClassName $c -ErrorAction SilentlyContinue) { '1' } else { '0' }; \
+       Write-Output \"$c|$found\" \
+     }";
+
+/// Dumps the `GetSmartFanMode` result properties as `Name|Value` lines.
+const GET_SMART_FAN_MODE_SCRIPT: &str =
+    "$gz = Get-WmiObject -Namespace root/WMI -Class LENOVO_GAMEZONE_DATA; \
+     $result = $gz.GetSmartFanMode(); \
+     $result.Properties | ForEach-Object { \
+       if ($_.Value -ne $null -and $_.Name -ne '__PATH' -and $_.Name -ne '__GENUS' -and \
+           $_.Name -ne '__CLASS' -and $_.Name -ne '__SUPERCLASS' -and \
+           $_.Name -ne '__DYNASTY' -and $_.Name -ne '__RELPATH' -and \
+           $_.Name -ne '__PROPERTY_COUNT' -and $_.Name -ne '__DERIVATION' -and \
+           $_.Name -ne '__SERVER' -and $_.Name -ne '__NAMESPACE') { \
+         Write-Output \"$($_.Name)|$($_.Value)\" \
+       } \
+     }";
+
+/// Read the current speed in RPM of every listed fan with a single
+/// `LENOVO_FAN_METHOD` lookup. Output: one `fan_id|speed` line per fan.
+fn fan_speeds_script(fan_ids: &[u32]) -> String {
+    let ids = fan_ids
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
+         foreach ($fid in @({ids})) {{ \
+           $speed = ($fm.Fan_GetCurrentFanSpeed($fid)).CurrentFanSpeed; \
+           Write-Output \"$fid|$speed\" \
+         }}"
+    )
+}
+
+/// Read the current temperature of every listed sensor with a single
+/// `LENOVO_FAN_METHOD` lookup. Output: one `sensor_id|temp` line per sensor.
+fn sensor_temps_script(sensor_ids: &[u32]) -> String {
+    let ids = sensor_ids
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    format!(
+        "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
+         foreach ($sid in @({ids})) {{ \
+           $temp = ($fm.Fan_GetCurrentSensorTemperature($sid)).CurrentSensorTemperature; \
+           Write-Output \"$sid|$temp\" \
+         }}"
+    )
+}
+
+/// Request a manual fan speed in RPM.
+fn set_fan_speed_script(fan_id: u32, rpm: u32) -> String {
+    format!(
+        "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
+         $fm.Fan_SetCurrentFanSpeed({fan_id}, {rpm})"
+    )
+}
+
+/// Enable (full speed) or disable (back to auto) full speed mode.
+fn full_speed_script(enabled: bool) -> String {
+    format!(
+        "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
+         $fm.Fan_Set_FullSpeed({})",
+        u8::from(enabled)
+    )
+}
+
+/// Switch SmartFanMode (1=Quiet, 2=Balanced, 3=Performance, 255=Custom).
+fn set_smart_fan_mode_script(mode: u32) -> String {
+    format!(
+        "$gz = Get-WmiObject -Namespace root/WMI -Class LENOVO_GAMEZONE_DATA; \
+         $gz.SetSmartFanMode({mode})"
+    )
+}
+
+/// Read one table via `Fan_Get_Table` and print its bytes as `RAW|` hex.
+/// The out-parameter's name varies between firmware, so the first byte
+/// array in the result is taken; no array prints a bare `RAW|`.
+fn get_table_script(fan_id: u32, sensor_id: u32) -> String {
+    format!(
+        "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
+         $result = $fm.Fan_Get_Table({fan_id}, {sensor_id}); \
+         $bytes = $result.Properties | Where-Object {{ $_.IsArray -and $_.Type -eq 'UInt8' }} | \
+           Select-Object -First 1 -ExpandProperty Value; \
+         Write-Output (\"RAW|\" + (($bytes | ForEach-Object {{ '{{0:X2}}' -f $_ }}) -join ' '))"
+    )
+}
+
+/// Parse the `RAW|` line of [`get_table_script`] output into bytes.
+fn parse_raw_table(output: &str) -> Result<Vec<u8>, FanControlError> {
+    let Some(hex) = output.lines().find_map(|line| line.strip_prefix("RAW|")) else {
+        return Err(FanControlError::Platform(format!(
+            "unexpected Fan_Get_Table output: {output}"
+        )));
+    };
+    hex.split_whitespace()
+        .map(|byte| {
+            u8::from_str_radix(byte, 16).map_err(|_| {
+                FanControlError::Platform(format!("invalid byte '{byte}' from Fan_Get_Table"))
+            })
+        })
+        .collect()
+}
+
+/// Write a custom curve via `Fan_Set_Table`.
+fn set_table_script(curve: &CustomFanCurve) -> String {
+    let ps_array = format_ps_byte_array(&encode_fan_table_bytes(curve));
+    format!(
+        "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
+         [byte[]]$table = {ps_array}; \
+         $fm.Fan_Set_Table($table)"
+    )
+}
+
+/// Every script the backend can run, labelled, with example arguments for
+/// the parameterized ones. Used by `--dump-script`.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn scripts() -> Vec<(String, String)> {
+    let example_curve = CustomFanCurve {
+        fan_id: 0,
+        sensor_id: 3,
+        steps: [1, 1, 1, 1, 1, 1, 1, 1, 3, 5],
+    };
+    vec![
+        ("discover".into(), DISCOVER_SCRIPT.into()),
+        ("fan curves (table)".into(), FAN_CURVES_SCRIPT.into()),
+        ("privilege check".into(), PRIVILEGES_SCRIPT.into()),
+        ("WMI class check (doctor)".into(), WMI_CLASSES_SCRIPT.into()),
+        (
+            "get speeds (fans 0, 1)".into(),
+            fan_speeds_script(&DEFAULT_FAN_IDS),
+        ),
+        (
+            format!("set speed (fan 0, {DEFAULT_MIN_RPM} RPM)"),
+            set_fan_speed_script(0, DEFAULT_MIN_RPM),
+        ),
+        ("full speed on (PWM 255)".into(), full_speed_script(true)),
+        (
+            "full speed off / auto (PWM 0)".into(),
+            full_speed_script(false),
+        ),
+        ("get SmartFanMode".into(), GET_SMART_FAN_MODE_SCRIPT.into()),
+        (
+            "set SmartFanMode (Custom)".into(),
+            set_smart_fan_mode_script(255),
+        ),
+        ("raw table (fan 0, sensor 3)".into(), get_table_script(0, 3)),
+        (
+            format!(
+                "set curve (fan 0, sensor 3, steps {:?})",
+                example_curve.steps
+            ),
+            set_table_script(&example_curve),
+        ),
+    ]
+}
+
+/// `doctor` check: whether every class in [`WMI_CLASSES`] exists.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub fn wmi_classes_check(runner: &impl CommandRunner) -> Check {
+    const NAME: &str = "Lenovo WMI classes";
+    let output = match runner.run_read(WMI_CLASSES_SCRIPT) {
+        Ok(output) => output,
+        Err(error) => return Check::fail(NAME, format!("cannot query root\\WMI: {error}")),
+    };
+    let missing = missing_wmi_classes(&output);
+    if missing.is_empty() {
+        Check::pass(NAME, WMI_CLASSES.join(", "))
+    } else {
+        Check::fail(
+            NAME,
+            format!(
+                "missing {}: this model's BIOS does not expose Lenovo fan control \
+                 (a BIOS update may add it)",
+                missing.join(", ")
+            ),
+        )
+    }
+}
+
+/// The classes in [`WMI_CLASSES`] that `WMI_CLASSES_SCRIPT` output does
+/// not report as present.
+fn missing_wmi_classes(output: &str) -> Vec<&'static str> {
+    WMI_CLASSES
+        .into_iter()
+        .filter(|class| {
+            !output
+                .lines()
+                .any(|line| line.trim() == format!("{class}|1"))
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Direct WMI method calls
+// ---------------------------------------------------------------------------
+
+/// Whether a `GetSmartFanMode` out-parameter holds the mode. Its name varies
+/// between firmware.
+pub(super) fn is_smart_fan_mode_property(name: &str) -> bool {
+    let name = name.trim().to_lowercase();
+    name == "mode" || name == "data" || name == "smartfanmode"
+}
+
+/// `LENOVO_FAN_METHOD` and `LENOVO_GAMEZONE_DATA` calls made without
+/// PowerShell. Each answers what the equivalent script prints.
+pub trait WmiMethods {
+    /// `Fan_GetCurrentFanSpeed` for each fan, by fan ID.
+    fn fan_speeds(&self, fan_ids: &[u32]) -> Result<HashMap<u32, u32>, FanControlError>;
+
+    /// `Fan_GetCurrentSensorTemperature` for each sensor, by sensor ID.
+    fn sensor_temperatures(&self, sensor_ids: &[u32])
+        -> Result<HashMap<u32, u32>, FanControlError>;
+
+    /// `Fan_SetCurrentFanSpeed(fan_id, rpm)`.
+    fn set_fan_speed(&self, fan_id: u32, rpm: u32) -> Result<(), FanControlError>;
+
+    /// `Fan_Set_FullSpeed(1)` or `Fan_Set_FullSpeed(0)`.
+    fn set_full_speed(&self, enabled: bool) -> Result<(), FanControlError>;
+
+    /// `GetSmartFanMode`, or `None` when no out-parameter holds the mode.
+    fn smart_fan_mode(&self) -> Result<Option<u32>, FanControlError>;
+
+    /// `SetSmartFanMode(mode)`.
+    fn set_smart_fan_mode(&self, mode: u32) -> Result<(), FanControlError>;
+}
+
 // ---------------------------------------------------------------------------
 // Controller
 // ---------------------------------------------------------------------------
 
 /// Lenovo Legion fan controller backed by vendor-specific WMI classes.
+///
+/// Scripts go through `R`, PowerShell unless a test swaps in a recorder.
 #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
-pub struct LenovoFanController {
+pub struct LenovoFanController<R = PowerShell> {
     /// Per-fan RPM ranges, populated on first discover().
-    fan_ranges: std::cell::RefCell<HashMap<u32, FanRpmRange>>,
+    fan_ranges: RefCell<HashMap<u32, FanRpmRange>>,
+    /// Last batched speed read, reused by `get_speed` for `SPEED_CACHE_TTL`.
+    speed_cache: RefCell<Option<(Instant, HashMap<u32, u32>)>>,
+    /// Runs the WMI method calls.
+    runner: R,
+    /// Point count of each `(fan_id, sensor_id)` table, from the last
+    /// discover() or get_fan_curves().
+    table_sizes: RefCell<HashMap<(u32, u32), usize>>,
+    /// Label of every sensor in those tables, by sensor ID.
+    sensor_labels: RefCell<BTreeMap<u32, String>>,
+    /// RPM last requested per fan with `Fan_SetCurrentFanSpeed`, until the
+    /// fan is handed back to the firmware. For stall detection.
+    held_rpm: RefCell<HashMap<u32, u32>>,
+    /// Measured PWM→RPM points per fan; fans without any map linearly.
+    calibration: HashMap<u32, Vec<CalibrationPoint>>,
+    /// Direct method calls, tried before the script; dropped on failure.
+    wmi_methods: RefCell<Option<Box<dyn WmiMethods>>>,
 }
 
 #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
 impl LenovoFanController {
     pub fn new() -> Self {
+        let controller = Self::with_runner(PowerShell::new());
+        #[cfg(all(target_os = "windows", feature = "wmi-direct"))]
+        let controller = match super::lenovo_com::ComMethods::connect() {
+            Ok(methods) => controller.with_wmi_methods(Box::new(methods)),
+            Err(error) => {
+                warn!("{error}; calling Lenovo WMI methods through PowerShell");
+                controller
+            }
+        };
+        controller
+    }
+
+    /// Kill PowerShell calls that take longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.runner = self.runner.with_timeout(timeout);
+        self
+    }
+
+    /// Try read scripts up to `attempts` times before giving up.
+    pub fn with_read_attempts(mut self, attempts: u32) -> Self {
+        self.runner = self.runner.with_read_attempts(attempts);
+        self
+    }
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+impl<R: CommandRunner> LenovoFanController<R> {
+    /// A controller that runs its scripts through `runner`.
+    pub fn with_runner(runner: R) -> Self {
         Self {
-            fan_ranges: std::cell::RefCell::new(HashMap::new()),
+            fan_ranges: RefCell::new(HashMap::new()),
+            speed_cache: RefCell::new(None),
+            runner,
+            table_sizes: RefCell::new(HashMap::new()),
+            sensor_labels: RefCell::new(BTreeMap::new()),
+            held_rpm: RefCell::new(HashMap::new()),
+            calibration: HashMap::new(),
+            wmi_methods: RefCell::new(None),
         }
     }
 
-    /// Call a WMI method via PowerShell and return the raw stdout.
-    fn ps_command(script: &str) -> Result<String, FanControlError> {
-        debug!("ps_command: {}", script);
-        let output = Command::new("powershell.exe")
-            .args(["-NoProfile", "-NonInteractive", "-Command", script])
-            .output()
-            .map_err(|e| {
-                warn!("ps_command failed to launch: {e}");
-                FanControlError::Platform(format!("failed to run powershell: {e}"))
-            })?;
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            warn!("ps_command stderr: {}", stderr.trim());
-            return Err(FanControlError::Platform(format!(
-                "powershell error: {}",
-                stderr.trim()
-            )));
+    /// Make the calls [`WmiMethods`] covers through `methods`, falling back
+    /// to PowerShell once one fails.
+    #[cfg_attr(not(feature = "wmi-direct"), allow(dead_code))]
+    pub fn with_wmi_methods(self, methods: Box<dyn WmiMethods>) -> Self {
+        *self.wmi_methods.borrow_mut() = Some(methods);
+        self
+    }
+
+    /// Map PWM to RPM through the config's `pwm_calibration` tables, keyed
+    /// by fan id. Tables for unknown ids or that fail validation are
+    /// skipped with a warning, leaving that fan linear.
+    pub fn with_calibration(mut self, tables: &BTreeMap<String, Vec<CalibrationPoint>>) -> Self {
+        for (fan_id, points) in tables {
+            let table = parse_fan_id(fan_id)
+                .map_err(|error| error.to_string())
+                .and_then(|numeric_id| Ok((numeric_id, validate_calibration(points)?)));
+            match table {
+                Ok((numeric_id, points)) => {
+                    self.calibration.insert(numeric_id, points);
+                }
+                Err(error) => warn!("ignoring pwm_calibration for {fan_id}: {error}"),
+            }
         }
+        self
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        debug!("ps_command stdout: {}", stdout);
-        Ok(stdout)
+    /// The RPM `set_pwm` requests for `pwm` on a fan with this range.
+    fn pwm_target(&self, numeric_id: u32, min_rpm: u32, max_rpm: u32, pwm: u8) -> u32 {
+        match self.calibration.get(&numeric_id) {
+            Some(points) => calibrated_pwm_to_rpm(points, min_rpm, max_rpm, pwm),
+            None => pwm_to_rpm(min_rpm, max_rpm, pwm),
+        }
     }
 
-    /// Read current fan speed in RPM for a given fan ID (0 or 1).
-    fn read_fan_speed(fan_id: u32) -> Result<u32, FanControlError> {
-        let script = format!(
-            "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
-             ($fm.Fan_GetCurrentFanSpeed({fan_id})).CurrentFanSpeed"
-        );
-        let output = Self::ps_command(&script)?;
-        output
-            .parse::<u32>()
-            .map_err(|e| FanControlError::Platform(format!("failed to parse fan speed: {e}")))
+    /// Confirm the firmware exposes `LENOVO_FAN_METHOD`. Many non-Legion
+    /// Lenovo models lack it, and then every call would fail with a raw
+    /// PowerShell error; this fails once, with a hint at the generic backend.
+    pub fn probe(&self) -> Result<(), FanControlError> {
+        let output = self.ps_read(WMI_CLASSES_SCRIPT)?;
+        if missing_wmi_classes(&output).contains(&"LENOVO_FAN_METHOD") {
+            return Err(FanControlError::Platform(
+                "this Lenovo model has no LENOVO_FAN_METHOD WMI class, so Legion fan \
+                 control is unavailable; use the generic Windows backend (--backend wmi)"
+                    .to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Run `script` once and return the raw stdout. For writes, which must
+    /// not be repeated.
+    fn ps_command(&self, script: &str) -> Result<String, FanControlError> {
+        self.runner.run(script)
+    }
+
+    /// Run a script that only reads, retrying transient WMI failures.
+    fn ps_read(&self, script: &str) -> Result<String, FanControlError> {
+        self.runner.run_read(script)
+    }
+
+    /// `call` through the direct methods, or `None` to run the script
+    /// instead: there are none, or `call` failed and they were dropped.
+    /// Dropping them is permanent, so it is logged as a warning: every later
+    /// call spawns PowerShell.
+    fn direct<T>(
+        &self,
+        method: &str,
+        call: impl FnOnce(&dyn WmiMethods) -> Result<T, FanControlError>,
+    ) -> Option<T> {
+        let result = call(self.wmi_methods.borrow().as_deref()?);
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                warn!("direct WMI call {method} failed, using PowerShell from now on: {error}");
+                *self.wmi_methods.borrow_mut() = None;
+                None
+            }
+        }
+    }
+
+    /// Read the current speed of every known fan in one PowerShell call.
+    ///
+    /// Queries the fans seen by the last discover(). A fresh controller
+    /// discovers first, so fans beyond CPU and GPU are read too; only when
+    /// the firmware lists no tables are CPU and GPU fan assumed. The result
+    /// also refreshes the `get_speed` cache.
+    pub fn read_all_fan_speeds(&self) -> Result<HashMap<u32, u32>, FanControlError> {
+        let mut fan_ids: Vec<u32> = self.fan_ranges.borrow().keys().copied().collect();
+        if fan_ids.is_empty() {
+            let fans = self.discover()?;
+            if !fans.is_empty() {
+                return Ok(fans
+                    .iter()
+                    .filter_map(|fan| Some((parse_fan_id(&fan.id).ok()?, fan.speed_rpm)))
+                    .collect());
+            }
+            fan_ids = DEFAULT_FAN_IDS.to_vec();
+        }
+        fan_ids.sort_unstable();
+
+        let speeds = match self.direct("Fan_GetCurrentFanSpeed", |wmi| wmi.fan_speeds(&fan_ids)) {
+            Some(speeds) => speeds,
+            None => {
+                let output = self.ps_read(&fan_speeds_script(&fan_ids))?;
+                let speeds = parse_id_values(&output);
+                if speeds.is_empty() {
+                    return Err(FanControlError::Platform(format!(
+                        "failed to parse fan speeds from: {output}"
+                    )));
+                }
+                speeds
+            }
+        };
+        *self.speed_cache.borrow_mut() = Some((Instant::now(), speeds.clone()));
+        Ok(speeds)
     }
 
-    /// Resolve RPM range for a fan, falling back to defaults.
-    fn fan_rpm_range(&self, fan_numeric_id: u32) -> (u32, u32) {
+    /// Speeds from the cache while fresh, otherwise from a new batched read.
+    fn fan_speeds(&self) -> Result<HashMap<u32, u32>, FanControlError> {
+        if let Some((read_at, speeds)) = self.speed_cache.borrow().as_ref() {
+            if read_at.elapsed() < SPEED_CACHE_TTL {
+                return Ok(speeds.clone());
+            }
+        }
+        self.read_all_fan_speeds()
+    }
+
+    /// Request a manual fan speed in RPM via `Fan_SetCurrentFanSpeed`.
+    fn write_fan_speed(&self, fan_id: u32, rpm: u32) -> Result<(), FanControlError> {
+        let direct = self.direct("Fan_SetCurrentFanSpeed", |wmi| {
+            wmi.set_fan_speed(fan_id, rpm)
+        });
+        if direct.is_none() {
+            self.ps_command(&set_fan_speed_script(fan_id, rpm))?;
+        }
+        self.held_rpm.borrow_mut().insert(fan_id, rpm);
+        Ok(())
+    }
+
+    /// Turn full speed mode on or off via `Fan_Set_FullSpeed`.
+    fn write_full_speed(&self, enabled: bool) -> Result<(), FanControlError> {
+        let direct = self.direct("Fan_Set_FullSpeed", |wmi| wmi.set_full_speed(enabled));
+        if direct.is_none() {
+            self.ps_command(&full_speed_script(enabled))?;
+        }
+        Ok(())
+    }
+
+    /// Record the table sizes of `curves` for `set_custom_curve` and the
+    /// sensors they use for `sensor_temperatures`.
+    fn remember_tables<'a>(&self, curves: impl IntoIterator<Item = &'a FanCurve>) {
+        let curves: Vec<FanCurve> = curves.into_iter().cloned().collect();
+        *self.table_sizes.borrow_mut() = curves
+            .iter()
+            .map(|c| ((c.fan_id, c.sensor_id), c.points.len()))
+            .collect();
+        *self.sensor_labels.borrow_mut() = curves
+            .iter()
+            .map(|c| (c.sensor_id, sensor_label(&curves, c.sensor_id)))
+            .collect();
+    }
+
+    /// The numeric id of `fan_id` and its RPM range from the tables.
+    ///
+    /// A fresh controller (CLI) discovers first to learn the ranges. Every
+    /// fan the tables list has its own range, however many there are; an id
+    /// they do not list is not a fan. Without any table data the default
+    /// range is assumed.
+    fn fan_range(&self, fan_id: &str) -> Result<(u32, u32, u32), FanControlError> {
+        let numeric_id = parse_fan_id(fan_id)?;
+        if self.fan_ranges.borrow().is_empty() {
+            self.discover()?;
+        }
         let ranges = self.fan_ranges.borrow();
-        match ranges.get(&fan_numeric_id) {
-            Some(range) => (range.min_rpm, range.max_rpm),
-            None => (DEFAULT_MIN_RPM, DEFAULT_MAX_RPM),
+        if ranges.is_empty() {
+            return Ok((numeric_id, DEFAULT_MIN_RPM, DEFAULT_MAX_RPM));
+        }
+        let range = ranges
+            .get(&numeric_id)
+            .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_string()))?;
+        Ok((numeric_id, range.min_rpm, range.max_rpm))
+    }
+
+    /// The numeric fan id and the RPM `set_rpm` writes for `rpm`: raised to
+    /// the fan's minimum, refused above its maximum.
+    fn rpm_target(&self, fan_id: &str, rpm: u32) -> Result<(u32, u32), FanControlError> {
+        let (numeric_id, min_rpm, max_rpm) = self.fan_range(fan_id)?;
+        if rpm > max_rpm {
+            return Err(FanControlError::Platform(format!(
+                "{rpm} RPM exceeds {fan_id}'s maximum of {max_rpm} RPM"
+            )));
+        }
+        Ok((numeric_id, rpm.max(min_rpm)))
+    }
+
+    /// `curve` validated under `min_high_temp_ratio` and fitted to the size
+    /// of its firmware table.
+    fn fitted_curve(
+        &self,
+        curve: &CustomFanCurve,
+        min_high_temp_ratio: f32,
+    ) -> Result<CustomFanCurve, FanControlError> {
+        validate_custom_curve_with_policy(curve, min_high_temp_ratio, None)?;
+
+        // Only write to a table the firmware reported; Fan_Set_Table
+        // silently accepts a pair that does not exist.
+        if self.table_sizes.borrow().is_empty() {
+            self.get_fan_curves()?;
+        }
+        let table_len = table_len_for(&self.table_sizes.borrow(), curve.fan_id, curve.sensor_id)?;
+        let steps = fit_steps_to_table(&curve.steps, table_len);
+        if steps != curve.steps {
+            info!(
+                "table for fan {} sensor {} has {table_len} points, writing steps {steps:?}",
+                curve.fan_id, curve.sensor_id
+            );
         }
+        Ok(CustomFanCurve { steps, ..*curve })
     }
 }
 
-impl FanController for LenovoFanController {
+impl<R: CommandRunner> FanController for LenovoFanController<R> {
     fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
-        // Single PowerShell invocation: discover fans, read speeds, temps,
-        // full fan table data (curves + RPM ranges), and full speed status.
-        //
-        // Output format:
-        //   FULLSPEED|0/1
-        //   FAN|fan_id|sensor_id|speed|temp          — one per fan (best sensor)
-        //   TABLE|fan_id|sensor_id|active|min_speed|max_speed|min_temp|max_temp|speeds_csv|temps_csv
-        let script =
-            "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
-             $tables = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_TABLE_DATA; \
-             $fs = ($fm.Fan_Get_FullSpeed()).Status; \
-             $fsVal = if ($fs) { '1' } else { '0' }; \
-             Write-Output \"FULLSPEED|$fsVal\"; \
-             $best = @{}; \
-             foreach ($t in $tables) { \
-               $fid = $t.Fan_Id; \
-               if (-not $best.ContainsKey($fid) -or $t.Sensor_ID -gt $best[$fid]) { \
-                 $best[$fid] = $t.Sensor_ID \
-               } \
-             }; \
-             foreach ($t in $tables) { \
-               $fid = $t.Fan_Id; \
-               $sid = $t.Sensor_ID; \
-               $active = if ($t.Active) { '1' } else { '0' }; \
-               $speeds = ($t.FanTable_Data -join ','); \
-               $temps = ($t.SensorTable_Data -join ','); \
-               $minSpd = ($t.FanTable_Data | Measure-Object -Minimum).Minimum; \
-               $maxSpd = ($t.FanTable_Data | Measure-Object -Maximum).Maximum; \
-               $minTmp = ($t.SensorTable_Data | Measure-Object -Minimum).Minimum; \
-               $maxTmp = ($t.SensorTable_Data | Measure-Object -Maximum).Maximum; \
-               Write-Output \"TABLE|$fid|$sid|$active|$minSpd|$maxSpd|$minTmp|$maxTmp|$speeds|$temps\" \
-             }; \
-             foreach ($fid in ($best.Keys | Sort-Object)) { \
-               $sid = $best[$fid]; \
-               $speed = ($fm.Fan_GetCurrentFanSpeed($fid)).CurrentFanSpeed; \
-               $temp = ($fm.Fan_GetCurrentSensorTemperature($sid)).CurrentSensorTemperature; \
-               Write-Output \"FAN|$fid|$sid|$speed|$temp\" \
-             }";
-
-        let output = Self::ps_command(script)?;
+        let output = self.ps_read(DISCOVER_SCRIPT)?;
 
         let full_speed_active = parse_fullspeed(&output);
         debug!("full_speed_active = {full_speed_active}");
 
         // First pass: parse TABLE lines to build curves and RPM ranges.
-        let mut curves_by_fan: HashMap<u32, Vec<FanCurve>> = HashMap::new();
+        let mut tables: Vec<FanCurve> = Vec::new();
         let mut rpm_ranges: HashMap<u32, FanRpmRange> = HashMap::new();
 
         for line in output.lines() {
@@ -389,7 +907,7 @@ impl FanController for LenovoFanController {
                 curve.points.len()
             );
 
-            curves_by_fan.entry(fan_id).or_default().push(curve);
+            tables.push(curve);
 
             // Update per-fan RPM range (take the widest range across curves).
             let existing = rpm_ranges.entry(fan_id).or_insert(range.clone());
@@ -401,8 +919,25 @@ impl FanController for LenovoFanController {
             }
         }
 
+        for (fan_id, range) in &rpm_ranges {
+            if range.max_rpm <= range.min_rpm {
+                warn!(
+                    "fan{fan_id}: the EC tables give no RPM range ({}-{} RPM); \
+                     every PWM maps to {} RPM",
+                    range.min_rpm, range.max_rpm, range.min_rpm
+                );
+            }
+        }
+
         // Store learned RPM ranges for pwm_to_rpm/rpm_to_pwm.
         *self.fan_ranges.borrow_mut() = rpm_ranges.clone();
+        self.remember_tables(&tables);
+
+        label_sensors(&mut tables);
+        let mut curves_by_fan: HashMap<u32, Vec<FanCurve>> = HashMap::new();
+        for curve in tables {
+            curves_by_fan.entry(curve.fan_id).or_default().push(curve);
+        }
 
         // Second pass: parse FAN lines to build Fan structs.
         let mut fans = Vec::new();
@@ -410,102 +945,184 @@ impl FanController for LenovoFanController {
             if !line.starts_with("FAN|") {
                 continue;
             }
-            if let Some(fan) =
-                parse_fan_line(line, &rpm_ranges, &mut curves_by_fan, full_speed_active)
-            {
+            if let Some(mut fan) = parse_fan_line(
+                line,
+                &rpm_ranges,
+                &self.calibration,
+                &mut curves_by_fan,
+                full_speed_active,
+            ) {
+                let held = parse_fan_id(&fan.id)
+                    .ok()
+                    .and_then(|id| self.held_rpm.borrow().get(&id).copied());
+                if let Some(rpm) = held {
+                    fan.stalled |= is_stalled(fan.speed_rpm, FanCommand::Rpm(rpm));
+                }
                 fans.push(fan);
             }
         }
 
+        // Discover already read every speed; let get_speed reuse them.
+        let speeds = fans
+            .iter()
+            .filter_map(|fan| Some((parse_fan_id(&fan.id).ok()?, fan.speed_rpm)))
+            .collect();
+        *self.speed_cache.borrow_mut() = Some((Instant::now(), speeds));
+
         Ok(fans)
     }
 
+    fn check_privileges(&self) -> Result<(), FanControlError> {
+        // LENOVO_FAN_METHOD calls silently fail without an elevated token.
+        let output = self.ps_read(PRIVILEGES_SCRIPT)?;
+        if output.eq_ignore_ascii_case("true") {
+            Ok(())
+        } else {
+            Err(FanControlError::PermissionDenied(
+                "fan control requires administrator rights: run as administrator".to_string(),
+            ))
+        }
+    }
+
     fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError> {
         let numeric_id = parse_fan_id(fan_id)?;
-        Self::read_fan_speed(numeric_id)
+        self.fan_speeds()?
+            .get(&numeric_id)
+            .copied()
+            .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_owned()))
     }
 
     fn set_pwm(&self, fan_id: &str, pwm: u8) -> Result<(), FanControlError> {
-        let numeric_id = parse_fan_id(fan_id)?;
-
         if pwm == 255 {
+            parse_fan_id(fan_id)?;
             info!("set_pwm({fan_id}, 255) -> Fan_Set_FullSpeed(1)");
-            let script = "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
-                 $fm.Fan_Set_FullSpeed(1)";
-            Self::ps_command(script)?;
+            self.set_full_speed(true)?;
         } else if pwm == 0 {
-            info!("set_pwm({fan_id}, 0) -> Fan_Set_FullSpeed(0) [auto]");
-            let script = "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
-                 $fm.Fan_Set_FullSpeed(0)";
-            Self::ps_command(script)?;
+            self.set_auto(fan_id)?;
         } else {
-            let (min_rpm, max_rpm) = self.fan_rpm_range(numeric_id);
-            let target_rpm = pwm_to_rpm(min_rpm, max_rpm, pwm);
+            let (numeric_id, min_rpm, max_rpm) = self.fan_range(fan_id)?;
+            let target_rpm = self.pwm_target(numeric_id, min_rpm, max_rpm, pwm);
             info!("set_pwm({fan_id}, {pwm}) -> Fan_SetCurrentFanSpeed({numeric_id}, {target_rpm})");
-            let script = format!(
-                "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
-                 $fm.Fan_SetCurrentFanSpeed({numeric_id}, {target_rpm})"
-            );
-            Self::ps_command(&script)?;
+            self.write_fan_speed(numeric_id, target_rpm)?;
         }
 
         Ok(())
     }
 
-    fn set_custom_curve(&self, curve: &CustomFanCurve) -> Result<(), FanControlError> {
-        validate_custom_curve(curve)?;
+    fn set_full_speed(&self, enabled: bool) -> Result<(), FanControlError> {
+        info!(
+            "set_full_speed({enabled}) -> Fan_Set_FullSpeed({})",
+            u8::from(enabled)
+        );
+        self.write_full_speed(enabled)?;
+        if !enabled {
+            self.held_rpm.borrow_mut().clear();
+        }
+        Ok(())
+    }
+
+    fn stop_note(&self) -> String {
+        "PWM 0 hands the fan back to firmware (Fan_Set_FullSpeed(0)); \
+         it keeps spinning under BIOS automatic control"
+            .to_string()
+    }
+
+    fn set_auto(&self, fan_id: &str) -> Result<(), FanControlError> {
+        parse_fan_id(fan_id)?;
+        info!("set_auto({fan_id}) -> Fan_Set_FullSpeed(0) [auto]");
+        self.write_full_speed(false)?;
+        // Full-speed off releases every fan, not only this one.
+        self.held_rpm.borrow_mut().clear();
+        Ok(())
+    }
+
+    fn set_rpm(&self, fan_id: &str, rpm: u32) -> Result<(), FanControlError> {
+        let (numeric_id, rpm) = self.rpm_target(fan_id, rpm)?;
+        info!("set_rpm({fan_id}, {rpm}) -> Fan_SetCurrentFanSpeed({numeric_id}, {rpm})");
+        self.write_fan_speed(numeric_id, rpm)
+    }
+
+    fn sensor_temperatures(&self) -> Result<Vec<SensorTemperature>, FanControlError> {
+        if self.sensor_labels.borrow().is_empty() {
+            self.get_fan_curves()?;
+        }
+        let labels = self.sensor_labels.borrow().clone();
+        if labels.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sensor_ids: Vec<u32> = labels.keys().copied().collect();
+        let temps = match self.direct("Fan_GetCurrentSensorTemperature", |wmi| {
+            wmi.sensor_temperatures(&sensor_ids)
+        }) {
+            Some(temps) => temps,
+            None => parse_id_values(&self.ps_read(&sensor_temps_script(&sensor_ids))?),
+        };
+        Ok(labels
+            .into_iter()
+            .filter_map(|(sensor_id, label)| {
+                Some(SensorTemperature {
+                    sensor_id,
+                    label,
+                    temperature: *temps.get(&sensor_id)?,
+                })
+            })
+            .collect())
+    }
+
+    fn gpu_sensor_id(&self) -> Result<Option<u32>, FanControlError> {
+        let sensor_id = find_gpu_sensor(&self.get_fan_curves()?);
+        debug!("gpu_sensor_id = {sensor_id:?}");
+        Ok(sensor_id)
+    }
+
+    fn set_custom_curve_with_policy(
+        &self,
+        curve: &CustomFanCurve,
+        min_high_temp_ratio: f32,
+    ) -> Result<(), FanControlError> {
+        let curve = &self.fitted_curve(curve, min_high_temp_ratio)?;
 
         // Ensure SmartFanMode is set to Custom (255) — required for Fan_Set_Table.
         // Mode values: 1=Quiet, 2=Balanced, 3=Performance, 255=Custom.
         match self.get_smart_fan_mode()? {
-            Some(255) => {
+            Some(SMART_FAN_MODE_CUSTOM) => {
                 debug!("SmartFanMode already Custom (255)");
             }
             Some(mode) => {
                 warn!("SmartFanMode is {mode}, switching to Custom (255) for fan curve write");
-                self.set_smart_fan_mode(255)?;
+                self.set_smart_fan_mode(SMART_FAN_MODE_CUSTOM)?;
+                // Some firmware ignores the switch (e.g. on battery); a
+                // table written outside Custom mode is silently dropped.
+                match self.get_smart_fan_mode()? {
+                    Some(SMART_FAN_MODE_CUSTOM) | None => {}
+                    Some(mode) => return Err(FanControlError::SmartFanModeRequired(mode)),
+                }
             }
             None => {
                 warn!("Could not read SmartFanMode, attempting Fan_Set_Table anyway");
             }
         }
 
-        let bytes = encode_fan_table_bytes(curve);
-        let ps_array = format_ps_byte_array(&bytes);
         info!(
             "set_custom_curve: fan_id={} sensor_id={} steps={:?}",
             curve.fan_id, curve.sensor_id, curve.steps
         );
 
-        let script = format!(
-            "$fm = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_METHOD; \
-             [byte[]]$table = {ps_array}; \
-             $fm.Fan_Set_Table($table)"
-        );
-        Self::ps_command(&script)?;
+        self.ps_command(&set_table_script(curve))?;
         info!("Fan_Set_Table called successfully");
         Ok(())
     }
 
     fn get_smart_fan_mode(&self) -> Result<Option<u32>, FanControlError> {
-        let script = "$gz = Get-WmiObject -Namespace root/WMI -Class LENOVO_GAMEZONE_DATA; \
-             $result = $gz.GetSmartFanMode(); \
-             $result.Properties | ForEach-Object { \
-               if ($_.Value -ne $null -and $_.Name -ne '__PATH' -and $_.Name -ne '__GENUS' -and \
-                   $_.Name -ne '__CLASS' -and $_.Name -ne '__SUPERCLASS' -and \
-                   $_.Name -ne '__DYNASTY' -and $_.Name -ne '__RELPATH' -and \
-                   $_.Name -ne '__PROPERTY_COUNT' -and $_.Name -ne '__DERIVATION' -and \
-                   $_.Name -ne '__SERVER' -and $_.Name -ne '__NAMESPACE') { \
-                 Write-Output \"$($_.Name)|$($_.Value)\" \
-               } \
-             }";
-
-        let output = Self::ps_command(script)?;
+        if let Some(mode) = self.direct("GetSmartFanMode", |wmi| wmi.smart_fan_mode()) {
+            debug!("SmartFanMode: {mode:?}");
+            return Ok(mode);
+        }
+        let output = self.ps_read(GET_SMART_FAN_MODE_SCRIPT)?;
         // Parse "PropertyName|Value" lines to find the mode value
         for line in output.lines() {
             if let Some((name, value_str)) = line.split_once('|') {
-                let name_lower = name.trim().to_lowercase();
-                if name_lower == "mode" || name_lower == "data" || name_lower == "smartfanmode" {
+                if is_smart_fan_mode_property(name) {
                     if let Ok(value) = value_str.trim().parse::<u32>() {
                         debug!("SmartFanMode: {name}={value}");
                         return Ok(Some(value));
@@ -520,31 +1137,99 @@ impl FanController for LenovoFanController {
 
     fn set_smart_fan_mode(&self, mode: u32) -> Result<(), FanControlError> {
         info!("set_smart_fan_mode({mode})");
-        let script = format!(
-            "$gz = Get-WmiObject -Namespace root/WMI -Class LENOVO_GAMEZONE_DATA; \
-             $gz.SetSmartFanMode({mode})"
-        );
-        Self::ps_command(&script)?;
+        if self
+            .direct("SetSmartFanMode", |wmi| wmi.set_smart_fan_mode(mode))
+            .is_none()
+        {
+            self.ps_command(&set_smart_fan_mode_script(mode))?;
+        }
         Ok(())
     }
 
+    fn set_thermal_mode(&self, mode: ThermalMode) -> Result<(), FanControlError> {
+        let value = mode.smart_fan_mode();
+        info!("set_thermal_mode({mode}) -> SetSmartFanMode({value})");
+        self.set_smart_fan_mode(value)?;
+        match self.get_smart_fan_mode()? {
+            Some(current) if current != value => Err(FanControlError::Platform(format!(
+                "firmware did not accept thermal mode {mode} (SmartFanMode is still {current})"
+            ))),
+            _ => Ok(()),
+        }
+    }
+
+    /// The WMI call and PowerShell script the write runs. Reads the
+    /// hardware where the real write would (ranges, tables, SmartFanMode).
+    fn describe_write(&self, write: &HardwareWrite) -> Result<Vec<String>, FanControlError> {
+        let mut lines = Vec::new();
+        let (method, script) = match write {
+            HardwareWrite::Pwm { fan_id, pwm: 255 } => {
+                parse_fan_id(fan_id)?;
+                ("Fan_Set_FullSpeed(1)".to_string(), full_speed_script(true))
+            }
+            HardwareWrite::Pwm { fan_id, pwm: 0 } | HardwareWrite::Auto { fan_id } => {
+                parse_fan_id(fan_id)?;
+                ("Fan_Set_FullSpeed(0)".to_string(), full_speed_script(false))
+            }
+            HardwareWrite::Pwm { fan_id, pwm } => {
+                let (numeric_id, min_rpm, max_rpm) = self.fan_range(fan_id)?;
+                let rpm = self.pwm_target(numeric_id, min_rpm, max_rpm, *pwm);
+                (
+                    format!("Fan_SetCurrentFanSpeed({numeric_id}, {rpm})"),
+                    set_fan_speed_script(numeric_id, rpm),
+                )
+            }
+            HardwareWrite::Rpm { fan_id, rpm } => {
+                let (numeric_id, rpm) = self.rpm_target(fan_id, *rpm)?;
+                (
+                    format!("Fan_SetCurrentFanSpeed({numeric_id}, {rpm})"),
+                    set_fan_speed_script(numeric_id, rpm),
+                )
+            }
+            HardwareWrite::CustomCurve {
+                curve,
+                min_high_temp_ratio,
+            } => {
+                let curve = self.fitted_curve(curve, *min_high_temp_ratio)?;
+                match self.get_smart_fan_mode()? {
+                    Some(SMART_FAN_MODE_CUSTOM) | None => {}
+                    Some(mode) => lines.push(format!(
+                        "SmartFanMode is {mode}: SetSmartFanMode({SMART_FAN_MODE_CUSTOM}) first"
+                    )),
+                }
+                (
+                    format!("Fan_Set_Table(steps {:?})", curve.steps),
+                    set_table_script(&curve),
+                )
+            }
+            HardwareWrite::SmartFanMode(mode) => (
+                format!("SetSmartFanMode({mode})"),
+                set_smart_fan_mode_script(*mode),
+            ),
+            HardwareWrite::FullSpeed(enabled) => (
+                format!("Fan_Set_FullSpeed({})", u8::from(*enabled)),
+                full_speed_script(*enabled),
+            ),
+            HardwareWrite::ThermalMode(mode) => {
+                let value = mode.smart_fan_mode();
+                (
+                    format!("SetSmartFanMode({value})"),
+                    set_smart_fan_mode_script(value),
+                )
+            }
+            HardwareWrite::PwmCurves(_) => return Err(unsupported_write(write)),
+        };
+        lines.push(format!("WMI: {method}"));
+        lines.push(format!("PowerShell: {script}"));
+        Ok(lines)
+    }
+
+    fn raw_fan_table(&self, fan_id: u32, sensor_id: u32) -> Result<Vec<u8>, FanControlError> {
+        parse_raw_table(&self.ps_read(&get_table_script(fan_id, sensor_id))?)
+    }
+
     fn get_fan_curves(&self) -> Result<Vec<FanCurve>, FanControlError> {
-        // Dedicated query for just the table data (no speed/temp reads).
-        let script = "$tables = Get-WmiObject -Namespace root/WMI -Class LENOVO_FAN_TABLE_DATA; \
-             foreach ($t in $tables) { \
-               $fid = $t.Fan_Id; \
-               $sid = $t.Sensor_ID; \
-               $active = if ($t.Active) { '1' } else { '0' }; \
-               $speeds = ($t.FanTable_Data -join ','); \
-               $temps = ($t.SensorTable_Data -join ','); \
-               $minSpd = ($t.FanTable_Data | Measure-Object -Minimum).Minimum; \
-               $maxSpd = ($t.FanTable_Data | Measure-Object -Maximum).Maximum; \
-               $minTmp = ($t.SensorTable_Data | Measure-Object -Minimum).Minimum; \
-               $maxTmp = ($t.SensorTable_Data | Measure-Object -Maximum).Maximum; \
-               Write-Output \"$fid|$sid|$active|$minSpd|$maxSpd|$minTmp|$maxTmp|$speeds|$temps\" \
-             }";
-
-        let output = Self::ps_command(script)?;
+        let output = self.ps_read(FAN_CURVES_SCRIPT)?;
         let mut curves = Vec::new();
 
         for line in output.lines() {
@@ -588,9 +1273,12 @@ impl FanController for LenovoFanController {
                 max_temp,
                 points,
                 active,
+                sensor_label: None,
             });
         }
 
+        self.remember_tables(&curves);
+        label_sensors(&mut curves);
         Ok(curves)
     }
 }
@@ -601,6 +1289,9 @@ impl FanController for LenovoFanController {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
     use super::*;
 
     // -- parse_fan_id -------------------------------------------------------
@@ -621,59 +1312,6 @@ mod tests {
         assert!(parse_fan_id("Fan0").is_err());
     }
 
-    // -- pwm_to_rpm / rpm_to_pwm -------------------------------------------
-
-    #[test]
-    fn pwm_to_rpm_boundaries() {
-        // PWM 0 → min RPM
-        assert_eq!(pwm_to_rpm(1600, 4800, 0), 1600);
-        // PWM 255 → max RPM
-        assert_eq!(pwm_to_rpm(1600, 4800, 255), 4800);
-    }
-
-    #[test]
-    fn pwm_to_rpm_midrange() {
-        // PWM 128 ≈ mid-range
-        let mid = pwm_to_rpm(1600, 4800, 128);
-        assert!(mid > 1600 && mid < 4800, "mid was {mid}");
-    }
-
-    #[test]
-    fn pwm_to_rpm_custom_range() {
-        assert_eq!(pwm_to_rpm(2000, 5400, 0), 2000);
-        assert_eq!(pwm_to_rpm(2000, 5400, 255), 5400);
-    }
-
-    #[test]
-    fn rpm_to_pwm_boundaries() {
-        // At or below min → 0
-        assert_eq!(rpm_to_pwm(1600, 4800, 1600), 0);
-        assert_eq!(rpm_to_pwm(1600, 4800, 0), 0);
-        // At or above max → 255
-        assert_eq!(rpm_to_pwm(1600, 4800, 4800), 255);
-        assert_eq!(rpm_to_pwm(1600, 4800, 9999), 255);
-    }
-
-    #[test]
-    fn rpm_to_pwm_midrange() {
-        let mid_rpm = 3200; // exactly halfway in 1600..4800
-        let pwm = rpm_to_pwm(1600, 4800, mid_rpm);
-        assert!(pwm > 100 && pwm < 160, "pwm was {pwm}");
-    }
-
-    #[test]
-    fn pwm_rpm_roundtrip() {
-        // pwm → rpm → pwm should be close to the original
-        let original_pwm: u8 = 100;
-        let rpm = pwm_to_rpm(1600, 4800, original_pwm);
-        let recovered_pwm = rpm_to_pwm(1600, 4800, rpm);
-        let diff = (original_pwm as i16 - recovered_pwm as i16).unsigned_abs();
-        assert!(
-            diff <= 1,
-            "original={original_pwm} recovered={recovered_pwm}"
-        );
-    }
-
     // -- parse_fullspeed ----------------------------------------------------
 
     #[test]
@@ -743,10 +1381,11 @@ mod tests {
         );
         let mut curves = HashMap::new();
 
-        let fan = parse_fan_line(line, &ranges, &mut curves, false).expect("should parse");
+        let fan = parse_fan_line(line, &ranges, &HashMap::new(), &mut curves, false)
+            .expect("should parse");
         assert_eq!(fan.id, "fan0");
-        assert!(fan.label.contains("CPU Fan"));
-        assert!(fan.label.contains("45"));
+        assert_eq!(fan.label, "CPU Fan");
+        assert_eq!(fan.temperature, Some(45));
         assert_eq!(fan.speed_rpm, 2100);
         assert!(fan.pwm.is_some());
         assert!(fan.controllable);
@@ -755,13 +1394,31 @@ mod tests {
         assert_eq!(fan.max_rpm, Some(4800));
     }
 
+    #[test]
+    fn parse_fan_line_flags_stall_in_full_speed_mode() {
+        let ranges = HashMap::new();
+        let mut curves = HashMap::new();
+        let line = "FAN|0|3|0|45";
+        assert!(
+            parse_fan_line(line, &ranges, &HashMap::new(), &mut curves, true)
+                .unwrap()
+                .stalled
+        );
+        assert!(
+            !parse_fan_line(line, &ranges, &HashMap::new(), &mut curves, false)
+                .unwrap()
+                .stalled
+        );
+    }
+
     #[test]
     fn parse_fan_line_gpu() {
         let line = "FAN|1|4|3200|52";
         let ranges = HashMap::new();
         let mut curves = HashMap::new();
 
-        let fan = parse_fan_line(line, &ranges, &mut curves, true).expect("should parse");
+        let fan = parse_fan_line(line, &ranges, &HashMap::new(), &mut curves, true)
+            .expect("should parse");
         assert_eq!(fan.id, "fan1");
         assert!(fan.label.contains("GPU Fan"));
         assert!(fan.full_speed_active);
@@ -774,8 +1431,8 @@ mod tests {
     fn parse_fan_line_too_short() {
         let ranges = HashMap::new();
         let mut curves = HashMap::new();
-        assert!(parse_fan_line("FAN|0|3", &ranges, &mut curves, false).is_none());
-        assert!(parse_fan_line("", &ranges, &mut curves, false).is_none());
+        assert!(parse_fan_line("FAN|0|3", &ranges, &HashMap::new(), &mut curves, false).is_none());
+        assert!(parse_fan_line("", &ranges, &HashMap::new(), &mut curves, false).is_none());
     }
 
     // -- encode_fan_table_bytes ---------------------------------------------
@@ -855,129 +1512,184 @@ mod tests {
         }
     }
 
-    // -- validate_custom_curve -----------------------------------------------
+    // -- table fitting -------------------------------------------------------
+
+    #[test]
+    fn fit_steps_to_table_keeps_full_tables() {
+        let steps = [1, 1, 1, 1, 2, 4, 6, 7, 8, 10];
+        assert_eq!(fit_steps_to_table(&steps, 10), steps);
+        assert_eq!(fit_steps_to_table(&steps, 0), steps);
+    }
+
+    #[test]
+    fn fit_steps_to_table_pads_short_tables_with_last_step() {
+        let steps = [1, 1, 1, 1, 2, 4, 6, 7, 8, 10];
+        assert_eq!(
+            fit_steps_to_table(&steps, 8),
+            [1, 1, 1, 1, 2, 4, 6, 7, 7, 7]
+        );
+    }
+
+    #[test]
+    fn table_len_for_known_pair() {
+        let sizes = HashMap::from([((0, 3), 10), ((1, 4), 8)]);
+        assert_eq!(table_len_for(&sizes, 1, 4).unwrap(), 8);
+    }
+
+    #[test]
+    fn table_len_for_unknown_sensor_lists_known_ones() {
+        let sizes = HashMap::from([((0, 3), 10), ((0, 1), 10), ((1, 4), 10)]);
+        let error = table_len_for(&sizes, 0, 4).unwrap_err().to_string();
+        assert!(error.contains("fan 0 sensor 4"), "{error}");
+        assert!(error.contains("known sensors: 1, 3"), "{error}");
+        let error = table_len_for(&sizes, 2, 3).unwrap_err().to_string();
+        assert!(error.contains("no tables for this fan"), "{error}");
+    }
+
+    // -- format_ps_byte_array ------------------------------------------------
+
+    #[test]
+    fn format_ps_byte_array_simple() {
+        let bytes = [1u8, 0, 0, 0, 0, 0];
+        assert_eq!(format_ps_byte_array(&bytes), "@(1,0,0,0,0,0)");
+    }
 
     #[test]
-    fn validate_custom_curve_llt_v2_minimum() {
+    fn format_ps_byte_array_full_buffer() {
         let curve = CustomFanCurve {
             fan_id: 0,
             sensor_id: 3,
             steps: [1, 1, 1, 1, 1, 1, 1, 1, 3, 5],
         };
-        assert!(validate_custom_curve(&curve).is_ok());
+        let bytes = encode_fan_table_bytes(&curve);
+        let ps = format_ps_byte_array(&bytes);
+        assert!(ps.starts_with("@("));
+        assert!(ps.ends_with(')'));
+        // Should have 64 comma-separated values
+        let values: Vec<&str> = ps
+            .trim_start_matches("@(")
+            .trim_end_matches(')')
+            .split(',')
+            .collect();
+        assert_eq!(values.len(), 64);
     }
 
+    // -- parse_id_values ----------------------------------------------------
+
     #[test]
-    fn validate_custom_curve_all_max() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [10; 10],
-        };
-        assert!(validate_custom_curve(&curve).is_ok());
+    fn parse_id_values_reads_every_fan() {
+        let speeds = parse_id_values("0|2400\r\n1|3100\n");
+        assert_eq!(speeds.len(), 2);
+        assert_eq!(speeds[&0], 2400);
+        assert_eq!(speeds[&1], 3100);
     }
 
     #[test]
-    fn validate_custom_curve_ascending() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [0, 1, 2, 3, 4, 5, 6, 7, 8, 10],
-        };
-        assert!(validate_custom_curve(&curve).is_ok());
+    fn parse_id_values_skips_malformed_lines() {
+        let speeds = parse_id_values("0|2400\n1|\ngarbage\n");
+        assert_eq!(speeds.len(), 1);
+        assert_eq!(speeds[&0], 2400);
     }
 
+    // -- script builders -----------------------------------------------------
+
     #[test]
-    fn validate_custom_curve_flat_then_ramp() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [0, 0, 0, 0, 0, 0, 0, 0, 5, 10],
-        };
-        assert!(validate_custom_curve(&curve).is_ok());
+    fn scripts_fit_on_one_session_line() {
+        for (label, script) in scripts() {
+            assert!(!script.contains('\n'), "{label} spans several lines");
+        }
     }
 
     #[test]
-    fn validate_custom_curve_step_exceeds_max() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [1, 1, 1, 1, 1, 1, 1, 1, 3, 11],
-        };
-        let err = validate_custom_curve(&curve).unwrap_err();
-        assert!(err.to_string().contains("exceeds maximum"));
+    fn sensor_temps_script_queries_all_sensors_at_once() {
+        let script = sensor_temps_script(&[3, 4]);
+        assert_eq!(script.matches("Get-WmiObject").count(), 1);
+        assert!(script.contains("foreach ($sid in @(3,4))"));
+        assert!(script.contains("Fan_GetCurrentSensorTemperature($sid)"));
     }
 
     #[test]
-    fn validate_custom_curve_decreasing_steps() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [5, 4, 3, 2, 1, 1, 1, 1, 3, 5],
-        };
-        let err = validate_custom_curve(&curve).unwrap_err();
-        assert!(err.to_string().contains("non-decreasing"));
+    fn fan_speeds_script_queries_all_fans_at_once() {
+        let script = fan_speeds_script(&[0, 1]);
+        assert_eq!(script.matches("Get-WmiObject").count(), 1);
+        assert!(script.contains("foreach ($fid in @(0,1))"));
+        assert!(script.contains("Fan_GetCurrentFanSpeed($fid)"));
     }
 
     #[test]
-    fn validate_custom_curve_step8_too_low() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [0, 0, 0, 0, 0, 0, 0, 0, 2, 5],
-        };
-        let err = validate_custom_curve(&curve).unwrap_err();
-        assert!(err.to_string().contains("step 8"));
+    fn set_fan_speed_script_embeds_arguments() {
+        let script = set_fan_speed_script(1, 3200);
+        assert!(script.contains("Fan_SetCurrentFanSpeed(1, 3200)"));
     }
 
     #[test]
-    fn validate_custom_curve_step9_too_low() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [0, 0, 0, 0, 0, 0, 0, 0, 3, 4],
-        };
-        let err = validate_custom_curve(&curve).unwrap_err();
-        assert!(err.to_string().contains("step 9"));
+    fn full_speed_script_on_and_off() {
+        assert!(full_speed_script(true).contains("Fan_Set_FullSpeed(1)"));
+        assert!(full_speed_script(false).contains("Fan_Set_FullSpeed(0)"));
     }
 
     #[test]
-    fn validate_custom_curve_single_decrease_at_end() {
+    fn set_table_script_embeds_encoded_curve() {
         let curve = CustomFanCurve {
             fan_id: 0,
             sensor_id: 3,
-            steps: [1, 2, 3, 4, 5, 6, 7, 8, 10, 9],
+            steps: [1, 1, 1, 1, 1, 1, 1, 1, 3, 5],
         };
-        let err = validate_custom_curve(&curve).unwrap_err();
-        assert!(err.to_string().contains("non-decreasing"));
+        let script = set_table_script(&curve);
+        let ps_array = format_ps_byte_array(&encode_fan_table_bytes(&curve));
+        assert!(script.contains(&format!("[byte[]]$table = {ps_array};")));
+        assert!(script.contains("Fan_Set_Table($table)"));
     }
 
-    // -- format_ps_byte_array ------------------------------------------------
+    #[test]
+    fn get_table_script_embeds_arguments() {
+        let script = get_table_script(1, 4);
+        assert!(script.contains("Fan_Get_Table(1, 4)"));
+        assert!(script.contains("'{0:X2}' -f $_"));
+    }
 
     #[test]
-    fn format_ps_byte_array_simple() {
-        let bytes = [1u8, 0, 0, 0, 0, 0];
-        assert_eq!(format_ps_byte_array(&bytes), "@(1,0,0,0,0,0)");
+    fn parse_raw_table_reads_hex_bytes() {
+        assert_eq!(
+            parse_raw_table("noise\nRAW|01 00 0A FF\n").unwrap(),
+            vec![0x01, 0x00, 0x0A, 0xFF]
+        );
+        // Firmware that answers without a byte array.
+        assert!(parse_raw_table("RAW|").unwrap().is_empty());
+        assert!(parse_raw_table("RAW|01 XY").is_err());
+        assert!(parse_raw_table("").is_err());
     }
 
     #[test]
-    fn format_ps_byte_array_full_buffer() {
-        let curve = CustomFanCurve {
-            fan_id: 0,
-            sensor_id: 3,
-            steps: [1, 1, 1, 1, 1, 1, 1, 1, 3, 5],
+    fn scripts_are_labelled_and_non_empty() {
+        let scripts = scripts();
+        assert!(scripts.iter().any(|(label, _)| label == "discover"));
+        for (label, script) in &scripts {
+            assert!(!script.trim().is_empty(), "{label} script is empty");
+        }
+    }
+
+    #[test]
+    fn describe_write_names_wmi_method_and_script() {
+        let controller = LenovoFanController::new();
+        let lines = controller
+            .describe_write(&HardwareWrite::FullSpeed(true))
+            .unwrap();
+        assert_eq!(lines[0], "WMI: Fan_Set_FullSpeed(1)");
+        assert_eq!(lines[1], format!("PowerShell: {}", full_speed_script(true)));
+
+        let auto = HardwareWrite::Pwm {
+            fan_id: "fan0".to_string(),
+            pwm: 0,
         };
-        let bytes = encode_fan_table_bytes(&curve);
-        let ps = format_ps_byte_array(&bytes);
-        assert!(ps.starts_with("@("));
-        assert!(ps.ends_with(')'));
-        // Should have 64 comma-separated values
-        let values: Vec<&str> = ps
-            .trim_start_matches("@(")
-            .trim_end_matches(')')
-            .split(',')
-            .collect();
-        assert_eq!(values.len(), 64);
+        assert_eq!(
+            controller.describe_write(&auto).unwrap()[0],
+            "WMI: Fan_Set_FullSpeed(0)"
+        );
+        let lines = controller
+            .describe_write(&HardwareWrite::ThermalMode(ThermalMode::Quiet))
+            .unwrap();
+        assert_eq!(lines[0], "WMI: SetSmartFanMode(1)");
     }
 
     // -- integration: full discover output ----------------------------------
@@ -1025,7 +1737,13 @@ FAN|1|4|0|31";
             if !line.starts_with("FAN|") {
                 continue;
             }
-            if let Some(fan) = parse_fan_line(line, &rpm_ranges, &mut curves_by_fan, full_speed) {
+            if let Some(fan) = parse_fan_line(
+                line,
+                &rpm_ranges,
+                &HashMap::new(),
+                &mut curves_by_fan,
+                full_speed,
+            ) {
                 fans.push(fan);
             }
         }
@@ -1038,4 +1756,405 @@ FAN|1|4|0|31";
         assert_eq!(fans[1].speed_rpm, 0);
         assert_eq!(fans[1].curves.len(), 1);
     }
+
+    // -- write paths through a recording runner ------------------------------
+
+    /// Records every script and answers from canned replies. A script with
+    /// several queued replies gets them in order; the last one repeats.
+    /// Scripts without a reply get empty output.
+    #[derive(Default)]
+    struct MockRunner {
+        calls: RefCell<Vec<String>>,
+        replies: RefCell<HashMap<String, VecDeque<String>>>,
+    }
+
+    impl MockRunner {
+        fn reply(self, script: impl Into<String>, outputs: &[&str]) -> Self {
+            self.replies.borrow_mut().insert(
+                script.into(),
+                outputs.iter().map(|output| output.to_string()).collect(),
+            );
+            self
+        }
+    }
+
+    impl CommandRunner for MockRunner {
+        fn run(&self, script: &str) -> Result<String, FanControlError> {
+            self.calls.borrow_mut().push(script.to_string());
+            let mut replies = self.replies.borrow_mut();
+            let Some(queue) = replies.get_mut(script) else {
+                return Ok(String::new());
+            };
+            Ok(if queue.len() > 1 {
+                queue.pop_front().unwrap_or_default()
+            } else {
+                queue.front().cloned().unwrap_or_default()
+            })
+        }
+    }
+
+    /// `get_fan_curves` output with one 10-point table for fan 0 sensor 3.
+    const CURVES_OUTPUT: &str = "0|3|1|1600|4800|58|100|\
+        1600,1800,2000,2200,2400,2800,3200,3600,4200,4800|58,63,68,73,78,83,88,93,97,100";
+
+    fn mock_controller(runner: MockRunner) -> LenovoFanController<MockRunner> {
+        LenovoFanController::with_runner(runner)
+    }
+
+    fn calls(controller: &LenovoFanController<MockRunner>) -> Vec<String> {
+        controller.runner.calls.borrow().clone()
+    }
+
+    const CURVE: CustomFanCurve = CustomFanCurve {
+        fan_id: 0,
+        sensor_id: 3,
+        steps: [1, 1, 1, 1, 2, 4, 6, 7, 8, 10],
+    };
+
+    #[test]
+    fn pwm_255_and_0_toggle_full_speed() {
+        let controller = mock_controller(MockRunner::default());
+        controller.set_pwm("fan0", 255).unwrap();
+        controller.set_pwm("fan1", 0).unwrap();
+        assert_eq!(
+            calls(&controller),
+            vec![full_speed_script(true), full_speed_script(false)]
+        );
+    }
+
+    #[test]
+    fn mid_pwm_sets_current_fan_speed() {
+        let controller = mock_controller(MockRunner::default());
+        controller.set_pwm("fan1", 128).unwrap();
+        let rpm = pwm_to_rpm(DEFAULT_MIN_RPM, DEFAULT_MAX_RPM, 128);
+        assert_eq!(
+            calls(&controller),
+            vec![DISCOVER_SCRIPT.to_string(), set_fan_speed_script(1, rpm)]
+        );
+    }
+
+    /// Discover output of a Legion with a third (system) fan.
+    const THREE_FAN_OUTPUT: &str = "\
+FULLSPEED|0
+TABLE|0|3|1|1600|4800|58|100|1600,2100,2700,3400,4200,4800|58,63,68,73,85,100
+TABLE|1|4|1|1800|4800|63|95|1800,2400,3200,4800|63,73,85,95
+TABLE|2|5|1|1000|3000|50|90|1000,2000,3000|50,70,90
+FAN|0|3|2100|45
+FAN|1|4|1800|50
+FAN|2|5|1200|40";
+
+    #[test]
+    fn discover_lists_third_fan_with_own_range() {
+        let runner = MockRunner::default().reply(DISCOVER_SCRIPT, &[THREE_FAN_OUTPUT]);
+        let fans = mock_controller(runner).discover().unwrap();
+        let fan = fans.iter().find(|fan| fan.id == "fan2").unwrap();
+        assert_eq!(fan.label, "Fan 2");
+        assert!(fan.controllable);
+        assert_eq!((fan.min_rpm, fan.max_rpm), (Some(1000), Some(3000)));
+        assert_eq!(fan.speed_rpm, 1200);
+    }
+
+    #[test]
+    fn discover_and_curves_label_cpu_and_gpu_sensors() {
+        let runner = MockRunner::default()
+            .reply(DISCOVER_SCRIPT, &[THREE_FAN_OUTPUT])
+            .reply(FAN_CURVES_SCRIPT, &[CURVES_OUTPUT]);
+        let controller = mock_controller(runner);
+        let names: Vec<String> = controller
+            .discover()
+            .unwrap()
+            .iter()
+            .flat_map(|fan| fan.curves.iter().map(FanCurve::sensor_name))
+            .collect();
+        assert_eq!(names, ["Sensor 3 (CPU)", "Sensor 4 (GPU)", "Sensor 5"]);
+        let curves = controller.get_fan_curves().unwrap();
+        assert_eq!(curves[0].sensor_label.as_deref(), Some("CPU"));
+    }
+
+    #[test]
+    fn mid_pwm_on_third_fan_uses_its_range() {
+        let runner = MockRunner::default().reply(DISCOVER_SCRIPT, &[THREE_FAN_OUTPUT]);
+        let controller = mock_controller(runner);
+        controller.set_pwm("fan2", 128).unwrap();
+        assert_eq!(
+            calls(&controller),
+            vec![
+                DISCOVER_SCRIPT.to_string(),
+                set_fan_speed_script(2, pwm_to_rpm(1000, 3000, 128)),
+            ]
+        );
+    }
+
+    fn calibrated(runner: MockRunner, fan_id: &str) -> LenovoFanController<MockRunner> {
+        let points = vec![
+            CalibrationPoint { pwm: 51, rpm: 3000 },
+            CalibrationPoint {
+                pwm: 153,
+                rpm: 4200,
+            },
+        ];
+        mock_controller(runner).with_calibration(&BTreeMap::from([(fan_id.to_string(), points)]))
+    }
+
+    #[test]
+    fn calibration_maps_pwm_writes_and_reads() {
+        let runner = MockRunner::default().reply(DISCOVER_SCRIPT, &[THREE_FAN_OUTPUT]);
+        let controller = calibrated(runner, "fan0");
+        controller.set_pwm("fan0", 102).unwrap();
+        controller.set_pwm("fan1", 102).unwrap();
+        assert_eq!(
+            calls(&controller)[1..],
+            [
+                set_fan_speed_script(0, 3600),
+                set_fan_speed_script(1, pwm_to_rpm(1800, 4800, 102)),
+            ]
+        );
+
+        // fan0 reads 2100 RPM: 1600..3000 spans PWM 0..51.
+        let fans = controller.discover().unwrap();
+        assert_eq!(fans[0].pwm, Some(18));
+        assert_eq!(fans[1].pwm, Some(rpm_to_pwm(1800, 4800, 1800)));
+    }
+
+    #[test]
+    fn invalid_calibration_leaves_fan_linear() {
+        let runner = MockRunner::default().reply(DISCOVER_SCRIPT, &[THREE_FAN_OUTPUT]);
+        let controller = calibrated(runner, "cpu");
+        assert!(controller.calibration.is_empty());
+    }
+
+    #[test]
+    fn zero_width_range_requests_its_single_speed() {
+        let output = "\
+FULLSPEED|0
+TABLE|0|3|1|2400|2400|58|100|2400,2400|58,100
+FAN|0|3|2400|45";
+        let runner = MockRunner::default().reply(DISCOVER_SCRIPT, &[output]);
+        let controller = mock_controller(runner);
+        let fans = controller.discover().unwrap();
+        assert_eq!((fans[0].min_rpm, fans[0].max_rpm), (Some(2400), Some(2400)));
+        assert_eq!(fans[0].pwm, Some(0));
+
+        controller.set_pwm("fan0", 128).unwrap();
+        assert_eq!(calls(&controller)[1], set_fan_speed_script(0, 2400));
+    }
+
+    #[test]
+    fn get_speed_reads_third_fan_on_fresh_controller() {
+        let runner = MockRunner::default().reply(DISCOVER_SCRIPT, &[THREE_FAN_OUTPUT]);
+        assert_eq!(mock_controller(runner).get_speed("fan2").unwrap(), 1200);
+    }
+
+    #[test]
+    fn unlisted_fan_is_not_found_and_not_written() {
+        let runner = MockRunner::default().reply(DISCOVER_SCRIPT, &[THREE_FAN_OUTPUT]);
+        let controller = mock_controller(runner);
+        assert!(matches!(
+            controller.set_pwm("fan3", 128),
+            Err(FanControlError::FanNotFound(id)) if id == "fan3"
+        ));
+        assert!(matches!(
+            controller.set_rpm("fan3", 2000),
+            Err(FanControlError::FanNotFound(_))
+        ));
+        assert_eq!(calls(&controller), vec![DISCOVER_SCRIPT.to_string()]);
+    }
+
+    #[test]
+    fn probe_requires_fan_method_class() {
+        let legion = MockRunner::default().reply(
+            WMI_CLASSES_SCRIPT,
+            &["LENOVO_FAN_METHOD|1\nLENOVO_FAN_TABLE_DATA|1\nLENOVO_GAMEZONE_DATA|0"],
+        );
+        assert!(mock_controller(legion).probe().is_ok());
+
+        let other = MockRunner::default().reply(
+            WMI_CLASSES_SCRIPT,
+            &["LENOVO_FAN_METHOD|0\nLENOVO_FAN_TABLE_DATA|0\nLENOVO_GAMEZONE_DATA|0"],
+        );
+        let error = mock_controller(other).probe().unwrap_err().to_string();
+        assert!(error.contains("--backend wmi"), "{error}");
+    }
+
+    #[test]
+    fn wmi_classes_check_names_missing_classes() {
+        let present = MockRunner::default().reply(
+            WMI_CLASSES_SCRIPT,
+            &["LENOVO_FAN_METHOD|1\nLENOVO_FAN_TABLE_DATA|1\nLENOVO_GAMEZONE_DATA|1"],
+        );
+        assert!(wmi_classes_check(&present).ok);
+
+        let missing = MockRunner::default().reply(
+            WMI_CLASSES_SCRIPT,
+            &["LENOVO_FAN_METHOD|1\nLENOVO_FAN_TABLE_DATA|0\nLENOVO_GAMEZONE_DATA|1"],
+        );
+        let check = wmi_classes_check(&missing);
+        assert!(!check.ok);
+        assert!(
+            check.detail.starts_with("missing LENOVO_FAN_TABLE_DATA:"),
+            "{}",
+            check.detail
+        );
+    }
+
+    #[test]
+    fn set_custom_curve_writes_table_in_custom_mode() {
+        let runner = MockRunner::default()
+            .reply(FAN_CURVES_SCRIPT, &[CURVES_OUTPUT])
+            .reply(GET_SMART_FAN_MODE_SCRIPT, &["Data|255"]);
+        let controller = mock_controller(runner);
+        controller.set_custom_curve(&CURVE).unwrap();
+        assert_eq!(
+            calls(&controller),
+            vec![
+                FAN_CURVES_SCRIPT.to_string(),
+                GET_SMART_FAN_MODE_SCRIPT.to_string(),
+                set_table_script(&CURVE),
+            ]
+        );
+    }
+
+    #[test]
+    fn set_custom_curve_switches_to_custom_mode_first() {
+        let runner = MockRunner::default()
+            .reply(FAN_CURVES_SCRIPT, &[CURVES_OUTPUT])
+            .reply(GET_SMART_FAN_MODE_SCRIPT, &["Data|2", "Data|255"]);
+        let controller = mock_controller(runner);
+        controller.set_custom_curve(&CURVE).unwrap();
+        let calls = calls(&controller);
+        assert_eq!(calls[2], set_smart_fan_mode_script(SMART_FAN_MODE_CUSTOM));
+        assert_eq!(calls.last(), Some(&set_table_script(&CURVE)));
+    }
+
+    #[test]
+    fn set_custom_curve_skips_write_when_mode_switch_is_ignored() {
+        let runner = MockRunner::default()
+            .reply(FAN_CURVES_SCRIPT, &[CURVES_OUTPUT])
+            .reply(GET_SMART_FAN_MODE_SCRIPT, &["Data|2"]);
+        let controller = mock_controller(runner);
+        assert!(matches!(
+            controller.set_custom_curve(&CURVE),
+            Err(FanControlError::SmartFanModeRequired(2))
+        ));
+        assert!(!calls(&controller)
+            .iter()
+            .any(|script| script.contains("Fan_Set_Table")));
+    }
+
+    #[test]
+    fn set_custom_curve_refuses_unknown_tables_without_writing() {
+        let runner = MockRunner::default().reply(FAN_CURVES_SCRIPT, &[CURVES_OUTPUT]);
+        let controller = mock_controller(runner);
+        let curve = CustomFanCurve {
+            sensor_id: 4,
+            ..CURVE
+        };
+        assert!(controller.set_custom_curve(&curve).is_err());
+        assert_eq!(calls(&controller), vec![FAN_CURVES_SCRIPT.to_string()]);
+    }
+
+    // -- direct WMI methods ---------------------------------------------------
+
+    /// Logs each method it is asked for into a shared list; fails them all
+    /// when `fail` is set.
+    struct FakeWmi {
+        calls: Rc<RefCell<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl FakeWmi {
+        fn record(&self, call: String) -> Result<(), FanControlError> {
+            self.calls.borrow_mut().push(call);
+            if self.fail {
+                return Err(FanControlError::Platform("ExecMethod failed".to_string()));
+            }
+            Ok(())
+        }
+    }
+
+    impl WmiMethods for FakeWmi {
+        fn fan_speeds(&self, fan_ids: &[u32]) -> Result<HashMap<u32, u32>, FanControlError> {
+            self.record(format!("Fan_GetCurrentFanSpeed{fan_ids:?}"))?;
+            Ok(fan_ids.iter().map(|&id| (id, 2000)).collect())
+        }
+
+        fn sensor_temperatures(
+            &self,
+            sensor_ids: &[u32],
+        ) -> Result<HashMap<u32, u32>, FanControlError> {
+            self.record(format!("Fan_GetCurrentSensorTemperature{sensor_ids:?}"))?;
+            Ok(sensor_ids.iter().map(|&id| (id, 50)).collect())
+        }
+
+        fn set_fan_speed(&self, fan_id: u32, rpm: u32) -> Result<(), FanControlError> {
+            self.record(format!("Fan_SetCurrentFanSpeed({fan_id}, {rpm})"))
+        }
+
+        fn set_full_speed(&self, enabled: bool) -> Result<(), FanControlError> {
+            self.record(format!("Fan_Set_FullSpeed({})", u8::from(enabled)))
+        }
+
+        fn smart_fan_mode(&self) -> Result<Option<u32>, FanControlError> {
+            self.record("GetSmartFanMode".to_string())?;
+            Ok(Some(SMART_FAN_MODE_CUSTOM))
+        }
+
+        fn set_smart_fan_mode(&self, mode: u32) -> Result<(), FanControlError> {
+            self.record(format!("SetSmartFanMode({mode})"))
+        }
+    }
+
+    fn direct_controller(
+        runner: MockRunner,
+        fail: bool,
+    ) -> (LenovoFanController<MockRunner>, Rc<RefCell<Vec<String>>>) {
+        let direct_calls = Rc::new(RefCell::new(Vec::new()));
+        let methods = FakeWmi {
+            calls: Rc::clone(&direct_calls),
+            fail,
+        };
+        let controller = mock_controller(runner).with_wmi_methods(Box::new(methods));
+        (controller, direct_calls)
+    }
+
+    #[test]
+    fn direct_methods_replace_method_scripts() {
+        let runner = MockRunner::default().reply(FAN_CURVES_SCRIPT, &[CURVES_OUTPUT]);
+        let (controller, direct_calls) = direct_controller(runner, false);
+        controller.set_pwm("fan0", 255).unwrap();
+        controller.set_auto("fan0").unwrap();
+        controller.set_rpm("fan0", 3000).unwrap();
+        assert_eq!(controller.get_smart_fan_mode().unwrap(), Some(255));
+        controller.set_smart_fan_mode(2).unwrap();
+        let temps = controller.sensor_temperatures().unwrap();
+        assert_eq!(temps[0].temperature, 50);
+        assert_eq!(
+            *direct_calls.borrow(),
+            [
+                "Fan_Set_FullSpeed(1)",
+                "Fan_Set_FullSpeed(0)",
+                "Fan_SetCurrentFanSpeed(0, 3000)",
+                "GetSmartFanMode",
+                "SetSmartFanMode(2)",
+                "Fan_GetCurrentSensorTemperature[3]",
+            ]
+        );
+        // Only the reads the direct methods do not cover went to PowerShell.
+        assert_eq!(
+            calls(&controller),
+            [DISCOVER_SCRIPT.to_string(), FAN_CURVES_SCRIPT.to_string()]
+        );
+    }
+
+    #[test]
+    fn failed_direct_call_falls_back_to_powershell_for_good() {
+        let (controller, direct_calls) = direct_controller(MockRunner::default(), true);
+        controller.set_full_speed(true).unwrap();
+        controller.set_full_speed(false).unwrap();
+        assert_eq!(*direct_calls.borrow(), ["Fan_Set_FullSpeed(1)"]);
+        assert_eq!(
+            calls(&controller),
+            [full_speed_script(true), full_speed_script(false)]
+        );
+    }
 }
@@ -0,0 +1,210 @@
+//! Direct COM transport for the Lenovo backend (`wmi-direct` feature).
+//!
+//! Calls `LENOVO_FAN_METHOD` and `LENOVO_GAMEZONE_DATA` methods through
+//! `IWbemServices::ExecMethod` on the single instance of each class, so the
+//! hot paths skip the PowerShell session. Parameter names are the ones in
+//! the classes' MOF; the out-parameter names match what the scripts read.
+
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use wmi::result_enumerator::IWbemClassWrapper;
+use wmi::{COMLibrary, Variant, WMIConnection};
+
+use super::lenovo::WmiMethods;
+use crate::errors::FanControlError;
+
+const NAMESPACE: &str = "root\\WMI";
+const FAN_METHOD_CLASS: &str = "LENOVO_FAN_METHOD";
+const GAMEZONE_CLASS: &str = "LENOVO_GAMEZONE_DATA";
+
+/// Any WMI class instance, for its object path.
+#[derive(Deserialize)]
+struct Instance {
+    #[serde(rename = "__Path")]
+    path: String,
+}
+
+/// `ExecMethod` on the Lenovo WMI class instances.
+pub struct ComMethods {
+    wmi_connection: WMIConnection,
+    /// Object path of the `LENOVO_FAN_METHOD` instance.
+    fan_method: String,
+    /// Object path of the `LENOVO_GAMEZONE_DATA` instance, if the model has one.
+    gamezone: Option<String>,
+}
+
+impl ComMethods {
+    /// Connect to `root\WMI` and find the class instances.
+    pub fn connect() -> Result<Self, FanControlError> {
+        let com_library = COMLibrary::new().map_err(|e| {
+            FanControlError::Platform(format!("failed to initialise COM library: {e}"))
+        })?;
+        let wmi_connection =
+            WMIConnection::with_namespace_path(NAMESPACE, com_library).map_err(|e| {
+                FanControlError::Platform(format!("failed to connect to WMI ({NAMESPACE}): {e}"))
+            })?;
+        let fan_method = instance_path(&wmi_connection, FAN_METHOD_CLASS)?.ok_or_else(|| {
+            FanControlError::Platform(format!("no {FAN_METHOD_CLASS} instance in {NAMESPACE}"))
+        })?;
+        let gamezone = instance_path(&wmi_connection, GAMEZONE_CLASS)?;
+        Ok(Self {
+            wmi_connection,
+            fan_method,
+            gamezone,
+        })
+    }
+
+    /// Run `method` with integer in-parameters and return its out-parameters.
+    fn call(
+        &self,
+        class: &str,
+        path: &str,
+        method: &str,
+        params: &[(&str, u32)],
+    ) -> Result<Option<IWbemClassWrapper>, FanControlError> {
+        let in_params = params
+            .iter()
+            .map(|&(name, value)| Ok((name.to_string(), int_variant(method, value)?)))
+            .collect::<Result<HashMap<_, _>, FanControlError>>()?;
+        self.wmi_connection
+            .exec_method_native_wrapper(class, path, method, in_params)
+            .map_err(|e| FanControlError::Platform(format!("{method} failed: {e}")))
+    }
+
+    /// Run a `LENOVO_FAN_METHOD` method and read the integer `out` parameter.
+    fn call_u32(
+        &self,
+        method: &str,
+        params: &[(&str, u32)],
+        out: &str,
+    ) -> Result<u32, FanControlError> {
+        let output = self
+            .call(FAN_METHOD_CLASS, &self.fan_method, method, params)?
+            .ok_or_else(|| FanControlError::Platform(format!("{method} returned nothing")))?;
+        let value = output
+            .get_property(out)
+            .map_err(|e| FanControlError::Platform(format!("{method} has no {out}: {e}")))?;
+        variant_u32(&value).ok_or_else(|| {
+            FanControlError::Platform(format!("{method} returned {out} = {value:?}"))
+        })
+    }
+
+    fn gamezone(&self) -> Result<&str, FanControlError> {
+        self.gamezone.as_deref().ok_or_else(|| {
+            FanControlError::Platform(format!("no {GAMEZONE_CLASS} instance in {NAMESPACE}"))
+        })
+    }
+}
+
+impl WmiMethods for ComMethods {
+    fn fan_speeds(&self, fan_ids: &[u32]) -> Result<HashMap<u32, u32>, FanControlError> {
+        fan_ids
+            .iter()
+            .map(|&fan_id| {
+                let speed = self.call_u32(
+                    "Fan_GetCurrentFanSpeed",
+                    &[("FanID", fan_id)],
+                    "CurrentFanSpeed",
+                )?;
+                Ok((fan_id, speed))
+            })
+            .collect()
+    }
+
+    fn sensor_temperatures(
+        &self,
+        sensor_ids: &[u32],
+    ) -> Result<HashMap<u32, u32>, FanControlError> {
+        sensor_ids
+            .iter()
+            .map(|&sensor_id| {
+                let temp = self.call_u32(
+                    "Fan_GetCurrentSensorTemperature",
+                    &[("SensorID", sensor_id)],
+                    "CurrentSensorTemperature",
+                )?;
+                Ok((sensor_id, temp))
+            })
+            .collect()
+    }
+
+    fn set_fan_speed(&self, fan_id: u32, rpm: u32) -> Result<(), FanControlError> {
+        self.call(
+            FAN_METHOD_CLASS,
+            &self.fan_method,
+            "Fan_SetCurrentFanSpeed",
+            &[("FanID", fan_id), ("CurrentFanSpeed", rpm)],
+        )?;
+        Ok(())
+    }
+
+    fn set_full_speed(&self, enabled: bool) -> Result<(), FanControlError> {
+        self.call(
+            FAN_METHOD_CLASS,
+            &self.fan_method,
+            "Fan_Set_FullSpeed",
+            &[("Status", u32::from(enabled))],
+        )?;
+        Ok(())
+    }
+
+    fn smart_fan_mode(&self) -> Result<Option<u32>, FanControlError> {
+        let Some(output) = self.call(GAMEZONE_CLASS, self.gamezone()?, "GetSmartFanMode", &[])?
+        else {
+            return Ok(None);
+        };
+        let names = output
+            .list_properties()
+            .map_err(|e| FanControlError::Platform(format!("GetSmartFanMode failed: {e}")))?;
+        Ok(names
+            .iter()
+            .filter(|name| super::lenovo::is_smart_fan_mode_property(name))
+            .find_map(|name| variant_u32(&output.get_property(name).ok()?)))
+    }
+
+    fn set_smart_fan_mode(&self, mode: u32) -> Result<(), FanControlError> {
+        self.call(
+            GAMEZONE_CLASS,
+            self.gamezone()?,
+            "SetSmartFanMode",
+            &[("Data", mode)],
+        )?;
+        Ok(())
+    }
+}
+
+/// Object path of the first instance of `class`, if there is one.
+fn instance_path(
+    wmi_connection: &WMIConnection,
+    class: &str,
+) -> Result<Option<String>, FanControlError> {
+    let instances: Vec<Instance> = wmi_connection
+        .raw_query(format!("SELECT __Path FROM {class}"))
+        .map_err(|e| FanControlError::Platform(format!("failed to query {class}: {e}")))?;
+    Ok(instances.into_iter().next().map(|instance| instance.path))
+}
+
+/// `value` as a `VT_I4`, the `VARIANT` type WMI expects for `uint32`
+/// properties.
+fn int_variant(method: &str, value: u32) -> Result<Variant, FanControlError> {
+    i32::try_from(value)
+        .map(Variant::I4)
+        .map_err(|_| FanControlError::Platform(format!("{method}: {value} is out of range")))
+}
+
+/// An unsigned integer out-parameter, whatever width the firmware used.
+fn variant_u32(value: &Variant) -> Option<u32> {
+    match *value {
+        Variant::UI1(v) => Some(v.into()),
+        Variant::UI2(v) => Some(v.into()),
+        Variant::UI4(v) => Some(v),
+        Variant::UI8(v) => u32::try_from(v).ok(),
+        Variant::I1(v) => u32::try_from(v).ok(),
+        Variant::I2(v) => u32::try_from(v).ok(),
+        Variant::I4(v) => u32::try_from(v).ok(),
+        Variant::I8(v) => u32::try_from(v).ok(),
+        Variant::Bool(v) => Some(v.into()),
+        _ => None,
+    }
+}
@@ -0,0 +1,275 @@
+//! LibreHardwareMonitor backend for generic Windows machines.
+//!
+//! LibreHardwareMonitor (LHM) publishes every sensor it reads in the
+//! `root\LibreHardwareMonitor` WMI namespace while it is running. Fans are
+//! `Fan` sensors (RPM), the PWM outputs driving them are `Control` sensors
+//! (duty %), and temperatures are `Temperature` sensors. Unlike `Win32_Fan`,
+//! this gives real data on most desktop boards.
+//!
+//! The namespace is read-only, so fans are reported but not controllable
+//! here; LHM's own UI has to set them.
+
+use serde::Deserialize;
+#[cfg(target_os = "windows")]
+use wmi::{COMLibrary, WMIConnection};
+
+#[cfg(target_os = "windows")]
+use super::FanController;
+#[cfg(target_os = "windows")]
+use crate::errors::FanControlError;
+use crate::fan::{percent_to_pwm, Fan, SensorTemperature};
+
+/// WMI namespace LibreHardwareMonitor publishes its sensors in.
+#[cfg(target_os = "windows")]
+const LHM_NAMESPACE: &str = "root\\LibreHardwareMonitor";
+
+// ---------------------------------------------------------------------------
+// WMI data model
+// ---------------------------------------------------------------------------
+
+/// Maps to LHM's `Sensor` class. Only the fields we use are included.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename = "Sensor")]
+#[serde(rename_all = "PascalCase")]
+struct LhmSensor {
+    /// Path such as `/lpc/nct6798d/fan/1`, unique per sensor.
+    identifier: String,
+    /// Human-readable name, e.g. "Fan #1" or "CPU Package".
+    name: String,
+    /// `Fan`, `Control`, `Temperature`, ...
+    sensor_type: String,
+    /// Identifier of the hardware the sensor belongs to.
+    parent: String,
+    /// Current reading in the sensor type's unit.
+    value: f32,
+}
+
+// ---------------------------------------------------------------------------
+// Pure mapping functions (no I/O — testable on any platform)
+// ---------------------------------------------------------------------------
+
+/// Identifier of the `Control` sensor paired with a fan sensor: LHM numbers
+/// a chip's fans and PWM outputs alike, so `/lpc/x/fan/1` pairs with
+/// `/lpc/x/control/1`.
+fn control_identifier(fan_identifier: &str) -> Option<String> {
+    let (hardware, index) = fan_identifier.rsplit_once("/fan/")?;
+    Some(format!("{hardware}/control/{index}"))
+}
+
+/// Build fans from LHM sensors. Each `Fan` sensor becomes one fan, with the
+/// PWM of its paired `Control` sensor when there is one.
+fn fans_from_sensors(sensors: &[LhmSensor]) -> Vec<Fan> {
+    sensors
+        .iter()
+        .filter(|sensor| sensor.sensor_type == "Fan")
+        .map(|sensor| {
+            let control = control_identifier(&sensor.identifier).and_then(|id| {
+                sensors
+                    .iter()
+                    .find(|s| s.sensor_type == "Control" && s.identifier == id)
+            });
+            Fan {
+                id: sensor.identifier.clone(),
+                label: format!("{}: {}", hardware_name(&sensor.parent), sensor.name),
+                speed_rpm: sensor.value.max(0.0).round() as u32,
+                temperature: None,
+                pwm: control.map(|c| percent_to_pwm(c.value.clamp(0.0, 100.0).round() as u8)),
+                pwm_mode: None,
+                controllable: false,
+                min_rpm: None,
+                max_rpm: None,
+                target_rpm: None,
+                curves: Vec::new(),
+                full_speed_active: false,
+                stalled: false,
+            }
+        })
+        .collect()
+}
+
+/// Short hardware name from its identifier, e.g. `nct6798d` for
+/// `/lpc/nct6798d`.
+fn hardware_name(parent: &str) -> &str {
+    parent
+        .rsplit('/')
+        .find(|part| !part.is_empty())
+        .unwrap_or(parent)
+}
+
+/// Every `Temperature` sensor as a reading, numbered in query order.
+fn temperatures_from_sensors(sensors: &[LhmSensor]) -> Vec<SensorTemperature> {
+    sensors
+        .iter()
+        .filter(|sensor| sensor.sensor_type == "Temperature")
+        .enumerate()
+        .map(|(index, sensor)| SensorTemperature {
+            sensor_id: index as u32,
+            label: sensor.name.clone(),
+            temperature: sensor.value.max(0.0).round() as u32,
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Controller
+// ---------------------------------------------------------------------------
+
+/// Read-only controller backed by a running LibreHardwareMonitor.
+#[cfg(target_os = "windows")]
+pub struct LhmFanController {
+    wmi_connection: WMIConnection,
+}
+
+#[cfg(target_os = "windows")]
+impl LhmFanController {
+    /// Connect to the LHM namespace. Fails when LHM is not running.
+    pub fn new() -> Result<Self, FanControlError> {
+        let com_library = COMLibrary::new().map_err(|e| {
+            FanControlError::Platform(format!("failed to initialise COM library: {e}"))
+        })?;
+        let wmi_connection = WMIConnection::with_namespace_path(LHM_NAMESPACE, com_library)
+            .map_err(|e| {
+                FanControlError::Platform(format!(
+                    "failed to connect to WMI ({LHM_NAMESPACE}): {e}"
+                ))
+            })?;
+        Ok(Self { wmi_connection })
+    }
+
+    /// Connect only if LHM is running and reports at least one fan.
+    pub fn detect() -> Option<Self> {
+        let controller = Self::new().ok()?;
+        let sensors = controller.query_sensors().ok()?;
+        sensors
+            .iter()
+            .any(|sensor| sensor.sensor_type == "Fan")
+            .then_some(controller)
+    }
+
+    fn query_sensors(&self) -> Result<Vec<LhmSensor>, FanControlError> {
+        self.wmi_connection
+            .raw_query(
+                "SELECT Identifier, Name, SensorType, Parent, Value FROM Sensor \
+                 WHERE SensorType = 'Fan' OR SensorType = 'Control' \
+                 OR SensorType = 'Temperature'",
+            )
+            .map_err(|error| {
+                FanControlError::Platform(format!("WMI query for LHM sensors failed: {error}"))
+            })
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl FanController for LhmFanController {
+    fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+        Ok(fans_from_sensors(&self.query_sensors()?))
+    }
+
+    fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError> {
+        fans_from_sensors(&self.query_sensors()?)
+            .into_iter()
+            .find(|fan| fan.id == fan_id)
+            .map(|fan| fan.speed_rpm)
+            .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_owned()))
+    }
+
+    fn read_only_hint(&self) -> String {
+        "LibreHardwareMonitor's WMI interface is read-only; set fan speeds in \
+         LibreHardwareMonitor itself"
+            .to_string()
+    }
+
+    fn set_pwm(&self, fan_id: &str, _pwm: u8) -> Result<(), FanControlError> {
+        if !self.discover()?.iter().any(|fan| fan.id == fan_id) {
+            return Err(FanControlError::FanNotFound(fan_id.to_owned()));
+        }
+        Err(FanControlError::NotControllable(format!(
+            "{fan_id}: {}",
+            self.read_only_hint()
+        )))
+    }
+
+    fn sensor_temperatures(&self) -> Result<Vec<SensorTemperature>, FanControlError> {
+        Ok(temperatures_from_sensors(&self.query_sensors()?))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests — pure mapping functions, runnable on any platform
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sensor(identifier: &str, name: &str, sensor_type: &str, value: f32) -> LhmSensor {
+        let parent = identifier
+            .rsplitn(3, '/')
+            .nth(2)
+            .unwrap_or_default()
+            .to_string();
+        LhmSensor {
+            identifier: identifier.to_string(),
+            name: name.to_string(),
+            sensor_type: sensor_type.to_string(),
+            parent,
+            value,
+        }
+    }
+
+    #[test]
+    fn control_identifier_pairs_by_index() {
+        assert_eq!(
+            control_identifier("/lpc/nct6798d/fan/2").as_deref(),
+            Some("/lpc/nct6798d/control/2")
+        );
+        assert_eq!(control_identifier("/lpc/nct6798d/temperature/0"), None);
+    }
+
+    #[test]
+    fn fans_from_sensors_reads_rpm_and_paired_duty() {
+        let sensors = vec![
+            sensor("/lpc/nct6798d/fan/1", "Fan #1", "Fan", 1234.6),
+            sensor("/lpc/nct6798d/fan/2", "Fan #2", "Fan", 0.0),
+            sensor("/lpc/nct6798d/control/1", "Fan Control #1", "Control", 50.0),
+            sensor(
+                "/lpc/nct6798d/temperature/0",
+                "CPU Core",
+                "Temperature",
+                48.0,
+            ),
+        ];
+        let fans = fans_from_sensors(&sensors);
+        assert_eq!(fans.len(), 2);
+        assert_eq!(fans[0].id, "/lpc/nct6798d/fan/1");
+        assert_eq!(fans[0].label, "nct6798d: Fan #1");
+        assert_eq!(fans[0].speed_rpm, 1235);
+        assert_eq!(fans[0].pwm, Some(128));
+        assert!(!fans[0].controllable);
+        assert_eq!(fans[1].pwm, None);
+    }
+
+    #[test]
+    fn temperatures_from_sensors_keeps_only_temperatures() {
+        let sensors = vec![
+            sensor("/lpc/nct6798d/fan/1", "Fan #1", "Fan", 1200.0),
+            sensor(
+                "/intelcpu/0/temperature/0",
+                "CPU Package",
+                "Temperature",
+                71.6,
+            ),
+            sensor(
+                "/gpu-nvidia/0/temperature/0",
+                "GPU Core",
+                "Temperature",
+                65.0,
+            ),
+        ];
+        let temps = temperatures_from_sensors(&sensors);
+        assert_eq!(temps.len(), 2);
+        assert_eq!(temps[0].label, "CPU Package");
+        assert_eq!(temps[0].temperature, 72);
+        assert_eq!(temps[1].sensor_id, 1);
+    }
+}
@@ -1,23 +1,53 @@
 // put id:"linux_discover", label:"Scan sysfs/hwmon", input:"/sys/class/hwmon/", output:"fan_list.internal"
 // put id:"linux_read", label:"Read Fan Speed", input:"/sys/class/hwmon/*/fan*_input"
+// put id:"linux_temps", label:"Read Temperatures", input:"/sys/class/hwmon/*/temp*_input"
 // put id:"linux_write", label:"Write PWM Value", output:"/sys/class/hwmon/*/pwm*"
 
+use std::collections::HashMap;
 use std::fs;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-use super::FanController;
+use log::{info, warn};
+
+use super::{set_rpm_via_pwm, unsupported_write, FanController, HardwareWrite};
+use crate::doctor::Check;
 use crate::errors::FanControlError;
-use crate::fan::Fan;
+use crate::fan::{
+    hysteresis_temperature, is_stalled, rpm_to_pwm, smoothed_temperature, CurveSample, Fan,
+    FanCommand, PwmCurve,
+};
 
 const HWMON_BASE: &str = "/sys/class/hwmon";
 
+/// `pwm{N}_enable` values tried, in order, to hand a fan back to the chip.
+const AUTO_ENABLE_MODES: [&str; 2] = ["2", "0"];
+
+/// hwmon drivers of graphics cards. Their fans have no `fan{N}_label`, so
+/// they are labelled as GPU fans rather than with the generic `Fan {N}`.
+const GPU_CHIPS: [&str; 3] = ["amdgpu", "radeon", "nouveau"];
+
 /// Linux fan controller backed by sysfs/hwmon.
 ///
-/// Discovers fans by scanning `/sys/class/hwmon/hwmon*/fan*_input` and
-/// exposes RPM reading and PWM-based speed control.
+/// Discovers fans by scanning `/sys/class/hwmon/hwmon*/fan*_input`, attaches
+/// a `temp*_input` reading from the same chip, and exposes RPM reading and
+/// PWM-based speed control. Software curves installed with
+/// `set_pwm_curves` are kept in memory and applied by `apply_pwm_curves`.
 pub struct LinuxFanController {
     hwmon_base: PathBuf,
+    curves: Mutex<Vec<PwmCurve>>,
+    /// What each curve was fed on the last tick, by fan id.
+    curve_inputs: Mutex<HashMap<String, CurveInput>>,
+}
+
+/// A curve's input on the previous tick, for smoothing and hysteresis.
+#[derive(Debug, Clone, Copy)]
+struct CurveInput {
+    /// Moving average of the readings (the raw reading without smoothing).
+    smoothed: f64,
+    /// Temperature the curve was evaluated at.
+    followed: u32,
 }
 
 impl LinuxFanController {
@@ -25,13 +55,49 @@ impl LinuxFanController {
     pub fn new() -> Self {
         Self {
             hwmon_base: PathBuf::from(HWMON_BASE),
+            curves: Mutex::new(Vec::new()),
+            curve_inputs: Mutex::new(HashMap::new()),
         }
     }
 
     /// Create a controller rooted at a custom path (useful for testing).
     #[cfg(test)]
     fn with_base(hwmon_base: PathBuf) -> Self {
-        Self { hwmon_base }
+        Self {
+            hwmon_base,
+            curves: Mutex::new(Vec::new()),
+            curve_inputs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Read the temperature driving a curve: its explicit `sensor` input if
+    /// set, otherwise the sensor `discover` attaches to the fan.
+    fn curve_temperature(&self, curve: &PwmCurve) -> Result<u32, FanControlError> {
+        if let Some(sensor) = &curve.sensor {
+            let (hwmon, temp) = sensor
+                .split_once('/')
+                .ok_or_else(|| invalid_sensor(sensor))?;
+            let index = temp
+                .strip_prefix("temp")
+                .ok_or_else(|| invalid_sensor(sensor))?;
+            let input_path = self
+                .hwmon_base
+                .join(hwmon)
+                .join(format!("temp{}_input", index));
+            return read_millidegrees(&input_path);
+        }
+
+        let (hwmon_dir, fan_index) = self.resolve_fan_paths(&curve.fan_id)?;
+        let temps = discover_temps(&hwmon_dir);
+        let label = read_fan_label(&hwmon_dir, &fan_index);
+        match_temp(&temps, &fan_index, &label)
+            .map(|temp| temp.celsius)
+            .ok_or_else(|| {
+                FanControlError::Platform(format!(
+                    "no temperature sensor found for '{}'; set \"sensor\" in the curve",
+                    curve.fan_id
+                ))
+            })
     }
 
     /// Resolve the sysfs paths for a given fan id.
@@ -98,6 +164,142 @@ impl FanController for LinuxFanController {
         Ok(fans)
     }
 
+    fn read_only_hint(&self) -> String {
+        "no writable pwm* files were found: the loaded hwmon drivers only expose \
+         fan speeds (try a driver with PWM support such as nct6775 or it87), or \
+         the fans are managed by firmware"
+            .to_string()
+    }
+
+    fn stop_note(&self) -> String {
+        "wrote 0 to the pwm file; the fan halts unless the driver or BIOS \
+         enforces a minimum speed"
+            .to_string()
+    }
+
+    fn set_pwm_curves(&self, curves: &[PwmCurve]) -> Result<(), FanControlError> {
+        for curve in curves {
+            curve.validate()?;
+            let (hwmon_dir, fan_index) = self.resolve_fan_paths(&curve.fan_id)?;
+            if !read_pwm_state(&hwmon_dir, &fan_index).controllable {
+                return Err(FanControlError::NotControllable(curve.fan_id.clone()));
+            }
+        }
+        *self
+            .curves
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner()) = curves.to_vec();
+        self.curve_inputs
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clear();
+        Ok(())
+    }
+
+    fn apply_pwm_curves(&self) -> Result<Vec<CurveSample>, FanControlError> {
+        let curves = self
+            .curves
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner())
+            .clone();
+
+        let mut last_inputs = self
+            .curve_inputs
+            .lock()
+            .unwrap_or_else(|poison| poison.into_inner());
+
+        // One failing fan should not leave the others unmanaged.
+        let mut samples = Vec::with_capacity(curves.len());
+        for curve in &curves {
+            let applied = self.curve_temperature(curve).and_then(|temperature| {
+                let last = last_inputs.get(&curve.fan_id).copied();
+                let smoothed = smoothed_temperature(
+                    last.map(|input| input.smoothed),
+                    temperature,
+                    curve.smoothing,
+                );
+                let effective = hysteresis_temperature(
+                    last.map(|input| input.followed),
+                    smoothed.round() as u32,
+                    curve.hysteresis,
+                );
+                last_inputs.insert(
+                    curve.fan_id.clone(),
+                    CurveInput {
+                        smoothed,
+                        followed: effective,
+                    },
+                );
+                let pwm = curve.pwm_at(effective);
+                self.set_pwm(&curve.fan_id, pwm)?;
+                Ok(CurveSample {
+                    fan_id: curve.fan_id.clone(),
+                    temperature,
+                    pwm,
+                })
+            });
+            match applied {
+                Ok(sample) => samples.push(sample),
+                Err(error) => warn!("curve for '{}' not applied: {error}", curve.fan_id),
+            }
+        }
+        Ok(samples)
+    }
+
+    fn check_privileges(&self) -> Result<(), FanControlError> {
+        let pwm_paths = find_pwm_files(&self.hwmon_base);
+        if pwm_paths.is_empty() {
+            // Nothing to control; not a privilege problem.
+            return Ok(());
+        }
+
+        if pwm_paths.iter().any(|path| can_write(path)) {
+            Ok(())
+        } else {
+            Err(FanControlError::PermissionDenied(format!(
+                "cannot write PWM files under {}: run as root or add a udev rule",
+                self.hwmon_base.display()
+            )))
+        }
+    }
+
+    fn set_auto(&self, fan_id: &str) -> Result<(), FanControlError> {
+        let (hwmon_dir, fan_index) = self.resolve_fan_paths(fan_id)?;
+        let pwm_enable_path = hwmon_dir.join(format!("pwm{}_enable", fan_index));
+        if !pwm_enable_path.exists() {
+            return Err(FanControlError::NotControllable(fan_id.to_string()));
+        }
+
+        release_gpu_rpm_target(fan_id, &hwmon_dir, &fan_index)?;
+
+        // "2" selects automatic mode on most chips; a few drivers only
+        // accept "0" (no manual control). A rejected write or a value that
+        // does not read back means the driver refused that mode.
+        for mode in AUTO_ENABLE_MODES {
+            match write_sysfs_value(&pwm_enable_path, mode) {
+                Ok(()) => {
+                    let read_back = fs::read_to_string(&pwm_enable_path).unwrap_or_default();
+                    if read_back.trim() == mode {
+                        info!("'{fan_id}': pwm{fan_index}_enable set to {mode}");
+                        return Ok(());
+                    }
+                    info!(
+                        "'{fan_id}': pwm{fan_index}_enable reads {} after writing {mode}",
+                        read_back.trim()
+                    );
+                }
+                Err(error @ FanControlError::PermissionDenied(_)) => return Err(error),
+                Err(error) => info!("'{fan_id}': pwm{fan_index}_enable rejected {mode}: {error}"),
+            }
+        }
+        Err(FanControlError::Platform(format!(
+            "driver for '{}' accepted none of the automatic modes {:?} in {}",
+            fan_id,
+            AUTO_ENABLE_MODES,
+            pwm_enable_path.display()
+        )))
+    }
+
     fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError> {
         let (hwmon_dir, fan_index) = self.resolve_fan_paths(fan_id)?;
         let input_path = hwmon_dir.join(format!("fan{}_input", fan_index));
@@ -115,16 +317,36 @@ impl FanController for LinuxFanController {
             return Err(FanControlError::NotControllable(fan_id.to_string()));
         }
 
+        let requested = pwm;
+        let pwm = read_pwm_state(&hwmon_dir, &fan_index).fit(fan_id, requested)?;
+        if pwm != requested {
+            info!("'{fan_id}': PWM {requested} clamped to driver maximum {pwm}");
+        }
+
+        release_gpu_rpm_target(fan_id, &hwmon_dir, &fan_index)?;
+
         // Switch to manual mode (value "1") before writing the duty cycle.
-        write_sysfs_value(&pwm_enable_path, "1").map_err(|error| match error {
-            FanControlError::Io(ref io_error) if io_error.kind() == ErrorKind::PermissionDenied => {
-                FanControlError::PermissionDenied(format!(
-                    "cannot enable manual PWM control for '{}': run as root or adjust permissions",
-                    fan_id
-                ))
-            }
-            other => other,
-        })?;
+        // Some minimal drivers expose `pwm{N}` without an enable file; those
+        // are always in manual mode, so the duty cycle can be written directly.
+        if pwm_enable_path.exists() {
+            write_sysfs_value(&pwm_enable_path, "1").map_err(|error| match error {
+                FanControlError::Io(ref io_error)
+                    if io_error.kind() == ErrorKind::PermissionDenied =>
+                {
+                    FanControlError::PermissionDenied(format!(
+                        "cannot enable manual PWM control for '{}': run as root or adjust permissions",
+                        fan_id
+                    ))
+                }
+                other => other,
+            })?;
+        } else {
+            info!(
+                "no {} found for '{}', writing PWM directly",
+                pwm_enable_path.display(),
+                fan_id
+            );
+        }
 
         // Write the PWM duty cycle (0-255).
         write_sysfs_value(&pwm_path, &pwm.to_string()).map_err(|error| match error {
@@ -139,6 +361,91 @@ impl FanController for LinuxFanController {
 
         Ok(())
     }
+
+    /// GPU drivers with an RPM target mode get the target directly; other
+    /// fans have the RPM mapped onto PWM.
+    fn set_rpm(&self, fan_id: &str, rpm: u32) -> Result<(), FanControlError> {
+        let (hwmon_dir, fan_index) = self.resolve_fan_paths(fan_id)?;
+        let Some(target) = GpuRpmTarget::find(&hwmon_dir, &fan_index) else {
+            return set_rpm_via_pwm(self, fan_id, rpm);
+        };
+        let rpm = fit_rpm_target(fan_id, &hwmon_dir, &fan_index, rpm)?;
+        for (path, value) in target.writes(rpm) {
+            write_sysfs_value(&path, &value)?;
+        }
+        info!("'{fan_id}': RPM target {rpm} written to fan{fan_index}_target");
+        Ok(())
+    }
+
+    /// The sysfs files `set_pwm` / `set_auto` would write and the values.
+    fn describe_write(&self, write: &HardwareWrite) -> Result<Vec<String>, FanControlError> {
+        let (fan_id, pwm) = match write {
+            HardwareWrite::Pwm { fan_id, pwm } => (fan_id, *pwm),
+            HardwareWrite::Rpm { fan_id, rpm } => {
+                let (hwmon_dir, fan_index) = self.resolve_fan_paths(fan_id)?;
+                if let Some(target) = GpuRpmTarget::find(&hwmon_dir, &fan_index) {
+                    let rpm = fit_rpm_target(fan_id, &hwmon_dir, &fan_index, *rpm)?;
+                    return Ok(target
+                        .writes(rpm)
+                        .into_iter()
+                        .map(|(path, value)| format!("write {value} to {}", path.display()))
+                        .collect());
+                }
+                let fan = self
+                    .discover()?
+                    .into_iter()
+                    .find(|fan| &fan.id == fan_id)
+                    .ok_or_else(|| FanControlError::FanNotFound(fan_id.clone()))?;
+                let (Some(min_rpm), Some(max_rpm)) = (fan.min_rpm, fan.max_rpm) else {
+                    return Err(FanControlError::Platform(format!(
+                        "RPM targets need a known RPM range, which {fan_id} does not report"
+                    )));
+                };
+                if *rpm > max_rpm {
+                    return Err(FanControlError::Platform(format!(
+                        "{rpm} RPM exceeds {fan_id}'s maximum of {max_rpm} RPM"
+                    )));
+                }
+                (fan_id, rpm_to_pwm(min_rpm, max_rpm, *rpm))
+            }
+            HardwareWrite::Auto { fan_id } => {
+                let (hwmon_dir, fan_index) = self.resolve_fan_paths(fan_id)?;
+                let pwm_enable_path = hwmon_dir.join(format!("pwm{}_enable", fan_index));
+                if !pwm_enable_path.exists() {
+                    return Err(FanControlError::NotControllable(fan_id.to_string()));
+                }
+                let mut lines = release_gpu_rpm_line(&hwmon_dir, &fan_index);
+                lines.push(format!(
+                    "write {} to {} (first mode the driver accepts)",
+                    AUTO_ENABLE_MODES.join(", then "),
+                    pwm_enable_path.display()
+                ));
+                return Ok(lines);
+            }
+            HardwareWrite::PwmCurves(_) => return Ok(Vec::new()),
+            other => return Err(unsupported_write(other)),
+        };
+
+        let (hwmon_dir, fan_index) = self.resolve_fan_paths(fan_id)?;
+        let pwm_path = hwmon_dir.join(format!("pwm{}", fan_index));
+        let pwm_enable_path = hwmon_dir.join(format!("pwm{}_enable", fan_index));
+        if !pwm_path.exists() {
+            return Err(FanControlError::NotControllable(fan_id.to_string()));
+        }
+        let fitted = read_pwm_state(&hwmon_dir, &fan_index).fit(fan_id, pwm)?;
+
+        let mut lines = release_gpu_rpm_line(&hwmon_dir, &fan_index);
+        if pwm_enable_path.exists() {
+            lines.push(format!("write 1 to {}", pwm_enable_path.display()));
+        }
+        let clamped = if fitted == pwm {
+            String::new()
+        } else {
+            format!(" (PWM {pwm} clamped to driver maximum)")
+        };
+        lines.push(format!("write {fitted} to {}{clamped}", pwm_path.display()));
+        Ok(lines)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -168,6 +475,9 @@ fn discover_fans_in_hwmon(hwmon_dir: &Path, hwmon_name: &str) -> Result<Vec<Fan>
         .collect();
     fan_inputs.sort();
 
+    let temps = discover_temps(hwmon_dir);
+    let chip_name = read_chip_name(hwmon_dir);
+
     for input_file in fan_inputs {
         // Extract the fan index, e.g. "fan1_input" -> "1".
         let fan_index = input_file
@@ -179,59 +489,410 @@ fn discover_fans_in_hwmon(hwmon_dir: &Path, hwmon_name: &str) -> Result<Vec<Fan>
 
         let label = read_fan_label(hwmon_dir, fan_index);
         let speed_rpm = read_sysfs_u32(&hwmon_dir.join(&input_file)).unwrap_or(0);
-        let (controllable, current_pwm) = read_pwm_state(hwmon_dir, fan_index);
+        let pwm_state = read_pwm_state(hwmon_dir, fan_index);
+        let temperature = match_temp(&temps, fan_index, &label).map(|temp| temp.celsius);
+        let (min_rpm, max_rpm) = read_rpm_range(hwmon_dir, fan_index);
+        let label = match &chip_name {
+            Some(chip) => format!("{chip}: {label}"),
+            None => label,
+        };
 
         fans.push(Fan {
             id: fan_id,
             label,
             speed_rpm,
-            pwm: current_pwm,
-            controllable,
-            min_rpm: None,
-            max_rpm: None,
+            temperature,
+            pwm: pwm_state.current,
+            pwm_mode: pwm_state.mode,
+            controllable: pwm_state.controllable,
+            min_rpm,
+            max_rpm,
+            target_rpm: read_sysfs_u32(&hwmon_dir.join(format!("fan{}_target", fan_index))).ok(),
             curves: Vec::new(),
             full_speed_active: false,
+            stalled: is_stalled(speed_rpm, pwm_state.command()),
         });
     }
 
     Ok(fans)
 }
 
-/// Read a fan label from `fan{N}_label`, falling back to `"Fan {N}"`.
+/// Read `fan{N}_min` and `fan{N}_max`. Many drivers leave the limits at 0
+/// (alarm disabled), so a zero maximum counts as unknown; without a maximum,
+/// or with a minimum not below it, neither bound is usable.
+fn read_rpm_range(hwmon_dir: &Path, fan_index: &str) -> (Option<u32>, Option<u32>) {
+    let max_rpm = read_sysfs_u32(&hwmon_dir.join(format!("fan{}_max", fan_index)))
+        .ok()
+        .filter(|&max| max > 0);
+    let Some(max_rpm) = max_rpm else {
+        return (None, None);
+    };
+    match read_sysfs_u32(&hwmon_dir.join(format!("fan{}_min", fan_index))) {
+        Ok(min_rpm) if min_rpm >= max_rpm => (None, None),
+        min_rpm => (min_rpm.ok(), Some(max_rpm)),
+    }
+}
+
+/// A `temp{N}_input` reading from one hwmon chip.
+#[derive(Debug, Clone, PartialEq)]
+struct TempSensor {
+    index: String,
+    label: Option<String>,
+    celsius: u32,
+}
+
+/// Read every `temp{N}_input` (millidegrees) and `temp{N}_label` under a
+/// hwmon directory, sorted by index. Unreadable inputs are skipped and
+/// sub-zero readings are clamped to 0 °C.
+fn discover_temps(hwmon_dir: &Path) -> Vec<TempSensor> {
+    let Ok(entries) = fs::read_dir(hwmon_dir) else {
+        return Vec::new();
+    };
+
+    let mut indices: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            file_name
+                .strip_prefix("temp")
+                .and_then(|remainder| remainder.strip_suffix("_input"))
+                .map(str::to_string)
+        })
+        .collect();
+    indices.sort_by_key(|index| index.parse::<u32>().unwrap_or(u32::MAX));
+
+    indices
+        .into_iter()
+        .filter_map(|index| {
+            let input_path = hwmon_dir.join(format!("temp{}_input", index));
+            let celsius = read_millidegrees(&input_path).ok()?;
+            let label = fs::read_to_string(hwmon_dir.join(format!("temp{}_label", index)))
+                .ok()
+                .map(|content| content.trim().to_string())
+                .filter(|label| !label.is_empty());
+            Some(TempSensor {
+                index,
+                label,
+                celsius,
+            })
+        })
+        .collect()
+}
+
+/// Read a `temp{N}_input` file (millidegrees) as whole °C, clamping sub-zero
+/// readings to 0.
+fn read_millidegrees(path: &Path) -> Result<u32, FanControlError> {
+    let content = fs::read_to_string(path).map_err(|error| map_io_error(error, path))?;
+    let millidegrees = content.trim().parse::<i64>().map_err(|parse_error| {
+        FanControlError::Platform(format!(
+            "failed to parse '{}' from {}: {}",
+            content.trim(),
+            path.display(),
+            parse_error
+        ))
+    })?;
+    Ok((millidegrees.max(0) / 1000) as u32)
+}
+
+/// Error for a curve `sensor` that is not of the form `hwmon{N}/temp{M}`.
+fn invalid_sensor(sensor: &str) -> FanControlError {
+    FanControlError::Platform(format!(
+        "sensor '{sensor}' is not of the form hwmon{{N}}/temp{{M}}"
+    ))
+}
+
+/// Pick the temperature most likely to drive a fan on the same chip: a
+/// sensor whose label names the fan (e.g. "CPU" for "CPU Fan"), then the
+/// sensor sharing the fan's index, then the chip's first sensor.
+fn match_temp<'a>(
+    temps: &'a [TempSensor],
+    fan_index: &str,
+    fan_label: &str,
+) -> Option<&'a TempSensor> {
+    let fan_label = fan_label.to_lowercase();
+    temps
+        .iter()
+        .find(|temp| {
+            temp.label
+                .as_deref()
+                .and_then(|label| label.split_whitespace().next())
+                .is_some_and(|word| fan_label.contains(&word.to_lowercase()))
+        })
+        .or_else(|| temps.iter().find(|temp| temp.index == fan_index))
+        .or_else(|| temps.first())
+}
+
+/// `doctor` checks: the hwmon directory has chips, and some PWM file is
+/// writable.
+pub fn environment_checks() -> Vec<Check> {
+    environment_checks_in(Path::new(HWMON_BASE))
+}
+
+fn environment_checks_in(hwmon_base: &Path) -> Vec<Check> {
+    let base = hwmon_base.display();
+    let Ok(entries) = fs::read_dir(hwmon_base) else {
+        return vec![Check::fail(
+            "hwmon directory",
+            format!("{base} is missing: the kernel exposes no hardware monitoring devices"),
+        )];
+    };
+    let mut chips: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| read_chip_name(&entry.path()).unwrap_or_else(|| "unnamed".to_string()))
+        .collect();
+    chips.sort();
+    let hwmon = if chips.is_empty() {
+        Check::fail(
+            "hwmon directory",
+            format!(
+                "{base} has no chips: load the sensor driver for your board \
+                 (`sensors-detect` from lm-sensors suggests one)"
+            ),
+        )
+    } else {
+        Check::pass(
+            "hwmon directory",
+            format!("{} chips: {}", chips.len(), chips.join(", ")),
+        )
+    };
+
+    let pwm_paths = find_pwm_files(hwmon_base);
+    let writable = pwm_paths.iter().filter(|path| can_write(path)).count();
+    let pwm = if pwm_paths.is_empty() {
+        Check::fail(
+            "PWM files",
+            "none found: the drivers report fan speeds only, so fans cannot be set \
+             (some boards need another driver, e.g. nct6775 or it87)",
+        )
+    } else if writable == 0 {
+        Check::fail(
+            "PWM files",
+            format!(
+                "{} found, none writable: run as root or add a udev rule",
+                pwm_paths.len()
+            ),
+        )
+    } else {
+        Check::pass(
+            "PWM files",
+            format!("{writable} of {} writable", pwm_paths.len()),
+        )
+    };
+
+    vec![hwmon, pwm]
+}
+
+/// Opening for write does not modify the file, but fails with EACCES
+/// exactly when a later write would.
+fn can_write(path: &Path) -> bool {
+    fs::OpenOptions::new().write(true).open(path).is_ok()
+}
+
+/// List every `pwm{N}` control file across all hwmon directories.
+fn find_pwm_files(hwmon_base: &Path) -> Vec<PathBuf> {
+    let Ok(hwmon_entries) = fs::read_dir(hwmon_base) else {
+        return Vec::new();
+    };
+
+    hwmon_entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| fs::read_dir(entry.path()).ok())
+        .flat_map(|entries| entries.filter_map(|entry| entry.ok()))
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix("pwm"))
+                .map(|index| !index.is_empty() && index.chars().all(|c| c.is_ascii_digit()))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+/// Read the driver name from a hwmon directory's `name` file (e.g.
+/// "nct6798", "amdgpu"), used to tell fans on different chips apart.
+fn read_chip_name(hwmon_dir: &Path) -> Option<String> {
+    fs::read_to_string(hwmon_dir.join("name"))
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+/// Whether a hwmon directory belongs to a graphics card driver.
+fn is_gpu_chip(hwmon_dir: &Path) -> bool {
+    read_chip_name(hwmon_dir).is_some_and(|name| GPU_CHIPS.contains(&name.as_str()))
+}
+
+/// Read a fan label from `fan{N}_label`, falling back to `"Fan {N}"`, or
+/// `"GPU Fan"` on a graphics card driver.
 fn read_fan_label(hwmon_dir: &Path, fan_index: &str) -> String {
     let label_path = hwmon_dir.join(format!("fan{}_label", fan_index));
-    match fs::read_to_string(&label_path) {
-        Ok(content) => {
-            let trimmed = content.trim().to_string();
-            if trimmed.is_empty() {
-                format!("Fan {}", fan_index)
-            } else {
-                trimmed
+    fs::read_to_string(&label_path)
+        .ok()
+        .map(|content| content.trim().to_string())
+        .filter(|label| !label.is_empty())
+        .unwrap_or_else(|| match (is_gpu_chip(hwmon_dir), fan_index) {
+            (true, "1") => "GPU Fan".to_string(),
+            (true, _) => format!("GPU Fan {}", fan_index),
+            (false, _) => format!("Fan {}", fan_index),
+        })
+}
+
+/// RPM target mode of a GPU fan, as `amdgpu` exposes it: `fan{N}_enable` 1
+/// makes the driver follow `fan{N}_target` instead of `pwm{N}`.
+struct GpuRpmTarget {
+    pwm_enable: PathBuf,
+    fan_enable: PathBuf,
+    target: PathBuf,
+}
+
+impl GpuRpmTarget {
+    /// The target files of a fan on a GPU chip, when `fan{N}_enable` exists
+    /// and `fan{N}_target` is writable.
+    fn find(hwmon_dir: &Path, fan_index: &str) -> Option<Self> {
+        let target = Self {
+            pwm_enable: hwmon_dir.join(format!("pwm{}_enable", fan_index)),
+            fan_enable: hwmon_dir.join(format!("fan{}_enable", fan_index)),
+            target: hwmon_dir.join(format!("fan{}_target", fan_index)),
+        };
+        (is_gpu_chip(hwmon_dir) && target.fan_enable.exists() && can_write(&target.target))
+            .then_some(target)
+    }
+
+    /// Writes that hold `rpm`, in order: manual control first, since the
+    /// driver ignores the RPM mode switch while the fan is automatic.
+    fn writes(&self, rpm: u32) -> Vec<(PathBuf, String)> {
+        let mut writes = Vec::new();
+        if self.pwm_enable.exists() {
+            writes.push((self.pwm_enable.clone(), "1".to_string()));
+        }
+        writes.push((self.fan_enable.clone(), "1".to_string()));
+        writes.push((self.target.clone(), rpm.to_string()));
+        writes
+    }
+
+    /// Whether `fan{N}_enable` reads 1, i.e. the driver follows the target.
+    fn is_active(&self) -> bool {
+        read_sysfs_u32(&self.fan_enable).ok() == Some(1)
+    }
+}
+
+/// Turn off the RPM target mode of a GPU fan before PWM or automatic
+/// control, which the driver would otherwise keep overriding.
+fn release_gpu_rpm_target(
+    fan_id: &str,
+    hwmon_dir: &Path,
+    fan_index: &str,
+) -> Result<(), FanControlError> {
+    let Some(target) = GpuRpmTarget::find(hwmon_dir, fan_index) else {
+        return Ok(());
+    };
+    if target.is_active() {
+        write_sysfs_value(&target.fan_enable, "0")?;
+        info!("'{fan_id}': fan{fan_index}_enable cleared to leave RPM target mode");
+    }
+    Ok(())
+}
+
+/// The `describe_write` line for [`release_gpu_rpm_target`], if it would
+/// write anything.
+fn release_gpu_rpm_line(hwmon_dir: &Path, fan_index: &str) -> Vec<String> {
+    GpuRpmTarget::find(hwmon_dir, fan_index)
+        .filter(GpuRpmTarget::is_active)
+        .map(|target| format!("write 0 to {}", target.fan_enable.display()))
+        .into_iter()
+        .collect()
+}
+
+/// Fit an RPM target into `fan{N}_min`..`fan{N}_max` as the PWM mapping
+/// does: above the maximum is refused, below the minimum is raised to it.
+/// Bounds the driver does not report are not checked.
+fn fit_rpm_target(
+    fan_id: &str,
+    hwmon_dir: &Path,
+    fan_index: &str,
+    rpm: u32,
+) -> Result<u32, FanControlError> {
+    match read_rpm_range(hwmon_dir, fan_index) {
+        (_, Some(max_rpm)) if rpm > max_rpm => Err(FanControlError::Platform(format!(
+            "{rpm} RPM exceeds {fan_id}'s maximum of {max_rpm} RPM"
+        ))),
+        (min_rpm, _) => Ok(min_rpm.map_or(rpm, |min_rpm| rpm.max(min_rpm))),
+    }
+}
+
+/// PWM control state of one fan, as read from sysfs.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct PwmState {
+    /// `pwm{N}` exists and is writable.
+    controllable: bool,
+    /// Current duty cycle from `pwm{N}`.
+    current: Option<u8>,
+    /// Control mode from `pwm{N}_enable` (0 = none, 1 = manual, 2 = auto).
+    mode: Option<u8>,
+    /// Driver limits from `pwm{N}_min` / `pwm{N}_max`, when exposed.
+    min: Option<u8>,
+    max: Option<u8>,
+}
+
+impl PwmState {
+    /// What the driver has been told: `pwm{N}_enable` 1 runs the written
+    /// duty cycle, 0 means no control (full speed), anything else is
+    /// automatic.
+    fn command(&self) -> FanCommand {
+        match (self.mode, self.current) {
+            (Some(1), Some(pwm)) => FanCommand::Pwm(pwm),
+            (Some(0), _) => FanCommand::FullSpeed,
+            _ => FanCommand::Auto,
+        }
+    }
+
+    /// Fit a requested duty cycle into the driver's limits.
+    ///
+    /// Values above `pwm{N}_max` are lowered to it. A nonzero value below
+    /// `pwm{N}_min` is refused: the driver marks it as too low to keep the fan
+    /// spinning, and rounding it up would silently change the request. PWM 0
+    /// (fan off) passes through for the driver to handle.
+    fn fit(&self, fan_id: &str, pwm: u8) -> Result<u8, FanControlError> {
+        if let Some(min) = self.min {
+            if pwm != 0 && pwm < min {
+                return Err(FanControlError::PwmOutOfRange {
+                    fan_id: fan_id.to_string(),
+                    value: pwm,
+                    min,
+                });
             }
         }
-        Err(_) => format!("Fan {}", fan_index),
+        Ok(self.max.map_or(pwm, |max| pwm.min(max)))
     }
 }
 
-/// Check whether PWM control is available for a fan and read its current value.
+/// Check whether PWM control is available for a fan and read its current
+/// value, enable mode, and driver limits.
 ///
-/// Returns `(controllable, current_pwm)`. A fan is considered controllable when
-/// the `pwm{N}` file exists and is writable.
-fn read_pwm_state(hwmon_dir: &Path, fan_index: &str) -> (bool, Option<u8>) {
+/// A fan is considered controllable when the `pwm{N}` file exists and is
+/// writable.
+fn read_pwm_state(hwmon_dir: &Path, fan_index: &str) -> PwmState {
     let pwm_path = hwmon_dir.join(format!("pwm{}", fan_index));
 
     if !pwm_path.exists() {
-        return (false, None);
+        return PwmState::default();
     }
 
-    let current_pwm = read_sysfs_u32(&pwm_path).ok().map(|value| value as u8);
+    let read_u8 = |path: &Path| read_sysfs_u32(path).ok().map(|value| value.min(255) as u8);
 
     // Check writability by inspecting file metadata.
     let writable = fs::metadata(&pwm_path)
         .map(|metadata| !metadata.permissions().readonly())
         .unwrap_or(false);
 
-    (writable, current_pwm)
+    PwmState {
+        controllable: writable,
+        current: read_u8(&pwm_path),
+        mode: read_u8(&hwmon_dir.join(format!("pwm{}_enable", fan_index))),
+        min: read_u8(&hwmon_dir.join(format!("pwm{}_min", fan_index))),
+        max: read_u8(&hwmon_dir.join(format!("pwm{}_max", fan_index))),
+    }
 }
 
 /// Read a sysfs file and parse its content as a `u32`.
@@ -303,6 +964,35 @@ mod tests {
             self
         }
 
+        /// Add `fan{N}_min` and `fan{N}_max` files (RPM).
+        fn add_rpm_limits(&self, hwmon_index: u32, fan_index: u32, min: u32, max: u32) -> &Self {
+            let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
+            fs::create_dir_all(&hwmon_dir).unwrap();
+            fs::write(
+                hwmon_dir.join(format!("fan{}_min", fan_index)),
+                format!("{}\n", min),
+            )
+            .unwrap();
+            fs::write(
+                hwmon_dir.join(format!("fan{}_max", fan_index)),
+                format!("{}\n", max),
+            )
+            .unwrap();
+            self
+        }
+
+        /// Add a `fan{N}_target` file (RPM).
+        fn add_target(&self, hwmon_index: u32, fan_index: u32, rpm: u32) -> &Self {
+            let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
+            fs::create_dir_all(&hwmon_dir).unwrap();
+            fs::write(
+                hwmon_dir.join(format!("fan{}_target", fan_index)),
+                format!("{}\n", rpm),
+            )
+            .unwrap();
+            self
+        }
+
         /// Add a label file for a fan.
         fn add_label(&self, hwmon_index: u32, fan_index: u32, label: &str) -> &Self {
             let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
@@ -315,6 +1005,31 @@ mod tests {
             self
         }
 
+        /// Add a `temp{N}_input` file (millidegrees) with an optional label.
+        fn add_temp(
+            &self,
+            hwmon_index: u32,
+            temp_index: u32,
+            millidegrees: i64,
+            label: Option<&str>,
+        ) -> &Self {
+            let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
+            fs::create_dir_all(&hwmon_dir).unwrap();
+            fs::write(
+                hwmon_dir.join(format!("temp{}_input", temp_index)),
+                millidegrees.to_string(),
+            )
+            .unwrap();
+            if let Some(label) = label {
+                fs::write(
+                    hwmon_dir.join(format!("temp{}_label", temp_index)),
+                    format!("{}\n", label),
+                )
+                .unwrap();
+            }
+            self
+        }
+
         /// Add writable PWM files for a fan.
         fn add_pwm(&self, hwmon_index: u32, fan_index: u32, current_pwm: u8) -> &Self {
             let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
@@ -331,6 +1046,40 @@ mod tests {
             self
         }
 
+        /// Add `pwm{N}_min` / `pwm{N}_max` limit files for a fan.
+        fn add_pwm_limits(&self, hwmon_index: u32, fan_index: u32, min: u8, max: u8) -> &Self {
+            let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
+            fs::create_dir_all(&hwmon_dir).unwrap();
+            fs::write(
+                hwmon_dir.join(format!("pwm{}_min", fan_index)),
+                min.to_string(),
+            )
+            .unwrap();
+            fs::write(
+                hwmon_dir.join(format!("pwm{}_max", fan_index)),
+                max.to_string(),
+            )
+            .unwrap();
+            self
+        }
+
+        /// Add a writable `pwm{N}` file with no matching `pwm{N}_enable`.
+        fn add_pwm_without_enable(
+            &self,
+            hwmon_index: u32,
+            fan_index: u32,
+            current_pwm: u8,
+        ) -> &Self {
+            let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
+            fs::create_dir_all(&hwmon_dir).unwrap();
+
+            let pwm_path = hwmon_dir.join(format!("pwm{}", fan_index));
+            fs::write(&pwm_path, current_pwm.to_string()).unwrap();
+            fs::set_permissions(&pwm_path, fs::Permissions::from_mode(0o644)).unwrap();
+
+            self
+        }
+
         /// Add a read-only PWM file (fan exists but is not controllable).
         fn add_readonly_pwm(&self, hwmon_index: u32, fan_index: u32, current_pwm: u8) -> &Self {
             let hwmon_dir = self.root.path().join(format!("hwmon{}", hwmon_index));
@@ -373,6 +1122,7 @@ mod tests {
         assert_eq!(fans[0].label, "Fan 1");
         assert_eq!(fans[0].speed_rpm, 1200);
         assert_eq!(fans[0].pwm, None);
+        assert_eq!(fans[0].temperature, None);
         assert!(!fans[0].controllable);
     }
 
@@ -388,6 +1138,66 @@ mod tests {
         assert_eq!(fans[0].label, "CPU Fan");
     }
 
+    #[test]
+    fn discover_reads_rpm_range_when_present() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1200).add_rpm_limits(0, 1, 600, 2400);
+        fake.add_fan(0, 2, 900);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let fans = controller.discover().unwrap();
+        assert_eq!((fans[0].min_rpm, fans[0].max_rpm), (Some(600), Some(2400)));
+        assert_eq!((fans[1].min_rpm, fans[1].max_rpm), (None, None));
+        assert!(fans[1].curves.is_empty());
+        assert!(!fans[1].full_speed_active);
+    }
+
+    #[test]
+    fn discover_ignores_unset_rpm_limits() {
+        let fake = FakeHwmon::new();
+        // Alarm limits left at 0 by the driver.
+        fake.add_fan(0, 1, 1200).add_rpm_limits(0, 1, 0, 0);
+        // A minimum with no maximum.
+        fake.add_fan(0, 2, 900).add_rpm_limits(0, 2, 300, 0);
+        // Inverted limits are not usable bounds.
+        fake.add_fan(0, 3, 900).add_rpm_limits(0, 3, 2000, 1000);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let fans = controller.discover().unwrap();
+        assert_eq!((fans[0].min_rpm, fans[0].max_rpm), (None, None));
+        assert_eq!((fans[1].min_rpm, fans[1].max_rpm), (None, None));
+        assert_eq!((fans[2].min_rpm, fans[2].max_rpm), (None, None));
+    }
+
+    #[test]
+    fn discover_reads_target_rpm() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1180).add_target(0, 1, 1200);
+        fake.add_fan(0, 2, 900);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let fans = controller.discover().unwrap();
+        assert_eq!(fans[0].target_rpm, Some(1200));
+        assert_eq!(fans[1].target_rpm, None);
+    }
+
+    #[test]
+    fn discover_prefixes_label_with_chip_name() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 950);
+        fake.add_fan(1, 1, 1800);
+        fake.add_label(1, 1, "CPU Fan");
+        fs::write(fake.base_path().join("hwmon0/name"), "amdgpu\n").unwrap();
+        fs::write(fake.base_path().join("hwmon1/name"), "nct6798\n").unwrap();
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let fans = controller.discover().unwrap();
+        assert_eq!(fans[0].id, "hwmon0/fan1");
+        assert_eq!(fans[0].label, "amdgpu: GPU Fan");
+        assert_eq!(fans[1].id, "hwmon1/fan1");
+        assert_eq!(fans[1].label, "nct6798: CPU Fan");
+    }
+
     #[test]
     fn discover_controllable_fan() {
         let fake = FakeHwmon::new();
@@ -399,6 +1209,34 @@ mod tests {
         assert_eq!(fans.len(), 1);
         assert!(fans[0].controllable);
         assert_eq!(fans[0].pwm, Some(128));
+        assert_eq!(fans[0].pwm_mode, Some(2));
+    }
+
+    #[test]
+    fn discover_reports_manual_mode_after_set_pwm() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 800);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        controller.set_pwm("hwmon0/fan1", 90).unwrap();
+        let fans = controller.discover().unwrap();
+        assert_eq!(fans[0].pwm_mode, Some(1));
+        assert_eq!(fans[0].pwm_mode_name(), Some("manual"));
+    }
+
+    #[test]
+    fn discover_flags_stalled_fan_only_under_manual_control() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 0);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        // Automatic mode: a stopped fan is the firmware's choice.
+        assert!(!controller.discover().unwrap()[0].stalled);
+
+        controller.set_pwm("hwmon0/fan1", 90).unwrap();
+        assert!(controller.discover().unwrap()[0].stalled);
     }
 
     #[test]
@@ -429,6 +1267,74 @@ mod tests {
         assert_eq!(fans[2].id, "hwmon1/fan1");
     }
 
+    #[test]
+    fn check_privileges_with_writable_pwm() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        assert!(controller.check_privileges().is_ok());
+    }
+
+    #[test]
+    fn check_privileges_without_pwm_files() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        assert!(controller.check_privileges().is_ok());
+    }
+
+    #[test]
+    fn environment_checks_report_missing_hwmon() {
+        let checks = environment_checks_in(Path::new("/nonexistent/hwmon"));
+        assert_eq!(checks.len(), 1);
+        assert!(!checks[0].ok);
+        assert!(checks[0].detail.contains("is missing"));
+    }
+
+    #[test]
+    fn environment_checks_count_chips_and_writable_pwm() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        fake.add_fan(1, 1, 900);
+        let checks = environment_checks_in(&fake.base_path());
+        assert_eq!(checks[0].detail, "2 chips: unnamed, unnamed");
+        assert_eq!(checks[1], Check::pass("PWM files", "1 of 1 writable"));
+    }
+
+    #[test]
+    fn environment_checks_flag_hwmon_without_pwm() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        let checks = environment_checks_in(&fake.base_path());
+        assert!(checks[0].ok);
+        assert!(!checks[1].ok);
+        assert!(checks[1].detail.starts_with("none found"));
+    }
+
+    #[test]
+    fn find_pwm_files_ignores_enable_and_other_attributes() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        fake.add_pwm(1, 2, 64);
+
+        let mut names: Vec<String> = find_pwm_files(&fake.base_path())
+            .iter()
+            .map(|path| {
+                path.strip_prefix(fake.base_path())
+                    .unwrap()
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, vec!["hwmon0/pwm1", "hwmon1/pwm2"]);
+    }
+
     #[test]
     fn get_speed_reads_current_rpm() {
         let fake = FakeHwmon::new();
@@ -457,6 +1363,193 @@ mod tests {
         assert!(matches!(result, Err(FanControlError::FanNotFound(_))));
     }
 
+    #[test]
+    fn discover_temps_reads_millidegrees_and_labels() {
+        let fake = FakeHwmon::new();
+        fake.add_temp(0, 2, 51_500, None);
+        fake.add_temp(0, 1, 45_000, Some("CPU"));
+        fake.add_temp(0, 3, -5_000, None);
+
+        let temps = discover_temps(&fake.base_path().join("hwmon0"));
+        let summary: Vec<_> = temps
+            .iter()
+            .map(|temp| (temp.index.as_str(), temp.label.as_deref(), temp.celsius))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("1", Some("CPU"), 45), ("2", None, 51), ("3", None, 0)]
+        );
+    }
+
+    #[test]
+    fn discover_attaches_label_matched_temperature() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 900);
+        fake.add_label(0, 1, "GPU Fan");
+        fake.add_temp(0, 1, 40_000, Some("CPU"));
+        fake.add_temp(0, 2, 62_000, Some("GPU"));
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let fans = controller.discover().unwrap();
+        assert_eq!(fans[0].temperature, Some(62));
+    }
+
+    #[test]
+    fn discover_attaches_same_index_then_first_temperature() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 900);
+        fake.add_fan(0, 2, 1100);
+        fake.add_fan(0, 3, 1300);
+        fake.add_temp(0, 1, 35_000, None);
+        fake.add_temp(0, 2, 48_000, None);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let temps: Vec<_> = controller
+            .discover()
+            .unwrap()
+            .iter()
+            .map(|fan| fan.temperature)
+            .collect();
+        assert_eq!(temps, vec![Some(35), Some(48), Some(35)]);
+    }
+
+    // -- Software curves ---------------------------------------------------
+
+    fn test_curve(sensor: Option<&str>) -> PwmCurve {
+        serde_json::from_value(serde_json::json!({
+            "fan_id": "hwmon0/fan1",
+            "sensor": sensor,
+            "points": [
+                {"temperature": 40, "pwm": 80},
+                {"temperature": 60, "pwm": 160},
+                {"temperature": 80, "pwm": 255}
+            ]
+        }))
+        .unwrap()
+    }
+
+    /// Run one daemon tick at `millidegrees` on temp1 and return the PWM written.
+    fn apply_at(fake: &FakeHwmon, controller: &LinuxFanController, millidegrees: i64) -> String {
+        fake.add_temp(0, 1, millidegrees, None);
+        let samples = controller.apply_pwm_curves().unwrap();
+        assert_eq!(samples.len(), 1);
+        fs::read_to_string(fake.base_path().join("hwmon0/pwm1")).unwrap()
+    }
+
+    #[test]
+    fn apply_pwm_curves_interpolates_from_hwmon_temp() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+        controller.set_pwm_curves(&[test_curve(None)]).unwrap();
+
+        assert_eq!(apply_at(&fake, &controller, 25_000), "80");
+        assert_eq!(apply_at(&fake, &controller, 50_000), "120");
+        assert_eq!(apply_at(&fake, &controller, 70_900), "208");
+        assert_eq!(apply_at(&fake, &controller, 95_000), "255");
+    }
+
+    #[test]
+    fn apply_pwm_curves_holds_pwm_within_hysteresis() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+        let curve = PwmCurve {
+            hysteresis: 4,
+            ..test_curve(None)
+        };
+        controller.set_pwm_curves(&[curve]).unwrap();
+
+        assert_eq!(apply_at(&fake, &controller, 60_000), "160");
+        assert_eq!(apply_at(&fake, &controller, 58_000), "160");
+        assert_eq!(apply_at(&fake, &controller, 61_000), "165");
+        assert_eq!(apply_at(&fake, &controller, 59_000), "165");
+        assert_eq!(apply_at(&fake, &controller, 50_000), "120");
+    }
+
+    /// PWM written for each reading of `trace`, one tick per reading.
+    fn pwms_for_trace(smoothing: Option<f64>, trace: &[i64]) -> Vec<u32> {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+        let curve = PwmCurve {
+            smoothing,
+            ..test_curve(None)
+        };
+        controller.set_pwm_curves(&[curve]).unwrap();
+        trace
+            .iter()
+            .map(|&millidegrees| apply_at(&fake, &controller, millidegrees).parse().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn apply_pwm_curves_smooths_spiky_temperatures() {
+        // Steady at 50 °C with two one-tick spikes to 70 °C.
+        let trace = [50_000, 70_000, 50_000, 50_000, 70_000, 50_000, 50_000];
+        let raw = pwms_for_trace(None, &trace);
+        let smoothed = pwms_for_trace(Some(0.25), &trace);
+        assert_eq!(raw, [120, 208, 120, 120, 208, 120, 120]);
+        assert_eq!(smoothed, [120, 140, 136, 132, 148, 140, 136]);
+
+        let largest_step = |pwms: &[u32]| {
+            pwms.windows(2)
+                .map(|pair| pair[0].abs_diff(pair[1]))
+                .max()
+                .unwrap()
+        };
+        assert!(largest_step(&smoothed) < largest_step(&raw) / 3);
+    }
+
+    #[test]
+    fn apply_pwm_curves_reads_explicit_sensor() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        fake.add_temp(0, 1, 30_000, None);
+        fake.add_temp(1, 3, 60_000, None);
+        let controller = LinuxFanController::with_base(fake.base_path());
+        controller
+            .set_pwm_curves(&[test_curve(Some("hwmon1/temp3"))])
+            .unwrap();
+
+        let samples = controller.apply_pwm_curves().unwrap();
+        assert_eq!(
+            samples,
+            vec![CurveSample {
+                fan_id: "hwmon0/fan1".to_string(),
+                temperature: 60,
+                pwm: 160,
+            }]
+        );
+    }
+
+    #[test]
+    fn apply_pwm_curves_skips_fan_without_sensor() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+        controller.set_pwm_curves(&[test_curve(None)]).unwrap();
+
+        assert!(controller.apply_pwm_curves().unwrap().is_empty());
+        let pwm_value = fs::read_to_string(fake.base_path().join("hwmon0/pwm1")).unwrap();
+        assert_eq!(pwm_value, "128");
+    }
+
+    #[test]
+    fn set_pwm_curves_rejects_read_only_fan() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let result = controller.set_pwm_curves(&[test_curve(None)]);
+        assert!(matches!(result, Err(FanControlError::NotControllable(_))));
+    }
+
     #[test]
     fn set_pwm_writes_enable_and_value() {
         let fake = FakeHwmon::new();
@@ -474,6 +1567,233 @@ mod tests {
         assert_eq!(pwm_value, "128");
     }
 
+    #[test]
+    fn set_pwm_without_enable_file_writes_value_directly() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm_without_enable(0, 1, 100);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        controller.set_pwm("hwmon0/fan1", 200).unwrap();
+
+        let hwmon_dir = fake.base_path().join("hwmon0");
+        assert!(!hwmon_dir.join("pwm1_enable").exists());
+        let pwm_value = fs::read_to_string(hwmon_dir.join("pwm1")).unwrap();
+        assert_eq!(pwm_value, "200");
+    }
+
+    #[test]
+    fn set_auto_writes_automatic_enable_mode() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        controller.set_pwm("hwmon0/fan1", 200).unwrap();
+        controller.set_auto("hwmon0/fan1").unwrap();
+
+        let enable_value = fs::read_to_string(fake.base_path().join("hwmon0/pwm1_enable")).unwrap();
+        assert_eq!(enable_value, "2");
+    }
+
+    /// An amdgpu fan with PWM files, an RPM range, and the RPM target mode.
+    fn fake_amdgpu() -> FakeHwmon {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1200);
+        fake.add_pwm(0, 1, 80);
+        fake.add_rpm_limits(0, 1, 500, 3300);
+        fake.add_target(0, 1, 0);
+        let hwmon_dir = fake.base_path().join("hwmon0");
+        fs::write(hwmon_dir.join("name"), "amdgpu\n").unwrap();
+        fs::write(hwmon_dir.join("fan1_enable"), "0").unwrap();
+        fake
+    }
+
+    #[test]
+    fn set_rpm_on_gpu_writes_target_in_rpm_mode() {
+        let fake = fake_amdgpu();
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        controller.set_rpm("hwmon0/fan1", 2000).unwrap();
+
+        let hwmon_dir = fake.base_path().join("hwmon0");
+        let read = |file: &str| fs::read_to_string(hwmon_dir.join(file)).unwrap();
+        assert_eq!(read("pwm1_enable"), "1");
+        assert_eq!(read("fan1_enable"), "1");
+        assert_eq!(read("fan1_target"), "2000");
+        assert_eq!(read("pwm1"), "80");
+        controller.set_rpm("hwmon0/fan1", 100).unwrap();
+        assert_eq!(read("fan1_target"), "500");
+        assert!(controller.set_rpm("hwmon0/fan1", 4000).is_err());
+    }
+
+    #[test]
+    fn set_pwm_on_gpu_leaves_rpm_mode() {
+        let fake = fake_amdgpu();
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        controller.set_rpm("hwmon0/fan1", 2000).unwrap();
+        let write = HardwareWrite::Pwm {
+            fan_id: "hwmon0/fan1".to_string(),
+            pwm: 150,
+        };
+        let lines = controller.describe_write(&write).unwrap();
+        assert!(lines[0].starts_with("write 0 to ") && lines[0].ends_with("fan1_enable"));
+        controller.set_pwm("hwmon0/fan1", 150).unwrap();
+
+        let hwmon_dir = fake.base_path().join("hwmon0");
+        let read = |file: &str| fs::read_to_string(hwmon_dir.join(file)).unwrap();
+        assert_eq!(read("fan1_enable"), "0");
+        assert_eq!(read("pwm1"), "150");
+    }
+
+    #[test]
+    fn set_rpm_without_gpu_target_mode_maps_onto_pwm() {
+        let fake = fake_amdgpu();
+        fs::write(fake.base_path().join("hwmon0/name"), "nct6798\n").unwrap();
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        controller.set_rpm("hwmon0/fan1", 3300).unwrap();
+
+        let hwmon_dir = fake.base_path().join("hwmon0");
+        assert_eq!(fs::read_to_string(hwmon_dir.join("pwm1")).unwrap(), "255");
+        assert_eq!(
+            fs::read_to_string(hwmon_dir.join("fan1_target"))
+                .unwrap()
+                .trim(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn describe_write_lists_gpu_target_files() {
+        let fake = fake_amdgpu();
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let write = HardwareWrite::Rpm {
+            fan_id: "hwmon0/fan1".to_string(),
+            rpm: 2000,
+        };
+        let lines = controller.describe_write(&write).unwrap();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("write 1 to ") && lines[0].ends_with("pwm1_enable"));
+        assert!(lines[1].starts_with("write 1 to ") && lines[1].ends_with("fan1_enable"));
+        assert!(lines[2].starts_with("write 2000 to ") && lines[2].ends_with("fan1_target"));
+    }
+
+    #[test]
+    fn describe_write_names_files_without_writing() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 100);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let write = HardwareWrite::Pwm {
+            fan_id: "hwmon0/fan1".to_string(),
+            pwm: 200,
+        };
+        let lines = controller.describe_write(&write).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("write 1 to ") && lines[0].ends_with("pwm1_enable"));
+        assert!(lines[1].starts_with("write 200 to ") && lines[1].ends_with("pwm1"));
+
+        let hwmon_dir = fake.base_path().join("hwmon0");
+        assert_eq!(
+            fs::read_to_string(hwmon_dir.join("pwm1")).unwrap().trim(),
+            "100"
+        );
+        assert_eq!(
+            fs::read_to_string(hwmon_dir.join("pwm1_enable"))
+                .unwrap()
+                .trim(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn describe_write_rejects_what_set_pwm_rejects() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 100);
+        fake.add_pwm_limits(0, 1, 60, 255);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let too_low = HardwareWrite::Pwm {
+            fan_id: "hwmon0/fan1".to_string(),
+            pwm: 30,
+        };
+        assert!(matches!(
+            controller.describe_write(&too_low),
+            Err(FanControlError::PwmOutOfRange { .. })
+        ));
+        assert!(controller
+            .describe_write(&HardwareWrite::SmartFanMode(255))
+            .is_err());
+    }
+
+    #[test]
+    fn set_auto_reports_driver_rejecting_every_mode() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        // A directory in place of the enable file fails every write, like a
+        // driver answering EINVAL.
+        fs::create_dir(fake.base_path().join("hwmon0/pwm1_enable")).unwrap();
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let result = controller.set_auto("hwmon0/fan1");
+        assert!(matches!(result, Err(FanControlError::Platform(_))));
+    }
+
+    #[test]
+    fn set_auto_without_enable_file_is_not_controllable() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm_without_enable(0, 1, 100);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let result = controller.set_auto("hwmon0/fan1");
+        assert!(matches!(result, Err(FanControlError::NotControllable(_))));
+    }
+
+    #[test]
+    fn set_pwm_clamps_to_driver_max() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        fake.add_pwm_limits(0, 1, 60, 200);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        controller.set_pwm("hwmon0/fan1", 255).unwrap();
+        let pwm_value = fs::read_to_string(fake.base_path().join("hwmon0/pwm1")).unwrap();
+        assert_eq!(pwm_value, "200");
+
+        controller.set_pwm("hwmon0/fan1", 0).unwrap();
+        let pwm_value = fs::read_to_string(fake.base_path().join("hwmon0/pwm1")).unwrap();
+        assert_eq!(pwm_value, "0");
+    }
+
+    #[test]
+    fn set_pwm_below_driver_min_is_rejected() {
+        let fake = FakeHwmon::new();
+        fake.add_fan(0, 1, 1000);
+        fake.add_pwm(0, 1, 128);
+        fake.add_pwm_limits(0, 1, 60, 255);
+        let controller = LinuxFanController::with_base(fake.base_path());
+
+        let result = controller.set_pwm("hwmon0/fan1", 30);
+        assert!(matches!(
+            result,
+            Err(FanControlError::PwmOutOfRange {
+                value: 30,
+                min: 60,
+                ..
+            })
+        ));
+        // Nothing was written.
+        let pwm_value = fs::read_to_string(fake.base_path().join("hwmon0/pwm1")).unwrap();
+        assert_eq!(pwm_value, "128");
+    }
+
     #[test]
     fn set_pwm_not_controllable() {
         let fake = FakeHwmon::new();
@@ -1,12 +1,35 @@
 #[cfg(any(target_os = "windows", test))]
+mod dell;
+mod dry_run;
+#[cfg(any(target_os = "windows", test))]
 mod lenovo;
+#[cfg(all(target_os = "windows", feature = "wmi-direct"))]
+mod lenovo_com;
+#[cfg(any(target_os = "windows", test))]
+mod lhm;
 #[cfg(target_os = "linux")]
 mod linux;
+#[cfg(any(target_os = "windows", test))]
+mod powershell;
 #[cfg(target_os = "windows")]
 mod windows;
 
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use log::info;
+
+pub use dry_run::{DryRunController, HardwareWrite};
+
+use crate::doctor::Check;
 use crate::errors::FanControlError;
-use crate::fan::{CustomFanCurve, Fan, FanCurve};
+use crate::fan::{
+    match_fan_id, ramp_steps, rpm_to_pwm, CurveSample, CustomFanCurve, Fan, FanCurve, PwmCurve,
+    SensorTemperature, ThermalMode, DEFAULT_MIN_HIGH_TEMP_RATIO,
+};
 
 /// Platform-agnostic fan controller interface.
 pub trait FanController {
@@ -16,9 +39,46 @@ pub trait FanController {
     /// Read current speed (RPM) of a fan by its id.
     fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError>;
 
+    /// Check whether this process has the rights to change fan settings.
+    ///
+    /// Returns `PermissionDenied` with a user-facing hint when writes would
+    /// fail (not root / not administrator), so callers can warn upfront
+    /// instead of on the first `set_pwm`. Default assumes access.
+    fn check_privileges(&self) -> Result<(), FanControlError> {
+        Ok(())
+    }
+
+    /// Backend-specific explanation of why discovered fans may be read-only,
+    /// used when no controllable fan is found and privileges are not the
+    /// cause.
+    fn read_only_hint(&self) -> String {
+        "this backend reports fan speeds but does not support changing them".to_string()
+    }
+
     /// Set PWM duty cycle (0–255) for a fan by its id.
     fn set_pwm(&self, fan_id: &str, pwm: u8) -> Result<(), FanControlError>;
 
+    /// What `set_pwm(fan_id, 0)` actually does on this backend, shown by the
+    /// `stop` command so users are not surprised when a fan keeps spinning.
+    fn stop_note(&self) -> String {
+        "wrote PWM 0; the fan halts if its driver allows zero duty".to_string()
+    }
+
+    /// Return a fan to automatic (firmware/BIOS) control. Default returns
+    /// not-supported.
+    fn set_auto(&self, _fan_id: &str) -> Result<(), FanControlError> {
+        Err(FanControlError::Platform(
+            "automatic mode not supported on this platform".to_string(),
+        ))
+    }
+
+    /// Set a target speed in RPM for a fan by its id. Backends that accept
+    /// RPM targets natively (Lenovo) override this; the default maps the RPM
+    /// onto PWM using the fan's discovered RPM range.
+    fn set_rpm(&self, fan_id: &str, rpm: u32) -> Result<(), FanControlError> {
+        set_rpm_via_pwm(self, fan_id, rpm)
+    }
+
     /// Read fan curve / table data from the EC. Default returns an error
     /// indicating the platform does not support fan curves.
     fn get_fan_curves(&self) -> Result<Vec<FanCurve>, FanControlError> {
@@ -27,48 +87,655 @@ pub trait FanController {
         ))
     }
 
+    /// Current temperature of every sensor the backend can name, sorted by
+    /// sensor ID. Default returns none.
+    fn sensor_temperatures(&self) -> Result<Vec<SensorTemperature>, FanControlError> {
+        Ok(Vec::new())
+    }
+
+    /// Identify the discrete GPU temperature sensor, if any. Returns `None`
+    /// on integrated-only systems and on platforms without sensor tables.
+    fn gpu_sensor_id(&self) -> Result<Option<u32>, FanControlError> {
+        Ok(None)
+    }
+
     /// Write a custom fan curve to the EC. Requires Lenovo hardware in
-    /// Custom SmartFanMode. Default returns not-supported.
-    fn set_custom_curve(&self, _curve: &CustomFanCurve) -> Result<(), FanControlError> {
+    /// Custom SmartFanMode. The curve must meet the default high-temperature
+    /// safety floors.
+    fn set_custom_curve(&self, curve: &CustomFanCurve) -> Result<(), FanControlError> {
+        self.set_custom_curve_with_policy(curve, DEFAULT_MIN_HIGH_TEMP_RATIO)
+    }
+
+    /// `set_custom_curve` with the safety floors for `min_high_temp_ratio`
+    /// (see `validate_custom_curve_with_policy`). Default returns
+    /// not-supported.
+    fn set_custom_curve_with_policy(
+        &self,
+        _curve: &CustomFanCurve,
+        _min_high_temp_ratio: f32,
+    ) -> Result<(), FanControlError> {
         Err(FanControlError::Platform(
             "custom fan curves not supported on this platform".to_string(),
         ))
     }
 
+    /// Raw bytes the firmware returns for one fan/sensor table, for
+    /// debugging the table format. Empty when the firmware answers without
+    /// data. Default returns not-supported.
+    fn raw_fan_table(&self, _fan_id: u32, _sensor_id: u32) -> Result<Vec<u8>, FanControlError> {
+        Err(FanControlError::Platform(
+            "raw fan tables not supported on this platform".to_string(),
+        ))
+    }
+
+    /// Install software curves for `apply_pwm_curves`, replacing any
+    /// installed earlier. Backends whose firmware runs curves itself leave
+    /// this unsupported.
+    fn set_pwm_curves(&self, _curves: &[PwmCurve]) -> Result<(), FanControlError> {
+        Err(FanControlError::Platform(
+            "software fan curves not supported on this platform".to_string(),
+        ))
+    }
+
+    /// Evaluate every installed software curve once: read its temperature,
+    /// interpolate, and write the PWM. Returns a sample per fan updated.
+    fn apply_pwm_curves(&self) -> Result<Vec<CurveSample>, FanControlError> {
+        Ok(Vec::new())
+    }
+
     /// Read the current SmartFanMode (Lenovo-specific). Returns `None` on
     /// platforms that don't support it.
-    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
     fn get_smart_fan_mode(&self) -> Result<Option<u32>, FanControlError> {
         Ok(None)
     }
 
     /// Set SmartFanMode (Lenovo-specific). Default returns not-supported.
-    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
     fn set_smart_fan_mode(&self, _mode: u32) -> Result<(), FanControlError> {
         Err(FanControlError::Platform(
             "SmartFanMode not supported on this platform".to_string(),
         ))
     }
+
+    /// Turn firmware full-speed mode on or off for all fans. Default returns
+    /// not-supported.
+    fn set_full_speed(&self, _enabled: bool) -> Result<(), FanControlError> {
+        Err(FanControlError::Platform(
+            "full-speed mode not supported on this platform".to_string(),
+        ))
+    }
+
+    /// Switch the vendor thermal profile. Default returns not-supported.
+    fn set_thermal_mode(&self, _mode: ThermalMode) -> Result<(), FanControlError> {
+        Err(FanControlError::Platform(
+            "thermal modes not supported on this platform".to_string(),
+        ))
+    }
+
+    /// Explain how this backend would carry out `write` (files, WMI
+    /// methods, scripts), for `--dry-run`. May read the hardware but must
+    /// not write to it. Default has nothing to add for PWM/RPM writes and
+    /// rejects the rest, matching the default write methods.
+    fn describe_write(&self, write: &HardwareWrite) -> Result<Vec<String>, FanControlError> {
+        match write {
+            HardwareWrite::Pwm { .. } | HardwareWrite::Rpm { .. } => Ok(Vec::new()),
+            other => Err(unsupported_write(other)),
+        }
+    }
+}
+
+/// The default [`FanController::set_rpm`]: map `rpm` onto PWM using the
+/// fan's discovered RPM range. Backends that only take RPM targets for some
+/// fans fall back to it for the rest.
+fn set_rpm_via_pwm<C: FanController + ?Sized>(
+    controller: &C,
+    fan_id: &str,
+    rpm: u32,
+) -> Result<(), FanControlError> {
+    let fan = controller
+        .discover()?
+        .into_iter()
+        .find(|fan| fan.id == fan_id)
+        .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_string()))?;
+    let (Some(min_rpm), Some(max_rpm)) = (fan.min_rpm, fan.max_rpm) else {
+        return Err(FanControlError::Platform(format!(
+            "RPM targets need a known RPM range, which {fan_id} does not report"
+        )));
+    };
+    if rpm > max_rpm {
+        return Err(FanControlError::Platform(format!(
+            "{rpm} RPM exceeds {fan_id}'s maximum of {max_rpm} RPM"
+        )));
+    }
+    controller.set_pwm(fan_id, rpm_to_pwm(min_rpm, max_rpm, rpm))
+}
+
+/// The error the default write method behind `write` returns, so a dry run
+/// fails where the real write would.
+fn unsupported_write(write: &HardwareWrite) -> FanControlError {
+    let feature = match write {
+        HardwareWrite::Pwm { .. } | HardwareWrite::Rpm { .. } => "manual fan speeds",
+        HardwareWrite::Auto { .. } => "automatic mode",
+        HardwareWrite::CustomCurve { .. } => "custom fan curves",
+        HardwareWrite::PwmCurves(_) => "software fan curves",
+        HardwareWrite::SmartFanMode(_) => "SmartFanMode",
+        HardwareWrite::FullSpeed(_) => "full-speed mode",
+        HardwareWrite::ThermalMode(_) => "thermal modes",
+    };
+    FanControlError::Platform(format!("{feature} not supported on this platform"))
+}
+
+/// Explain why none of the discovered fans is controllable: missing
+/// privileges if that check fails, otherwise the backend's own hint.
+pub fn no_control_reason(controller: &dyn FanController) -> String {
+    match controller.check_privileges() {
+        Err(error) => error.to_string(),
+        Ok(()) => controller.read_only_hint(),
+    }
+}
+
+/// Labelled PowerShell scripts the Lenovo backend runs, for `--dump-script`.
+/// Returns `None` on platforms without the Lenovo backend.
+pub fn lenovo_scripts() -> Option<Vec<(String, String)>> {
+    #[cfg(target_os = "windows")]
+    {
+        Some(lenovo::scripts())
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        None
+    }
+}
+
+/// The id of the fan `input` names: an id, an index, or a label such as
+/// "cpu" (see [`match_fan_id`]). When discovery fails, `input` is passed
+/// through so the command reports the backend's own error.
+pub fn resolve_fan_id(
+    controller: &dyn FanController,
+    input: &str,
+) -> Result<String, FanControlError> {
+    match controller.discover() {
+        Ok(fans) => match_fan_id(&fans, input),
+        Err(error) => {
+            info!("cannot discover fans to resolve '{input}': {error}");
+            Ok(input.to_string())
+        }
+    }
+}
+
+/// Step `fan_id` from its current PWM to `target` in `steps` increments,
+/// `delay` apart, instead of jumping at once. Works on any backend.
+///
+/// When `stop` is raised mid-ramp, the fan is never left below where it
+/// started: a ramp down is put back to the starting PWM, a ramp up keeps
+/// the value reached. A fan whose PWM cannot be read gets `target` at once.
+/// Returns the PWM the fan was left at.
+pub fn ramp_to(
+    controller: &dyn FanController,
+    fan_id: &str,
+    target: u8,
+    steps: u32,
+    delay: Duration,
+    stop: &AtomicBool,
+) -> Result<u8, FanControlError> {
+    let fan = controller
+        .discover()?
+        .into_iter()
+        .find(|fan| fan.id == fan_id)
+        .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_string()))?;
+    let Some(start) = fan.pwm else {
+        info!("{fan_id} reports no PWM to ramp from; setting {target} directly");
+        controller.set_pwm(fan_id, target)?;
+        return Ok(target);
+    };
+
+    let mut written = start;
+    for (index, pwm) in ramp_steps(start, target, steps).into_iter().enumerate() {
+        if index > 0 && crate::shutdown::sleep_or_stop(stop, delay) {
+            if written < start {
+                info!("ramp of {fan_id} stopped at PWM {written}; restoring {start}");
+                controller.set_pwm(fan_id, start)?;
+                written = start;
+            }
+            return Ok(written);
+        }
+        controller.set_pwm(fan_id, pwm)?;
+        written = pwm;
+    }
+    Ok(written)
+}
+
+/// One fan's id and the outcome of writing it, from [`set_pwm_all`].
+pub type FanWrite = (String, Result<(), FanControlError>);
+
+/// Set every discovered fan to `pwm`, carrying on past failures. Returns
+/// each fan's id and outcome in discovery order; fans that are not
+/// controllable are not written and come back as `NotControllable`.
+pub fn set_pwm_all(
+    controller: &dyn FanController,
+    pwm: u8,
+) -> Result<Vec<FanWrite>, FanControlError> {
+    let results = controller
+        .discover()?
+        .into_iter()
+        .map(|fan| {
+            let result = if fan.controllable {
+                controller.set_pwm(&fan.id, pwm)
+            } else {
+                Err(FanControlError::NotControllable(fan.id.clone()))
+            };
+            (fan.id, result)
+        })
+        .collect();
+    Ok(results)
+}
+
+/// Checks that the OS interfaces this platform's backends use are present
+/// and accessible, for `doctor`.
+pub fn environment_checks() -> Vec<Check> {
+    #[cfg(target_os = "linux")]
+    {
+        linux::environment_checks()
+    }
+    #[cfg(target_os = "windows")]
+    {
+        windows::environment_checks()
+    }
+}
+
+/// A controller implementation that `--backend` or `FANCONTROL_BACKEND` can
+/// force, bypassing manufacturer autodetection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Linux sysfs/hwmon.
+    Hwmon,
+    /// Lenovo Legion WMI.
+    Lenovo,
+    /// Dell Command | Monitor WMI.
+    Dell,
+    /// LibreHardwareMonitor's WMI namespace.
+    Lhm,
+    /// The standard `Win32_Fan` WMI class.
+    Wmi,
+}
+
+impl Backend {
+    /// Every backend name, in the order they are listed in errors.
+    pub const ALL: [Backend; 5] = [
+        Backend::Hwmon,
+        Backend::Lenovo,
+        Backend::Dell,
+        Backend::Lhm,
+        Backend::Wmi,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Hwmon => "hwmon",
+            Backend::Lenovo => "lenovo",
+            Backend::Dell => "dell",
+            Backend::Lhm => "lhm",
+            Backend::Wmi => "wmi",
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wanted = s.trim();
+        Backend::ALL
+            .into_iter()
+            .find(|backend| backend.name().eq_ignore_ascii_case(wanted))
+            .ok_or_else(|| {
+                format!(
+                    "unknown backend '{s}': expected one of {}",
+                    backend_names(&Backend::ALL)
+                )
+            })
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Backends compiled into this build.
+#[cfg(target_os = "linux")]
+const PLATFORM_BACKENDS: &[Backend] = &[Backend::Hwmon];
+#[cfg(target_os = "windows")]
+const PLATFORM_BACKENDS: &[Backend] = &[Backend::Lenovo, Backend::Dell, Backend::Lhm, Backend::Wmi];
+
+/// Environment variable that forces a backend when `--backend` is not given.
+pub const BACKEND_ENV: &str = "FANCONTROL_BACKEND";
+
+static BACKEND_OVERRIDE: OnceLock<Backend> = OnceLock::new();
+
+/// Register the `--backend` flag, which takes precedence over
+/// `FANCONTROL_BACKEND`.
+pub fn set_backend_override(backend: Backend) {
+    let _ = BACKEND_OVERRIDE.set(backend);
+}
+
+fn backend_names(backends: &[Backend]) -> String {
+    backends
+        .iter()
+        .map(|backend| backend.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parse a `FANCONTROL_BACKEND` or config `backend` value. Unset or blank
+/// means autodetect.
+fn parse_backend_env(value: Option<&str>) -> Result<Option<Backend>, String> {
+    match value.map(str::trim) {
+        None | Some("") => Ok(None),
+        Some(name) => name.parse().map(Some),
+    }
+}
+
+/// The backend forced by `--backend`, else by `FANCONTROL_BACKEND`, else by
+/// the config's `backend` key. `None` means autodetect.
+fn requested_backend() -> Result<Option<Backend>, FanControlError> {
+    if let Some(backend) = BACKEND_OVERRIDE.get() {
+        return Ok(Some(*backend));
+    }
+    if let Some(backend) = parse_backend_env(std::env::var(BACKEND_ENV).ok().as_deref())
+        .map_err(|e| FanControlError::Platform(format!("{BACKEND_ENV}: {e}")))?
+    {
+        return Ok(Some(backend));
+    }
+    parse_backend_env(crate::config::load_config().backend.as_deref())
+        .map_err(|e| FanControlError::Platform(format!("config key `backend`: {e}")))
+}
+
+/// Error for a backend that exists but is not compiled into this build.
+fn unavailable(backend: Backend) -> FanControlError {
+    FanControlError::Platform(format!(
+        "the {backend} backend is not available on this platform: expected one of {}",
+        backend_names(PLATFORM_BACKENDS)
+    ))
+}
+
+#[cfg(target_os = "windows")]
+fn powershell_timeout() -> std::time::Duration {
+    std::time::Duration::from_secs(crate::config::load_config().powershell_timeout_secs)
+}
+
+#[cfg(target_os = "windows")]
+fn powershell_read_attempts() -> u32 {
+    crate::config::load_config().powershell_read_attempts
+}
+
+static IGNORE_PWM_CALIBRATION: AtomicBool = AtomicBool::new(false);
+
+/// Map PWM to RPM linearly in controllers created from now on, whatever
+/// `pwm_calibration` holds. `calibrate` measures the uncalibrated mapping.
+pub fn ignore_pwm_calibration() {
+    IGNORE_PWM_CALIBRATION.store(true, Ordering::SeqCst);
+}
+
+#[cfg(target_os = "windows")]
+fn pwm_calibration() -> std::collections::BTreeMap<String, Vec<crate::fan::CalibrationPoint>> {
+    if IGNORE_PWM_CALIBRATION.load(Ordering::SeqCst) {
+        return std::collections::BTreeMap::new();
+    }
+    crate::config::load_config().pwm_calibration
 }
 
 // put id:"platform_select", label:"Platform Detection", node_type:"decision", output:"controller.internal"
 
-/// Create the platform-appropriate controller.
+/// Create the platform-appropriate controller: the backend forced by
+/// `--backend` / `FANCONTROL_BACKEND` if any, else the autodetected one.
 pub fn create_controller() -> Result<Box<dyn FanController>, FanControlError> {
+    match requested_backend()? {
+        Some(backend) => {
+            info!("Using backend {backend} (autodetection bypassed)");
+            create_backend(backend)
+        }
+        None => autodetect_controller(),
+    }
+}
+
+/// Create a specific backend without checking the hardware first.
+fn create_backend(backend: Backend) -> Result<Box<dyn FanController>, FanControlError> {
+    #[cfg(target_os = "linux")]
+    {
+        match backend {
+            Backend::Hwmon => Ok(Box::new(linux::LinuxFanController::new())),
+            other => Err(unavailable(other)),
+        }
+    }
+    #[cfg(target_os = "windows")]
+    {
+        match backend {
+            Backend::Lenovo => {
+                let controller = lenovo::LenovoFanController::new()
+                    .with_timeout(powershell_timeout())
+                    .with_read_attempts(powershell_read_attempts())
+                    .with_calibration(&pwm_calibration());
+                controller.probe()?;
+                Ok(Box::new(controller))
+            }
+            Backend::Dell => Ok(Box::new(
+                dell::DellFanController::new()
+                    .with_timeout(powershell_timeout())
+                    .with_read_attempts(powershell_read_attempts()),
+            )),
+            Backend::Lhm => Ok(Box::new(lhm::LhmFanController::new()?)),
+            Backend::Wmi => Ok(Box::new(windows::WindowsFanController::new()?)),
+            other => Err(unavailable(other)),
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    {
+        compile_error!("Unsupported platform: only Linux and Windows are supported");
+    }
+}
+
+/// Pick a backend from the hardware.
+fn autodetect_controller() -> Result<Box<dyn FanController>, FanControlError> {
     #[cfg(target_os = "linux")]
     {
         Ok(Box::new(linux::LinuxFanController::new()))
     }
     #[cfg(target_os = "windows")]
     {
+        let timeout = powershell_timeout();
+        let read_attempts = powershell_read_attempts();
         if windows::is_lenovo() {
-            Ok(Box::new(lenovo::LenovoFanController::new()))
+            let controller = lenovo::LenovoFanController::new()
+                .with_timeout(timeout)
+                .with_read_attempts(read_attempts)
+                .with_calibration(&pwm_calibration());
+            // Non-Legion Lenovo models lack the vendor classes; treat them
+            // like any other Windows machine.
+            match controller.probe() {
+                Ok(()) => return Ok(Box::new(controller)),
+                Err(error) => log::warn!("{error}; falling back to the generic Windows backend"),
+            }
+        } else if windows::is_dell() {
+            return Ok(Box::new(
+                dell::DellFanController::new()
+                    .with_timeout(timeout)
+                    .with_read_attempts(read_attempts),
+            ));
+        }
+        if let Some(controller) = lhm::LhmFanController::detect() {
+            Ok(Box::new(controller))
         } else {
             Ok(Box::new(windows::WindowsFanController::new()?))
         }
     }
-    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
-    {
-        compile_error!("Unsupported platform: only Linux and Windows are supported");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::sync::atomic::Ordering;
+
+    /// One fan at a fixed starting PWM that records every write and raises
+    /// `stop` once `stop_after` writes have been made.
+    struct Ramp<'a> {
+        start: Option<u8>,
+        writes: RefCell<Vec<u8>>,
+        stop: &'a AtomicBool,
+        stop_after: usize,
+    }
+
+    impl FanController for Ramp<'_> {
+        fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+            Ok(vec![Fan {
+                id: "fan0".to_string(),
+                label: "Fan".to_string(),
+                speed_rpm: 2000,
+                temperature: None,
+                pwm: self.start,
+                pwm_mode: None,
+                controllable: true,
+                min_rpm: None,
+                max_rpm: None,
+                target_rpm: None,
+                curves: Vec::new(),
+                full_speed_active: false,
+                stalled: false,
+            }])
+        }
+
+        fn get_speed(&self, _fan_id: &str) -> Result<u32, FanControlError> {
+            Ok(2000)
+        }
+
+        fn set_pwm(&self, _fan_id: &str, pwm: u8) -> Result<(), FanControlError> {
+            let mut writes = self.writes.borrow_mut();
+            writes.push(pwm);
+            if writes.len() >= self.stop_after {
+                self.stop.store(true, Ordering::SeqCst);
+            }
+            Ok(())
+        }
+    }
+
+    fn ramp(start: Option<u8>, target: u8, stop_after: usize) -> (u8, Vec<u8>) {
+        let stop = AtomicBool::new(false);
+        let controller = Ramp {
+            start,
+            writes: RefCell::new(Vec::new()),
+            stop: &stop,
+            stop_after,
+        };
+        let left = ramp_to(&controller, "fan0", target, 4, Duration::ZERO, &stop).unwrap();
+        (left, controller.writes.into_inner())
+    }
+
+    #[test]
+    fn ramp_to_steps_up_to_target() {
+        assert_eq!(
+            ramp(Some(0), 200, usize::MAX),
+            (200, vec![50, 100, 150, 200])
+        );
+    }
+
+    #[test]
+    fn ramp_to_interrupted_keeps_higher_value_on_the_way_up() {
+        assert_eq!(ramp(Some(0), 200, 2), (100, vec![50, 100]));
+    }
+
+    #[test]
+    fn ramp_to_interrupted_restores_start_on_the_way_down() {
+        assert_eq!(ramp(Some(200), 100, 2), (200, vec![175, 150, 200]));
+    }
+
+    #[test]
+    fn ramp_to_without_known_pwm_sets_target_directly() {
+        assert_eq!(ramp(None, 200, usize::MAX), (200, vec![200]));
+    }
+
+    /// Three fans: `fan0` accepts writes, `fan1` rejects them, `fan2` is not
+    /// controllable.
+    struct Mixed {
+        writes: RefCell<Vec<(String, u8)>>,
+    }
+
+    impl FanController for Mixed {
+        fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
+            Ok(["fan0", "fan1", "fan2"]
+                .into_iter()
+                .map(|id| Fan {
+                    id: id.to_string(),
+                    label: id.to_string(),
+                    speed_rpm: 2000,
+                    temperature: None,
+                    pwm: Some(100),
+                    pwm_mode: None,
+                    controllable: id != "fan2",
+                    min_rpm: None,
+                    max_rpm: None,
+                    target_rpm: None,
+                    curves: Vec::new(),
+                    full_speed_active: false,
+                    stalled: false,
+                })
+                .collect())
+        }
+
+        fn get_speed(&self, _fan_id: &str) -> Result<u32, FanControlError> {
+            Ok(2000)
+        }
+
+        fn set_pwm(&self, fan_id: &str, pwm: u8) -> Result<(), FanControlError> {
+            if fan_id == "fan1" {
+                return Err(FanControlError::PermissionDenied("read-only".into()));
+            }
+            self.writes.borrow_mut().push((fan_id.to_string(), pwm));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn set_pwm_all_reports_each_fan() {
+        let controller = Mixed {
+            writes: RefCell::new(Vec::new()),
+        };
+        let results = set_pwm_all(&controller, 180).unwrap();
+        let ids: Vec<&str> = results.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["fan0", "fan1", "fan2"]);
+        assert!(results[0].1.is_ok());
+        assert!(matches!(
+            results[1].1,
+            Err(FanControlError::PermissionDenied(_))
+        ));
+        assert!(matches!(
+            results[2].1,
+            Err(FanControlError::NotControllable(_))
+        ));
+        // The failure on fan1 did not stop the others; fan2 was never written.
+        assert_eq!(controller.writes.into_inner(), [("fan0".to_string(), 180)]);
+    }
+
+    #[test]
+    fn backend_names_round_trip() {
+        for backend in Backend::ALL {
+            assert_eq!(backend.name().parse::<Backend>(), Ok(backend));
+        }
+        assert_eq!(" LHM ".parse::<Backend>(), Ok(Backend::Lhm));
+    }
+
+    #[test]
+    fn unknown_backend_lists_choices() {
+        let err = "asus".parse::<Backend>().unwrap_err();
+        assert!(err.contains("'asus'"));
+        assert!(err.contains("hwmon, lenovo, dell, lhm, wmi"));
+    }
+
+    #[test]
+    fn parse_backend_env_blank_means_autodetect() {
+        assert_eq!(parse_backend_env(None), Ok(None));
+        assert_eq!(parse_backend_env(Some("  ")), Ok(None));
+        assert_eq!(parse_backend_env(Some("dell")), Ok(Some(Backend::Dell)));
+        assert!(parse_backend_env(Some("foo")).is_err());
     }
 }
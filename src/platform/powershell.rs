@@ -0,0 +1,513 @@
+//! PowerShell transport for the vendor WMI backends.
+//!
+//! The `wmi` crate can query classes but not call their methods, so the
+//! Lenovo and Dell backends run their method calls as PowerShell scripts.
+//! [`PowerShell`] keeps one `powershell.exe` session alive across calls,
+//! falls back to a one-shot process if it dies, and kills calls that exceed
+//! a timeout. Read scripts are retried with backoff when WMI fails
+//! transiently; writes run once so a curve is never applied twice.
+
+use std::cell::RefCell;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use log::{debug, warn};
+
+use crate::errors::FanControlError;
+
+/// How long one PowerShell call may take before the process is killed.
+pub const DEFAULT_PS_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tries a read script gets when the config does not say otherwise.
+pub const DEFAULT_READ_ATTEMPTS: u32 = 3;
+
+/// Wait before the first retry of a read; doubled for each later one.
+const RETRY_BACKOFF: Duration = Duration::from_millis(250);
+
+/// Prints `True` when the process runs with an elevated token.
+pub const PRIVILEGES_SCRIPT: &str = "([Security.Principal.WindowsPrincipal] \
+     [Security.Principal.WindowsIdentity]::GetCurrent()).IsInRole( \
+     [Security.Principal.WindowsBuiltInRole]::Administrator)";
+
+/// PowerShell processes launched so far, logged so the cost of a command
+/// can be checked with `RUST_LOG=debug`.
+static PS_SPAWNS: AtomicUsize = AtomicUsize::new(0);
+
+// ---------------------------------------------------------------------------
+// Persistent session
+// ---------------------------------------------------------------------------
+
+/// Markers framing one reply from the session. Anything printed outside
+/// them (banners, prompts) is ignored.
+const SESSION_BEGIN: &str = "__FANCONTROL_BEGIN__";
+const SESSION_END: &str = "__FANCONTROL_END__";
+/// Prefix of the line reporting a script error inside a reply.
+const SESSION_ERROR: &str = "__FANCONTROL_ERROR__|";
+
+/// Wrap a script for the session: one stdin line that frames its output
+/// and turns a failure into an error line instead of killing the session.
+fn session_command(script: &str) -> String {
+    format!(
+        "Write-Output '{SESSION_BEGIN}'; \
+         try {{ {script} }} catch {{ Write-Output \"{SESSION_ERROR}$($_.Exception.Message)\" }}; \
+         Write-Output '{SESSION_END}'\n"
+    )
+}
+
+/// Read one framed reply. The outer error means the session is unusable
+/// (EOF or pipe failure); the inner one is the script's own error.
+fn read_session_reply(reader: &mut impl BufRead) -> io::Result<Result<String, String>> {
+    let mut lines = Vec::new();
+    let mut error = None;
+    let mut started = false;
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "powershell session exited",
+            ));
+        }
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if !started {
+            started = line == SESSION_BEGIN;
+        } else if line == SESSION_END {
+            break;
+        } else if let Some(message) = line.strip_prefix(SESSION_ERROR) {
+            error = Some(message.to_string());
+        } else {
+            lines.push(line.to_string());
+        }
+    }
+    Ok(match error {
+        Some(message) => Err(message),
+        None => Ok(lines.join("\n").trim().to_string()),
+    })
+}
+
+/// Drain a child pipe on its own thread so the child never blocks on a
+/// full pipe while we wait for it.
+fn drain_pipe(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// Wait for `child` like `Command::output`, but kill it and return
+/// `Timeout` once `timeout` has passed.
+fn output_with_timeout(mut child: Child, timeout: Duration) -> Result<Output, FanControlError> {
+    let stdout = drain_pipe(child.stdout.take());
+    let stderr = drain_pipe(child.stderr.take());
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(FanControlError::Timeout(timeout));
+        }
+        thread::sleep(remaining.min(Duration::from_millis(20)));
+    };
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+/// A long-lived `powershell.exe` reading commands from stdin, so each WMI
+/// call is a round-trip instead of a cold start. Replies are read on a
+/// separate thread so a hung call can be abandoned after a timeout.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+struct PsSession {
+    child: Child,
+    stdin: ChildStdin,
+    replies: Receiver<io::Result<Result<String, String>>>,
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+impl PsSession {
+    fn spawn(timeout: Duration) -> io::Result<Self> {
+        let mut child = Command::new("powershell.exe")
+            .args(["-NoLogo", "-NoProfile", "-NonInteractive", "-Command", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let spawn = PS_SPAWNS.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!("ps_command #{spawn}: persistent session started");
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| io::Error::other("no stdin"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("no stdout"))?;
+        let (reply_tx, replies) = mpsc::channel();
+        thread::spawn(move || {
+            let mut stdout = BufReader::new(stdout);
+            loop {
+                let reply = read_session_reply(&mut stdout);
+                let exited = reply.is_err();
+                if reply_tx.send(reply).is_err() || exited {
+                    break;
+                }
+            }
+        });
+        let mut session = Self {
+            child,
+            stdin,
+            replies,
+        };
+        // Errors must be terminating so the wrapper's catch reports them.
+        session
+            .run("$ErrorActionPreference = 'Stop'", timeout)?
+            .map_err(io::Error::other)?;
+        Ok(session)
+    }
+
+    /// Run one script. A reply that takes longer than `timeout` fails with
+    /// `ErrorKind::TimedOut`; the session is then out of step and must be
+    /// dropped.
+    fn run(&mut self, script: &str, timeout: Duration) -> io::Result<Result<String, String>> {
+        self.stdin.write_all(session_command(script).as_bytes())?;
+        self.stdin.flush()?;
+        match self.replies.recv_timeout(timeout) {
+            Ok(reply) => reply,
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "powershell session did not answer",
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "powershell session exited",
+            )),
+        }
+    }
+}
+
+impl Drop for PsSession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Runner
+// ---------------------------------------------------------------------------
+
+/// Runs a backend's scripts: [`PowerShell`] on real hardware, a recorder in
+/// tests that checks which scripts a write emits.
+pub trait CommandRunner {
+    /// Run `script` once and return its trimmed stdout.
+    fn run(&self, script: &str) -> Result<String, FanControlError>;
+
+    /// Run a script that only reads. Runners may retry it; the default
+    /// runs it once.
+    fn run_read(&self, script: &str) -> Result<String, FanControlError> {
+        self.run(script)
+    }
+}
+
+/// Runs scripts in a shared PowerShell session, started on the first call.
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub struct PowerShell {
+    session: RefCell<Option<PsSession>>,
+    /// Limit for each call.
+    timeout: Duration,
+    /// Tries for each `run_read` call, at least 1.
+    read_attempts: u32,
+}
+
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+impl PowerShell {
+    pub fn new() -> Self {
+        Self {
+            session: RefCell::new(None),
+            timeout: DEFAULT_PS_TIMEOUT,
+            read_attempts: DEFAULT_READ_ATTEMPTS,
+        }
+    }
+
+    /// Kill calls that take longer than `timeout`.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Try read scripts up to `attempts` times (0 counts as 1).
+    pub fn with_read_attempts(mut self, attempts: u32) -> Self {
+        self.read_attempts = attempts.max(1);
+        self
+    }
+
+    /// [`run`](Self::run) for scripts that only read: retried with backoff
+    /// while it fails with a transient error. Only for scripts that are safe
+    /// to repeat.
+    pub fn run_read(&self, script: &str) -> Result<String, FanControlError> {
+        retry_transient(self.read_attempts, || self.run(script), thread::sleep)
+    }
+
+    /// Run `script` and return its trimmed stdout.
+    ///
+    /// Runs in the persistent session, starting it if needed. If the session
+    /// cannot start or dies mid-call, it is dropped (the next call starts a
+    /// new one) and this call falls back to a one-shot process. A call that
+    /// outlives the timeout kills the process and returns `Timeout`.
+    pub fn run(&self, script: &str) -> Result<String, FanControlError> {
+        let mut session = self.session.borrow_mut();
+        if session.is_none() {
+            match PsSession::spawn(self.timeout) {
+                Ok(started) => *session = Some(started),
+                Err(e) => warn!("powershell session failed to start: {e}"),
+            }
+        }
+        if let Some(active) = session.as_mut() {
+            debug!("ps_command (session): {}", script);
+            match active.run(script, self.timeout) {
+                Ok(Ok(stdout)) => {
+                    debug!("ps_command stdout: {}", stdout);
+                    return Ok(stdout);
+                }
+                Ok(Err(message)) => {
+                    warn!("ps_command error: {message}");
+                    return Err(FanControlError::Platform(format!(
+                        "powershell error: {message}"
+                    )));
+                }
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => {
+                    warn!("ps_command timed out after {:?}: {}", self.timeout, script);
+                    *session = None;
+                    return Err(FanControlError::Timeout(self.timeout));
+                }
+                Err(e) => {
+                    warn!("powershell session died, retrying one-shot: {e}");
+                    *session = None;
+                }
+            }
+        }
+        Self::run_once(script, self.timeout)
+    }
+
+    /// Run a script in a fresh `powershell.exe` and return the raw stdout.
+    fn run_once(script: &str, timeout: Duration) -> Result<String, FanControlError> {
+        let spawn = PS_SPAWNS.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!("ps_command #{spawn}: {}", script);
+        let child = Command::new("powershell.exe")
+            .args(["-NoProfile", "-NonInteractive", "-Command", script])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                warn!("ps_command failed to launch: {e}");
+                FanControlError::Platform(format!("failed to run powershell: {e}"))
+            })?;
+        let output = output_with_timeout(child, timeout).inspect_err(|e| {
+            warn!("ps_command failed: {e}");
+        })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            warn!("ps_command stderr: {}", stderr.trim());
+            return Err(FanControlError::Platform(format!(
+                "powershell error: {}",
+                stderr.trim()
+            )));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        debug!("ps_command stdout: {}", stdout);
+        Ok(stdout)
+    }
+}
+
+impl CommandRunner for PowerShell {
+    fn run(&self, script: &str) -> Result<String, FanControlError> {
+        PowerShell::run(self, script)
+    }
+
+    fn run_read(&self, script: &str) -> Result<String, FanControlError> {
+        PowerShell::run_read(self, script)
+    }
+}
+
+/// Call `run` up to `attempts` times while it fails with a transient error,
+/// passing `sleep` a backoff that starts at `RETRY_BACKOFF` and doubles.
+/// WMI reports a provider that is still loading as a script error, so
+/// `Platform` errors count as transient; timeouts and permission errors
+/// do not, since a retry would only repeat them.
+fn retry_transient(
+    attempts: u32,
+    mut run: impl FnMut() -> Result<String, FanControlError>,
+    mut sleep: impl FnMut(Duration),
+) -> Result<String, FanControlError> {
+    let mut backoff = RETRY_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match run() {
+            Err(FanControlError::Platform(message)) if attempt < attempts => {
+                debug!("ps_command attempt {attempt}/{attempts} failed, retrying in {backoff:?}: {message}");
+                sleep(backoff);
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+
+    // -- session framing ------------------------------------------------------
+
+    #[test]
+    fn session_command_is_one_framed_line() {
+        let command = session_command("Write-Output 1");
+        assert_eq!(command.matches('\n').count(), 1);
+        assert!(command.ends_with('\n'));
+        assert!(command.starts_with(&format!("Write-Output '{SESSION_BEGIN}'")));
+        assert!(command.contains("try { Write-Output 1 }"));
+        assert!(command.trim_end().ends_with(&format!("'{SESSION_END}'")));
+    }
+
+    #[test]
+    fn read_session_reply_skips_banner_and_collects_output() {
+        let raw = format!("PS> banner\r\n{SESSION_BEGIN}\r\n0|2400\r\n1|3100\r\n{SESSION_END}\r\n");
+        let reply = read_session_reply(&mut raw.as_bytes()).unwrap();
+        assert_eq!(reply, Ok("0|2400\n1|3100".to_string()));
+    }
+
+    #[test]
+    fn read_session_reply_reports_script_errors() {
+        let raw = format!("{SESSION_BEGIN}\n{SESSION_ERROR}Access denied\n{SESSION_END}\n");
+        let reply = read_session_reply(&mut raw.as_bytes()).unwrap();
+        assert_eq!(reply, Err("Access denied".to_string()));
+    }
+
+    #[test]
+    fn read_session_reply_fails_when_session_exits() {
+        let raw = format!("{SESSION_BEGIN}\n0|2400\n");
+        let error = read_session_reply(&mut raw.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    // -- timeouts -------------------------------------------------------------
+
+    #[cfg(unix)]
+    #[test]
+    fn output_with_timeout_kills_hung_command() {
+        let child = Command::new("sleep")
+            .arg("5")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let started = Instant::now();
+        let result = output_with_timeout(child, Duration::from_millis(100));
+        assert!(matches!(result, Err(FanControlError::Timeout(_))));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_with_timeout_returns_output_of_finished_command() {
+        let child = Command::new("echo")
+            .arg("2400")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let output = output_with_timeout(child, DEFAULT_PS_TIMEOUT).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "2400");
+    }
+
+    // -- retries --------------------------------------------------------------
+
+    /// A runner that fails with each error in `failures`, then succeeds.
+    fn flaky(
+        failures: Vec<FanControlError>,
+    ) -> (
+        impl FnMut() -> Result<String, FanControlError>,
+        Rc<Cell<u32>>,
+    ) {
+        let calls = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&calls);
+        let mut failures = failures.into_iter();
+        let run = move || {
+            counter.set(counter.get() + 1);
+            match failures.next() {
+                Some(error) => Err(error),
+                None => Ok("0|2400".to_string()),
+            }
+        };
+        (run, calls)
+    }
+
+    fn provider_not_loaded() -> FanControlError {
+        FanControlError::Platform("powershell error: provider not loaded".into())
+    }
+
+    #[test]
+    fn flaky_read_succeeds_on_second_attempt() {
+        let (run, calls) = flaky(vec![provider_not_loaded()]);
+        let mut sleeps = Vec::new();
+        let result = retry_transient(3, run, |wait| sleeps.push(wait));
+        assert_eq!(result.unwrap(), "0|2400");
+        assert_eq!(calls.get(), 2);
+        assert_eq!(sleeps, vec![RETRY_BACKOFF]);
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_then_give_up() {
+        let (run, calls) = flaky(vec![
+            provider_not_loaded(),
+            provider_not_loaded(),
+            provider_not_loaded(),
+        ]);
+        let mut sleeps = Vec::new();
+        let result = retry_transient(3, run, |wait| sleeps.push(wait));
+        assert!(matches!(result, Err(FanControlError::Platform(_))));
+        assert_eq!(calls.get(), 3);
+        assert_eq!(sleeps, vec![RETRY_BACKOFF, RETRY_BACKOFF * 2]);
+    }
+
+    #[test]
+    fn timeouts_and_single_attempts_are_not_retried() {
+        let (run, calls) = flaky(vec![FanControlError::Timeout(DEFAULT_PS_TIMEOUT)]);
+        assert!(matches!(
+            retry_transient(3, run, |_| {}),
+            Err(FanControlError::Timeout(_))
+        ));
+        assert_eq!(calls.get(), 1);
+
+        let (run, calls) = flaky(vec![provider_not_loaded()]);
+        assert!(retry_transient(1, run, |_| panic!("slept")).is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -7,16 +7,37 @@
 //! possible through the standard WMI fan class — vendor-specific WMI
 //! namespaces or BIOS interfaces (Dell, ASUS, Lenovo, etc.) are required
 //! for write access.
+//!
+//! Many BIOSes leave `Win32_Fan.DesiredSpeed` empty, so a fan's RPM is the
+//! first of these that has a value:
+//!
+//! 1. `Win32_Fan.DesiredSpeed`
+//! 2. `CurrentReading` of the `CIM_Tachometer` at the same position in
+//!    `root\cimv2` (firmware lists tachometers in fan order)
+//! 3. 0
 
 use serde::Deserialize;
 use wmi::{COMLibrary, WMIConnection};
 
-use super::FanController;
+use super::powershell::PowerShell;
+use super::{lenovo, FanController};
+use crate::doctor::Check;
 use crate::errors::FanControlError;
-use crate::fan::Fan;
+use crate::fan::{Fan, SensorTemperature};
 
 /// Detect whether this machine is a Lenovo system.
 pub fn is_lenovo() -> bool {
+    manufacturer_contains("LENOVO")
+}
+
+/// Detect whether this machine is a Dell system.
+pub fn is_dell() -> bool {
+    manufacturer_contains("DELL")
+}
+
+/// Whether `Win32_ComputerSystem.Manufacturer` contains `name`
+/// (case-insensitive). False when WMI cannot be queried.
+fn manufacturer_contains(name: &str) -> bool {
     let com = match COMLibrary::new() {
         Ok(c) => c,
         Err(_) => return false,
@@ -39,10 +60,57 @@ pub fn is_lenovo() -> bool {
 
     results
         .first()
-        .map(|cs| cs.manufacturer.to_uppercase().contains("LENOVO"))
+        .map(|cs| cs.manufacturer.to_uppercase().contains(name))
         .unwrap_or(false)
 }
 
+/// `doctor` checks: COM and WMI come up, PowerShell runs (the Lenovo and
+/// Dell backends script WMI through it), and on Lenovo machines the vendor
+/// WMI classes exist.
+pub fn environment_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+
+    checks.push(match COMLibrary::new() {
+        Ok(com) => match WMIConnection::new(com) {
+            Ok(_) => Check::pass("COM / WMI", "connected to root\\cimv2"),
+            Err(error) => Check::fail(
+                "COM / WMI",
+                format!("cannot connect to root\\cimv2: {error}; is the WMI service running?"),
+            ),
+        },
+        Err(error) => Check::fail("COM / WMI", format!("COM initialisation failed: {error}")),
+    });
+
+    let powershell = PowerShell::new();
+    let version = powershell.run("$PSVersionTable.PSVersion.ToString()");
+    checks.push(match &version {
+        Ok(version) => Check::pass("PowerShell", format!("powershell.exe {version}")),
+        Err(error) => Check::fail(
+            "PowerShell",
+            format!(
+                "{error}: the Lenovo and Dell backends need Windows PowerShell \
+                 (powershell.exe) on PATH"
+            ),
+        ),
+    });
+
+    if !is_lenovo() {
+        checks.push(Check::pass(
+            "Lenovo WMI classes",
+            "skipped, not a Lenovo system",
+        ));
+    } else if version.is_ok() {
+        checks.push(lenovo::wmi_classes_check(&powershell));
+    } else {
+        checks.push(Check::fail(
+            "Lenovo WMI classes",
+            "not checked, PowerShell is unavailable",
+        ));
+    }
+
+    checks
+}
+
 // ---------------------------------------------------------------------------
 // WMI data model
 // ---------------------------------------------------------------------------
@@ -72,6 +140,42 @@ struct Win32Fan {
     active_cooling: Option<bool>,
 }
 
+/// Maps to the WMI `CIM_Tachometer` class (root\cimv2), the RPM fallback
+/// for fans without `DesiredSpeed`.
+#[derive(Deserialize, Debug)]
+#[serde(rename = "CIM_Tachometer")]
+#[serde(rename_all = "PascalCase")]
+struct Tachometer {
+    /// Current reading in RPM; signed in the schema, often null.
+    current_reading: Option<i32>,
+}
+
+/// Maps to `MSAcpi_ThermalZoneTemperature` (root\WMI). Many desktops
+/// publish ACPI thermal zones even when `Win32_Fan` is empty.
+#[derive(Deserialize, Debug)]
+#[serde(rename = "MSAcpi_ThermalZoneTemperature")]
+#[serde(rename_all = "PascalCase")]
+struct ThermalZone {
+    /// e.g. `ACPI\ThermalZone\TZ00_0`.
+    instance_name: String,
+
+    /// Temperature in tenths of a kelvin.
+    current_temperature: u32,
+}
+
+/// Convert tenths of a kelvin to whole degrees Celsius, rounding. Readings
+/// below freezing (never real for a thermal zone) clamp to 0.
+fn kelvin_tenths_to_celsius(tenths: u32) -> u32 {
+    (tenths.saturating_sub(2732) + 5) / 10
+}
+
+/// Short zone name from its instance name: `TZ00` for
+/// `ACPI\ThermalZone\TZ00_0`.
+fn zone_label(instance_name: &str) -> String {
+    let zone = instance_name.rsplit('\\').next().unwrap_or(instance_name);
+    zone.strip_suffix("_0").unwrap_or(zone).to_string()
+}
+
 // ---------------------------------------------------------------------------
 // Controller
 // ---------------------------------------------------------------------------
@@ -110,21 +214,85 @@ impl WindowsFanController {
         Ok(results)
     }
 
+    /// Tachometer readings in enumeration order; empty when the class is
+    /// missing. Negative readings count as no reading.
+    fn query_tachometers(&self) -> Vec<Option<u32>> {
+        let tachometers: Vec<Tachometer> = self
+            .wmi_connection
+            .raw_query("SELECT CurrentReading FROM CIM_Tachometer")
+            .unwrap_or_default();
+        tachometers
+            .iter()
+            .map(|t| t.current_reading.and_then(|rpm| u32::try_from(rpm).ok()))
+            .collect()
+    }
+
+    /// RPM of each fan, in order, following the module's fallback order.
+    /// Tachometers are only queried when some fan lacks `DesiredSpeed`.
+    fn fan_speeds(&self, wmi_fans: &[Win32Fan]) -> Vec<u32> {
+        let tachometers = if wmi_fans.iter().any(|fan| fan.desired_speed.is_none()) {
+            self.query_tachometers()
+        } else {
+            Vec::new()
+        };
+        wmi_fans
+            .iter()
+            .enumerate()
+            .map(|(index, fan)| {
+                fan.desired_speed
+                    .or_else(|| tachometers.get(index).copied().flatten())
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// ACPI thermal zone temperatures, or `None` when the class is missing
+    /// or unreadable (common without administrator rights).
+    fn thermal_zones(&self) -> Option<Vec<SensorTemperature>> {
+        let com_library = COMLibrary::new().ok()?;
+        let connection = WMIConnection::with_namespace_path("root\\WMI", com_library).ok()?;
+        let zones: Vec<ThermalZone> = connection
+            .raw_query("SELECT InstanceName, CurrentTemperature FROM MSAcpi_ThermalZoneTemperature")
+            .ok()?;
+        Some(
+            zones
+                .iter()
+                .enumerate()
+                .map(|(index, zone)| SensorTemperature {
+                    sensor_id: index as u32,
+                    label: zone_label(&zone.instance_name),
+                    temperature: kelvin_tenths_to_celsius(zone.current_temperature),
+                })
+                .collect(),
+        )
+    }
+
+    /// Hottest thermal zone, used as the system temperature for every fan.
+    fn system_temperature(&self) -> Option<u32> {
+        self.thermal_zones()?
+            .iter()
+            .map(|zone| zone.temperature)
+            .max()
+    }
+
     /// Convert a [`Win32Fan`] WMI record into our domain [`Fan`] struct.
-    fn win32_fan_to_fan(wmi_fan: &Win32Fan) -> Fan {
-        let speed_rpm = wmi_fan.desired_speed.unwrap_or(0);
+    fn win32_fan_to_fan(wmi_fan: &Win32Fan, speed_rpm: u32, temperature: Option<u32>) -> Fan {
         let is_controllable = wmi_fan.active_cooling.unwrap_or(false);
 
         Fan {
             id: wmi_fan.device_id.clone(),
             label: wmi_fan.name.clone(),
             speed_rpm,
+            temperature,
             pwm: None, // WMI does not expose a PWM duty-cycle value
+            pwm_mode: None,
             controllable: is_controllable,
             min_rpm: None,
             max_rpm: None,
+            target_rpm: None,
             curves: Vec::new(),
             full_speed_active: false,
+            stalled: false,
         }
     }
 }
@@ -141,8 +309,17 @@ impl FanController for WindowsFanController {
     /// WMI fan data.
     fn discover(&self) -> Result<Vec<Fan>, FanControlError> {
         let wmi_fans = self.query_fans()?;
+        if wmi_fans.is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let fans = wmi_fans.iter().map(Self::win32_fan_to_fan).collect();
+        let temperature = self.system_temperature();
+        let speeds = self.fan_speeds(&wmi_fans);
+        let fans = wmi_fans
+            .iter()
+            .zip(speeds)
+            .map(|(fan, speed_rpm)| Self::win32_fan_to_fan(fan, speed_rpm, temperature))
+            .collect();
 
         Ok(fans)
     }
@@ -153,12 +330,25 @@ impl FanController for WindowsFanController {
     fn get_speed(&self, fan_id: &str) -> Result<u32, FanControlError> {
         let wmi_fans = self.query_fans()?;
 
-        let matching_fan = wmi_fans
+        let index = wmi_fans
             .iter()
-            .find(|fan| fan.device_id == fan_id)
+            .position(|fan| fan.device_id == fan_id)
             .ok_or_else(|| FanControlError::FanNotFound(fan_id.to_owned()))?;
 
-        Ok(matching_fan.desired_speed.unwrap_or(0))
+        Ok(self.fan_speeds(&wmi_fans)[index])
+    }
+
+    /// ACPI thermal zones; empty when `MSAcpi_ThermalZoneTemperature` is
+    /// unavailable.
+    fn sensor_temperatures(&self) -> Result<Vec<SensorTemperature>, FanControlError> {
+        Ok(self.thermal_zones().unwrap_or_default())
+    }
+
+    fn read_only_hint(&self) -> String {
+        "the generic Win32_Fan WMI class is read-only; fan control needs a \
+         vendor backend (Lenovo Legion is detected automatically, or force one \
+         with --backend) or a vendor tool (e.g. FanControl by Rem0o)"
+            .to_string()
     }
 
     /// Attempt to set the PWM duty cycle for a fan.
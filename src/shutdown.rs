@@ -0,0 +1,45 @@
+//! Ctrl+C / SIGTERM handling for long-running CLI loops.
+//!
+//! Loops that change fan state (`set --watch`, `set --ramp`,
+//! `benchmark-hold`), hold open files (`monitor --csv`), or serve `metrics`
+//! poll a shared stop flag and sleep in short slices, so a signal ends the
+//! loop within ~100 ms and the caller can restore fans or flush logs before
+//! the process exits.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::{info, warn};
+
+/// Install a SIGINT/SIGTERM handler and return the flag it raises.
+///
+/// If a handler cannot be installed the flag is still returned; the loop
+/// then only ends when the process is killed.
+pub fn stop_flag() -> Arc<AtomicBool> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    if let Err(error) = ctrlc::set_handler(move || {
+        info!("stop signal received");
+        handler_stop.store(true, Ordering::SeqCst);
+    }) {
+        warn!("failed to install signal handler: {error}");
+    }
+    stop
+}
+
+/// Sleep for `duration`, waking early if `stop` is raised.
+///
+/// Returns `true` if the loop should stop.
+pub fn sleep_or_stop(stop: &AtomicBool, duration: Duration) -> bool {
+    let deadline = Instant::now() + duration;
+    while Instant::now() < deadline {
+        if stop.load(Ordering::SeqCst) {
+            return true;
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        thread::sleep(remaining.min(Duration::from_millis(100)));
+    }
+    stop.load(Ordering::SeqCst)
+}
@@ -15,7 +15,7 @@ use ratatui::prelude::*;
 use ratatui::widgets::*;
 
 use crate::config;
-use crate::fan::{CustomFanCurve, Fan, FanCurve};
+use crate::fan::{smart_fan_mode_name, CustomFanCurve, Fan, FanCurve};
 use crate::platform::create_controller;
 
 // ---------------------------------------------------------------------------
@@ -665,6 +665,9 @@ fn handle_key(
     }
 }
 
+// Each key arm owns its key even when it does nothing, rather than
+// falling through to later arms through a guard.
+#[allow(clippy::collapsible_match)]
 fn handle_fan_select(app: &mut App, code: KeyCode, cmd_tx: &mpsc::Sender<CmdMsg>) {
     match code {
         KeyCode::Char('q') => app.quit = true,
@@ -722,14 +725,10 @@ fn handle_fan_select(app: &mut App, code: KeyCode, cmd_tx: &mpsc::Sender<CmdMsg>
                     all_curves.push(current);
                 }
             }
-            let cfg = config::Config {
-                custom_curves: all_curves,
-                auto_smart_fan_mode: true,
-            };
-            match config::save_config(&cfg) {
-                Ok(()) => {
+            match config::save_custom_curves(all_curves) {
+                Ok(count) => {
                     app.set_status(
-                        format!("Saved {} curve(s) to config", cfg.custom_curves.len()),
+                        format!("Saved {count} curve(s) to config"),
                         Duration::from_secs(5),
                     );
                 }
@@ -752,6 +751,9 @@ fn handle_fan_select(app: &mut App, code: KeyCode, cmd_tx: &mpsc::Sender<CmdMsg>
     }
 }
 
+// Each key arm owns its key even when it does nothing, rather than
+// falling through to later arms through a guard.
+#[allow(clippy::collapsible_match)]
 fn handle_curve_edit(app: &mut App, code: KeyCode, step_idx: usize, cmd_tx: &mpsc::Sender<CmdMsg>) {
     match code {
         KeyCode::Esc | KeyCode::Char('q') => {
@@ -818,14 +820,10 @@ fn handle_curve_edit(app: &mut App, code: KeyCode, step_idx: usize, cmd_tx: &mps
                     all_curves.push(current);
                 }
             }
-            let cfg = config::Config {
-                custom_curves: all_curves,
-                auto_smart_fan_mode: true,
-            };
-            match config::save_config(&cfg) {
-                Ok(()) => {
+            match config::save_custom_curves(all_curves) {
+                Ok(count) => {
                     app.set_status(
-                        format!("Saved {} curve(s) to config", cfg.custom_curves.len()),
+                        format!("Saved {count} curve(s) to config"),
                         Duration::from_secs(5),
                     );
                 }
@@ -855,15 +853,11 @@ fn handle_curve_edit(app: &mut App, code: KeyCode, step_idx: usize, cmd_tx: &mps
 
 fn smart_fan_mode_label(mode: Option<u32>) -> &'static str {
     match mode {
-        Some(1) => "Quiet",
-        Some(2) => "Balanced",
-        Some(3) => "Performance",
-        Some(255) => "Custom",
-        Some(v) => {
+        Some(v) => smart_fan_mode_name(v).unwrap_or_else(|| {
             // Log unknown values for future discovery
             log::debug!("Unknown SmartFanMode value: {v}");
             "Unknown"
-        }
+        }),
         None => "N/A",
     }
 }
@@ -995,7 +989,7 @@ fn draw_fan_list(f: &mut Frame, app: &App, area: Rect) {
         rows.push(
             Row::new(vec![
                 Cell::from(marker),
-                Cell::from(fan.label.clone()),
+                Cell::from(fan.display_label()),
                 Cell::from(rpm_text),
                 Cell::from(curve_status),
             ])